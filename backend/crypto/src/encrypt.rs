@@ -0,0 +1,145 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use rand_core::{CryptoRng, RngCore};
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("decoding error: {0}")]
+    DecodingError(String),
+
+    #[error("encryption key must be {KEY_LEN} bytes")]
+    InvalidKeyLength,
+
+    #[error("failed to encrypt data")]
+    EncryptionFailed,
+
+    #[error("failed to decrypt data")]
+    DecryptionFailed,
+}
+
+/// Symmetric key used to encrypt data at rest (AES-256-GCM).
+#[derive(Clone)]
+pub struct EncryptionKey([u8; KEY_LEN]);
+
+impl EncryptionKey {
+    pub fn generate<R>(rng: &mut R) -> Self
+    where
+        R: CryptoRng + RngCore,
+    {
+        let mut key = [0; KEY_LEN];
+        rng.fill_bytes(&mut key);
+        Self(key)
+    }
+
+    pub fn from_encoded<S: AsRef<str>>(key: S) -> Result<Self, Error> {
+        let key =
+            crate::decode_base64(key.as_ref()).map_err(|e| Error::DecodingError(e.to_string()))?;
+        let key = key.try_into().map_err(|_| Error::InvalidKeyLength)?;
+        Ok(Self(key))
+    }
+
+    pub fn encode(&self) -> String {
+        crate::encode_base64(self.0)
+    }
+
+    /// Encrypts `plaintext`. The random nonce is prepended to the returned ciphertext.
+    pub fn encrypt<R>(&self, rng: &mut R, plaintext: &[u8]) -> Result<Vec<u8>, Error>
+    where
+        R: CryptoRng + RngCore,
+    {
+        let mut nonce = [0; NONCE_LEN];
+        rng.fill_bytes(&mut nonce);
+
+        let ciphertext = self
+            .cipher()
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|_| Error::EncryptionFailed)?;
+
+        let mut data = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+        Ok(data)
+    }
+
+    /// Decrypts data produced by [`EncryptionKey::encrypt`].
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        if data.len() < NONCE_LEN {
+            return Err(Error::DecryptionFailed);
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+
+        self.cipher()
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| Error::DecryptionFailed)
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(&self.0.into())
+    }
+}
+
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypts_and_decrypts() {
+        let mut rng = crate::new_rng();
+        let key = EncryptionKey::generate(&mut rng);
+
+        let encrypted = key.encrypt(&mut rng, b"secret").unwrap();
+        assert_ne!(&encrypted[NONCE_LEN..], b"secret");
+
+        let decrypted = key.decrypt(&encrypted).unwrap();
+        assert_eq!(decrypted, b"secret");
+    }
+
+    #[test]
+    fn fails_to_decrypt_with_wrong_key() {
+        let mut rng = crate::new_rng();
+        let key = EncryptionKey::generate(&mut rng);
+        let other_key = EncryptionKey::generate(&mut rng);
+
+        let encrypted = key.encrypt(&mut rng, b"secret").unwrap();
+        assert!(other_key.decrypt(&encrypted).is_err());
+    }
+
+    #[test]
+    fn fails_to_decrypt_tampered_data() {
+        let mut rng = crate::new_rng();
+        let key = EncryptionKey::generate(&mut rng);
+
+        let mut encrypted = key.encrypt(&mut rng, b"secret").unwrap();
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 1;
+        assert!(key.decrypt(&encrypted).is_err());
+    }
+
+    #[test]
+    fn encodes_and_decodes_key() {
+        let mut rng = crate::new_rng();
+        let key = EncryptionKey::generate(&mut rng);
+        let decoded = EncryptionKey::from_encoded(key.encode()).unwrap();
+
+        let encrypted = key.encrypt(&mut rng, b"secret").unwrap();
+        assert_eq!(decoded.decrypt(&encrypted).unwrap(), b"secret");
+    }
+
+    #[test]
+    fn rejects_short_keys() {
+        let encoded = crate::encode_base64([0; 16]);
+        assert!(matches!(
+            EncryptionKey::from_encoded(encoded),
+            Err(Error::InvalidKeyLength)
+        ));
+    }
+}
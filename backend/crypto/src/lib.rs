@@ -1,7 +1,13 @@
+pub mod encrypt;
+
 pub mod password;
 
 pub mod sign;
 
+pub mod sigv4;
+
+pub mod totp;
+
 pub use password::{hash_password, verify_password};
 
 pub fn new_rng() -> rand::rngs::StdRng {
@@ -19,3 +25,10 @@ pub fn decode_base64<T: AsRef<[u8]>>(data: T) -> Result<Vec<u8>, base64::DecodeE
 
     general_purpose::STANDARD_NO_PAD.decode(data.as_ref())
 }
+
+/// Hex encoded SHA-256 of `data`.
+pub fn sha256_hex<T: AsRef<[u8]>>(data: T) -> String {
+    use sha2::{Digest, Sha256};
+
+    data_encoding::HEXLOWER.encode(&Sha256::digest(data.as_ref()))
+}
@@ -0,0 +1,272 @@
+//! Time-based one-time passwords ([RFC 6238](https://www.rfc-editor.org/rfc/rfc6238)).
+
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rand_core::{CryptoRng, RngCore};
+use sha1::Sha1;
+use subtle::ConstantTimeEq;
+
+/// Number of digits in a generated code.
+pub const DIGITS: u32 = 6;
+
+/// Seconds covered by a single code.
+pub const STEP_SECS: u64 = 30;
+
+/// Number of steps before and after the current one which are still accepted, to account for
+/// clock drift between the server and the authenticator app.
+pub const ALLOWED_SKEW: u64 = 1;
+
+/// Secret size recommended by RFC 4226 for HMAC-SHA1.
+pub const SECRET_LEN: usize = 20;
+
+const RECOVERY_CODE_GROUP_LEN: usize = 5;
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("invalid secret encoding")]
+    InvalidSecret,
+
+    #[error("invalid code")]
+    InvalidCode,
+}
+
+#[derive(Clone)]
+pub struct TotpSecret(Vec<u8>);
+
+impl TotpSecret {
+    pub fn generate<R>(rng: &mut R) -> Self
+    where
+        R: CryptoRng + RngCore,
+    {
+        let mut secret = vec![0; SECRET_LEN];
+        rng.fill_bytes(&mut secret);
+        Self(secret)
+    }
+
+    pub fn from_bytes<T: Into<Vec<u8>>>(secret: T) -> Self {
+        Self(secret.into())
+    }
+
+    pub fn from_base32<S: AsRef<str>>(secret: S) -> Result<Self, Error> {
+        BASE32_NOPAD
+            .decode(secret.as_ref().as_bytes())
+            .map(Self)
+            .map_err(|_| Error::InvalidSecret)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Encoding used when the secret is typed in manually.
+    pub fn to_base32(&self) -> String {
+        BASE32_NOPAD.encode(&self.0)
+    }
+
+    /// `otpauth://` URI understood by authenticator apps (usually displayed as a QR code).
+    pub fn provisioning_uri(&self, issuer: &str, account: &str) -> String {
+        let issuer = utf8_percent_encode(issuer, NON_ALPHANUMERIC);
+        let account = utf8_percent_encode(account, NON_ALPHANUMERIC);
+        let secret = self.to_base32();
+        format!(
+            "otpauth://totp/{issuer}:{account}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={DIGITS}&period={STEP_SECS}"
+        )
+    }
+
+    /// Code for the step containing `unix_time`.
+    pub fn code_at(&self, unix_time: u64) -> String {
+        format_code(hotp(&self.0, unix_time / STEP_SECS))
+    }
+
+    /// Checks `code` against the steps surrounding `unix_time`.
+    ///
+    /// Returns the matched step so the caller can reject codes that were already used.
+    pub fn verify(&self, code: &str, unix_time: u64) -> Result<u64, Error> {
+        let code = code.trim();
+        if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Error::InvalidCode);
+        }
+
+        let current = unix_time / STEP_SECS;
+        (current.saturating_sub(ALLOWED_SKEW)..=current + ALLOWED_SKEW)
+            .find(|step| {
+                let expected = format_code(hotp(&self.0, *step));
+                bool::from(expected.as_bytes().ct_eq(code.as_bytes()))
+            })
+            .ok_or(Error::InvalidCode)
+    }
+}
+
+impl std::fmt::Debug for TotpSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("TotpSecret(..)")
+    }
+}
+
+fn hotp(key: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // dynamic truncation (RFC 4226 section 5.3)
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    binary % 10_u32.pow(DIGITS)
+}
+
+fn format_code(code: u32) -> String {
+    format!("{code:0width$}", width = DIGITS as usize)
+}
+
+/// Generates single-use recovery codes in the form `xxxxx-xxxxx`.
+pub fn generate_recovery_codes<R>(rng: &mut R, count: usize) -> Vec<String>
+where
+    R: CryptoRng + RngCore,
+{
+    use rand::Rng;
+
+    let group = |rng: &mut R| -> String {
+        (0..RECOVERY_CODE_GROUP_LEN)
+            .map(|_| {
+                let idx = rng.gen_range(0..RECOVERY_CODE_ALPHABET.len());
+                RECOVERY_CODE_ALPHABET[idx] as char
+            })
+            .collect()
+    };
+
+    (0..count)
+        .map(|_| format!("{}-{}", group(rng), group(rng)))
+        .collect()
+}
+
+/// Hashes a recovery code for storage.
+///
+/// Recovery codes are random with ~50 bits of entropy, so a fast hash is sufficient and allows
+/// looking the code up directly. Formatting (case, dashes, whitespace) is ignored.
+pub fn hash_recovery_code<S: AsRef<str>>(code: S) -> String {
+    let normalized: String = code
+        .as_ref()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+
+    crate::sha256_hex(normalized)
+}
+
+/// Whether `code` is shaped like a recovery code rather than a TOTP code.
+pub fn is_recovery_code<S: AsRef<str>>(code: S) -> bool {
+    let code = code.as_ref().trim();
+    !(code.len() == DIGITS as usize && code.bytes().all(|b| b.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // test vectors from RFC 6238 appendix B (SHA1, truncated to 6 digits)
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn generates_rfc_test_vectors() {
+        let secret = TotpSecret::from_bytes(RFC_SECRET);
+        assert_eq!(secret.code_at(59), "287082");
+        assert_eq!(secret.code_at(1111111109), "081804");
+        assert_eq!(secret.code_at(1111111111), "050471");
+        assert_eq!(secret.code_at(1234567890), "005924");
+        assert_eq!(secret.code_at(2000000000), "279037");
+    }
+
+    #[test]
+    fn verifies_current_and_adjacent_steps() {
+        let secret = TotpSecret::from_bytes(RFC_SECRET);
+        let now = 1111111111;
+        let step = now / STEP_SECS;
+
+        assert_eq!(secret.verify(&secret.code_at(now), now).unwrap(), step);
+        assert_eq!(
+            secret
+                .verify(&secret.code_at(now - STEP_SECS), now)
+                .unwrap(),
+            step - 1
+        );
+        assert_eq!(
+            secret
+                .verify(&secret.code_at(now + STEP_SECS), now)
+                .unwrap(),
+            step + 1
+        );
+    }
+
+    #[test]
+    fn rejects_codes_outside_of_skew() {
+        let secret = TotpSecret::from_bytes(RFC_SECRET);
+        let now = 1111111111;
+        let old_code = secret.code_at(now - 3 * STEP_SECS);
+        assert!(secret.verify(&old_code, now).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_codes() {
+        let secret = TotpSecret::from_bytes(RFC_SECRET);
+        assert!(secret.verify("12345", 59).is_err());
+        assert!(secret.verify("abcdef", 59).is_err());
+        assert!(secret.verify("2870820", 59).is_err());
+    }
+
+    #[test]
+    fn base32_roundtrip() {
+        let mut rng = crate::new_rng();
+        let secret = TotpSecret::generate(&mut rng);
+        let decoded = TotpSecret::from_base32(secret.to_base32()).unwrap();
+        assert_eq!(secret.as_bytes(), decoded.as_bytes());
+        assert_eq!(secret.as_bytes().len(), SECRET_LEN);
+    }
+
+    #[test]
+    fn builds_provisioning_uri() {
+        let secret = TotpSecret::from_bytes(RFC_SECRET);
+        let uri = secret.provisioning_uri("uChat", "my user");
+        assert_eq!(
+            uri,
+            "otpauth://totp/uChat:my%20user?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=uChat&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+
+    #[test]
+    fn generates_unique_recovery_codes() {
+        let mut rng = crate::new_rng();
+        let codes = generate_recovery_codes(&mut rng, 10);
+        assert_eq!(codes.len(), 10);
+        for code in &codes {
+            assert_eq!(code.len(), RECOVERY_CODE_GROUP_LEN * 2 + 1);
+            assert!(is_recovery_code(code));
+        }
+
+        let mut deduped = codes.clone();
+        deduped.sort();
+        deduped.dedup();
+        assert_eq!(deduped.len(), codes.len());
+    }
+
+    #[test]
+    fn recovery_code_hash_ignores_formatting() {
+        let hash = hash_recovery_code("abcde-fghjk");
+        assert_eq!(hash, hash_recovery_code(" ABCDE FGHJK "));
+        assert_ne!(hash, hash_recovery_code("abcde-fghjm"));
+    }
+
+    #[test]
+    fn totp_codes_are_not_recovery_codes() {
+        assert!(!is_recovery_code("123456"));
+        assert!(!is_recovery_code(" 123456 "));
+        assert!(is_recovery_code("abcde-fghjk"));
+    }
+}
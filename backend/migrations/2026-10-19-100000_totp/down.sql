@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.user_totp DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.totp_recovery_codes DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.mfa_challenges DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+DROP TABLE IF EXISTS public.user_totp CASCADE;
+DROP TABLE IF EXISTS public.totp_recovery_codes CASCADE;
+DROP TABLE IF EXISTS public.mfa_challenges CASCADE;
@@ -0,0 +1,63 @@
+-- object: public.user_totp | type: TABLE --
+-- DROP TABLE IF EXISTS public.user_totp CASCADE;
+CREATE TABLE public.user_totp (
+  user_id uuid NOT NULL,
+  secret bytea NOT NULL,
+  confirmed_at timestamptz,
+  last_used_step bigint NOT NULL DEFAULT 0,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT user_totp_pk PRIMARY KEY (user_id)
+);
+-- ddl-end --
+COMMENT ON COLUMN public.user_totp.secret IS E'encrypted with the server encryption key';
+-- ddl-end --
+COMMENT ON COLUMN public.user_totp.confirmed_at IS E'two-factor authentication is only enabled once confirmed';
+-- ddl-end --
+COMMENT ON COLUMN public.user_totp.last_used_step IS E'prevents replay of codes that were already accepted';
+-- ddl-end --
+
+-- object: public.totp_recovery_codes | type: TABLE --
+-- DROP TABLE IF EXISTS public.totp_recovery_codes CASCADE;
+CREATE TABLE public.totp_recovery_codes (
+  user_id uuid NOT NULL,
+  code_hash text NOT NULL,
+  used_at timestamptz,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT totp_recovery_codes_pk PRIMARY KEY (user_id,code_hash)
+);
+-- ddl-end --
+
+-- object: public.mfa_challenges | type: TABLE --
+-- DROP TABLE IF EXISTS public.mfa_challenges CASCADE;
+CREATE TABLE public.mfa_challenges (
+  id uuid NOT NULL,
+  user_id uuid NOT NULL,
+  expires_at timestamptz NOT NULL,
+  failed_attempts smallint NOT NULL DEFAULT 0,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT mfa_challenges_pk PRIMARY KEY (id)
+);
+-- ddl-end --
+COMMENT ON TABLE public.mfa_challenges IS E'logins which passed the password check and are waiting for a second factor';
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.user_totp DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.user_totp ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.totp_recovery_codes DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.totp_recovery_codes ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.mfa_challenges DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.mfa_challenges ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
@@ -0,0 +1,2 @@
+-- ids of real-time events, shared by every server instance
+CREATE SEQUENCE public.event_ids AS bigint;
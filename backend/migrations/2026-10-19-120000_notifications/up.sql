@@ -0,0 +1,44 @@
+-- object: public.notifications | type: TABLE --
+-- DROP TABLE IF EXISTS public.notifications CASCADE;
+CREATE TABLE public.notifications (
+  id uuid NOT NULL,
+  user_id uuid NOT NULL,
+  actor_id uuid NOT NULL,
+  kind text NOT NULL,
+  post_id uuid,
+  read_at timestamptz,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT notifications_pk PRIMARY KEY (id),
+  CONSTRAINT notifications_kind_ck CHECK (kind IN ('follow', 'reply', 'boost', 'reaction', 'poll_vote'))
+);
+-- ddl-end --
+COMMENT ON COLUMN public.notifications.user_id IS E'user who is notified';
+-- ddl-end --
+COMMENT ON COLUMN public.notifications.actor_id IS E'user whose action caused the notification';
+-- ddl-end --
+
+-- object: notifications_inbox_idx | type: INDEX --
+-- DROP INDEX IF EXISTS public.notifications_inbox_idx CASCADE;
+CREATE INDEX notifications_inbox_idx ON public.notifications (user_id, created_at DESC, id DESC);
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.notifications DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.notifications ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: actor_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.notifications DROP CONSTRAINT IF EXISTS actor_id_fk CASCADE;
+ALTER TABLE public.notifications ADD CONSTRAINT actor_id_fk FOREIGN KEY (actor_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: post_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.notifications DROP CONSTRAINT IF EXISTS post_id_fk CASCADE;
+ALTER TABLE public.notifications ADD CONSTRAINT post_id_fk FOREIGN KEY (post_id)
+REFERENCES public.posts (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
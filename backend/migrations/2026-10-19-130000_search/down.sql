@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.users DROP COLUMN IF EXISTS search_text CASCADE;
+ALTER TABLE public.posts DROP COLUMN IF EXISTS search_text CASCADE;
+DROP FUNCTION IF EXISTS public.post_text(jsonb) CASCADE;
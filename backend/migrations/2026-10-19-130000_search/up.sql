@@ -0,0 +1,37 @@
+-- object: public.post_text | type: FUNCTION --
+-- DROP FUNCTION IF EXISTS public.post_text(jsonb) CASCADE;
+CREATE FUNCTION public.post_text (content jsonb)
+	RETURNS text
+	LANGUAGE sql
+	IMMUTABLE
+	STRICT
+	AS $$
+SELECT coalesce(string_agg(value #>> '{}', ' '), '')
+FROM jsonb_path_query(content, 'strict $.**') AS value
+WHERE jsonb_typeof(value) = 'string'
+$$;
+-- ddl-end --
+COMMENT ON FUNCTION public.post_text(jsonb) IS E'every string inside the content of a post, separated by spaces';
+-- ddl-end --
+
+-- object: search_text | type: COLUMN --
+-- ALTER TABLE public.posts DROP COLUMN IF EXISTS search_text CASCADE;
+ALTER TABLE public.posts ADD COLUMN search_text tsvector NOT NULL
+	GENERATED ALWAYS AS (to_tsvector('english', public.post_text(content))) STORED;
+-- ddl-end --
+
+-- object: search_text | type: COLUMN --
+-- ALTER TABLE public.users DROP COLUMN IF EXISTS search_text CASCADE;
+ALTER TABLE public.users ADD COLUMN search_text tsvector NOT NULL
+	GENERATED ALWAYS AS (to_tsvector('simple', handle || ' ' || coalesce(display_name, ''))) STORED;
+-- ddl-end --
+
+-- object: posts_search_idx | type: INDEX --
+-- DROP INDEX IF EXISTS public.posts_search_idx CASCADE;
+CREATE INDEX posts_search_idx ON public.posts USING gin (search_text);
+-- ddl-end --
+
+-- object: users_search_idx | type: INDEX --
+-- DROP INDEX IF EXISTS public.users_search_idx CASCADE;
+CREATE INDEX users_search_idx ON public.users USING gin (search_text);
+-- ddl-end --
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM public.notifications WHERE kind = 'mention';
+ALTER TABLE public.notifications DROP CONSTRAINT notifications_kind_ck;
+ALTER TABLE public.notifications ADD CONSTRAINT notifications_kind_ck
+CHECK (kind IN ('follow', 'reply', 'boost', 'reaction', 'poll_vote'));
+DROP TABLE IF EXISTS public.mentions CASCADE;
+DROP TABLE IF EXISTS public.post_hashtags CASCADE;
+DROP TABLE IF EXISTS public.hashtags CASCADE;
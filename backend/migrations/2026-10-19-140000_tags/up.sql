@@ -0,0 +1,82 @@
+-- object: public.hashtags | type: TABLE --
+-- DROP TABLE IF EXISTS public.hashtags CASCADE;
+CREATE TABLE public.hashtags (
+  id uuid NOT NULL,
+  tag text NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT hashtags_pk PRIMARY KEY (id),
+  CONSTRAINT hashtags_tag_uq UNIQUE (tag)
+);
+-- ddl-end --
+COMMENT ON COLUMN public.hashtags.tag IS E'lowercase, without the #';
+-- ddl-end --
+
+-- object: public.post_hashtags | type: TABLE --
+-- DROP TABLE IF EXISTS public.post_hashtags CASCADE;
+CREATE TABLE public.post_hashtags (
+  post_id uuid NOT NULL,
+  hashtag_id uuid NOT NULL,
+  time_posted timestamptz NOT NULL,
+  CONSTRAINT post_hashtags_pk PRIMARY KEY (post_id,hashtag_id)
+);
+-- ddl-end --
+COMMENT ON COLUMN public.post_hashtags.time_posted IS E'copied from the post, so tag timelines and trends don''t need to scan posts';
+-- ddl-end --
+
+-- object: post_hashtags_timeline_idx | type: INDEX --
+-- DROP INDEX IF EXISTS public.post_hashtags_timeline_idx CASCADE;
+CREATE INDEX post_hashtags_timeline_idx ON public.post_hashtags (hashtag_id, time_posted DESC, post_id DESC);
+-- ddl-end --
+
+-- object: post_hashtags_time_posted_idx | type: INDEX --
+-- DROP INDEX IF EXISTS public.post_hashtags_time_posted_idx CASCADE;
+CREATE INDEX post_hashtags_time_posted_idx ON public.post_hashtags (time_posted);
+-- ddl-end --
+
+-- object: public.mentions | type: TABLE --
+-- DROP TABLE IF EXISTS public.mentions CASCADE;
+CREATE TABLE public.mentions (
+  post_id uuid NOT NULL,
+  user_id uuid NOT NULL,
+  CONSTRAINT mentions_pk PRIMARY KEY (post_id,user_id)
+);
+-- ddl-end --
+
+-- object: mentions_user_idx | type: INDEX --
+-- DROP INDEX IF EXISTS public.mentions_user_idx CASCADE;
+CREATE INDEX mentions_user_idx ON public.mentions (user_id);
+-- ddl-end --
+
+-- object: post_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.post_hashtags DROP CONSTRAINT IF EXISTS post_id_fk CASCADE;
+ALTER TABLE public.post_hashtags ADD CONSTRAINT post_id_fk FOREIGN KEY (post_id)
+REFERENCES public.posts (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: hashtag_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.post_hashtags DROP CONSTRAINT IF EXISTS hashtag_id_fk CASCADE;
+ALTER TABLE public.post_hashtags ADD CONSTRAINT hashtag_id_fk FOREIGN KEY (hashtag_id)
+REFERENCES public.hashtags (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: post_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.mentions DROP CONSTRAINT IF EXISTS post_id_fk CASCADE;
+ALTER TABLE public.mentions ADD CONSTRAINT post_id_fk FOREIGN KEY (post_id)
+REFERENCES public.posts (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.mentions DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.mentions ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: notifications_kind_ck | type: CONSTRAINT --
+ALTER TABLE public.notifications DROP CONSTRAINT notifications_kind_ck;
+ALTER TABLE public.notifications ADD CONSTRAINT notifications_kind_ck
+CHECK (kind IN ('follow', 'reply', 'boost', 'reaction', 'poll_vote', 'mention'));
+-- ddl-end --
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP FUNCTION IF EXISTS public.hidden_from(uuid,uuid) CASCADE;
+DROP FUNCTION IF EXISTS public.blocked_between(uuid,uuid) CASCADE;
+DROP TABLE IF EXISTS public.mutes CASCADE;
+DROP TABLE IF EXISTS public.blocks CASCADE;
@@ -0,0 +1,84 @@
+-- object: public.blocks | type: TABLE --
+-- DROP TABLE IF EXISTS public.blocks CASCADE;
+CREATE TABLE public.blocks (
+  user_id uuid NOT NULL,
+  blocked_id uuid NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT blocks_pk PRIMARY KEY (user_id,blocked_id),
+  CONSTRAINT blocks_self_ck CHECK (user_id <> blocked_id)
+);
+-- ddl-end --
+
+-- object: blocks_blocked_idx | type: INDEX --
+-- DROP INDEX IF EXISTS public.blocks_blocked_idx CASCADE;
+CREATE INDEX blocks_blocked_idx ON public.blocks (blocked_id);
+-- ddl-end --
+
+-- object: public.mutes | type: TABLE --
+-- DROP TABLE IF EXISTS public.mutes CASCADE;
+CREATE TABLE public.mutes (
+  user_id uuid NOT NULL,
+  muted_id uuid NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT mutes_pk PRIMARY KEY (user_id,muted_id),
+  CONSTRAINT mutes_self_ck CHECK (user_id <> muted_id)
+);
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.blocks DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.blocks ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: blocked_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.blocks DROP CONSTRAINT IF EXISTS blocked_id_fk CASCADE;
+ALTER TABLE public.blocks ADD CONSTRAINT blocked_id_fk FOREIGN KEY (blocked_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.mutes DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.mutes ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: muted_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.mutes DROP CONSTRAINT IF EXISTS muted_id_fk CASCADE;
+ALTER TABLE public.mutes ADD CONSTRAINT muted_id_fk FOREIGN KEY (muted_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: public.blocked_between | type: FUNCTION --
+-- DROP FUNCTION IF EXISTS public.blocked_between(uuid,uuid) CASCADE;
+CREATE FUNCTION public.blocked_between (a uuid, b uuid)
+	RETURNS boolean
+	LANGUAGE sql
+	STABLE
+	AS $$
+SELECT EXISTS (
+  SELECT 1 FROM public.blocks
+  WHERE (user_id = a AND blocked_id = b) OR (user_id = b AND blocked_id = a)
+)
+$$;
+-- ddl-end --
+COMMENT ON FUNCTION public.blocked_between(uuid,uuid) IS E'whether either user blocked the other';
+-- ddl-end --
+
+-- object: public.hidden_from | type: FUNCTION --
+-- DROP FUNCTION IF EXISTS public.hidden_from(uuid,uuid) CASCADE;
+CREATE FUNCTION public.hidden_from (viewer uuid, author uuid)
+	RETURNS boolean
+	LANGUAGE sql
+	STABLE
+	AS $$
+SELECT public.blocked_between(viewer, author)
+  OR EXISTS (SELECT 1 FROM public.mutes WHERE user_id = viewer AND muted_id = author)
+$$;
+-- ddl-end --
+COMMENT ON FUNCTION public.hidden_from(uuid,uuid) IS E'whether content of author is hidden from viewer by a block or a mute';
+-- ddl-end --
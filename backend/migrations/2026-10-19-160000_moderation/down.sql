@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS public.audit_log CASCADE;
+DROP FUNCTION IF EXISTS public.audit_log_append_only() CASCADE;
+DROP TABLE IF EXISTS public.reports CASCADE;
+ALTER TABLE public.posts DROP COLUMN IF EXISTS hidden_at;
+ALTER TABLE public.users DROP COLUMN IF EXISTS suspended_at;
+ALTER TABLE public.users DROP CONSTRAINT IF EXISTS users_role_ck;
+ALTER TABLE public.users DROP COLUMN IF EXISTS role;
@@ -0,0 +1,137 @@
+-- object: role | type: COLUMN --
+-- ALTER TABLE public.users DROP COLUMN IF EXISTS role CASCADE;
+ALTER TABLE public.users ADD COLUMN role text NOT NULL DEFAULT 'user';
+-- ddl-end --
+
+-- object: users_role_ck | type: CONSTRAINT --
+-- ALTER TABLE public.users DROP CONSTRAINT IF EXISTS users_role_ck CASCADE;
+ALTER TABLE public.users ADD CONSTRAINT users_role_ck CHECK (role IN ('user', 'moderator', 'admin'));
+-- ddl-end --
+
+-- object: suspended_at | type: COLUMN --
+-- ALTER TABLE public.users DROP COLUMN IF EXISTS suspended_at CASCADE;
+ALTER TABLE public.users ADD COLUMN suspended_at timestamptz;
+-- ddl-end --
+COMMENT ON COLUMN public.users.suspended_at IS E'suspended users can''t log in until a moderator lifts the suspension';
+-- ddl-end --
+
+-- object: hidden_at | type: COLUMN --
+-- ALTER TABLE public.posts DROP COLUMN IF EXISTS hidden_at CASCADE;
+ALTER TABLE public.posts ADD COLUMN hidden_at timestamptz;
+-- ddl-end --
+COMMENT ON COLUMN public.posts.hidden_at IS E'hidden by a moderator';
+-- ddl-end --
+
+-- object: public.reports | type: TABLE --
+-- DROP TABLE IF EXISTS public.reports CASCADE;
+CREATE TABLE public.reports (
+  id uuid NOT NULL,
+  reporter_id uuid NOT NULL,
+  user_id uuid NOT NULL,
+  post_id uuid,
+  reason text NOT NULL,
+  details text NOT NULL DEFAULT '',
+  status text NOT NULL DEFAULT 'open',
+  resolved_by uuid,
+  resolved_at timestamptz,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT reports_pk PRIMARY KEY (id),
+  CONSTRAINT reports_reason_ck CHECK (reason IN ('spam', 'harassment', 'hate', 'violence', 'other')),
+  CONSTRAINT reports_status_ck CHECK (status IN ('open', 'resolved', 'dismissed')),
+  CONSTRAINT reports_resolved_ck CHECK ((status = 'open') = (resolved_at IS NULL))
+);
+-- ddl-end --
+COMMENT ON COLUMN public.reports.user_id IS E'reported user, the author for reports of posts';
+-- ddl-end --
+
+-- object: reports_queue_idx | type: INDEX --
+-- DROP INDEX IF EXISTS public.reports_queue_idx CASCADE;
+CREATE INDEX reports_queue_idx ON public.reports (status, created_at, id);
+-- ddl-end --
+
+-- object: reports_open_idx | type: INDEX --
+-- DROP INDEX IF EXISTS public.reports_open_idx CASCADE;
+CREATE UNIQUE INDEX reports_open_idx ON public.reports (reporter_id, user_id, coalesce(post_id, '00000000-0000-0000-0000-000000000000'))
+WHERE status = 'open';
+-- ddl-end --
+COMMENT ON INDEX public.reports_open_idx IS E'one open report per reporter and post or user';
+-- ddl-end --
+
+-- object: reporter_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.reports DROP CONSTRAINT IF EXISTS reporter_id_fk CASCADE;
+ALTER TABLE public.reports ADD CONSTRAINT reporter_id_fk FOREIGN KEY (reporter_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.reports DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.reports ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: post_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.reports DROP CONSTRAINT IF EXISTS post_id_fk CASCADE;
+ALTER TABLE public.reports ADD CONSTRAINT post_id_fk FOREIGN KEY (post_id)
+REFERENCES public.posts (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: resolved_by_fk | type: CONSTRAINT --
+-- ALTER TABLE public.reports DROP CONSTRAINT IF EXISTS resolved_by_fk CASCADE;
+ALTER TABLE public.reports ADD CONSTRAINT resolved_by_fk FOREIGN KEY (resolved_by)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE SET NULL ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: public.audit_log | type: TABLE --
+-- DROP TABLE IF EXISTS public.audit_log CASCADE;
+CREATE TABLE public.audit_log (
+  id uuid NOT NULL,
+  actor_id uuid,
+  action text NOT NULL,
+  user_id uuid,
+  post_id uuid,
+  report_id uuid,
+  details jsonb NOT NULL DEFAULT '{}',
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT audit_log_pk PRIMARY KEY (id),
+  CONSTRAINT audit_log_action_ck CHECK (action IN ('resolve_report', 'dismiss_report', 'hide_post', 'unhide_post', 'suspend_user', 'unsuspend_user', 'set_role'))
+);
+-- ddl-end --
+COMMENT ON TABLE public.audit_log IS E'append-only record of moderation actions. Entries outlive the users, posts and reports they mention, so there are no foreign keys';
+-- ddl-end --
+COMMENT ON COLUMN public.audit_log.actor_id IS E'moderator who took the action, NULL for the command line';
+-- ddl-end --
+
+-- object: audit_log_time_idx | type: INDEX --
+-- DROP INDEX IF EXISTS public.audit_log_time_idx CASCADE;
+CREATE INDEX audit_log_time_idx ON public.audit_log (created_at DESC, id DESC);
+-- ddl-end --
+
+-- object: public.audit_log_append_only | type: FUNCTION --
+-- DROP FUNCTION IF EXISTS public.audit_log_append_only() CASCADE;
+CREATE FUNCTION public.audit_log_append_only ()
+	RETURNS trigger
+	LANGUAGE plpgsql
+	AS $$
+BEGIN
+  RAISE EXCEPTION 'audit_log is append-only';
+END
+$$;
+-- ddl-end --
+
+-- object: audit_log_append_only_tg | type: TRIGGER --
+-- DROP TRIGGER IF EXISTS audit_log_append_only_tg ON public.audit_log CASCADE;
+CREATE TRIGGER audit_log_append_only_tg
+BEFORE UPDATE OR DELETE ON public.audit_log
+FOR EACH ROW EXECUTE FUNCTION public.audit_log_append_only();
+-- ddl-end --
+
+-- object: audit_log_no_truncate_tg | type: TRIGGER --
+-- DROP TRIGGER IF EXISTS audit_log_no_truncate_tg ON public.audit_log CASCADE;
+CREATE TRIGGER audit_log_no_truncate_tg
+BEFORE TRUNCATE ON public.audit_log
+FOR EACH STATEMENT EXECUTE FUNCTION public.audit_log_append_only();
+-- ddl-end --
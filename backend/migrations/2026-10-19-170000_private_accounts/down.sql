@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM public.notifications WHERE kind = 'follow_request';
+ALTER TABLE public.notifications DROP CONSTRAINT notifications_kind_ck;
+ALTER TABLE public.notifications ADD CONSTRAINT notifications_kind_ck
+CHECK (kind IN ('follow', 'reply', 'boost', 'reaction', 'poll_vote', 'mention'));
+DROP FUNCTION IF EXISTS public.can_see_posts_of(uuid,uuid) CASCADE;
+DROP TABLE IF EXISTS public.follow_requests CASCADE;
+ALTER TABLE public.users DROP COLUMN IF EXISTS private CASCADE;
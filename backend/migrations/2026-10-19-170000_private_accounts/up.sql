@@ -0,0 +1,59 @@
+-- object: private | type: COLUMN --
+-- ALTER TABLE public.users DROP COLUMN IF EXISTS private CASCADE;
+ALTER TABLE public.users ADD COLUMN private boolean NOT NULL DEFAULT false;
+-- ddl-end --
+COMMENT ON COLUMN public.users.private IS E'posts of private users are only visible to approved followers';
+-- ddl-end --
+
+-- object: public.follow_requests | type: TABLE --
+-- DROP TABLE IF EXISTS public.follow_requests CASCADE;
+CREATE TABLE public.follow_requests (
+  user_id uuid NOT NULL,
+  follows uuid NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT follow_requests_pk PRIMARY KEY (user_id,follows),
+  CONSTRAINT follow_requests_self_ck CHECK (user_id <> follows)
+);
+-- ddl-end --
+COMMENT ON TABLE public.follow_requests IS E'follows of private users waiting for their approval';
+-- ddl-end --
+
+-- object: follow_requests_follows_idx | type: INDEX --
+-- DROP INDEX IF EXISTS public.follow_requests_follows_idx CASCADE;
+CREATE INDEX follow_requests_follows_idx ON public.follow_requests (follows, created_at);
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.follow_requests DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.follow_requests ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: follows_fk | type: CONSTRAINT --
+-- ALTER TABLE public.follow_requests DROP CONSTRAINT IF EXISTS follows_fk CASCADE;
+ALTER TABLE public.follow_requests ADD CONSTRAINT follows_fk FOREIGN KEY (follows)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
+
+-- object: public.can_see_posts_of | type: FUNCTION --
+-- DROP FUNCTION IF EXISTS public.can_see_posts_of(uuid,uuid) CASCADE;
+CREATE FUNCTION public.can_see_posts_of (viewer uuid, author uuid)
+	RETURNS boolean
+	LANGUAGE sql
+	STABLE
+	AS $$
+SELECT viewer = author
+  OR NOT (SELECT private FROM public.users WHERE id = author)
+  OR EXISTS (SELECT 1 FROM public.followers WHERE user_id = viewer AND follows = author)
+$$;
+-- ddl-end --
+COMMENT ON FUNCTION public.can_see_posts_of(uuid,uuid) IS E'whether author is public, or viewer is the author or one of their followers';
+-- ddl-end --
+
+-- object: notifications_kind_ck | type: CONSTRAINT --
+ALTER TABLE public.notifications DROP CONSTRAINT notifications_kind_ck;
+ALTER TABLE public.notifications ADD CONSTRAINT notifications_kind_ck
+CHECK (kind IN ('follow', 'reply', 'boost', 'reaction', 'poll_vote', 'mention', 'follow_request'));
+-- ddl-end --
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP FUNCTION IF EXISTS public.can_see_post(uuid,uuid,text) CASCADE;
+ALTER TABLE public.posts DROP COLUMN IF EXISTS visibility CASCADE;
@@ -0,0 +1,27 @@
+-- object: visibility | type: COLUMN --
+-- ALTER TABLE public.posts DROP COLUMN IF EXISTS visibility CASCADE;
+ALTER TABLE public.posts ADD COLUMN visibility text NOT NULL DEFAULT 'public';
+-- ddl-end --
+COMMENT ON COLUMN public.posts.visibility IS E'followers posts are only visible to followers of the author, unlisted posts are left out of feeds but can be opened by anyone';
+-- ddl-end --
+
+-- object: posts_visibility_ck | type: CONSTRAINT --
+-- ALTER TABLE public.posts DROP CONSTRAINT IF EXISTS posts_visibility_ck CASCADE;
+ALTER TABLE public.posts ADD CONSTRAINT posts_visibility_ck CHECK (visibility IN ('public', 'followers', 'unlisted'));
+-- ddl-end --
+
+-- object: public.can_see_post | type: FUNCTION --
+-- DROP FUNCTION IF EXISTS public.can_see_post(uuid,uuid,text) CASCADE;
+CREATE FUNCTION public.can_see_post (viewer uuid, author uuid, visibility text)
+	RETURNS boolean
+	LANGUAGE sql
+	STABLE
+	AS $$
+SELECT public.can_see_posts_of(viewer, author)
+  AND (visibility <> 'followers'
+    OR viewer = author
+    OR EXISTS (SELECT 1 FROM public.followers WHERE user_id = viewer AND follows = author))
+$$;
+-- ddl-end --
+COMMENT ON FUNCTION public.can_see_post(uuid,uuid,text) IS E'whether viewer can see a post of author with the visibility, ignoring blocks and moderation';
+-- ddl-end --
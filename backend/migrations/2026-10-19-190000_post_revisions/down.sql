@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS public.post_revisions CASCADE;
+ALTER TABLE public.posts DROP COLUMN IF EXISTS edited_at CASCADE;
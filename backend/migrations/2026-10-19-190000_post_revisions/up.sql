@@ -0,0 +1,31 @@
+-- object: edited_at | type: COLUMN --
+-- ALTER TABLE public.posts DROP COLUMN IF EXISTS edited_at CASCADE;
+ALTER TABLE public.posts ADD COLUMN edited_at timestamptz;
+-- ddl-end --
+COMMENT ON COLUMN public.posts.edited_at IS E'when the content was last changed, earlier versions are in post_revisions';
+-- ddl-end --
+
+-- object: public.post_revisions | type: TABLE --
+-- DROP TABLE IF EXISTS public.post_revisions CASCADE;
+CREATE TABLE public.post_revisions (
+  id uuid NOT NULL,
+  post_id uuid NOT NULL,
+  content jsonb NOT NULL,
+  revised_at timestamptz NOT NULL,
+  CONSTRAINT post_revisions_pk PRIMARY KEY (id)
+);
+-- ddl-end --
+COMMENT ON COLUMN public.post_revisions.content IS E'content of the post until it was edited at revised_at';
+-- ddl-end --
+
+-- object: post_revisions_post_idx | type: INDEX --
+-- DROP INDEX IF EXISTS public.post_revisions_post_idx CASCADE;
+CREATE INDEX post_revisions_post_idx ON public.post_revisions (post_id, revised_at);
+-- ddl-end --
+
+-- object: post_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.post_revisions DROP CONSTRAINT IF EXISTS post_id_fk CASCADE;
+ALTER TABLE public.post_revisions ADD CONSTRAINT post_id_fk FOREIGN KEY (post_id)
+REFERENCES public.posts (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
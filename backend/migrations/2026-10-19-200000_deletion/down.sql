@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM public.users WHERE id = '00000000-0000-0000-0000-000000000000';
+
+ALTER TABLE public.posts DROP CONSTRAINT IF EXISTS comment_fk CASCADE;
+ALTER TABLE public.posts ADD CONSTRAINT comment_fk FOREIGN KEY (reply_to)
+REFERENCES public.posts (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+
+DROP INDEX IF EXISTS public.users_delete_after_idx CASCADE;
+ALTER TABLE public.users DROP COLUMN IF EXISTS delete_after CASCADE;
+ALTER TABLE public.posts DROP COLUMN IF EXISTS deleted_at CASCADE;
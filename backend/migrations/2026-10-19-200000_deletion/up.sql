@@ -0,0 +1,31 @@
+-- object: deleted_at | type: COLUMN --
+-- ALTER TABLE public.posts DROP COLUMN IF EXISTS deleted_at CASCADE;
+ALTER TABLE public.posts ADD COLUMN deleted_at timestamptz;
+-- ddl-end --
+COMMENT ON COLUMN public.posts.deleted_at IS E'deleted by the author, the row stays so replies keep their parent';
+-- ddl-end --
+
+-- object: delete_after | type: COLUMN --
+-- ALTER TABLE public.users DROP COLUMN IF EXISTS delete_after CASCADE;
+ALTER TABLE public.users ADD COLUMN delete_after timestamptz;
+-- ddl-end --
+COMMENT ON COLUMN public.users.delete_after IS E'the user asked to delete their account, which is removed once this has passed';
+-- ddl-end --
+
+-- object: users_delete_after_idx | type: INDEX --
+-- DROP INDEX IF EXISTS public.users_delete_after_idx CASCADE;
+CREATE INDEX users_delete_after_idx ON public.users (delete_after)
+WHERE delete_after IS NOT NULL;
+-- ddl-end --
+
+-- author of the posts of deleted accounts which replies of other users point to
+INSERT INTO public.users (id, password_hash, handle)
+VALUES ('00000000-0000-0000-0000-000000000000', '', '[deleted]');
+
+-- replies of other users outlive the posts of deleted accounts
+-- object: comment_fk | type: CONSTRAINT --
+ALTER TABLE public.posts DROP CONSTRAINT IF EXISTS comment_fk CASCADE;
+ALTER TABLE public.posts ADD CONSTRAINT comment_fk FOREIGN KEY (reply_to)
+REFERENCES public.posts (id) MATCH SIMPLE
+ON DELETE SET NULL ON UPDATE NO ACTION;
+-- ddl-end --
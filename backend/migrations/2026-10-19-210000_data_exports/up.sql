@@ -0,0 +1,25 @@
+-- object: public.data_exports | type: TABLE --
+-- DROP TABLE IF EXISTS public.data_exports CASCADE;
+CREATE TABLE public.data_exports (
+  id uuid NOT NULL,
+  user_id uuid NOT NULL,
+  requested_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  completed_at timestamptz,
+  failed_at timestamptz,
+  CONSTRAINT data_exports_pk PRIMARY KEY (id)
+);
+-- ddl-end --
+COMMENT ON TABLE public.data_exports IS E'archives of everything stored about a user, the files live on the API server';
+-- ddl-end --
+
+-- object: data_exports_user_idx | type: INDEX --
+-- DROP INDEX IF EXISTS public.data_exports_user_idx CASCADE;
+CREATE INDEX data_exports_user_idx ON public.data_exports (user_id, requested_at);
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.data_exports DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.data_exports ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE CASCADE ON UPDATE NO ACTION;
+-- ddl-end --
@@ -0,0 +1,28 @@
+-- object: public.jobs | type: TABLE --
+-- DROP TABLE IF EXISTS public.jobs CASCADE;
+CREATE TABLE public.jobs (
+  id uuid NOT NULL,
+  kind text NOT NULL,
+  payload jsonb NOT NULL,
+  run_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  attempts smallint NOT NULL DEFAULT 0,
+  max_attempts smallint NOT NULL DEFAULT 5,
+  locked_until timestamptz,
+  last_error text,
+  dead_at timestamptz,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT jobs_pk PRIMARY KEY (id),
+  CONSTRAINT max_attempts_positive CHECK (max_attempts > 0)
+);
+-- ddl-end --
+COMMENT ON TABLE public.jobs IS E'work done in the background by the API servers, jobs are deleted once they succeed unless they recur';
+-- ddl-end --
+COMMENT ON COLUMN public.jobs.locked_until IS E'set while a worker runs the job, other workers may claim it again afterwards';
+-- ddl-end --
+COMMENT ON COLUMN public.jobs.dead_at IS E'set once the job failed max_attempts times, dead jobs are kept for inspection';
+-- ddl-end --
+
+-- object: jobs_run_at_idx | type: INDEX --
+-- DROP INDEX IF EXISTS public.jobs_run_at_idx CASCADE;
+CREATE INDEX jobs_run_at_idx ON public.jobs (run_at) WHERE dead_at IS NULL;
+-- ddl-end --
@@ -0,0 +1,4 @@
+-- object: web_expires_at_idx | type: INDEX --
+-- DROP INDEX IF EXISTS public.web_expires_at_idx CASCADE;
+CREATE INDEX web_expires_at_idx ON public.web (expires_at);
+-- ddl-end --
@@ -0,0 +1,35 @@
+-- object: public.media | type: TABLE --
+-- DROP TABLE IF EXISTS public.media CASCADE;
+CREATE TABLE public.media (
+  id uuid NOT NULL,
+  user_id uuid,
+  key text NOT NULL,
+  content_type text NOT NULL,
+  size bigint NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT media_pk PRIMARY KEY (id),
+  CONSTRAINT one_upload_per_user UNIQUE (user_id,key),
+  CONSTRAINT size_not_negative CHECK (size >= 0)
+);
+-- ddl-end --
+COMMENT ON TABLE public.media IS E'files uploaded by users, stored in the media store under a key derived from their content';
+-- ddl-end --
+COMMENT ON COLUMN public.media.user_id IS E'unset once the uploader removed the upload or deleted their account, the file is removed unless someone else uploaded it too';
+-- ddl-end --
+
+-- object: media_key_idx | type: INDEX --
+-- DROP INDEX IF EXISTS public.media_key_idx CASCADE;
+CREATE INDEX media_key_idx ON public.media (key);
+-- ddl-end --
+
+-- object: media_unowned_idx | type: INDEX --
+-- DROP INDEX IF EXISTS public.media_unowned_idx CASCADE;
+CREATE INDEX media_unowned_idx ON public.media (id) WHERE user_id IS NULL;
+-- ddl-end --
+
+-- object: user_id_fk | type: CONSTRAINT --
+-- ALTER TABLE public.media DROP CONSTRAINT IF EXISTS user_id_fk CASCADE;
+ALTER TABLE public.media ADD CONSTRAINT user_id_fk FOREIGN KEY (user_id)
+REFERENCES public.users (id) MATCH SIMPLE
+ON DELETE SET NULL ON UPDATE NO ACTION;
+-- ddl-end --
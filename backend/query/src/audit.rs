@@ -0,0 +1,224 @@
+//! Append-only log of moderation actions.
+//!
+//! The database rejects updates and deletes of entries, so the log can be trusted to show
+//! everything moderators did.
+
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::QueryError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AuditAction {
+    ResolveReport,
+    DismissReport,
+    HidePost,
+    UnhidePost,
+    SuspendUser,
+    UnsuspendUser,
+    SetRole,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ResolveReport => "resolve_report",
+            Self::DismissReport => "dismiss_report",
+            Self::HidePost => "hide_post",
+            Self::UnhidePost => "unhide_post",
+            Self::SuspendUser => "suspend_user",
+            Self::UnsuspendUser => "unsuspend_user",
+            Self::SetRole => "set_role",
+        }
+    }
+
+    pub fn parse(action: &str) -> Option<Self> {
+        match action {
+            "resolve_report" => Some(Self::ResolveReport),
+            "dismiss_report" => Some(Self::DismissReport),
+            "hide_post" => Some(Self::HidePost),
+            "unhide_post" => Some(Self::UnhidePost),
+            "suspend_user" => Some(Self::SuspendUser),
+            "unsuspend_user" => Some(Self::UnsuspendUser),
+            "set_role" => Some(Self::SetRole),
+            _ => None,
+        }
+    }
+}
+
+/// Action to record. Only the targets the action applies to are set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewEntry {
+    /// `None` for actions taken from the command line.
+    pub actor_id: Option<Uuid>,
+    pub action: AuditAction,
+    pub user_id: Option<Uuid>,
+    pub post_id: Option<Uuid>,
+    pub report_id: Option<Uuid>,
+    pub details: Value,
+}
+
+impl NewEntry {
+    pub fn new(actor_id: Option<Uuid>, action: AuditAction) -> Self {
+        Self {
+            actor_id,
+            action,
+            user_id: None,
+            post_id: None,
+            report_id: None,
+            details: Value::Object(Default::default()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub id: Uuid,
+    pub actor_id: Option<Uuid>,
+    /// `None` for actions taken from the command line, or by users who don't exist anymore.
+    pub actor_handle: Option<String>,
+    pub action: AuditAction,
+    pub user_id: Option<Uuid>,
+    pub post_id: Option<Uuid>,
+    pub report_id: Option<Uuid>,
+    pub details: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Queryable)]
+struct EntryRow {
+    id: Uuid,
+    actor_id: Option<Uuid>,
+    actor_handle: Option<String>,
+    action: String,
+    user_id: Option<Uuid>,
+    post_id: Option<Uuid>,
+    report_id: Option<Uuid>,
+    details: Value,
+    created_at: DateTime<Utc>,
+}
+
+/// Position in the log. Pages continue before the last entry of the previous page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+/// Appends an entry to the log. Call it in the transaction which takes the action, so actions
+/// are never taken without being recorded.
+pub(crate) fn record(conn: &mut PgConnection, entry: &NewEntry) -> Result<Uuid, QueryError> {
+    use crate::schema::audit_log::{self, columns};
+
+    let id = Uuid::new_v4();
+    diesel::insert_into(audit_log::table)
+        .values((
+            columns::id.eq(id),
+            columns::actor_id.eq(entry.actor_id),
+            columns::action.eq(entry.action.as_str()),
+            columns::user_id.eq(entry.user_id),
+            columns::post_id.eq(entry.post_id),
+            columns::report_id.eq(entry.report_id),
+            columns::details.eq(&entry.details),
+        ))
+        .execute(conn)?;
+    Ok(id)
+}
+
+/// Entries of the log, newest first, starting before `before`.
+pub fn list(
+    conn: &mut PgConnection,
+    before: Option<Cursor>,
+    limit: i64,
+) -> Result<Vec<Entry>, QueryError> {
+    use crate::schema::{audit_log as a, users};
+
+    let mut query = a::table
+        .left_join(users::table.on(a::actor_id.eq(users::id.nullable())))
+        .select((
+            a::id,
+            a::actor_id,
+            users::handle.nullable(),
+            a::action,
+            a::user_id,
+            a::post_id,
+            a::report_id,
+            a::details,
+            a::created_at,
+        ))
+        .order((a::created_at.desc(), a::id.desc()))
+        .limit(limit)
+        .into_boxed();
+    if let Some(before) = before {
+        query = query.filter(
+            a::created_at
+                .lt(before.created_at)
+                .or(a::created_at.eq(before.created_at).and(a::id.lt(before.id))),
+        );
+    }
+
+    let rows: Vec<EntryRow> = query.load(conn)?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            Some(Entry {
+                id: row.id,
+                actor_id: row.actor_id,
+                actor_handle: row.actor_handle,
+                // the check constraint only allows known actions
+                action: AuditAction::parse(&row.action)?,
+                user_id: row.user_id,
+                post_id: row.post_id,
+                report_id: row.report_id,
+                details: row.details,
+                created_at: row.created_at,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db::new_connection;
+    use crate::user::tests::new_user;
+
+    #[test]
+    fn parses_actions() {
+        for action in [
+            AuditAction::ResolveReport,
+            AuditAction::DismissReport,
+            AuditAction::HidePost,
+            AuditAction::UnhidePost,
+            AuditAction::SuspendUser,
+            AuditAction::UnsuspendUser,
+            AuditAction::SetRole,
+        ] {
+            assert_eq!(AuditAction::parse(action.as_str()), Some(action));
+        }
+        assert_eq!(AuditAction::parse("delete_everything"), None);
+    }
+
+    #[test]
+    fn rejects_changes_to_entries() {
+        use crate::schema::audit_log;
+
+        let mut conn = new_connection();
+        let moderator = new_user(&mut conn, "moderator");
+        let mut entry = NewEntry::new(Some(moderator), AuditAction::HidePost);
+        entry.post_id = Some(Uuid::new_v4());
+        let id = record(&mut conn, &entry).unwrap();
+
+        let log = list(&mut conn, None, 10).unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].id, id);
+        assert_eq!(log[0].actor_handle.as_deref(), Some("moderator"));
+
+        let deleted =
+            conn.transaction(|conn| diesel::delete(audit_log::table.find(id)).execute(conn));
+        assert!(deleted.is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! Blocks between users.
+//!
+//! A block removes the follows and follow requests between both users and keeps them from
+//! interacting: neither can follow, reply to, message or boost the other, and neither sees content
+//! of the other. Queries written in SQL use the `blocked_between` and `hidden_from` database
+//! functions for this, and other queries use [`hidden_from()`].
+
+use diesel::prelude::*;
+use diesel::sql_types::Uuid as SqlUuid;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::QueryError;
+
+sql_function! {
+    /// Whether content of `author` is hidden from `viewer` by a block or a mute.
+    fn hidden_from(viewer: SqlUuid, author: SqlUuid) -> Bool;
+}
+
+/// Makes `user_id` block `blocked_id`. Blocking someone twice has no effect.
+pub fn block(conn: &mut PgConnection, user_id: Uuid, blocked_id: Uuid) -> Result<(), QueryError> {
+    use crate::schema::{blocks, follow_requests, followers};
+
+    conn.transaction(|conn| {
+        diesel::insert_into(blocks::table)
+            .values((
+                blocks::user_id.eq(user_id),
+                blocks::blocked_id.eq(blocked_id),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        diesel::delete(
+            followers::table.filter(
+                (followers::user_id
+                    .eq(user_id)
+                    .and(followers::follows.eq(blocked_id)))
+                .or(followers::user_id
+                    .eq(blocked_id)
+                    .and(followers::follows.eq(user_id))),
+            ),
+        )
+        .execute(conn)?;
+        diesel::delete(
+            follow_requests::table.filter(
+                (follow_requests::user_id
+                    .eq(user_id)
+                    .and(follow_requests::follows.eq(blocked_id)))
+                .or(follow_requests::user_id
+                    .eq(blocked_id)
+                    .and(follow_requests::follows.eq(user_id))),
+            ),
+        )
+        .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+pub fn unblock(conn: &mut PgConnection, user_id: Uuid, blocked_id: Uuid) -> Result<(), QueryError> {
+    use crate::schema::blocks::dsl;
+
+    diesel::delete(
+        dsl::blocks
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::blocked_id.eq(blocked_id)),
+    )
+    .execute(conn)?;
+
+    Ok(())
+}
+
+/// Whether either user blocked the other.
+pub fn is_blocked_between(conn: &mut PgConnection, a: Uuid, b: Uuid) -> Result<bool, QueryError> {
+    use crate::schema::blocks::dsl;
+
+    Ok(diesel::select(diesel::dsl::exists(
+        dsl::blocks.filter(
+            (dsl::user_id.eq(a).and(dsl::blocked_id.eq(b)))
+                .or(dsl::user_id.eq(b).and(dsl::blocked_id.eq(a))),
+        ),
+    ))
+    .get_result(conn)?)
+}
+
+/// Fails with [`QueryError::Blocked`] if either user blocked the other.
+pub fn ensure_not_blocked(conn: &mut PgConnection, a: Uuid, b: Uuid) -> Result<(), QueryError> {
+    if is_blocked_between(conn, a, b)? {
+        Err(QueryError::Blocked)
+    } else {
+        Ok(())
+    }
+}
+
+/// Ids and handles of the users `user_id` blocked, most recent first.
+pub fn blocked_users(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<Vec<(Uuid, String)>, QueryError> {
+    use crate::schema::{blocks, users};
+
+    Ok(blocks::table
+        .inner_join(users::table.on(users::id.eq(blocks::blocked_id)))
+        .filter(blocks::user_id.eq(user_id))
+        .order(blocks::created_at.desc())
+        .select((users::id, users::handle))
+        .load(conn)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::follow::{follow, follower_ids};
+    use crate::test_db::new_connection;
+    use crate::user::tests::new_user;
+
+    #[test]
+    fn removes_follows_in_both_directions() {
+        let mut conn = new_connection();
+        let alice = new_user(&mut conn, "alice");
+        let bob = new_user(&mut conn, "bob");
+
+        follow(&mut conn, alice, bob).unwrap();
+        follow(&mut conn, bob, alice).unwrap();
+        block(&mut conn, alice, bob).unwrap();
+
+        assert!(follower_ids(&mut conn, alice).unwrap().is_empty());
+        assert!(follower_ids(&mut conn, bob).unwrap().is_empty());
+        assert!(is_blocked_between(&mut conn, bob, alice).unwrap());
+        assert!(matches!(
+            follow(&mut conn, bob, alice),
+            Err(QueryError::Blocked)
+        ));
+        assert_eq!(blocked_users(&mut conn, alice).unwrap().len(), 1);
+
+        unblock(&mut conn, alice, bob).unwrap();
+        assert!(!is_blocked_between(&mut conn, alice, bob).unwrap());
+        follow(&mut conn, bob, alice).unwrap();
+    }
+}
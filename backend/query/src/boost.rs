@@ -0,0 +1,124 @@
+//! Boosts, which share a post of someone else with the followers of the booster.
+
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::notification::{self, NotificationKind};
+use crate::{block, post, QueryError};
+
+/// Makes `user_id` boost post `post_id`. The author is notified about the first boost of each
+/// user. Boosting a post twice has no further effect. Returns the author of the post.
+///
+/// Only public and unlisted posts can be boosted. Fails with [`QueryError::NotFound`] for other
+/// posts, posts `user_id` can't see and deleted posts, and with [`QueryError::Blocked`] if the
+/// author blocked `user_id` or was blocked by them.
+pub fn boost(conn: &mut PgConnection, user_id: Uuid, post_id: Uuid) -> Result<Uuid, QueryError> {
+    use crate::schema::{boosts, posts};
+
+    conn.transaction(|conn| {
+        let (author_id, visibility, direct_message_to, deleted_at): (
+            Uuid,
+            String,
+            Option<Uuid>,
+            Option<DateTime<Utc>>,
+        ) = posts::table
+            .find(post_id)
+            .select((
+                posts::user_id,
+                posts::visibility,
+                posts::direct_message_to,
+                posts::deleted_at,
+            ))
+            .get_result(conn)?;
+        block::ensure_not_blocked(conn, user_id, author_id)?;
+        let shareable = visibility != post::Visibility::Followers.as_str()
+            && direct_message_to.is_none()
+            && deleted_at.is_none();
+        if !shareable || !post::is_visible_to(conn, user_id, post_id)? {
+            return Err(QueryError::NotFound);
+        }
+
+        let inserted = diesel::insert_into(boosts::table)
+            .values((boosts::post_id.eq(post_id), boosts::user_id.eq(user_id)))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        if inserted > 0 {
+            notification::new(
+                conn,
+                author_id,
+                user_id,
+                NotificationKind::Boost,
+                Some(post_id),
+            )?;
+        }
+        Ok(author_id)
+    })
+}
+
+/// Takes back the boost of post `post_id` by `user_id`. Does nothing if there is none.
+pub fn unboost(conn: &mut PgConnection, user_id: Uuid, post_id: Uuid) -> Result<(), QueryError> {
+    use crate::schema::boosts;
+
+    diesel::delete(boosts::table.find((post_id, user_id))).execute(conn)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post::tests::new_public_post;
+    use crate::test_db::new_connection;
+    use crate::user::tests::new_user;
+
+    #[test]
+    fn notifies_authors_of_first_boosts() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let reader = new_user(&mut conn, "reader");
+        let post_id = new_public_post(&mut conn, author, "hello");
+
+        assert_eq!(boost(&mut conn, reader, post_id).unwrap(), author);
+        boost(&mut conn, reader, post_id).unwrap();
+        unboost(&mut conn, reader, post_id).unwrap();
+        unboost(&mut conn, reader, post_id).unwrap();
+
+        let inbox = notification::list(&mut conn, author, None, 10).unwrap();
+        assert_eq!(inbox.len(), 1);
+        assert_eq!(inbox[0].kind, NotificationKind::Boost);
+        assert_eq!(inbox[0].actor_id, reader);
+        assert_eq!(inbox[0].post_id, Some(post_id));
+    }
+
+    #[test]
+    fn rejects_boosts_across_blocks_and_of_followers_only_posts() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let reader = new_user(&mut conn, "reader");
+        let post_id = crate::post::new(
+            &mut conn,
+            &crate::post::NewPost {
+                user_id: author,
+                content: serde_json::json!({ "Chat": { "message": "friends only" } }),
+                time_posted: Utc::now(),
+                direct_message_to: None,
+                reply_to: None,
+                visibility: post::Visibility::Followers,
+            },
+        )
+        .unwrap();
+        crate::follow::follow(&mut conn, reader, author).unwrap();
+        assert!(matches!(
+            boost(&mut conn, reader, post_id),
+            Err(QueryError::NotFound)
+        ));
+
+        let public_id = new_public_post(&mut conn, author, "hello");
+        block::block(&mut conn, author, reader).unwrap();
+        assert!(matches!(
+            boost(&mut conn, reader, public_id),
+            Err(QueryError::Blocked)
+        ));
+    }
+}
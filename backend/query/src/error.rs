@@ -22,6 +22,21 @@ pub enum QueryError {
 
     #[error("not found")]
     NotFound,
+
+    #[error("blocked")]
+    Blocked,
+
+    #[error("the post can't be edited anymore")]
+    EditWindowClosed,
+
+    #[error("poll choices can't be changed once there are votes")]
+    PollHasVotes,
+
+    #[error("the media quota is used up")]
+    QuotaExceeded,
+
+    #[error("the job was claimed by another worker")]
+    LeaseLost,
 }
 
 impl From<DieselError> for QueryError {
@@ -0,0 +1,347 @@
+//! Exports of everything stored about a user.
+//!
+//! The data is collected as one JSON document per file of the archive, see [`collect`]. Media
+//! like profile images stay references to where they are stored instead of being copied.
+
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Jsonb, Uuid as SqlUuid};
+use diesel::PgConnection;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::job::{self, Task};
+use crate::QueryError;
+
+#[derive(Clone, Debug, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::data_exports)]
+pub struct DataExport {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub requested_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub failed_at: Option<DateTime<Utc>>,
+}
+
+/// Files of an archive with the query producing their content. Every query takes the id of the
+/// user as `$1` and returns a single `data` column.
+const FILES: &[(&str, &str)] = &[
+    (
+        "profile.json",
+        "SELECT to_jsonb(u) AS data FROM ( \
+             SELECT id, handle, display_name, email, email_confirmed, profile_image, private, \
+                    role, created_at \
+             FROM users WHERE id = $1) u",
+    ),
+    (
+        "posts.json",
+        "SELECT coalesce(jsonb_agg(p ORDER BY p.time_posted), '[]') AS data FROM ( \
+             SELECT id, content, time_posted, reply_to, visibility, edited_at, deleted_at, \
+                    hidden_at \
+             FROM posts WHERE user_id = $1 AND direct_message_to IS NULL) p",
+    ),
+    (
+        "direct_messages.json",
+        "SELECT coalesce(jsonb_agg(m ORDER BY m.time_posted), '[]') AS data FROM ( \
+             SELECT p.id, p.user_id AS sender_id, s.handle AS sender_handle, \
+                    p.direct_message_to AS recipient_id, r.handle AS recipient_handle, \
+                    p.content, p.time_posted \
+             FROM posts p \
+             JOIN users s ON s.id = p.user_id \
+             JOIN users r ON r.id = p.direct_message_to \
+             WHERE p.user_id = $1 OR p.direct_message_to = $1) m",
+    ),
+    (
+        "reactions.json",
+        "SELECT coalesce(jsonb_agg(r ORDER BY r.created_at), '[]') AS data FROM ( \
+             SELECT post_id, like_status, reaction, created_at \
+             FROM reactions WHERE user_id = $1) r",
+    ),
+    (
+        "bookmarks.json",
+        "SELECT coalesce(jsonb_agg(b ORDER BY b.created_at), '[]') AS data FROM ( \
+             SELECT post_id, created_at FROM bookmarks WHERE user_id = $1) b",
+    ),
+    (
+        "poll_votes.json",
+        "SELECT coalesce(jsonb_agg(v ORDER BY v.created_at), '[]') AS data FROM ( \
+             SELECT v.post_id, v.choice_id, c.choice, v.created_at \
+             FROM poll_votes v JOIN poll_choices c ON c.id = v.choice_id \
+             WHERE v.user_id = $1) v",
+    ),
+    (
+        "follows.json",
+        "SELECT jsonb_build_object( \
+             'following', (SELECT coalesce(jsonb_agg(jsonb_build_object( \
+                                'user_id', u.id, 'handle', u.handle) ORDER BY u.handle), '[]') \
+                           FROM followers f JOIN users u ON u.id = f.follows \
+                           WHERE f.user_id = $1), \
+             'followers', (SELECT coalesce(jsonb_agg(jsonb_build_object( \
+                                'user_id', u.id, 'handle', u.handle) ORDER BY u.handle), '[]') \
+                           FROM followers f JOIN users u ON u.id = f.user_id \
+                           WHERE f.follows = $1) \
+         ) AS data",
+    ),
+    (
+        "follow_requests.json",
+        "SELECT jsonb_build_object( \
+             'sent', (SELECT coalesce(jsonb_agg(jsonb_build_object( \
+                           'user_id', u.id, 'handle', u.handle, 'created_at', r.created_at) \
+                           ORDER BY r.created_at), '[]') \
+                      FROM follow_requests r JOIN users u ON u.id = r.follows \
+                      WHERE r.user_id = $1), \
+             'received', (SELECT coalesce(jsonb_agg(jsonb_build_object( \
+                               'user_id', u.id, 'handle', u.handle, 'created_at', r.created_at) \
+                               ORDER BY r.created_at), '[]') \
+                          FROM follow_requests r JOIN users u ON u.id = r.user_id \
+                          WHERE r.follows = $1) \
+         ) AS data",
+    ),
+    (
+        "blocks.json",
+        "SELECT coalesce(jsonb_agg(b ORDER BY b.created_at), '[]') AS data FROM ( \
+             SELECT u.id AS user_id, u.handle, b.created_at \
+             FROM blocks b JOIN users u ON u.id = b.blocked_id \
+             WHERE b.user_id = $1) b",
+    ),
+    (
+        "mutes.json",
+        "SELECT coalesce(jsonb_agg(m ORDER BY m.created_at), '[]') AS data FROM ( \
+             SELECT u.id AS user_id, u.handle, m.created_at \
+             FROM mutes m JOIN users u ON u.id = m.muted_id \
+             WHERE m.user_id = $1) m",
+    ),
+    (
+        "notifications.json",
+        "SELECT coalesce(jsonb_agg(n ORDER BY n.created_at), '[]') AS data FROM ( \
+             SELECT n.id, n.kind, n.actor_id, u.handle AS actor_handle, n.post_id, n.read_at, \
+                    n.created_at \
+             FROM notifications n JOIN users u ON u.id = n.actor_id \
+             WHERE n.user_id = $1) n",
+    ),
+    (
+        "reports.json",
+        "SELECT coalesce(jsonb_agg(r ORDER BY r.created_at), '[]') AS data FROM ( \
+             SELECT r.id, r.user_id AS reported_id, u.handle AS reported_handle, r.post_id, \
+                    r.reason, r.details, r.status, r.resolved_at, r.created_at \
+             FROM reports r JOIN users u ON u.id = r.user_id \
+             WHERE r.reporter_id = $1) r",
+    ),
+    (
+        "media.json",
+        "SELECT coalesce(jsonb_agg(m ORDER BY m.created_at), '[]') AS data FROM ( \
+             SELECT id, key, content_type, size, created_at FROM media WHERE user_id = $1) m",
+    ),
+    (
+        "sessions.json",
+        "SELECT coalesce(jsonb_agg(s ORDER BY s.created_at), '[]') AS data FROM ( \
+             SELECT id, created_at, expires_at, fingerprint FROM web WHERE user_id = $1) s",
+    ),
+];
+
+#[derive(QueryableByName)]
+struct FileData {
+    #[diesel(sql_type = Jsonb)]
+    data: Value,
+}
+
+/// Starts a new export for `user_id`, unless they already requested one at or after `since`.
+/// Exports which failed don't count. The archive is written by a [`Task::BuildExport`] job.
+///
+/// Returns the id of the new export.
+pub fn request(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    since: DateTime<Utc>,
+) -> Result<Option<Uuid>, QueryError> {
+    use crate::schema::{data_exports, users};
+
+    conn.transaction(|conn| {
+        // concurrent requests of the same user wait here, so only one of them gets through
+        users::table
+            .find(user_id)
+            .select(users::id)
+            .for_update()
+            .get_result::<Uuid>(conn)?;
+
+        let recent = diesel::select(diesel::dsl::exists(
+            data_exports::table
+                .filter(data_exports::user_id.eq(user_id))
+                .filter(data_exports::requested_at.ge(since))
+                .filter(data_exports::failed_at.is_null()),
+        ))
+        .get_result(conn)?;
+        if recent {
+            return Ok(None);
+        }
+
+        let id = Uuid::new_v4();
+        diesel::insert_into(data_exports::table)
+            .values((
+                data_exports::id.eq(id),
+                data_exports::user_id.eq(user_id),
+                data_exports::requested_at.eq(Utc::now()),
+            ))
+            .execute(conn)?;
+        let task = Task::BuildExport {
+            export_id: id,
+            user_id,
+        };
+        job::enqueue(conn, &task, Utc::now())?;
+        Ok(Some(id))
+    })
+}
+
+pub fn find(conn: &mut PgConnection, export_id: Uuid) -> Result<DataExport, QueryError> {
+    use crate::schema::data_exports;
+
+    Ok(data_exports::table
+        .find(export_id)
+        .select(DataExport::as_select())
+        .get_result(conn)?)
+}
+
+/// The most recently requested export of `user_id`.
+pub fn latest(conn: &mut PgConnection, user_id: Uuid) -> Result<Option<DataExport>, QueryError> {
+    use crate::schema::data_exports;
+
+    Ok(data_exports::table
+        .filter(data_exports::user_id.eq(user_id))
+        .order(data_exports::requested_at.desc())
+        .select(DataExport::as_select())
+        .first(conn)
+        .optional()?)
+}
+
+/// Marks the archive of `export_id` as ready for download.
+pub fn complete(conn: &mut PgConnection, export_id: Uuid) -> Result<(), QueryError> {
+    use crate::schema::data_exports;
+
+    diesel::update(data_exports::table.find(export_id))
+        .set(data_exports::completed_at.eq(Utc::now()))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn fail(conn: &mut PgConnection, export_id: Uuid) -> Result<(), QueryError> {
+    use crate::schema::data_exports;
+
+    diesel::update(data_exports::table.find(export_id))
+        .set(data_exports::failed_at.eq(Utc::now()))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Deletes the exports requested before `before`, returning their ids.
+pub fn delete_older_than(
+    conn: &mut PgConnection,
+    before: DateTime<Utc>,
+) -> Result<Vec<Uuid>, QueryError> {
+    use crate::schema::data_exports;
+
+    Ok(
+        diesel::delete(data_exports::table.filter(data_exports::requested_at.lt(before)))
+            .returning(data_exports::id)
+            .get_results(conn)?,
+    )
+}
+
+/// The ids in `export_ids` which belong to an export.
+pub fn existing(conn: &mut PgConnection, export_ids: &[Uuid]) -> Result<Vec<Uuid>, QueryError> {
+    use crate::schema::data_exports;
+
+    Ok(data_exports::table
+        .filter(data_exports::id.eq_any(export_ids))
+        .select(data_exports::id)
+        .load(conn)?)
+}
+
+/// Everything stored about `user_id`, as file names with their JSON content. Password hashes and
+/// other secrets are left out.
+pub fn collect(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<Vec<(&'static str, Value)>, QueryError> {
+    FILES
+        .iter()
+        .map(|&(name, query)| {
+            let file: FileData = diesel::sql_query(query)
+                .bind::<SqlUuid, _>(user_id)
+                .get_result(conn)?;
+            Ok((name, file.data))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post::tests::new_public_post;
+    use crate::test_db::new_connection;
+    use crate::user::tests::new_user;
+
+    #[test]
+    fn collects_user_data() {
+        let mut conn = new_connection();
+        let user = new_user(&mut conn, "user");
+        let friend = new_user(&mut conn, "friend");
+        new_public_post(&mut conn, user, "hello");
+        new_public_post(&mut conn, friend, "not mine");
+        let stranger = new_user(&mut conn, "stranger");
+        crate::follow::follow(&mut conn, user, friend).unwrap();
+        crate::follow::follow(&mut conn, friend, user).unwrap();
+        crate::report::new(
+            &mut conn,
+            user,
+            crate::report::Target::User(stranger),
+            crate::report::ReportReason::Spam,
+            "",
+        )
+        .unwrap();
+        crate::block::block(&mut conn, user, stranger).unwrap();
+        crate::post::new(
+            &mut conn,
+            &crate::post::NewPost {
+                user_id: friend,
+                content: serde_json::json!({ "Chat": { "message": "psst" } }),
+                time_posted: Utc::now(),
+                direct_message_to: Some(user),
+                reply_to: None,
+                visibility: Default::default(),
+            },
+        )
+        .unwrap();
+
+        let files: std::collections::HashMap<_, _> =
+            collect(&mut conn, user).unwrap().into_iter().collect();
+        assert_eq!(files.len(), FILES.len());
+        assert_eq!(files["profile.json"]["handle"], "user");
+        assert!(files["profile.json"].get("password_hash").is_none());
+        assert_eq!(files["posts.json"].as_array().unwrap().len(), 1);
+        assert_eq!(files["direct_messages.json"].as_array().unwrap().len(), 1);
+        assert_eq!(files["follows.json"]["following"][0]["handle"], "friend");
+        assert!(files["bookmarks.json"].as_array().unwrap().is_empty());
+        assert_eq!(files["blocks.json"][0]["handle"], "stranger");
+        assert_eq!(files["reports.json"][0]["reported_handle"], "stranger");
+        assert_eq!(files["notifications.json"][0]["kind"], "follow");
+        assert_eq!(files["notifications.json"][0]["actor_handle"], "friend");
+        assert!(files["mutes.json"].as_array().unwrap().is_empty());
+        assert!(files["media.json"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn allows_one_request_per_period() {
+        let mut conn = new_connection();
+        let user = new_user(&mut conn, "user");
+        let since = Utc::now() - chrono::Duration::days(1);
+
+        let first = request(&mut conn, user, since).unwrap().unwrap();
+        assert!(request(&mut conn, user, since).unwrap().is_none());
+        assert_eq!(latest(&mut conn, user).unwrap().unwrap().id, first);
+
+        fail(&mut conn, first).unwrap();
+        let second = request(&mut conn, user, since).unwrap().unwrap();
+        complete(&mut conn, second).unwrap();
+        assert!(find(&mut conn, second).unwrap().completed_at.is_some());
+    }
+}
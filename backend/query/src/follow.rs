@@ -0,0 +1,273 @@
+//! Follows between users.
+//!
+//! Following a private user only creates a follow request, which becomes a follow once the
+//! private user approves it. Posts of private users are only visible to their followers, see
+//! [`post`](crate::post).
+
+use diesel::prelude::*;
+use diesel::sql_types::Uuid as SqlUuid;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::notification::{self, NotificationKind};
+use crate::{block, QueryError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FollowStatus {
+    Following,
+    /// Waiting for the followed user to approve the request.
+    Requested,
+}
+
+/// Makes `user_id` follow `follows`, who gets notified. If `follows` is private, a follow request
+/// is created instead. Following someone twice has no effect.
+///
+/// Fails with [`QueryError::Blocked`] if either user blocked the other.
+pub fn follow(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    follows: Uuid,
+) -> Result<FollowStatus, QueryError> {
+    use crate::schema::{follow_requests, followers, users};
+
+    conn.transaction(|conn| {
+        block::ensure_not_blocked(conn, user_id, follows)?;
+
+        let private: bool = users::table
+            .find(follows)
+            .select(users::private)
+            .get_result(conn)?;
+        if private && user_id != follows && !is_following(conn, user_id, follows)? {
+            let inserted = diesel::insert_into(follow_requests::table)
+                .values((
+                    follow_requests::user_id.eq(user_id),
+                    follow_requests::follows.eq(follows),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+            if inserted > 0 {
+                notification::new(
+                    conn,
+                    follows,
+                    user_id,
+                    NotificationKind::FollowRequest,
+                    None,
+                )?;
+            }
+            return Ok(FollowStatus::Requested);
+        }
+
+        let inserted = diesel::insert_into(followers::table)
+            .values((
+                followers::user_id.eq(user_id),
+                followers::follows.eq(follows),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        if inserted > 0 {
+            notification::new(conn, follows, user_id, NotificationKind::Follow, None)?;
+        }
+        Ok(FollowStatus::Following)
+    })
+}
+
+/// Ends the follow of `user_id`, or withdraws their follow request.
+pub fn unfollow(conn: &mut PgConnection, user_id: Uuid, follows: Uuid) -> Result<(), QueryError> {
+    use crate::schema::{follow_requests, followers};
+
+    conn.transaction(|conn| {
+        diesel::delete(
+            followers::table
+                .filter(followers::user_id.eq(user_id))
+                .filter(followers::follows.eq(follows)),
+        )
+        .execute(conn)?;
+        diesel::delete(
+            follow_requests::table
+                .filter(follow_requests::user_id.eq(user_id))
+                .filter(follow_requests::follows.eq(follows)),
+        )
+        .execute(conn)?;
+        Ok(())
+    })
+}
+
+/// Whether `user_id` follows `follows`. Pending follow requests don't count.
+pub fn is_following(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    follows: Uuid,
+) -> Result<bool, QueryError> {
+    use crate::schema::followers::dsl;
+
+    Ok(diesel::select(diesel::dsl::exists(
+        dsl::followers
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::follows.eq(follows)),
+    ))
+    .get_result(conn)?)
+}
+
+/// Ids of the users following `user_id`.
+pub fn follower_ids(conn: &mut PgConnection, user_id: Uuid) -> Result<Vec<Uuid>, QueryError> {
+    use crate::schema::followers::dsl;
+
+    Ok(dsl::followers
+        .filter(dsl::follows.eq(user_id))
+        .select(dsl::user_id)
+        .load(conn)?)
+}
+
+/// Ids and handles of the users waiting for `user_id` to approve their follow, oldest first.
+pub fn requests(conn: &mut PgConnection, user_id: Uuid) -> Result<Vec<(Uuid, String)>, QueryError> {
+    use crate::schema::{follow_requests, users};
+
+    Ok(follow_requests::table
+        .inner_join(users::table.on(users::id.eq(follow_requests::user_id)))
+        .filter(follow_requests::follows.eq(user_id))
+        .order(follow_requests::created_at)
+        .select((users::id, users::handle))
+        .load(conn)?)
+}
+
+/// Approves or denies the follow request of `requester_id` to `user_id`.
+///
+/// Fails with [`QueryError::NotFound`] if there is no such request.
+pub fn answer_request(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    requester_id: Uuid,
+    approve: bool,
+) -> Result<(), QueryError> {
+    use crate::schema::{follow_requests, followers};
+
+    conn.transaction(|conn| {
+        let deleted = diesel::delete(
+            follow_requests::table
+                .filter(follow_requests::user_id.eq(requester_id))
+                .filter(follow_requests::follows.eq(user_id)),
+        )
+        .execute(conn)?;
+        if deleted == 0 {
+            return Err(QueryError::NotFound);
+        }
+
+        if approve {
+            diesel::insert_into(followers::table)
+                .values((
+                    followers::user_id.eq(requester_id),
+                    followers::follows.eq(user_id),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+        }
+        Ok(())
+    })
+}
+
+/// Makes the posts of `user_id` visible to their followers only, or to everyone again. Pending
+/// follow requests are approved once the user is public.
+pub fn set_private(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    private: bool,
+) -> Result<(), QueryError> {
+    use crate::schema::{follow_requests, users};
+
+    conn.transaction(|conn| {
+        diesel::update(users::table.find(user_id))
+            .set(users::private.eq(private))
+            .execute(conn)?;
+
+        if !private {
+            diesel::sql_query(
+                "INSERT INTO followers (user_id, follows) \
+                 SELECT user_id, follows FROM follow_requests WHERE follows = $1 \
+                 ON CONFLICT DO NOTHING",
+            )
+            .bind::<SqlUuid, _>(user_id)
+            .execute(conn)?;
+            diesel::delete(follow_requests::table.filter(follow_requests::follows.eq(user_id)))
+                .execute(conn)?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post::tests::new_public_post;
+    use crate::test_db::new_connection;
+    use crate::user::tests::new_user;
+
+    #[test]
+    fn lists_followers() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let reader = new_user(&mut conn, "reader");
+
+        follow(&mut conn, reader, author).unwrap();
+        follow(&mut conn, reader, author).unwrap();
+        assert_eq!(follower_ids(&mut conn, author).unwrap(), vec![reader]);
+        assert!(follower_ids(&mut conn, reader).unwrap().is_empty());
+
+        assert_eq!(notification::unread_count(&mut conn, author).unwrap(), 1);
+
+        unfollow(&mut conn, reader, author).unwrap();
+        assert!(follower_ids(&mut conn, author).unwrap().is_empty());
+    }
+
+    #[test]
+    fn approves_follows_of_private_users() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let reader = new_user(&mut conn, "reader");
+        let stranger = new_user(&mut conn, "stranger");
+        new_public_post(&mut conn, author, "#secret");
+        set_private(&mut conn, author, true).unwrap();
+
+        assert_eq!(
+            follow(&mut conn, reader, author).unwrap(),
+            FollowStatus::Requested
+        );
+        follow(&mut conn, stranger, author).unwrap();
+        assert!(follower_ids(&mut conn, author).unwrap().is_empty());
+        assert_eq!(requests(&mut conn, author).unwrap().len(), 2);
+        assert!(crate::tag::timeline(&mut conn, reader, "secret", None, 10)
+            .unwrap()
+            .is_empty());
+
+        answer_request(&mut conn, author, reader, true).unwrap();
+        answer_request(&mut conn, author, stranger, false).unwrap();
+        assert!(matches!(
+            answer_request(&mut conn, author, stranger, true),
+            Err(QueryError::NotFound)
+        ));
+        assert_eq!(follower_ids(&mut conn, author).unwrap(), vec![reader]);
+        assert!(requests(&mut conn, author).unwrap().is_empty());
+
+        assert_eq!(
+            crate::tag::timeline(&mut conn, reader, "secret", None, 10)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(
+            crate::tag::timeline(&mut conn, stranger, "secret", None, 10)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            crate::tag::timeline(&mut conn, author, "secret", None, 10)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        follow(&mut conn, stranger, author).unwrap();
+        set_private(&mut conn, author, false).unwrap();
+        assert_eq!(follower_ids(&mut conn, author).unwrap().len(), 2);
+        assert!(requests(&mut conn, author).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,400 @@
+//! Queue of work done in the background, outside of requests.
+//!
+//! Workers [`claim`] due jobs with `SELECT ... FOR UPDATE SKIP LOCKED`, so every job is run by
+//! one worker at a time even with several server instances. A claimed job is leased to its worker
+//! until `locked_until`. Jobs which succeed are deleted with [`complete`], the others are retried
+//! with [`backoff`] by [`fail`], until they failed `max_attempts` times and are dead-lettered. Dead
+//! jobs stay in the table with their last error. Recurring jobs aren't deleted but run again after
+//! their [`Task::interval`], see [`schedule_recurring`].
+//!
+//! If a worker stops while running a job, e.g. because the server crashed, the job can be claimed
+//! again once its lease is over. Every claim counts as an attempt, so such jobs are dead-lettered
+//! as well eventually. The attempt identifies the lease: [`complete`] and [`fail`] fail with
+//! [`QueryError::LeaseLost`] if the job was claimed again in the meantime, so a worker which took
+//! too long can't undo the work of the next one.
+
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Double, Jsonb, SmallInt, Text, Uuid as SqlUuid};
+use diesel::PgConnection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::QueryError;
+
+const FIRST_RETRY_SECONDS: i64 = 30;
+const MAX_RETRY_SECONDS: i64 = 60 * 60;
+
+/// What a job does. Stored as JSON, so jobs enqueued by older versions of the server must keep
+/// deserializing.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Task {
+    /// Notifies the users mentioned by a scheduled post once it is published, see
+    /// [`crate::post::publish`].
+    PublishPost { post_id: Uuid },
+    /// Writes the archive of a data export, see [`crate::export`].
+    BuildExport { export_id: Uuid, user_id: Uuid },
+    /// Deletes the accounts whose deletion is due, see [`crate::user::purge_deleted`]. Recurring.
+    PurgeDeletedAccounts,
+    /// Deletes expired sessions, see [`crate::session::delete_expired`]. Recurring.
+    DeleteExpiredSessions,
+    /// Sends an email with the mailer of the server, retried while delivery fails.
+    SendEmail {
+        to: String,
+        subject: String,
+        body: String,
+    },
+}
+
+impl Task {
+    /// Copied to the `kind` column, to look at the queue without reading the payloads.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::PublishPost { .. } => "publish_post",
+            Self::BuildExport { .. } => "build_export",
+            Self::PurgeDeletedAccounts => "purge_deleted_accounts",
+            Self::DeleteExpiredSessions => "delete_expired_sessions",
+            Self::SendEmail { .. } => "send_email",
+        }
+    }
+
+    /// Time between the end of a run of a recurring task and the start of the next one, `None`
+    /// for tasks which run once.
+    pub fn interval(&self) -> Option<Duration> {
+        match self {
+            Self::PublishPost { .. } | Self::BuildExport { .. } | Self::SendEmail { .. } => None,
+            Self::PurgeDeletedAccounts | Self::DeleteExpiredSessions => Some(Duration::hours(1)),
+        }
+    }
+}
+
+/// A job claimed by a worker.
+#[derive(Clone, Debug, QueryableByName)]
+pub struct Job {
+    #[diesel(sql_type = SqlUuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = Text)]
+    pub kind: String,
+    #[diesel(sql_type = Jsonb)]
+    pub payload: Value,
+    /// Including the current one.
+    #[diesel(sql_type = SmallInt)]
+    pub attempts: i16,
+    #[diesel(sql_type = SmallInt)]
+    pub max_attempts: i16,
+}
+
+impl Job {
+    pub fn task(&self) -> Result<Task, serde_json::Error> {
+        Task::deserialize(&self.payload)
+    }
+
+    /// Whether earlier attempts already used up all attempts without failing, because their
+    /// workers stopped while running the job. Such jobs should be failed without running them.
+    pub fn is_abandoned(&self) -> bool {
+        self.attempts > self.max_attempts
+    }
+}
+
+/// Adds a job running `task` at `run_at` or as soon as possible after it.
+pub fn enqueue(
+    conn: &mut PgConnection,
+    task: &Task,
+    run_at: DateTime<Utc>,
+) -> Result<Uuid, QueryError> {
+    use crate::schema::jobs;
+
+    let payload = serde_json::to_value(task).expect("tasks serialize to JSON");
+    let id = Uuid::new_v4();
+    diesel::insert_into(jobs::table)
+        .values((
+            jobs::id.eq(id),
+            jobs::kind.eq(task.kind()),
+            jobs::payload.eq(payload),
+            jobs::run_at.eq(run_at),
+        ))
+        .execute(conn)?;
+    Ok(id)
+}
+
+/// Claims the job which is due the longest, leasing it for `lease`. Jobs which are claimed by
+/// other workers are skipped without waiting for them.
+pub fn claim(conn: &mut PgConnection, lease: Duration) -> Result<Option<Job>, QueryError> {
+    Ok(diesel::sql_query(
+        "UPDATE jobs \
+         SET attempts = attempts + 1, \
+             locked_until = statement_timestamp() + make_interval(secs => $1) \
+         WHERE id = ( \
+             SELECT id FROM jobs \
+             WHERE dead_at IS NULL \
+               AND run_at <= statement_timestamp() \
+               AND (locked_until IS NULL OR locked_until <= statement_timestamp()) \
+             ORDER BY run_at \
+             LIMIT 1 \
+             FOR UPDATE SKIP LOCKED) \
+         RETURNING id, kind, payload, attempts, max_attempts",
+    )
+    .bind::<Double, _>(lease.num_milliseconds() as f64 / 1000.0)
+    .get_result(conn)
+    .optional()?)
+}
+
+/// Adds a job running the recurring `task` right away, unless there already is one which isn't
+/// dead. Every server instance calls this on startup, so a job lost to dead-lettering comes back
+/// with the next deployment. Returns whether a job was added.
+pub fn schedule_recurring(conn: &mut PgConnection, task: &Task) -> Result<bool, QueryError> {
+    use crate::schema::jobs;
+
+    conn.transaction(|conn| {
+        // instances starting at the same time wait here, so only one of them adds the job
+        diesel::sql_query("SELECT pg_advisory_xact_lock(hashtext('jobs:' || $1))")
+            .bind::<Text, _>(task.kind())
+            .execute(conn)?;
+        let queued = diesel::select(diesel::dsl::exists(
+            jobs::table
+                .filter(jobs::kind.eq(task.kind()))
+                .filter(jobs::dead_at.is_null()),
+        ))
+        .get_result(conn)?;
+        if queued {
+            return Ok(false);
+        }
+        enqueue(conn, task, Utc::now())?;
+        Ok(true)
+    })
+}
+
+/// Deletes a job which succeeded, or queues the next run of a recurring job.
+///
+/// Fails with [`QueryError::LeaseLost`] if `job` was claimed again since.
+pub fn complete(conn: &mut PgConnection, job: &Job) -> Result<(), QueryError> {
+    use crate::schema::jobs;
+
+    let target = jobs::table
+        .find(job.id)
+        .filter(jobs::attempts.eq(job.attempts));
+    let interval = job.task().ok().and_then(|task| task.interval());
+    let updated = match interval {
+        Some(interval) => diesel::update(target)
+            .set((
+                jobs::attempts.eq(0),
+                jobs::locked_until.eq(None::<DateTime<Utc>>),
+                jobs::last_error.eq(None::<String>),
+                jobs::run_at.eq(Utc::now() + interval),
+            ))
+            .execute(conn)?,
+        None => diesel::delete(target).execute(conn)?,
+    };
+    if updated == 0 {
+        return Err(QueryError::LeaseLost);
+    }
+    Ok(())
+}
+
+/// Records that `job` failed with `error`. The job is retried after [`backoff`], unless it
+/// used up its attempts and is dead-lettered.
+///
+/// Returns when the job runs again, `None` if it was dead-lettered. Fails with
+/// [`QueryError::LeaseLost`] if `job` was claimed again since.
+pub fn fail(
+    conn: &mut PgConnection,
+    job: &Job,
+    error: &str,
+) -> Result<Option<DateTime<Utc>>, QueryError> {
+    use crate::schema::jobs;
+
+    let now = Utc::now();
+    let target = jobs::table
+        .find(job.id)
+        .filter(jobs::attempts.eq(job.attempts));
+    let (updated, run_at) = if job.attempts >= job.max_attempts {
+        let updated = diesel::update(target)
+            .set((
+                jobs::locked_until.eq(None::<DateTime<Utc>>),
+                jobs::last_error.eq(error),
+                jobs::dead_at.eq(now),
+            ))
+            .execute(conn)?;
+        (updated, None)
+    } else {
+        let run_at = now + backoff(job.attempts);
+        let updated = diesel::update(target)
+            .set((
+                jobs::locked_until.eq(None::<DateTime<Utc>>),
+                jobs::last_error.eq(error),
+                jobs::run_at.eq(run_at),
+            ))
+            .execute(conn)?;
+        (updated, Some(run_at))
+    };
+    if updated == 0 {
+        return Err(QueryError::LeaseLost);
+    }
+    Ok(run_at)
+}
+
+/// Time to wait before retrying a job which failed `attempts` times. Doubles with every attempt,
+/// up to an hour.
+pub fn backoff(attempts: i16) -> Duration {
+    let doublings = u32::try_from(attempts.saturating_sub(1))
+        .unwrap_or(0)
+        .min(16);
+    let seconds = FIRST_RETRY_SECONDS.saturating_mul(1 << doublings);
+    Duration::seconds(seconds.min(MAX_RETRY_SECONDS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db::new_connection;
+
+    fn make_due(conn: &mut PgConnection, job_id: Uuid) {
+        use crate::schema::jobs;
+
+        diesel::update(jobs::table.find(job_id))
+            .set(jobs::run_at.eq(Utc::now() - Duration::seconds(1)))
+            .execute(conn)
+            .unwrap();
+    }
+
+    #[test]
+    fn backs_off_exponentially() {
+        assert_eq!(backoff(1), Duration::seconds(30));
+        assert_eq!(backoff(2), Duration::seconds(60));
+        assert_eq!(backoff(3), Duration::seconds(120));
+        assert_eq!(backoff(8), Duration::hours(1));
+        assert_eq!(backoff(i16::MAX), Duration::hours(1));
+    }
+
+    #[test]
+    fn tags_payloads_with_their_kind() {
+        let task = Task::SendEmail {
+            to: "user@example.com".to_string(),
+            subject: "Hello".to_string(),
+            body: "Welcome!".to_string(),
+        };
+        let payload = serde_json::to_value(&task).unwrap();
+        assert_eq!(payload["kind"], task.kind());
+        assert_eq!(Task::deserialize(&payload).unwrap(), task);
+    }
+
+    #[test]
+    fn runs_due_jobs_once() {
+        let mut conn = new_connection();
+        let later = Task::PublishPost {
+            post_id: Uuid::new_v4(),
+        };
+        let now = Task::PublishPost {
+            post_id: Uuid::new_v4(),
+        };
+        enqueue(&mut conn, &later, Utc::now() + Duration::hours(1)).unwrap();
+        let id = enqueue(&mut conn, &now, Utc::now()).unwrap();
+
+        let job = claim(&mut conn, Duration::minutes(5)).unwrap().unwrap();
+        assert_eq!(job.id, id);
+        assert_eq!(job.kind, "publish_post");
+        assert_eq!(job.task().unwrap(), now);
+        assert_eq!(job.attempts, 1);
+        // leased to the first worker
+        assert!(claim(&mut conn, Duration::minutes(5)).unwrap().is_none());
+
+        complete(&mut conn, &job).unwrap();
+        assert!(claim(&mut conn, Duration::minutes(5)).unwrap().is_none());
+    }
+
+    #[test]
+    fn reschedules_recurring_jobs() {
+        let mut conn = new_connection();
+        let task = Task::DeleteExpiredSessions;
+        assert!(schedule_recurring(&mut conn, &task).unwrap());
+        assert!(!schedule_recurring(&mut conn, &task).unwrap());
+
+        let job = claim(&mut conn, Duration::minutes(5)).unwrap().unwrap();
+        assert_eq!(job.task().unwrap(), task);
+        complete(&mut conn, &job).unwrap();
+        // the next run waits for the interval
+        assert!(claim(&mut conn, Duration::minutes(5)).unwrap().is_none());
+        assert!(!schedule_recurring(&mut conn, &task).unwrap());
+
+        make_due(&mut conn, job.id);
+        let job = claim(&mut conn, Duration::minutes(5)).unwrap().unwrap();
+        assert_eq!(job.attempts, 1);
+    }
+
+    #[test]
+    fn rejects_results_of_lost_leases() {
+        let mut conn = new_connection();
+        let task = Task::PublishPost {
+            post_id: Uuid::new_v4(),
+        };
+        enqueue(&mut conn, &task, Utc::now()).unwrap();
+
+        let slow = claim(&mut conn, Duration::zero()).unwrap().unwrap();
+        let next = claim(&mut conn, Duration::minutes(5)).unwrap().unwrap();
+        assert!(matches!(
+            complete(&mut conn, &slow),
+            Err(QueryError::LeaseLost)
+        ));
+        assert!(matches!(
+            fail(&mut conn, &slow, "too slow"),
+            Err(QueryError::LeaseLost)
+        ));
+        complete(&mut conn, &next).unwrap();
+    }
+
+    #[test]
+    fn dead_letters_jobs_which_keep_failing() {
+        let mut conn = new_connection();
+        let task = Task::BuildExport {
+            export_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        };
+        let id = enqueue(&mut conn, &task, Utc::now()).unwrap();
+
+        let mut retries = 0;
+        loop {
+            let job = claim(&mut conn, Duration::minutes(5)).unwrap().unwrap();
+            match fail(&mut conn, &job, "broken").unwrap() {
+                Some(run_at) => {
+                    assert!(run_at > Utc::now());
+                    assert!(claim(&mut conn, Duration::minutes(5)).unwrap().is_none());
+                    retries += 1;
+                    make_due(&mut conn, id);
+                }
+                None => break,
+            }
+        }
+        assert_eq!(retries, 4);
+        assert!(claim(&mut conn, Duration::minutes(5)).unwrap().is_none());
+
+        use crate::schema::jobs;
+        let (last_error, dead_at): (Option<String>, Option<DateTime<Utc>>) = jobs::table
+            .find(id)
+            .select((jobs::last_error, jobs::dead_at))
+            .get_result(&mut conn)
+            .unwrap();
+        assert_eq!(last_error.as_deref(), Some("broken"));
+        assert!(dead_at.is_some());
+    }
+
+    #[test]
+    fn reclaims_jobs_after_their_lease() {
+        let mut conn = new_connection();
+        let task = Task::PublishPost {
+            post_id: Uuid::new_v4(),
+        };
+        let id = enqueue(&mut conn, &task, Utc::now()).unwrap();
+
+        for attempt in 1..=5 {
+            let job = claim(&mut conn, Duration::zero()).unwrap().unwrap();
+            assert_eq!(job.id, id);
+            assert_eq!(job.attempts, attempt);
+            assert!(!job.is_abandoned());
+        }
+        let job = claim(&mut conn, Duration::zero()).unwrap().unwrap();
+        assert!(job.is_abandoned());
+        assert_eq!(fail(&mut conn, &job, "abandoned").unwrap(), None);
+    }
+}
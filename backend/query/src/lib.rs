@@ -9,5 +9,27 @@ pub use diesel::result::Error as DieselError;
 pub mod error;
 pub use error::QueryError;
 
+pub mod audit;
+pub mod block;
+pub mod boost;
+pub mod export;
+pub mod follow;
+pub mod job;
+pub mod media;
+pub mod moderation;
+pub mod mute;
+pub mod notification;
+pub mod notify;
+pub mod poll;
+pub mod post;
+pub mod reaction;
+pub mod report;
+pub mod schema;
+pub mod search;
+pub mod session;
+pub mod tag;
+pub mod totp;
+pub mod user;
+
 pub mod util;
 pub use util::{AsyncConnection, AsyncConnectionPool, OwnedAsyncConnection};
@@ -0,0 +1,224 @@
+//! Files uploaded by users.
+//!
+//! The files live in a media store of the API server, under keys derived from their content, so
+//! identical files are stored once. Every upload is recorded here with its size, which counts
+//! towards the quota of the uploader even if someone else uploaded the same file.
+//!
+//! Removed uploads, and those of deleted accounts, lose their uploader and stop counting. Their
+//! files are deleted later by a sweep over the [`orphaned_keys`], which holds the [`lock`] of the
+//! key, so a new upload of the same file can't lose it in between.
+
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Text, Uuid as SqlUuid};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::QueryError;
+
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::media)]
+pub struct Media {
+    pub id: Uuid,
+    /// `None` once the uploader removed the upload or deleted their account.
+    pub user_id: Option<Uuid>,
+    pub key: String,
+    pub content_type: String,
+    pub size: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(QueryableByName)]
+struct Usage {
+    #[diesel(sql_type = BigInt)]
+    used: i64,
+}
+
+/// Records that `user_id` uploaded the file stored at `key`. Uploading the same file twice only
+/// counts once.
+///
+/// Fails with [`QueryError::QuotaExceeded`] if the uploads of the user would take more than
+/// `quota` bytes.
+pub fn add(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    key: &str,
+    content_type: &str,
+    size: i64,
+    quota: i64,
+) -> Result<Media, QueryError> {
+    use crate::schema::{media, users};
+
+    conn.transaction(|conn| {
+        // waits while the file is being deleted, see `lock`
+        diesel::sql_query("SELECT pg_advisory_xact_lock(hashtext('media:' || $1))")
+            .bind::<Text, _>(key)
+            .execute(conn)?;
+        // concurrent uploads of the same user wait here, so they can't exceed the quota together
+        users::table
+            .find(user_id)
+            .select(users::id)
+            .for_update()
+            .get_result::<Uuid>(conn)?;
+
+        let existing = media::table
+            .filter(media::user_id.eq(user_id))
+            .filter(media::key.eq(key))
+            .select(Media::as_select())
+            .get_result(conn)
+            .optional()?;
+        if let Some(existing) = existing {
+            return Ok(existing);
+        }
+
+        if usage(conn, user_id)? + size > quota {
+            return Err(QueryError::QuotaExceeded);
+        }
+        Ok(diesel::insert_into(media::table)
+            .values((
+                media::id.eq(Uuid::new_v4()),
+                media::user_id.eq(user_id),
+                media::key.eq(key),
+                media::content_type.eq(content_type),
+                media::size.eq(size),
+            ))
+            .returning(Media::as_returning())
+            .get_result(conn)?)
+    })
+}
+
+/// Bytes taken by the uploads of `user_id`.
+pub fn usage(conn: &mut PgConnection, user_id: Uuid) -> Result<i64, QueryError> {
+    let usage: Usage = diesel::sql_query(
+        "SELECT coalesce(sum(size), 0)::bigint AS used FROM media WHERE user_id = $1",
+    )
+    .bind::<SqlUuid, _>(user_id)
+    .get_result(conn)?;
+    Ok(usage.used)
+}
+
+/// Removes the upload of `key` by `user_id`. The file is deleted by the sweep over the
+/// [`orphaned_keys`], unless someone uploads it again.
+///
+/// Fails with [`QueryError::NotFound`] if the user didn't upload it.
+pub fn remove(conn: &mut PgConnection, user_id: Uuid, key: &str) -> Result<(), QueryError> {
+    use crate::schema::media;
+
+    let removed = diesel::update(
+        media::table
+            .filter(media::user_id.eq(user_id))
+            .filter(media::key.eq(key)),
+    )
+    .set(media::user_id.eq(None::<Uuid>))
+    .execute(conn)?;
+    if removed == 0 {
+        return Err(QueryError::NotFound);
+    }
+    Ok(())
+}
+
+/// Keys of removed uploads and of uploads of deleted accounts, whose files may be unused.
+pub fn orphaned_keys(conn: &mut PgConnection) -> Result<Vec<String>, QueryError> {
+    use crate::schema::media;
+
+    Ok(media::table
+        .filter(media::user_id.is_null())
+        .select(media::key)
+        .distinct()
+        .load(conn)?)
+}
+
+/// Takes the lock of the file at `key` until [`unlock`], across transactions. [`add`] waits for
+/// it, so nobody uploads the file while it is checked with [`is_uploaded`] and deleted.
+pub fn lock(conn: &mut PgConnection, key: &str) -> Result<(), QueryError> {
+    diesel::sql_query("SELECT pg_advisory_lock(hashtext('media:' || $1))")
+        .bind::<Text, _>(key)
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn unlock(conn: &mut PgConnection, key: &str) -> Result<(), QueryError> {
+    diesel::sql_query("SELECT pg_advisory_unlock(hashtext('media:' || $1))")
+        .bind::<Text, _>(key)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Whether someone has an upload of the file at `key`.
+pub fn is_uploaded(conn: &mut PgConnection, key: &str) -> Result<bool, QueryError> {
+    use crate::schema::media;
+
+    Ok(diesel::select(diesel::dsl::exists(
+        media::table
+            .filter(media::key.eq(key))
+            .filter(media::user_id.is_not_null()),
+    ))
+    .get_result(conn)?)
+}
+
+/// Deletes the removed uploads of `key`, once the file is taken care of.
+pub fn forget_orphans(conn: &mut PgConnection, key: &str) -> Result<(), QueryError> {
+    use crate::schema::media;
+
+    diesel::delete(
+        media::table
+            .filter(media::key.eq(key))
+            .filter(media::user_id.is_null()),
+    )
+    .execute(conn)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db::new_connection;
+    use crate::user::tests::new_user;
+
+    #[test]
+    fn enforces_quotas() {
+        let mut conn = new_connection();
+        let user = new_user(&mut conn, "user");
+
+        add(&mut conn, user, "a.png", "image/png", 600, 1000).unwrap();
+        // the same file again is free
+        add(&mut conn, user, "a.png", "image/png", 600, 1000).unwrap();
+        assert_eq!(usage(&mut conn, user).unwrap(), 600);
+        assert!(matches!(
+            add(&mut conn, user, "b.png", "image/png", 600, 1000),
+            Err(QueryError::QuotaExceeded)
+        ));
+
+        remove(&mut conn, user, "a.png").unwrap();
+        add(&mut conn, user, "b.png", "image/png", 600, 1000).unwrap();
+        assert!(matches!(
+            remove(&mut conn, user, "a.png"),
+            Err(QueryError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn keeps_files_uploaded_by_others() {
+        let mut conn = new_connection();
+        let user = new_user(&mut conn, "user");
+        let other = new_user(&mut conn, "other");
+
+        add(&mut conn, user, "shared.png", "image/png", 10, 1000).unwrap();
+        add(&mut conn, other, "shared.png", "image/png", 10, 1000).unwrap();
+        add(&mut conn, user, "mine.png", "image/png", 10, 1000).unwrap();
+        remove(&mut conn, other, "shared.png").unwrap();
+        assert_eq!(orphaned_keys(&mut conn).unwrap(), ["shared.png"]);
+        assert!(is_uploaded(&mut conn, "shared.png").unwrap());
+
+        diesel::delete(crate::schema::users::table.find(user))
+            .execute(&mut conn)
+            .unwrap();
+        let mut orphans = orphaned_keys(&mut conn).unwrap();
+        orphans.sort();
+        assert_eq!(orphans, ["mine.png", "shared.png"]);
+        assert!(!is_uploaded(&mut conn, "shared.png").unwrap());
+
+        forget_orphans(&mut conn, "shared.png").unwrap();
+        assert_eq!(orphaned_keys(&mut conn).unwrap(), ["mine.png"]);
+    }
+}
@@ -0,0 +1,260 @@
+//! Roles of users and the actions moderators take. Every action is recorded in the
+//! [audit log](crate::audit).
+//!
+//! `moderator_id` is `None` for actions taken from the command line.
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::audit::{self, AuditAction};
+use crate::{report, QueryError};
+
+/// Roles in ascending order of privileges.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Role {
+    #[default]
+    User,
+    Moderator,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Moderator => "moderator",
+            Self::Admin => "admin",
+        }
+    }
+
+    pub fn parse(role: &str) -> Option<Self> {
+        match role {
+            "user" => Some(Self::User),
+            "moderator" => Some(Self::Moderator),
+            "admin" => Some(Self::Admin),
+            _ => None,
+        }
+    }
+}
+
+pub fn role(conn: &mut PgConnection, user_id: Uuid) -> Result<Role, QueryError> {
+    use crate::schema::users::dsl::*;
+
+    let found: String = users.filter(id.eq(user_id)).select(role).get_result(conn)?;
+    // the check constraint only allows known roles
+    Ok(Role::parse(&found).unwrap_or_default())
+}
+
+pub fn set_role(
+    conn: &mut PgConnection,
+    moderator_id: Option<Uuid>,
+    user_id: Uuid,
+    new_role: Role,
+) -> Result<(), QueryError> {
+    use crate::schema::users::{self, columns};
+
+    conn.transaction(|conn| {
+        let previous = role(conn, user_id)?;
+        diesel::update(users::table.find(user_id))
+            .set(columns::role.eq(new_role.as_str()))
+            .execute(conn)?;
+
+        let mut entry = audit::NewEntry::new(moderator_id, AuditAction::SetRole);
+        entry.user_id = Some(user_id);
+        entry.details = json!({ "role": new_role.as_str(), "previous": previous.as_str() });
+        audit::record(conn, &entry)?;
+        Ok(())
+    })
+}
+
+/// Hides a post from everyone but its author. Resolves `report_id` if the post was hidden
+/// because of a report.
+pub fn hide_post(
+    conn: &mut PgConnection,
+    moderator_id: Option<Uuid>,
+    post_id: Uuid,
+    report_id: Option<Uuid>,
+) -> Result<(), QueryError> {
+    conn.transaction(|conn| {
+        set_post_hidden(conn, moderator_id, post_id, true, report_id)?;
+        if let Some(report_id) = report_id {
+            report::close(conn, moderator_id, report_id, false)?;
+        }
+        Ok(())
+    })
+}
+
+pub fn unhide_post(
+    conn: &mut PgConnection,
+    moderator_id: Option<Uuid>,
+    post_id: Uuid,
+) -> Result<(), QueryError> {
+    set_post_hidden(conn, moderator_id, post_id, false, None)
+}
+
+fn set_post_hidden(
+    conn: &mut PgConnection,
+    moderator_id: Option<Uuid>,
+    post_id: Uuid,
+    hidden: bool,
+    report_id: Option<Uuid>,
+) -> Result<(), QueryError> {
+    use crate::schema::posts::{self, columns};
+
+    let (hidden_at, action) = if hidden {
+        (Some(Utc::now()), AuditAction::HidePost)
+    } else {
+        (None, AuditAction::UnhidePost)
+    };
+
+    conn.transaction(|conn| {
+        let author: Uuid = diesel::update(posts::table.find(post_id))
+            .set(columns::hidden_at.eq(hidden_at))
+            .returning(columns::user_id)
+            .get_result(conn)?;
+
+        let mut entry = audit::NewEntry::new(moderator_id, action);
+        entry.user_id = Some(author);
+        entry.post_id = Some(post_id);
+        entry.report_id = report_id;
+        audit::record(conn, &entry)?;
+        Ok(())
+    })
+}
+
+/// Suspends a user and ends all of their sessions. Resolves `report_id` if the user was
+/// suspended because of a report.
+pub fn suspend(
+    conn: &mut PgConnection,
+    moderator_id: Option<Uuid>,
+    user_id: Uuid,
+    reason: &str,
+    report_id: Option<Uuid>,
+) -> Result<(), QueryError> {
+    use crate::schema::{users, web};
+
+    conn.transaction(|conn| {
+        let updated = diesel::update(users::table.find(user_id))
+            .set(users::suspended_at.eq(Utc::now()))
+            .execute(conn)?;
+        if updated == 0 {
+            return Err(QueryError::NotFound);
+        }
+        diesel::delete(web::table.filter(web::user_id.eq(user_id))).execute(conn)?;
+
+        let mut entry = audit::NewEntry::new(moderator_id, AuditAction::SuspendUser);
+        entry.user_id = Some(user_id);
+        entry.report_id = report_id;
+        entry.details = json!({ "reason": reason });
+        audit::record(conn, &entry)?;
+
+        if let Some(report_id) = report_id {
+            report::close(conn, moderator_id, report_id, false)?;
+        }
+        Ok(())
+    })
+}
+
+pub fn unsuspend(
+    conn: &mut PgConnection,
+    moderator_id: Option<Uuid>,
+    user_id: Uuid,
+) -> Result<(), QueryError> {
+    use crate::schema::users;
+
+    conn.transaction(|conn| {
+        let updated = diesel::update(users::table.find(user_id))
+            .set(users::suspended_at.eq(None::<chrono::DateTime<Utc>>))
+            .execute(conn)?;
+        if updated == 0 {
+            return Err(QueryError::NotFound);
+        }
+
+        let mut entry = audit::NewEntry::new(moderator_id, AuditAction::UnsuspendUser);
+        entry.user_id = Some(user_id);
+        audit::record(conn, &entry)?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post::tests::new_public_post;
+    use crate::test_db::new_connection;
+    use crate::user::tests::new_user;
+    use std::collections::HashSet;
+
+    #[test]
+    fn orders_roles_by_privileges() {
+        assert!(Role::User < Role::Moderator);
+        assert!(Role::Moderator < Role::Admin);
+        assert_eq!(Role::parse(Role::Moderator.as_str()), Some(Role::Moderator));
+    }
+
+    #[test]
+    fn records_actions() {
+        let mut conn = new_connection();
+        let moderator = new_user(&mut conn, "moderator");
+        let author = new_user(&mut conn, "author");
+        let reporter = new_user(&mut conn, "reporter");
+        let post_id = new_public_post(&mut conn, author, "#spam");
+
+        assert_eq!(role(&mut conn, author).unwrap(), Role::User);
+        set_role(&mut conn, None, moderator, Role::Moderator).unwrap();
+        assert_eq!(role(&mut conn, moderator).unwrap(), Role::Moderator);
+
+        let report_id = report::new(
+            &mut conn,
+            reporter,
+            report::Target::Post(post_id),
+            report::ReportReason::Spam,
+            "",
+        )
+        .unwrap();
+        hide_post(&mut conn, Some(moderator), post_id, Some(report_id)).unwrap();
+        assert!(crate::tag::timeline(&mut conn, reporter, "spam", None, 10)
+            .unwrap()
+            .is_empty());
+        assert!(
+            report::list(&mut conn, report::ReportStatus::Open, None, 10)
+                .unwrap()
+                .is_empty()
+        );
+
+        let session = crate::session::new(
+            &mut conn,
+            author,
+            Utc::now() + chrono::Duration::days(1),
+            json!({}),
+        )
+        .unwrap();
+        suspend(&mut conn, Some(moderator), author, "spam", None).unwrap();
+        let user = crate::user::find(&mut conn, author).unwrap();
+        assert!(user.suspended_at.is_some());
+        assert!(crate::session::get(&mut conn, session.id)
+            .unwrap()
+            .is_none());
+
+        // entries of the test transaction share their time, so their order is arbitrary
+        let entries = audit::list(&mut conn, None, 10).unwrap();
+        let hidden = entries
+            .iter()
+            .find(|entry| entry.action == AuditAction::HidePost)
+            .unwrap();
+        assert_eq!(hidden.report_id, Some(report_id));
+        let actions: HashSet<_> = entries.into_iter().map(|entry| entry.action).collect();
+        assert_eq!(
+            actions,
+            HashSet::from([
+                AuditAction::SuspendUser,
+                AuditAction::ResolveReport,
+                AuditAction::HidePost,
+                AuditAction::SetRole,
+            ])
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Mutes hide content of a user from the muter only. The muted user isn't affected and can still
+//! interact with the muter.
+
+use diesel::prelude::*;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::QueryError;
+
+/// Makes `user_id` mute `muted_id`. Muting someone twice has no effect.
+pub fn mute(conn: &mut PgConnection, user_id: Uuid, muted_id: Uuid) -> Result<(), QueryError> {
+    use crate::schema::mutes::dsl;
+
+    diesel::insert_into(dsl::mutes)
+        .values((dsl::user_id.eq(user_id), dsl::muted_id.eq(muted_id)))
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn unmute(conn: &mut PgConnection, user_id: Uuid, muted_id: Uuid) -> Result<(), QueryError> {
+    use crate::schema::mutes::dsl;
+
+    diesel::delete(
+        dsl::mutes
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::muted_id.eq(muted_id)),
+    )
+    .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn is_muted(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    muted_id: Uuid,
+) -> Result<bool, QueryError> {
+    use crate::schema::mutes::dsl;
+
+    Ok(diesel::select(diesel::dsl::exists(
+        dsl::mutes
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::muted_id.eq(muted_id)),
+    ))
+    .get_result(conn)?)
+}
+
+/// Ids and handles of the users `user_id` muted, most recent first.
+pub fn muted_users(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<Vec<(Uuid, String)>, QueryError> {
+    use crate::schema::{mutes, users};
+
+    Ok(mutes::table
+        .inner_join(users::table.on(users::id.eq(mutes::muted_id)))
+        .filter(mutes::user_id.eq(user_id))
+        .order(mutes::created_at.desc())
+        .select((users::id, users::handle))
+        .load(conn)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post::tests::new_public_post;
+    use crate::post::{is_visible_to, PostPreview};
+    use crate::test_db::new_connection;
+    use crate::user::tests::new_user;
+    use std::collections::HashSet;
+
+    fn ids(posts: Vec<PostPreview>) -> HashSet<Uuid> {
+        posts.into_iter().map(|post| post.id).collect()
+    }
+
+    #[test]
+    fn hides_muted_users_from_the_muter_only() {
+        let mut conn = new_connection();
+        let muter = new_user(&mut conn, "muter");
+        let muted = new_user(&mut conn, "muted");
+        let muter_post = new_public_post(&mut conn, muter, "#news from the muter");
+        let muted_post = new_public_post(&mut conn, muted, "#news from the muted");
+
+        mute(&mut conn, muter, muted).unwrap();
+        mute(&mut conn, muter, muted).unwrap();
+        assert!(is_muted(&mut conn, muter, muted).unwrap());
+        assert!(!is_muted(&mut conn, muted, muter).unwrap());
+        assert_eq!(
+            muted_users(&mut conn, muter).unwrap(),
+            vec![(muted, "muted".to_string())]
+        );
+
+        let timeline = |conn: &mut PgConnection, viewer_id| {
+            ids(crate::tag::timeline(conn, viewer_id, "news", None, 10).unwrap())
+        };
+        let search = |conn: &mut PgConnection, viewer_id| {
+            ids(crate::search::posts(conn, viewer_id, "news", 0, 10).unwrap())
+        };
+        assert_eq!(timeline(&mut conn, muter), HashSet::from([muter_post]));
+        assert_eq!(search(&mut conn, muter), HashSet::from([muter_post]));
+
+        // the muted user still sees the muter
+        let both = HashSet::from([muter_post, muted_post]);
+        assert_eq!(timeline(&mut conn, muted), both);
+        assert_eq!(search(&mut conn, muted), both);
+        assert!(is_visible_to(&mut conn, muted, muter_post).unwrap());
+
+        unmute(&mut conn, muter, muted).unwrap();
+        assert_eq!(timeline(&mut conn, muter), both);
+        assert!(muted_users(&mut conn, muter).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,275 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::{block, mute, QueryError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NotificationKind {
+    Follow,
+    Reply,
+    Boost,
+    Reaction,
+    PollVote,
+    Mention,
+    FollowRequest,
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Follow => "follow",
+            Self::Reply => "reply",
+            Self::Boost => "boost",
+            Self::Reaction => "reaction",
+            Self::PollVote => "poll_vote",
+            Self::Mention => "mention",
+            Self::FollowRequest => "follow_request",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "follow" => Some(Self::Follow),
+            "reply" => Some(Self::Reply),
+            "boost" => Some(Self::Boost),
+            "reaction" => Some(Self::Reaction),
+            "poll_vote" => Some(Self::PollVote),
+            "mention" => Some(Self::Mention),
+            "follow_request" => Some(Self::FollowRequest),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Notification {
+    pub id: Uuid,
+    pub kind: NotificationKind,
+    pub actor_id: Uuid,
+    pub actor_handle: String,
+    pub post_id: Option<Uuid>,
+    pub read_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Queryable)]
+struct NotificationRow {
+    id: Uuid,
+    kind: String,
+    actor_id: Uuid,
+    actor_handle: String,
+    post_id: Option<Uuid>,
+    read_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+/// Position in the inbox. Pages continue after the last notification of the previous page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+/// Notifies `user_id` that `actor_id` did something, optionally involving one of their posts.
+///
+/// Users aren't notified about their own actions, or about actions of users they muted or who
+/// are blocked, in which case `None` is returned.
+pub fn new(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    actor_id: Uuid,
+    kind: NotificationKind,
+    post_id: Option<Uuid>,
+) -> Result<Option<Uuid>, QueryError> {
+    use crate::schema::notifications::{self, columns};
+
+    if user_id == actor_id
+        || block::is_blocked_between(conn, user_id, actor_id)?
+        || mute::is_muted(conn, user_id, actor_id)?
+    {
+        return Ok(None);
+    }
+
+    let id = Uuid::new_v4();
+    diesel::insert_into(notifications::table)
+        .values((
+            columns::id.eq(id),
+            columns::user_id.eq(user_id),
+            columns::actor_id.eq(actor_id),
+            columns::kind.eq(kind.as_str()),
+            columns::post_id.eq(post_id),
+        ))
+        .execute(conn)?;
+
+    Ok(Some(id))
+}
+
+/// Notifications of `user_id`, newest first, starting after `after`. Notifications from users
+/// who are blocked or muted now are left out.
+pub fn list(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    after: Option<Cursor>,
+    limit: i64,
+) -> Result<Vec<Notification>, QueryError> {
+    use crate::schema::{notifications as n, users};
+
+    let mut query = n::table
+        .inner_join(users::table.on(users::id.eq(n::actor_id)))
+        .filter(n::user_id.eq(user_id))
+        .filter(diesel::dsl::not(block::hidden_from(
+            n::user_id,
+            n::actor_id,
+        )))
+        .select((
+            n::id,
+            n::kind,
+            n::actor_id,
+            users::handle,
+            n::post_id,
+            n::read_at,
+            n::created_at,
+        ))
+        .order((n::created_at.desc(), n::id.desc()))
+        .limit(limit)
+        .into_boxed();
+    if let Some(after) = after {
+        query = query.filter(
+            n::created_at
+                .lt(after.created_at)
+                .or(n::created_at.eq(after.created_at).and(n::id.lt(after.id))),
+        );
+    }
+
+    let rows: Vec<NotificationRow> = query.load(conn)?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            // the check constraint only allows known kinds
+            Some(Notification {
+                id: row.id,
+                kind: NotificationKind::parse(&row.kind)?,
+                actor_id: row.actor_id,
+                actor_handle: row.actor_handle,
+                post_id: row.post_id,
+                read_at: row.read_at,
+                created_at: row.created_at,
+            })
+        })
+        .collect())
+}
+
+/// Number of unread notifications of `user_id` which [`list`] returns.
+pub fn unread_count(conn: &mut PgConnection, user_id: Uuid) -> Result<i64, QueryError> {
+    use crate::schema::notifications::dsl;
+
+    Ok(dsl::notifications
+        .filter(dsl::user_id.eq(user_id))
+        .filter(dsl::read_at.is_null())
+        .filter(diesel::dsl::not(block::hidden_from(
+            dsl::user_id,
+            dsl::actor_id,
+        )))
+        .count()
+        .get_result(conn)?)
+}
+
+/// Marks notifications of `user_id` as read: the ones in `ids`, or all of them if `ids` is
+/// `None`. Returns the number of notifications which were unread.
+pub fn mark_read(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    ids: Option<&[Uuid]>,
+) -> Result<usize, QueryError> {
+    use crate::schema::notifications::dsl;
+
+    let mut query = diesel::update(dsl::notifications)
+        .filter(dsl::user_id.eq(user_id))
+        .filter(dsl::read_at.is_null())
+        .into_boxed();
+    if let Some(ids) = ids {
+        query = query.filter(dsl::id.eq_any(ids));
+    }
+
+    Ok(query.set(dsl::read_at.eq(Utc::now())).execute(conn)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db::new_connection;
+    use crate::user::tests::new_user;
+
+    #[test]
+    fn pages_through_the_inbox() {
+        let mut conn = new_connection();
+        let user = new_user(&mut conn, "user");
+        let actor = new_user(&mut conn, "actor");
+
+        for _ in 0..3 {
+            new(&mut conn, user, actor, NotificationKind::Follow, None).unwrap();
+        }
+        assert_eq!(
+            new(&mut conn, user, user, NotificationKind::Follow, None).unwrap(),
+            None
+        );
+
+        let first = list(&mut conn, user, None, 2).unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0].actor_handle, "actor");
+
+        let last = first.last().unwrap();
+        let cursor = Cursor {
+            created_at: last.created_at,
+            id: last.id,
+        };
+        let second = list(&mut conn, user, Some(cursor), 2).unwrap();
+        assert_eq!(second.len(), 1);
+        assert!(first.iter().all(|n| n.id != second[0].id));
+    }
+
+    #[test]
+    fn tracks_unread_notifications() {
+        let mut conn = new_connection();
+        let user = new_user(&mut conn, "user");
+        let actor = new_user(&mut conn, "actor");
+
+        let first = new(&mut conn, user, actor, NotificationKind::Follow, None)
+            .unwrap()
+            .unwrap();
+        new(&mut conn, user, actor, NotificationKind::Follow, None).unwrap();
+        assert_eq!(unread_count(&mut conn, user).unwrap(), 2);
+
+        assert_eq!(mark_read(&mut conn, user, Some(&[first])).unwrap(), 1);
+        assert_eq!(unread_count(&mut conn, user).unwrap(), 1);
+
+        assert_eq!(mark_read(&mut conn, user, None).unwrap(), 1);
+        assert_eq!(unread_count(&mut conn, user).unwrap(), 0);
+    }
+
+    #[test]
+    fn hides_notifications_from_blocked_and_muted_users() {
+        let mut conn = new_connection();
+        let user = new_user(&mut conn, "user");
+        let blocked = new_user(&mut conn, "blocked");
+        let muted = new_user(&mut conn, "muted");
+        let actor = new_user(&mut conn, "actor");
+
+        for from in [blocked, muted, actor] {
+            new(&mut conn, user, from, NotificationKind::Follow, None).unwrap();
+        }
+        block::block(&mut conn, user, blocked).unwrap();
+        mute::mute(&mut conn, user, muted).unwrap();
+
+        let inbox = list(&mut conn, user, None, 10).unwrap();
+        assert_eq!(inbox.len(), 1);
+        assert_eq!(inbox[0].actor_id, actor);
+        assert_eq!(unread_count(&mut conn, user).unwrap(), 1);
+
+        mute::unmute(&mut conn, user, muted).unwrap();
+        assert_eq!(unread_count(&mut conn, user).unwrap(), 2);
+    }
+}
@@ -0,0 +1,205 @@
+//! Messages between server instances using Postgres `LISTEN`/`NOTIFY`.
+//!
+//! Notifications are sent on pooled connections with [`notify`]. Receiving them needs a
+//! connection which stays open and is never handed to anyone else, so [`Listener`] opens its own
+//! connection outside of the [`AsyncConnectionPool`](crate::AsyncConnectionPool).
+//!
+//! Notifications of different transactions arrive in the order the transactions committed, which
+//! isn't the order their ids were taken in. [`notify_numbered`] takes the id and sends the
+//! notifications under a lock held until the commit, so both orders match.
+
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Text};
+use diesel::PgConnection;
+use tokio::sync::mpsc;
+
+use crate::{DieselError, QueryError};
+
+/// Postgres rejects notification payloads of 8000 bytes or more.
+pub const MAX_PAYLOAD_LEN: usize = 7999;
+
+/// Sends `payload` to every session listening on `channel`.
+///
+/// Inside a transaction, the notification is only sent once the transaction commits.
+pub fn notify(conn: &mut PgConnection, channel: &str, payload: &str) -> Result<(), QueryError> {
+    diesel::sql_query("SELECT pg_notify($1, $2)")
+        .bind::<Text, _>(channel)
+        .bind::<Text, _>(payload)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+#[derive(QueryableByName)]
+struct NextId {
+    #[diesel(sql_type = BigInt)]
+    id: i64,
+}
+
+/// Next id for a real-time event. Ids are unique across every server instance.
+pub fn next_event_id(conn: &mut PgConnection) -> Result<i64, QueryError> {
+    let next: NextId = diesel::sql_query("SELECT nextval('event_ids') AS id").get_result(conn)?;
+    Ok(next.id)
+}
+
+/// Takes the next id for a real-time event with [`next_event_id`] and sends the `payloads` built
+/// for it on `channel`. Returns the id.
+///
+/// Listeners receive the notifications in the order of their ids. Nothing is sent if `payloads`
+/// fails.
+pub fn notify_numbered<F, E>(conn: &mut PgConnection, channel: &str, payloads: F) -> Result<i64, E>
+where
+    F: FnOnce(i64) -> Result<Vec<String>, E>,
+    E: From<QueryError> + From<DieselError>,
+{
+    conn.transaction(|conn| {
+        // held until the commit, so the next id is only taken once these notifications are sent
+        diesel::sql_query("SELECT pg_advisory_xact_lock(hashtext('event_ids'))").execute(conn)?;
+        let id = next_event_id(conn)?;
+        for payload in payloads(id)? {
+            notify(conn, channel, &payload)?;
+        }
+        Ok(id)
+    })
+}
+
+/// Fails if connecting to `url` needs TLS, which [`Listener`] doesn't support. The listener
+/// connects in the background, so check this when starting up instead of failing later.
+pub fn check_listener_url(url: &str) -> Result<(), QueryError> {
+    use tokio_postgres::config::{Config, SslMode};
+
+    let config: Config = url
+        .parse()
+        .map_err(|e: tokio_postgres::Error| QueryError::Connection(e.to_string()))?;
+    match config.get_ssl_mode() {
+        // `prefer` falls back to a connection without TLS
+        SslMode::Disable | SslMode::Prefer => Ok(()),
+        _ => Err(QueryError::Connection(
+            "the notification listener doesn't support TLS, use `sslmode=disable` or `sslmode=prefer`"
+                .to_string(),
+        )),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Notification {
+    pub channel: String,
+    pub payload: String,
+}
+
+/// Dedicated connection which receives notifications.
+///
+/// The connection doesn't use TLS, see [`check_listener_url`].
+pub struct Listener {
+    // the connection is closed once the client is dropped
+    _client: tokio_postgres::Client,
+    notifications: mpsc::UnboundedReceiver<Notification>,
+}
+
+impl Listener {
+    /// Connects to the database and listens on `channels`.
+    pub async fn connect<S: AsRef<str>>(url: S, channels: &[&str]) -> Result<Self, QueryError> {
+        use tokio_postgres::AsyncMessage;
+
+        check_listener_url(url.as_ref())?;
+        let (client, mut connection) = tokio_postgres::connect(url.as_ref(), tokio_postgres::NoTls)
+            .await
+            .map_err(|e| QueryError::Connection(e.to_string()))?;
+
+        let (sender, notifications) = mpsc::unbounded_channel();
+        // the connection only makes progress while it is polled, which is also what delivers the
+        // notifications
+        tokio::spawn(async move {
+            loop {
+                match std::future::poll_fn(|cx| connection.poll_message(cx)).await {
+                    Some(Ok(AsyncMessage::Notification(notification))) => {
+                        let notification = Notification {
+                            channel: notification.channel().to_string(),
+                            payload: notification.payload().to_string(),
+                        };
+                        if sender.send(notification).is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(_)) => (),
+                    Some(Err(e)) => {
+                        tracing::error!(target: "uchat_query", error = %e, "notification listener failed");
+                        break;
+                    }
+                    None => break,
+                }
+            }
+        });
+
+        for channel in channels {
+            client
+                .batch_execute(&format!("LISTEN {}", quote_identifier(channel)))
+                .await
+                .map_err(|e| QueryError::Connection(e.to_string()))?;
+        }
+
+        Ok(Self {
+            _client: client,
+            notifications,
+        })
+    }
+
+    /// Next notification, or `None` once the connection was lost.
+    pub async fn recv(&mut self) -> Option<Notification> {
+        self.notifications.recv().await
+    }
+}
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db::new_connection;
+
+    #[test]
+    fn quotes_identifiers() {
+        assert_eq!(quote_identifier("events"), "\"events\"");
+        assert_eq!(quote_identifier("a\"b"), "\"a\"\"b\"");
+    }
+
+    #[test]
+    fn hands_out_increasing_event_ids() {
+        let mut conn = new_connection();
+        let first = next_event_id(&mut conn).unwrap();
+        let second = next_event_id(&mut conn).unwrap();
+        assert!(second > first);
+
+        notify(&mut conn, "uchat_test", "payload").unwrap();
+    }
+
+    #[test]
+    fn numbers_notifications() {
+        let mut conn = new_connection();
+        let first = next_event_id(&mut conn).unwrap();
+        let id = notify_numbered(&mut conn, "uchat_test", |id| {
+            Ok::<_, QueryError>(vec![id.to_string()])
+        })
+        .unwrap();
+        assert!(id > first);
+
+        let failed = notify_numbered(&mut conn, "uchat_test", |_| {
+            Err::<Vec<String>, _>(QueryError::NotFound)
+        });
+        assert!(matches!(failed, Err(QueryError::NotFound)));
+    }
+
+    #[test]
+    fn refuses_urls_which_need_tls() {
+        assert!(check_listener_url("postgres://localhost/uchat").is_ok());
+        assert!(check_listener_url("postgres://localhost/uchat?sslmode=prefer").is_ok());
+        assert!(check_listener_url("postgres://localhost/uchat?sslmode=disable").is_ok());
+        assert!(matches!(
+            check_listener_url("postgres://localhost/uchat?sslmode=require"),
+            Err(QueryError::Connection(_))
+        ));
+        assert!(check_listener_url("host=localhost sslmode=require").is_err());
+    }
+}
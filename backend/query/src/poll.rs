@@ -0,0 +1,193 @@
+//! Votes on polls.
+
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Uuid as SqlUuid};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::notification::{self, NotificationKind};
+use crate::{block, post, QueryError};
+
+/// Poll after a vote.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tally {
+    pub author_id: Uuid,
+    /// Everyone who voted, in no particular order.
+    pub voter_ids: Vec<Uuid>,
+    /// Votes of every choice, including those without any.
+    pub votes: Vec<ChoiceVotes>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, QueryableByName)]
+pub struct ChoiceVotes {
+    #[diesel(sql_type = SqlUuid)]
+    pub choice_id: Uuid,
+    #[diesel(sql_type = BigInt)]
+    pub votes: i64,
+}
+
+/// Makes `user_id` vote for `choice_id` in poll `post_id`, replacing their earlier vote. The
+/// author is notified about the first vote of each user.
+///
+/// Fails with [`QueryError::NotFound`] if `user_id` can't see the post, it was deleted, or the
+/// choice belongs to another post, and with [`QueryError::Blocked`] if the author blocked
+/// `user_id` or was blocked by them.
+pub fn vote(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    post_id: Uuid,
+    choice_id: Uuid,
+) -> Result<Tally, QueryError> {
+    use crate::schema::{poll_choices, poll_votes, posts};
+
+    conn.transaction(|conn| {
+        let (author_id, deleted_at): (Uuid, Option<chrono::DateTime<chrono::Utc>>) = posts::table
+            .find(post_id)
+            .select((posts::user_id, posts::deleted_at))
+            .get_result(conn)?;
+        block::ensure_not_blocked(conn, user_id, author_id)?;
+        if deleted_at.is_some() || !post::is_visible_to(conn, user_id, post_id)? {
+            return Err(QueryError::NotFound);
+        }
+        poll_choices::table
+            .find(choice_id)
+            .filter(poll_choices::post_id.eq(post_id))
+            .select(poll_choices::id)
+            .get_result::<Uuid>(conn)?;
+
+        let voted_before: bool = diesel::select(diesel::dsl::exists(
+            poll_votes::table.find((user_id, post_id)),
+        ))
+        .get_result(conn)?;
+        diesel::insert_into(poll_votes::table)
+            .values((
+                poll_votes::user_id.eq(user_id),
+                poll_votes::post_id.eq(post_id),
+                poll_votes::choice_id.eq(choice_id),
+            ))
+            .on_conflict((poll_votes::user_id, poll_votes::post_id))
+            .do_update()
+            .set((
+                poll_votes::choice_id.eq(choice_id),
+                poll_votes::created_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)?;
+        if !voted_before {
+            notification::new(
+                conn,
+                author_id,
+                user_id,
+                NotificationKind::PollVote,
+                Some(post_id),
+            )?;
+        }
+
+        let voter_ids = poll_votes::table
+            .filter(poll_votes::post_id.eq(post_id))
+            .select(poll_votes::user_id)
+            .load(conn)?;
+        let votes = diesel::sql_query(
+            "SELECT c.id AS choice_id, count(v.user_id) AS votes \
+             FROM poll_choices c \
+             LEFT JOIN poll_votes v ON v.choice_id = c.id \
+             WHERE c.post_id = $1 \
+             GROUP BY c.id \
+             ORDER BY c.id",
+        )
+        .bind::<SqlUuid, _>(post_id)
+        .load(conn)?;
+
+        Ok(Tally {
+            author_id,
+            voter_ids,
+            votes,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post::{self, NewPost, Visibility};
+    use crate::test_db::new_connection;
+    use crate::user::tests::new_user;
+    use chrono::Utc;
+    use serde_json::json;
+
+    /// Poll by `user_id` with the choices "a" and "b".
+    fn new_poll(conn: &mut PgConnection, user_id: Uuid) -> (Uuid, [Uuid; 2]) {
+        use crate::schema::poll_choices;
+
+        let post_id = post::new(
+            conn,
+            &NewPost {
+                user_id,
+                content: json!({ "Poll": { "headline": "Best?", "choices": ["a", "b"] } }),
+                time_posted: Utc::now(),
+                direct_message_to: None,
+                reply_to: None,
+                visibility: Visibility::Public,
+            },
+        )
+        .unwrap();
+        let choice_ids = [Uuid::new_v4(), Uuid::new_v4()];
+        for (choice_id, choice) in choice_ids.iter().zip(["a", "b"]) {
+            diesel::insert_into(poll_choices::table)
+                .values((
+                    poll_choices::id.eq(choice_id),
+                    poll_choices::choice.eq(choice),
+                    poll_choices::post_id.eq(post_id),
+                ))
+                .execute(conn)
+                .unwrap();
+        }
+        (post_id, choice_ids)
+    }
+
+    #[test]
+    fn tallies_votes() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let voter = new_user(&mut conn, "voter");
+        let (post_id, [a, b]) = new_poll(&mut conn, author);
+
+        vote(&mut conn, voter, post_id, a).unwrap();
+        let tally = vote(&mut conn, author, post_id, a).unwrap();
+        assert_eq!(tally.author_id, author);
+        assert_eq!(tally.voter_ids.len(), 2);
+        let votes_of = |tally: &Tally, choice_id| {
+            tally
+                .votes
+                .iter()
+                .find(|votes| votes.choice_id == choice_id)
+                .unwrap()
+                .votes
+        };
+        assert_eq!(votes_of(&tally, a), 2);
+        assert_eq!(votes_of(&tally, b), 0);
+
+        // voting again changes the vote
+        let tally = vote(&mut conn, voter, post_id, b).unwrap();
+        assert_eq!(votes_of(&tally, a), 1);
+        assert_eq!(votes_of(&tally, b), 1);
+
+        // once for the first vote of the voter, never for the vote of the author
+        let inbox = notification::list(&mut conn, author, None, 10).unwrap();
+        assert_eq!(inbox.len(), 1);
+        assert_eq!(inbox[0].kind, NotificationKind::PollVote);
+        assert_eq!(inbox[0].actor_id, voter);
+    }
+
+    #[test]
+    fn rejects_choices_of_other_polls() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let (post_id, _) = new_poll(&mut conn, author);
+        let (_, [other, _]) = new_poll(&mut conn, author);
+
+        assert!(matches!(
+            vote(&mut conn, author, post_id, other),
+            Err(QueryError::NotFound)
+        ));
+    }
+}
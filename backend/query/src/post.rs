@@ -0,0 +1,982 @@
+//! Posts and who can see them.
+//!
+//! Besides blocks and moderation, posts of private users are only visible to their followers, and
+//! every post has a [`Visibility`]. Queries written in SQL check both with the `can_see_post`
+//! database function, other code with [`get`] or [`is_visible_to`].
+//!
+//! Scheduled posts are hidden until their `time_posted`. Queries compare it with
+//! `statement_timestamp()` rather than `now()`, which is when the transaction started and can be
+//! before posts published inside of it.
+//!
+//! Authors can [`edit`] their posts for a while after publishing them. The content before each
+//! edit is kept as a [`Revision`]. Deleted posts stay as empty placeholders, so the replies to
+//! them keep their place in the thread.
+
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Nullable, Text, Timestamptz, Uuid as SqlUuid};
+use diesel::PgConnection;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::job::{self, Task};
+use crate::notification::{self, NotificationKind};
+use crate::{block, tag, QueryError};
+
+/// Who can see a post, on top of blocks, moderation and private users.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Visibility {
+    #[default]
+    Public,
+    /// Only followers of the author can see the post.
+    Followers,
+    /// Anyone can open the post, but it is left out of search results and timelines.
+    Unlisted,
+}
+
+impl Visibility {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Public => "public",
+            Self::Followers => "followers",
+            Self::Unlisted => "unlisted",
+        }
+    }
+
+    pub fn parse(visibility: &str) -> Option<Self> {
+        match visibility {
+            "public" => Some(Self::Public),
+            "followers" => Some(Self::Followers),
+            "unlisted" => Some(Self::Unlisted),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewPost {
+    pub user_id: Uuid,
+    pub content: Value,
+    /// Posts with a time in the future are scheduled for that time.
+    pub time_posted: DateTime<Utc>,
+    pub direct_message_to: Option<Uuid>,
+    pub reply_to: Option<Uuid>,
+    pub visibility: Visibility,
+}
+
+/// Public post as shown in lists.
+#[derive(Clone, Debug, PartialEq, Eq, QueryableByName)]
+pub struct PostPreview {
+    #[diesel(sql_type = SqlUuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = SqlUuid)]
+    pub author_id: Uuid,
+    #[diesel(sql_type = Text)]
+    pub author_handle: String,
+    /// Every string inside the content of the post.
+    #[diesel(sql_type = Text)]
+    pub text: String,
+    #[diesel(sql_type = Timestamptz)]
+    pub time_posted: DateTime<Utc>,
+    /// When the content was last changed, if it ever was.
+    #[diesel(sql_type = Nullable<Timestamptz>)]
+    pub edited_at: Option<DateTime<Utc>>,
+    /// Deleted posts have no text left.
+    #[diesel(sql_type = Nullable<Timestamptz>)]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Content of a post before one of its edits.
+#[derive(Clone, Debug, PartialEq, Queryable)]
+pub struct Revision {
+    pub content: Value,
+    /// When this content was replaced.
+    pub revised_at: DateTime<Utc>,
+}
+
+/// Position in a timeline, see [`PostPreview`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cursor {
+    pub time_posted: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+/// Publishes a post.
+///
+/// Hashtags and mentions in public posts are stored, and mentioned users who can see the post
+/// are notified once it is published, like the author of the post it replies to. Direct messages
+/// are private, so neither are taken from them.
+///
+/// Fails with [`QueryError::Blocked`] for replies and direct messages to users who blocked the
+/// author or were blocked by them, and with [`QueryError::NotFound`] for replies to posts the
+/// author can't see or which were deleted.
+pub fn new(conn: &mut PgConnection, post: &NewPost) -> Result<Uuid, QueryError> {
+    use crate::schema::posts::{self, columns};
+
+    let id = Uuid::new_v4();
+    conn.transaction(|conn| {
+        if let Some(reply_to) = post.reply_to {
+            let (parent_author, parent_deleted_at): (Uuid, Option<DateTime<Utc>>) = posts::table
+                .find(reply_to)
+                .select((columns::user_id, columns::deleted_at))
+                .get_result(conn)?;
+            block::ensure_not_blocked(conn, post.user_id, parent_author)?;
+            if parent_deleted_at.is_some() || !is_visible_to(conn, post.user_id, reply_to)? {
+                return Err(QueryError::NotFound);
+            }
+        }
+        if let Some(recipient) = post.direct_message_to {
+            block::ensure_not_blocked(conn, post.user_id, recipient)?;
+        }
+
+        diesel::insert_into(posts::table)
+            .values((
+                columns::id.eq(id),
+                columns::user_id.eq(post.user_id),
+                columns::content.eq(&post.content),
+                columns::time_posted.eq(post.time_posted),
+                columns::direct_message_to.eq(post.direct_message_to),
+                columns::reply_to.eq(post.reply_to),
+                columns::visibility.eq(post.visibility.as_str()),
+            ))
+            .execute(conn)?;
+
+        if post.direct_message_to.is_none() {
+            let text = text(&post.content);
+            tag::attach(conn, id, post.time_posted, &uchat_text::hashtags(&text))?;
+            let handles = uchat_text::mentions(&text);
+            mention(
+                conn,
+                id,
+                post.user_id,
+                post.time_posted,
+                post.visibility,
+                &handles,
+            )?;
+            if post.time_posted > Utc::now() {
+                job::enqueue(conn, &Task::PublishPost { post_id: id }, post.time_posted)?;
+            } else if let Some(reply_to) = post.reply_to {
+                notify_replied_to(conn, id, post.user_id, post.visibility, reply_to)?;
+            }
+        }
+        Ok(id)
+    })
+}
+
+/// Changes the content of post `post_id` by `user_id`, keeping the old content as a
+/// [`Revision`]. Hashtags are replaced, and users who are mentioned for the first time are
+/// notified like for new posts.
+///
+/// Fails with [`QueryError::NotFound`] if `user_id` didn't write the post or deleted it, with
+/// [`QueryError::EditWindowClosed`] if it was published before `editable_since`, and with
+/// [`QueryError::PollHasVotes`] if the choices of a poll change after someone voted on it.
+pub fn edit(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    post_id: Uuid,
+    content: &Value,
+    editable_since: DateTime<Utc>,
+) -> Result<(), QueryError> {
+    use crate::schema::{mentions, poll_votes, post_hashtags, post_revisions, posts, users};
+
+    conn.transaction(|conn| {
+        let (old_content, time_posted, direct_message_to, visibility): (
+            Value,
+            DateTime<Utc>,
+            Option<Uuid>,
+            String,
+        ) = posts::table
+            .find(post_id)
+            .filter(posts::user_id.eq(user_id))
+            .filter(posts::deleted_at.is_null())
+            .select((
+                posts::content,
+                posts::time_posted,
+                posts::direct_message_to,
+                posts::visibility,
+            ))
+            .for_update()
+            .get_result(conn)?;
+
+        if time_posted < editable_since {
+            return Err(QueryError::EditWindowClosed);
+        }
+        if poll_choices(&old_content) != poll_choices(content) {
+            let voted = diesel::select(diesel::dsl::exists(
+                poll_votes::table.filter(poll_votes::post_id.eq(post_id)),
+            ))
+            .get_result(conn)?;
+            if voted {
+                return Err(QueryError::PollHasVotes);
+            }
+        }
+
+        let now = Utc::now();
+        diesel::insert_into(post_revisions::table)
+            .values((
+                post_revisions::id.eq(Uuid::new_v4()),
+                post_revisions::post_id.eq(post_id),
+                post_revisions::content.eq(&old_content),
+                post_revisions::revised_at.eq(now),
+            ))
+            .execute(conn)?;
+        diesel::update(posts::table.find(post_id))
+            .set((posts::content.eq(content), posts::edited_at.eq(now)))
+            .execute(conn)?;
+
+        if direct_message_to.is_none() {
+            let text = text(content);
+            diesel::delete(post_hashtags::table.filter(post_hashtags::post_id.eq(post_id)))
+                .execute(conn)?;
+            tag::attach(conn, post_id, time_posted, &uchat_text::hashtags(&text))?;
+
+            let mentioned: Vec<String> = mentions::table
+                .inner_join(users::table)
+                .filter(mentions::post_id.eq(post_id))
+                .select(users::handle)
+                .load(conn)?;
+            let handles: Vec<&str> = uchat_text::mentions(&text)
+                .into_iter()
+                .filter(|handle| !mentioned.iter().any(|m| m == handle))
+                .collect();
+            // the check constraint only allows known visibilities
+            let visibility = Visibility::parse(&visibility).unwrap_or_default();
+            mention(conn, post_id, user_id, time_posted, visibility, &handles)?;
+        }
+        Ok(())
+    })
+}
+
+/// Deletes post `post_id` by `user_id`. The post stays as a placeholder without content, and
+/// everything taken from the content, like hashtags, mentions and earlier versions, is removed.
+///
+/// Fails with [`QueryError::NotFound`] if `user_id` didn't write the post or already deleted it.
+pub fn delete(conn: &mut PgConnection, user_id: Uuid, post_id: Uuid) -> Result<(), QueryError> {
+    use crate::schema::{mentions, post_hashtags, post_revisions, posts};
+
+    conn.transaction(|conn| {
+        let deleted = diesel::update(
+            posts::table
+                .find(post_id)
+                .filter(posts::user_id.eq(user_id))
+                .filter(posts::deleted_at.is_null()),
+        )
+        .set((
+            posts::content.eq(Value::Object(Default::default())),
+            posts::deleted_at.eq(Utc::now()),
+        ))
+        .execute(conn)?;
+        if deleted == 0 {
+            return Err(QueryError::NotFound);
+        }
+
+        diesel::delete(post_hashtags::table.filter(post_hashtags::post_id.eq(post_id)))
+            .execute(conn)?;
+        diesel::delete(mentions::table.filter(mentions::post_id.eq(post_id))).execute(conn)?;
+        diesel::delete(post_revisions::table.filter(post_revisions::post_id.eq(post_id)))
+            .execute(conn)?;
+        Ok(())
+    })
+}
+
+/// Choices of a poll, `None` for other posts.
+fn poll_choices(content: &Value) -> Option<&Value> {
+    content.get("Poll")?.get("choices")
+}
+
+/// Earlier contents of post `post_id`, newest first.
+///
+/// Fails with [`QueryError::NotFound`] if `viewer_id` can't see the post, see [`get`].
+pub fn revisions(
+    conn: &mut PgConnection,
+    viewer_id: Uuid,
+    post_id: Uuid,
+) -> Result<Vec<Revision>, QueryError> {
+    use crate::schema::post_revisions;
+
+    if !is_visible_to(conn, viewer_id, post_id)? {
+        return Err(QueryError::NotFound);
+    }
+    Ok(post_revisions::table
+        .filter(post_revisions::post_id.eq(post_id))
+        .order(post_revisions::revised_at.desc())
+        .select((post_revisions::content, post_revisions::revised_at))
+        .load(conn)?)
+}
+
+/// Records the users mentioned by post `post_id`. Handles which don't exist are ignored.
+fn mention(
+    conn: &mut PgConnection,
+    post_id: Uuid,
+    author_id: Uuid,
+    time_posted: DateTime<Utc>,
+    visibility: Visibility,
+    handles: &[&str],
+) -> Result<(), QueryError> {
+    use crate::schema::{mentions, users};
+
+    if handles.is_empty() {
+        return Ok(());
+    }
+
+    let user_ids: Vec<Uuid> = users::table
+        .filter(users::handle.eq_any(handles))
+        .select(users::id)
+        .load(conn)?;
+    let rows: Vec<_> = user_ids
+        .iter()
+        .map(|&user_id| (mentions::post_id.eq(post_id), mentions::user_id.eq(user_id)))
+        .collect();
+    diesel::insert_into(mentions::table)
+        .values(&rows)
+        .execute(conn)?;
+
+    // scheduled posts would be revealed early, they notify once they are published
+    if time_posted <= Utc::now() {
+        notify_mentioned(conn, post_id, author_id, visibility, &user_ids)?;
+    }
+    Ok(())
+}
+
+/// Notifies the users mentioned by scheduled post `post_id` now that it is published, and the
+/// author of the post it replies to. Nothing happens if the post was deleted in the meantime.
+pub fn publish(conn: &mut PgConnection, post_id: Uuid) -> Result<(), QueryError> {
+    use crate::schema::{mentions, posts};
+
+    let post: Option<(Uuid, String, Option<Uuid>)> = posts::table
+        .find(post_id)
+        .filter(posts::deleted_at.is_null())
+        .select((posts::user_id, posts::visibility, posts::reply_to))
+        .get_result(conn)
+        .optional()?;
+    let Some((author_id, visibility, reply_to)) = post else {
+        return Ok(());
+    };
+
+    let user_ids: Vec<Uuid> = mentions::table
+        .filter(mentions::post_id.eq(post_id))
+        .select(mentions::user_id)
+        .load(conn)?;
+    // the check constraint only allows known visibilities
+    let visibility = Visibility::parse(&visibility).unwrap_or_default();
+    notify_mentioned(conn, post_id, author_id, visibility, &user_ids)?;
+    if let Some(reply_to) = reply_to {
+        notify_replied_to(conn, post_id, author_id, visibility, reply_to)?;
+    }
+    Ok(())
+}
+
+/// Notifies the author of post `reply_to` about the reply `post_id`, if they can see it.
+fn notify_replied_to(
+    conn: &mut PgConnection,
+    post_id: Uuid,
+    author_id: Uuid,
+    visibility: Visibility,
+    reply_to: Uuid,
+) -> Result<(), QueryError> {
+    use crate::schema::posts;
+
+    let parent_author: Uuid = posts::table
+        .find(reply_to)
+        .select(posts::user_id)
+        .get_result(conn)?;
+    if can_see(conn, parent_author, author_id, visibility)? {
+        notification::new(
+            conn,
+            parent_author,
+            author_id,
+            NotificationKind::Reply,
+            Some(post_id),
+        )?;
+    }
+    Ok(())
+}
+
+/// Users who are told about a post as soon as it is published.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Audience {
+    pub author_id: Uuid,
+    /// Recipient of a direct message.
+    pub direct_message_to: Option<Uuid>,
+    /// Followers of the author who see the post in their timeline. Empty for direct messages.
+    pub followers: Vec<Uuid>,
+}
+
+#[derive(QueryableByName)]
+struct AudienceRow {
+    #[diesel(sql_type = SqlUuid)]
+    author_id: Uuid,
+    #[diesel(sql_type = Nullable<SqlUuid>)]
+    direct_message_to: Option<Uuid>,
+    #[diesel(sql_type = Nullable<SqlUuid>)]
+    follower_id: Option<Uuid>,
+}
+
+/// [`Audience`] of post `post_id`, or `None` if it isn't published, was deleted or hidden by
+/// moderators. Followers who can't see the post or hide the author are left out, and so are
+/// followers of unlisted posts, which stay out of timelines.
+pub fn audience(conn: &mut PgConnection, post_id: Uuid) -> Result<Option<Audience>, QueryError> {
+    let rows: Vec<AudienceRow> = diesel::sql_query(
+        "SELECT p.user_id AS author_id, p.direct_message_to, f.user_id AS follower_id \
+         FROM posts p \
+         LEFT JOIN followers f ON f.follows = p.user_id \
+              AND p.direct_message_to IS NULL \
+              AND p.visibility <> 'unlisted' \
+              AND NOT hidden_from(f.user_id, p.user_id) \
+              AND can_see_post(f.user_id, p.user_id, p.visibility) \
+         WHERE p.id = $1 \
+           AND p.time_posted <= statement_timestamp() \
+           AND p.deleted_at IS NULL \
+           AND p.hidden_at IS NULL \
+           AND (p.direct_message_to IS NULL \
+                OR NOT blocked_between(p.user_id, p.direct_message_to))",
+    )
+    .bind::<SqlUuid, _>(post_id)
+    .load(conn)?;
+
+    let Some(first) = rows.first() else {
+        return Ok(None);
+    };
+    Ok(Some(Audience {
+        author_id: first.author_id,
+        direct_message_to: first.direct_message_to,
+        followers: rows.iter().filter_map(|row| row.follower_id).collect(),
+    }))
+}
+
+fn notify_mentioned(
+    conn: &mut PgConnection,
+    post_id: Uuid,
+    author_id: Uuid,
+    visibility: Visibility,
+    user_ids: &[Uuid],
+) -> Result<(), QueryError> {
+    for &user_id in user_ids {
+        if !can_see(conn, user_id, author_id, visibility)? {
+            continue;
+        }
+        notification::new(
+            conn,
+            user_id,
+            author_id,
+            NotificationKind::Mention,
+            Some(post_id),
+        )?;
+    }
+    Ok(())
+}
+
+/// Post `post_id` as `viewer_id` sees it. Unlike search results and timelines, this includes
+/// unlisted posts.
+///
+/// Fails with [`QueryError::NotFound`] if the post doesn't exist or `viewer_id` can't see it, like
+/// posts of other users which are scheduled, hidden by moderators, direct messages to someone
+/// else, or not visible to `viewer_id` because of blocks, private users or their visibility.
+pub fn get(
+    conn: &mut PgConnection,
+    viewer_id: Uuid,
+    post_id: Uuid,
+) -> Result<PostPreview, QueryError> {
+    Ok(diesel::sql_query(
+        "SELECT p.id, p.user_id AS author_id, u.handle AS author_handle, \
+                post_text(p.content) AS text, p.time_posted, p.edited_at, \
+                p.deleted_at \
+         FROM posts p \
+         JOIN users u ON u.id = p.user_id \
+         WHERE p.id = $1 \
+           AND (p.user_id = $2 OR ( \
+                p.time_posted <= statement_timestamp() \
+                AND p.hidden_at IS NULL \
+                AND NOT blocked_between($2, p.user_id) \
+                AND (p.direct_message_to = $2 \
+                     OR (p.direct_message_to IS NULL \
+                         AND can_see_post($2, p.user_id, p.visibility)))))",
+    )
+    .bind::<SqlUuid, _>(post_id)
+    .bind::<SqlUuid, _>(viewer_id)
+    .get_result(conn)?)
+}
+
+/// Whether `viewer_id` can see post `post_id`, see [`get`].
+pub fn is_visible_to(
+    conn: &mut PgConnection,
+    viewer_id: Uuid,
+    post_id: Uuid,
+) -> Result<bool, QueryError> {
+    match get(conn, viewer_id, post_id) {
+        Ok(_) => Ok(true),
+        Err(QueryError::NotFound) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[derive(QueryableByName)]
+struct CanSee {
+    #[diesel(sql_type = Bool)]
+    visible: bool,
+}
+
+/// Whether `viewer_id` can see posts of `author_id` with `visibility`, ignoring blocks and
+/// moderation.
+fn can_see(
+    conn: &mut PgConnection,
+    viewer_id: Uuid,
+    author_id: Uuid,
+    visibility: Visibility,
+) -> Result<bool, QueryError> {
+    let found: CanSee = diesel::sql_query("SELECT can_see_post($1, $2, $3) AS visible")
+        .bind::<SqlUuid, _>(viewer_id)
+        .bind::<SqlUuid, _>(author_id)
+        .bind::<Text, _>(visibility.as_str())
+        .get_result(conn)?;
+    Ok(found.visible)
+}
+
+/// Every string inside the content of a post, separated by spaces. Matches the `post_text`
+/// function in the database, apart from the order of object fields.
+pub fn text(content: &Value) -> String {
+    fn collect<'a>(value: &'a Value, strings: &mut Vec<&'a str>) {
+        match value {
+            Value::String(s) => strings.push(s),
+            Value::Array(values) => values.iter().for_each(|v| collect(v, strings)),
+            Value::Object(fields) => fields.values().for_each(|v| collect(v, strings)),
+            Value::Null | Value::Bool(_) | Value::Number(_) => (),
+        }
+    }
+
+    let mut strings = vec![];
+    collect(content, &mut strings);
+    strings.join(" ")
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::test_db::new_connection;
+    use crate::user::tests::new_user;
+    use serde_json::json;
+    use std::collections::HashSet;
+
+    pub fn new_public_post(conn: &mut PgConnection, user_id: Uuid, message: &str) -> Uuid {
+        new_post(conn, user_id, message, Visibility::Public)
+    }
+
+    pub fn new_post(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        message: &str,
+        visibility: Visibility,
+    ) -> Uuid {
+        new(
+            conn,
+            &NewPost {
+                user_id,
+                content: json!({ "Chat": { "message": message } }),
+                time_posted: Utc::now(),
+                direct_message_to: None,
+                reply_to: None,
+                visibility,
+            },
+        )
+        .unwrap()
+    }
+
+    /// Public post published, or scheduled, at `time_posted`.
+    pub fn new_post_at(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        message: &str,
+        time_posted: DateTime<Utc>,
+    ) -> Uuid {
+        new(
+            conn,
+            &NewPost {
+                user_id,
+                content: json!({ "Chat": { "message": message } }),
+                time_posted,
+                direct_message_to: None,
+                reply_to: None,
+                visibility: Visibility::Public,
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn collects_text() {
+        let content = json!({ "Poll": { "headline": "Best?", "choices": ["a", "b"], "n": 1 } });
+        let text = text(&content);
+        assert!(text.contains("Best?"));
+        assert!(text.contains("a b"));
+    }
+
+    #[test]
+    fn notifies_mentioned_users() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let reader = new_user(&mut conn, "reader");
+
+        let post_id = new_public_post(&mut conn, author, "hi @reader, @author and @nobody");
+
+        let mentioned: Vec<Uuid> = crate::schema::mentions::table
+            .filter(crate::schema::mentions::post_id.eq(post_id))
+            .select(crate::schema::mentions::user_id)
+            .load(&mut conn)
+            .unwrap();
+        assert_eq!(mentioned.len(), 2);
+
+        let inbox = notification::list(&mut conn, reader, None, 10).unwrap();
+        assert_eq!(inbox.len(), 1);
+        assert_eq!(inbox[0].kind, NotificationKind::Mention);
+        assert_eq!(inbox[0].post_id, Some(post_id));
+        // authors aren't notified about mentioning themselves
+        assert_eq!(notification::unread_count(&mut conn, author).unwrap(), 0);
+    }
+
+    #[test]
+    fn notifies_authors_of_replies() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let reader = new_user(&mut conn, "reader");
+        let post_id = new_public_post(&mut conn, author, "hello");
+
+        let mut reply = NewPost {
+            user_id: reader,
+            content: json!({ "Chat": { "message": "hi" } }),
+            time_posted: Utc::now(),
+            direct_message_to: None,
+            reply_to: Some(post_id),
+            visibility: Visibility::Public,
+        };
+        let reply_id = new(&mut conn, &reply).unwrap();
+
+        let inbox = notification::list(&mut conn, author, None, 10).unwrap();
+        assert_eq!(inbox.len(), 1);
+        assert_eq!(inbox[0].kind, NotificationKind::Reply);
+        assert_eq!(inbox[0].actor_id, reader);
+        assert_eq!(inbox[0].post_id, Some(reply_id));
+
+        // replies to their own posts don't notify authors
+        reply.user_id = author;
+        new(&mut conn, &reply).unwrap();
+        assert_eq!(notification::unread_count(&mut conn, author).unwrap(), 1);
+
+        // authors who can't see the reply aren't told about it
+        reply.user_id = reader;
+        reply.visibility = Visibility::Followers;
+        new(&mut conn, &reply).unwrap();
+        assert_eq!(notification::unread_count(&mut conn, author).unwrap(), 1);
+    }
+
+    #[test]
+    fn notifies_mentions_of_scheduled_posts_once_published() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let reader = new_user(&mut conn, "reader");
+
+        let post_id = new(
+            &mut conn,
+            &NewPost {
+                user_id: author,
+                content: json!({ "Chat": { "message": "soon @reader" } }),
+                time_posted: Utc::now() + chrono::Duration::hours(1),
+                direct_message_to: None,
+                reply_to: None,
+                visibility: Visibility::Public,
+            },
+        )
+        .unwrap();
+        assert_eq!(notification::unread_count(&mut conn, reader).unwrap(), 0);
+        // the job isn't due yet
+        assert!(job::claim(&mut conn, chrono::Duration::minutes(5))
+            .unwrap()
+            .is_none());
+
+        publish(&mut conn, post_id).unwrap();
+        let inbox = notification::list(&mut conn, reader, None, 10).unwrap();
+        assert_eq!(inbox.len(), 1);
+        assert_eq!(inbox[0].post_id, Some(post_id));
+    }
+
+    #[test]
+    fn rejects_interactions_across_blocks() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let reader = new_user(&mut conn, "reader");
+        let post_id = new_public_post(&mut conn, author, "hello");
+        crate::block::block(&mut conn, author, reader).unwrap();
+
+        let mut reply = NewPost {
+            user_id: reader,
+            content: json!({ "Chat": { "message": "hi" } }),
+            time_posted: Utc::now(),
+            direct_message_to: None,
+            reply_to: Some(post_id),
+            visibility: Visibility::Public,
+        };
+        assert!(matches!(new(&mut conn, &reply), Err(QueryError::Blocked)));
+
+        reply.reply_to = None;
+        reply.direct_message_to = Some(author);
+        assert!(matches!(new(&mut conn, &reply), Err(QueryError::Blocked)));
+    }
+
+    #[test]
+    fn skips_direct_messages() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let reader = new_user(&mut conn, "reader");
+
+        new(
+            &mut conn,
+            &NewPost {
+                user_id: author,
+                content: json!({ "Chat": { "message": "#secret @reader" } }),
+                time_posted: Utc::now(),
+                direct_message_to: Some(reader),
+                reply_to: None,
+                visibility: Visibility::Public,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(notification::unread_count(&mut conn, reader).unwrap(), 0);
+        assert!(
+            tag::trending(&mut conn, Utc::now() - chrono::Duration::hours(1), 10)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn finds_the_audience_of_posts() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let follower = new_user(&mut conn, "follower");
+        let muter = new_user(&mut conn, "muter");
+        crate::follow::follow(&mut conn, follower, author).unwrap();
+        crate::follow::follow(&mut conn, muter, author).unwrap();
+        crate::mute::mute(&mut conn, muter, author).unwrap();
+
+        let public = new_public_post(&mut conn, author, "hello");
+        let audience = super::audience(&mut conn, public).unwrap().unwrap();
+        assert_eq!(audience.author_id, author);
+        assert_eq!(audience.direct_message_to, None);
+        assert_eq!(audience.followers, vec![follower]);
+
+        let unlisted = new_post(&mut conn, author, "quiet", Visibility::Unlisted);
+        let audience = super::audience(&mut conn, unlisted).unwrap().unwrap();
+        assert!(audience.followers.is_empty());
+
+        let message = new(
+            &mut conn,
+            &NewPost {
+                user_id: author,
+                content: json!({ "Chat": { "message": "psst" } }),
+                time_posted: Utc::now(),
+                direct_message_to: Some(muter),
+                reply_to: None,
+                visibility: Visibility::Public,
+            },
+        )
+        .unwrap();
+        let audience = super::audience(&mut conn, message).unwrap().unwrap();
+        assert_eq!(audience.direct_message_to, Some(muter));
+        assert!(audience.followers.is_empty());
+
+        delete(&mut conn, author, public).unwrap();
+        assert_eq!(super::audience(&mut conn, public).unwrap(), None);
+    }
+
+    #[test]
+    fn enforces_visibility() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let follower = new_user(&mut conn, "follower");
+        let stranger = new_user(&mut conn, "stranger");
+        crate::follow::follow(&mut conn, follower, author).unwrap();
+
+        let public = new_post(&mut conn, author, "#news public", Visibility::Public);
+        let followers = new_post(&mut conn, author, "#news followers", Visibility::Followers);
+        let unlisted = new_post(&mut conn, author, "#news unlisted", Visibility::Unlisted);
+
+        assert!(matches!(
+            get(&mut conn, stranger, followers),
+            Err(QueryError::NotFound)
+        ));
+        assert_eq!(get(&mut conn, follower, followers).unwrap().id, followers);
+        assert_eq!(get(&mut conn, author, followers).unwrap().id, followers);
+        assert_eq!(get(&mut conn, stranger, unlisted).unwrap().id, unlisted);
+
+        let timeline = |conn: &mut PgConnection, viewer_id| -> HashSet<Uuid> {
+            tag::timeline(conn, viewer_id, "news", None, 10)
+                .unwrap()
+                .into_iter()
+                .map(|post| post.id)
+                .collect()
+        };
+        assert_eq!(timeline(&mut conn, stranger), HashSet::from([public]));
+        assert_eq!(
+            timeline(&mut conn, follower),
+            HashSet::from([public, followers])
+        );
+        assert!(crate::search::posts(&mut conn, stranger, "unlisted", 0, 10)
+            .unwrap()
+            .is_empty());
+
+        new_post(
+            &mut conn,
+            author,
+            "hi @follower and @stranger",
+            Visibility::Followers,
+        );
+        assert_eq!(notification::unread_count(&mut conn, follower).unwrap(), 1);
+        assert_eq!(notification::unread_count(&mut conn, stranger).unwrap(), 0);
+
+        let reply = NewPost {
+            user_id: stranger,
+            content: json!({ "Chat": { "message": "hi" } }),
+            time_posted: Utc::now(),
+            direct_message_to: None,
+            reply_to: Some(followers),
+            visibility: Visibility::Public,
+        };
+        assert!(matches!(new(&mut conn, &reply), Err(QueryError::NotFound)));
+    }
+
+    #[test]
+    fn keeps_revisions_of_edits() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let reader = new_user(&mut conn, "reader");
+        let post_id = new_public_post(&mut conn, author, "#old hi");
+        let since = Utc::now() - chrono::Duration::minutes(15);
+
+        let content = json!({ "Chat": { "message": "#new hi @reader" } });
+        assert!(matches!(
+            edit(&mut conn, reader, post_id, &content, since),
+            Err(QueryError::NotFound)
+        ));
+        edit(&mut conn, author, post_id, &content, since).unwrap();
+        edit(&mut conn, author, post_id, &content, since).unwrap();
+
+        let post = get(&mut conn, reader, post_id).unwrap();
+        assert_eq!(post.text, "#new hi @reader");
+        assert!(post.edited_at.is_some());
+        let revisions = revisions(&mut conn, reader, post_id).unwrap();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(
+            revisions[1].content,
+            json!({ "Chat": { "message": "#old hi" } })
+        );
+        assert!(tag::timeline(&mut conn, reader, "old", None, 10)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            tag::timeline(&mut conn, reader, "new", None, 10)
+                .unwrap()
+                .len(),
+            1
+        );
+        // mentioned once, even though both edits contain the mention
+        assert_eq!(notification::unread_count(&mut conn, reader).unwrap(), 1);
+
+        assert!(matches!(
+            edit(&mut conn, author, post_id, &content, Utc::now()),
+            Err(QueryError::EditWindowClosed)
+        ));
+    }
+
+    #[test]
+    fn locks_poll_choices_after_votes() {
+        use crate::schema::{poll_choices, poll_votes};
+
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let voter = new_user(&mut conn, "voter");
+        let post_id = new(
+            &mut conn,
+            &NewPost {
+                user_id: author,
+                content: json!({ "Poll": { "headline": "Best?", "choices": ["a", "b"] } }),
+                time_posted: Utc::now(),
+                direct_message_to: None,
+                reply_to: None,
+                visibility: Visibility::Public,
+            },
+        )
+        .unwrap();
+        let since = Utc::now() - chrono::Duration::minutes(15);
+
+        let choice_id = Uuid::new_v4();
+        diesel::insert_into(poll_choices::table)
+            .values((
+                poll_choices::id.eq(choice_id),
+                poll_choices::choice.eq("a"),
+                poll_choices::post_id.eq(post_id),
+            ))
+            .execute(&mut conn)
+            .unwrap();
+        diesel::insert_into(poll_votes::table)
+            .values((
+                poll_votes::user_id.eq(voter),
+                poll_votes::post_id.eq(post_id),
+                poll_votes::choice_id.eq(choice_id),
+            ))
+            .execute(&mut conn)
+            .unwrap();
+
+        let renamed = json!({ "Poll": { "headline": "Worst?", "choices": ["a", "b"] } });
+        edit(&mut conn, author, post_id, &renamed, since).unwrap();
+        let rechosen = json!({ "Poll": { "headline": "Worst?", "choices": ["a", "c"] } });
+        assert!(matches!(
+            edit(&mut conn, author, post_id, &rechosen, since),
+            Err(QueryError::PollHasVotes)
+        ));
+    }
+
+    #[test]
+    fn keeps_deleted_posts_as_placeholders() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let reader = new_user(&mut conn, "reader");
+        let post_id = new_public_post(&mut conn, author, "#gone soon");
+        let reply_id = new(
+            &mut conn,
+            &NewPost {
+                user_id: reader,
+                content: json!({ "Chat": { "message": "reply" } }),
+                time_posted: Utc::now(),
+                direct_message_to: None,
+                reply_to: Some(post_id),
+                visibility: Visibility::Public,
+            },
+        )
+        .unwrap();
+
+        assert!(matches!(
+            delete(&mut conn, reader, post_id),
+            Err(QueryError::NotFound)
+        ));
+        delete(&mut conn, author, post_id).unwrap();
+        assert!(matches!(
+            delete(&mut conn, author, post_id),
+            Err(QueryError::NotFound)
+        ));
+
+        let post = get(&mut conn, reader, post_id).unwrap();
+        assert!(post.deleted_at.is_some());
+        assert_eq!(post.text, "");
+        assert!(get(&mut conn, reader, reply_id).is_ok());
+        assert!(tag::timeline(&mut conn, reader, "gone", None, 10)
+            .unwrap()
+            .is_empty());
+
+        let content = json!({ "Chat": { "message": "back" } });
+        let since = Utc::now() - chrono::Duration::minutes(15);
+        assert!(matches!(
+            edit(&mut conn, author, post_id, &content, since),
+            Err(QueryError::NotFound)
+        ));
+    }
+}
@@ -0,0 +1,125 @@
+//! Likes and dislikes of posts.
+
+use diesel::prelude::*;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::notification::{self, NotificationKind};
+use crate::{block, post, QueryError};
+
+/// Sets the reaction of `user_id` to post `post_id`, replacing an earlier one. `like_status` is
+/// `1` for likes, `-1` for dislikes and `0` to take the reaction back. The author is notified
+/// unless the reaction was taken back or didn't change. Returns the author of the post.
+///
+/// Fails with [`QueryError::NotFound`] if `user_id` can't see the post or it was deleted, and with
+/// [`QueryError::Blocked`] if the author blocked `user_id` or was blocked by them.
+pub fn react(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    post_id: Uuid,
+    like_status: i16,
+) -> Result<Uuid, QueryError> {
+    use crate::schema::{posts, reactions};
+
+    conn.transaction(|conn| {
+        let (author_id, deleted_at): (Uuid, Option<chrono::DateTime<chrono::Utc>>) = posts::table
+            .find(post_id)
+            .select((posts::user_id, posts::deleted_at))
+            .get_result(conn)?;
+        block::ensure_not_blocked(conn, user_id, author_id)?;
+        if deleted_at.is_some() || !post::is_visible_to(conn, user_id, post_id)? {
+            return Err(QueryError::NotFound);
+        }
+
+        let previous: Option<i16> = reactions::table
+            .find((user_id, post_id))
+            .select(reactions::like_status)
+            .get_result(conn)
+            .optional()?;
+        diesel::insert_into(reactions::table)
+            .values((
+                reactions::user_id.eq(user_id),
+                reactions::post_id.eq(post_id),
+                reactions::like_status.eq(like_status),
+            ))
+            .on_conflict((reactions::user_id, reactions::post_id))
+            .do_update()
+            .set((
+                reactions::like_status.eq(like_status),
+                reactions::created_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)?;
+
+        if like_status != 0 && previous != Some(like_status) {
+            notification::new(
+                conn,
+                author_id,
+                user_id,
+                NotificationKind::Reaction,
+                Some(post_id),
+            )?;
+        }
+        Ok(author_id)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post::tests::new_public_post;
+    use crate::test_db::new_connection;
+    use crate::user::tests::new_user;
+
+    #[test]
+    fn replaces_earlier_reactions() {
+        use crate::schema::reactions;
+
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let reader = new_user(&mut conn, "reader");
+        let post_id = new_public_post(&mut conn, author, "hello");
+
+        assert_eq!(react(&mut conn, reader, post_id, 1).unwrap(), author);
+        react(&mut conn, reader, post_id, -1).unwrap();
+
+        let like_status: Vec<i16> = reactions::table
+            .filter(reactions::post_id.eq(post_id))
+            .select(reactions::like_status)
+            .load(&mut conn)
+            .unwrap();
+        assert_eq!(like_status, vec![-1]);
+    }
+
+    #[test]
+    fn notifies_authors_of_changed_reactions() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let reader = new_user(&mut conn, "reader");
+        let post_id = new_public_post(&mut conn, author, "hello");
+
+        react(&mut conn, reader, post_id, 1).unwrap();
+        react(&mut conn, reader, post_id, 1).unwrap();
+        react(&mut conn, reader, post_id, 0).unwrap();
+        let inbox = notification::list(&mut conn, author, None, 10).unwrap();
+        assert_eq!(inbox.len(), 1);
+        assert_eq!(inbox[0].kind, NotificationKind::Reaction);
+        assert_eq!(inbox[0].post_id, Some(post_id));
+
+        react(&mut conn, reader, post_id, -1).unwrap();
+        assert_eq!(notification::unread_count(&mut conn, author).unwrap(), 2);
+    }
+
+    #[test]
+    fn rejects_reactions_across_blocks() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let reader = new_user(&mut conn, "reader");
+        let post_id = new_public_post(&mut conn, author, "hello");
+        crate::block::block(&mut conn, author, reader).unwrap();
+
+        assert!(matches!(
+            react(&mut conn, reader, post_id, 1),
+            Err(QueryError::Blocked)
+        ));
+    }
+}
@@ -0,0 +1,341 @@
+//! Reports of abusive posts and users, which moderators work through in the order they were
+//! filed.
+
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Nullable, Text, Timestamptz, Uuid as SqlUuid};
+use diesel::PgConnection;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::audit::{self, AuditAction};
+use crate::QueryError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReportReason {
+    Spam,
+    Harassment,
+    Hate,
+    Violence,
+    Other,
+}
+
+impl ReportReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Spam => "spam",
+            Self::Harassment => "harassment",
+            Self::Hate => "hate",
+            Self::Violence => "violence",
+            Self::Other => "other",
+        }
+    }
+
+    pub fn parse(reason: &str) -> Option<Self> {
+        match reason {
+            "spam" => Some(Self::Spam),
+            "harassment" => Some(Self::Harassment),
+            "hate" => Some(Self::Hate),
+            "violence" => Some(Self::Violence),
+            "other" => Some(Self::Other),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReportStatus {
+    Open,
+    /// A moderator acted on the report.
+    Resolved,
+    /// A moderator found nothing wrong.
+    Dismissed,
+}
+
+impl ReportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Resolved => "resolved",
+            Self::Dismissed => "dismissed",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<Self> {
+        match status {
+            "open" => Some(Self::Open),
+            "resolved" => Some(Self::Resolved),
+            "dismissed" => Some(Self::Dismissed),
+            _ => None,
+        }
+    }
+}
+
+/// What a report is about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    User(Uuid),
+    Post(Uuid),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    pub id: Uuid,
+    pub reporter_id: Uuid,
+    pub reporter_handle: String,
+    /// Reported user, or the author of the reported post.
+    pub user_id: Uuid,
+    pub user_handle: String,
+    pub post_id: Option<Uuid>,
+    /// Every string inside the content of the reported post.
+    pub post_text: Option<String>,
+    pub reason: ReportReason,
+    pub details: String,
+    pub status: ReportStatus,
+    /// `None` for open reports, and reports of moderators who don't exist anymore.
+    pub resolved_by_handle: Option<String>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(QueryableByName)]
+struct ReportRow {
+    #[diesel(sql_type = SqlUuid)]
+    id: Uuid,
+    #[diesel(sql_type = SqlUuid)]
+    reporter_id: Uuid,
+    #[diesel(sql_type = Text)]
+    reporter_handle: String,
+    #[diesel(sql_type = SqlUuid)]
+    user_id: Uuid,
+    #[diesel(sql_type = Text)]
+    user_handle: String,
+    #[diesel(sql_type = Nullable<SqlUuid>)]
+    post_id: Option<Uuid>,
+    #[diesel(sql_type = Nullable<Text>)]
+    post_text: Option<String>,
+    #[diesel(sql_type = Text)]
+    reason: String,
+    #[diesel(sql_type = Text)]
+    details: String,
+    #[diesel(sql_type = Text)]
+    status: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    resolved_by_handle: Option<String>,
+    #[diesel(sql_type = Nullable<Timestamptz>)]
+    resolved_at: Option<DateTime<Utc>>,
+    #[diesel(sql_type = Timestamptz)]
+    created_at: DateTime<Utc>,
+}
+
+/// Position in the queue. Pages continue after the last report of the previous page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+/// Files a report. Reports of posts are filed against their author as well.
+///
+/// Fails with [`QueryError::UniqueViolation`] if the reporter already has an open report about
+/// the same post or user.
+pub fn new(
+    conn: &mut PgConnection,
+    reporter_id: Uuid,
+    target: Target,
+    reason: ReportReason,
+    details: &str,
+) -> Result<Uuid, QueryError> {
+    use crate::schema::{posts, reports};
+
+    let (user_id, post_id) = match target {
+        Target::User(user_id) => (user_id, None),
+        Target::Post(post_id) => {
+            let author = posts::table
+                .find(post_id)
+                .select(posts::user_id)
+                .get_result(conn)?;
+            (author, Some(post_id))
+        }
+    };
+
+    let id = Uuid::new_v4();
+    diesel::insert_into(reports::table)
+        .values((
+            reports::id.eq(id),
+            reports::reporter_id.eq(reporter_id),
+            reports::user_id.eq(user_id),
+            reports::post_id.eq(post_id),
+            reports::reason.eq(reason.as_str()),
+            reports::details.eq(details),
+        ))
+        .execute(conn)?;
+    Ok(id)
+}
+
+/// Reports with `status`, oldest first, starting after `after`.
+pub fn list(
+    conn: &mut PgConnection,
+    status: ReportStatus,
+    after: Option<Cursor>,
+    limit: i64,
+) -> Result<Vec<Report>, QueryError> {
+    let rows: Vec<ReportRow> = diesel::sql_query(
+        "SELECT r.id, r.reporter_id, reporter.handle AS reporter_handle, \
+                r.user_id, reported.handle AS user_handle, \
+                r.post_id, post_text(p.content) AS post_text, \
+                r.reason, r.details, r.status, \
+                moderator.handle AS resolved_by_handle, r.resolved_at, r.created_at \
+         FROM reports r \
+         JOIN users reporter ON reporter.id = r.reporter_id \
+         JOIN users reported ON reported.id = r.user_id \
+         LEFT JOIN posts p ON p.id = r.post_id \
+         LEFT JOIN users moderator ON moderator.id = r.resolved_by \
+         WHERE r.status = $1 \
+           AND ($2::timestamptz IS NULL OR (r.created_at, r.id) > ($2, $3)) \
+         ORDER BY r.created_at, r.id \
+         LIMIT $4",
+    )
+    .bind::<Text, _>(status.as_str())
+    .bind::<Nullable<Timestamptz>, _>(after.map(|c| c.created_at))
+    .bind::<Nullable<SqlUuid>, _>(after.map(|c| c.id))
+    .bind::<BigInt, _>(limit)
+    .load(conn)?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            // the check constraints only allow known reasons and statuses
+            Some(Report {
+                id: row.id,
+                reporter_id: row.reporter_id,
+                reporter_handle: row.reporter_handle,
+                user_id: row.user_id,
+                user_handle: row.user_handle,
+                post_id: row.post_id,
+                post_text: row.post_text,
+                reason: ReportReason::parse(&row.reason)?,
+                details: row.details,
+                status: ReportStatus::parse(&row.status)?,
+                resolved_by_handle: row.resolved_by_handle,
+                resolved_at: row.resolved_at,
+                created_at: row.created_at,
+            })
+        })
+        .collect())
+}
+
+/// Closes an open report as resolved or dismissed, and records it in the audit log.
+///
+/// Fails with [`QueryError::NotFound`] if there is no open report with the id.
+pub fn close(
+    conn: &mut PgConnection,
+    moderator_id: Option<Uuid>,
+    report_id: Uuid,
+    dismissed: bool,
+) -> Result<(), QueryError> {
+    use crate::schema::reports::{self, columns};
+
+    let (status, action) = if dismissed {
+        (ReportStatus::Dismissed, AuditAction::DismissReport)
+    } else {
+        (ReportStatus::Resolved, AuditAction::ResolveReport)
+    };
+
+    conn.transaction(|conn| {
+        let (user_id, post_id): (Uuid, Option<Uuid>) = diesel::update(reports::table)
+            .filter(columns::id.eq(report_id))
+            .filter(columns::status.eq(ReportStatus::Open.as_str()))
+            .set((
+                columns::status.eq(status.as_str()),
+                columns::resolved_by.eq(moderator_id),
+                columns::resolved_at.eq(Utc::now()),
+            ))
+            .returning((columns::user_id, columns::post_id))
+            .get_result(conn)?;
+
+        let mut entry = audit::NewEntry::new(moderator_id, action);
+        entry.user_id = Some(user_id);
+        entry.post_id = post_id;
+        entry.report_id = Some(report_id);
+        entry.details = json!({ "status": status.as_str() });
+        audit::record(conn, &entry)?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post::tests::new_public_post;
+    use crate::test_db::new_connection;
+    use crate::user::tests::new_user;
+
+    #[test]
+    fn works_through_the_queue() {
+        let mut conn = new_connection();
+        let reporter = new_user(&mut conn, "reporter");
+        let author = new_user(&mut conn, "author");
+        let moderator = new_user(&mut conn, "moderator");
+        let post_id = new_public_post(&mut conn, author, "buy now");
+
+        let first = new(
+            &mut conn,
+            reporter,
+            Target::Post(post_id),
+            ReportReason::Spam,
+            "",
+        )
+        .unwrap();
+        let second = new(
+            &mut conn,
+            reporter,
+            Target::User(author),
+            ReportReason::Spam,
+            "all of their posts",
+        )
+        .unwrap();
+        assert!(matches!(
+            new(
+                &mut conn,
+                reporter,
+                Target::Post(post_id),
+                ReportReason::Other,
+                ""
+            ),
+            Err(QueryError::UniqueViolation)
+        ));
+
+        let page = list(&mut conn, ReportStatus::Open, None, 1).unwrap();
+        assert_eq!(page.len(), 1);
+        let cursor = Cursor {
+            created_at: page[0].created_at,
+            id: page[0].id,
+        };
+        let mut queue = page;
+        queue.extend(list(&mut conn, ReportStatus::Open, Some(cursor), 10).unwrap());
+        assert_eq!(queue.len(), 2);
+
+        let post_report = queue.iter().find(|r| r.id == first).unwrap();
+        assert_eq!(post_report.user_id, author);
+        assert_eq!(post_report.post_text.as_deref(), Some("buy now"));
+        assert!(queue.iter().any(|r| r.id == second));
+
+        close(&mut conn, Some(moderator), first, true).unwrap();
+        assert!(matches!(
+            close(&mut conn, Some(moderator), first, false),
+            Err(QueryError::NotFound)
+        ));
+        let dismissed = list(&mut conn, ReportStatus::Dismissed, None, 10).unwrap();
+        assert_eq!(dismissed.len(), 1);
+        assert_eq!(
+            dismissed[0].resolved_by_handle.as_deref(),
+            Some("moderator")
+        );
+
+        let log = audit::list(&mut conn, None, 10).unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].action, AuditAction::DismissReport);
+        assert_eq!(log[0].report_id, Some(first));
+    }
+}
@@ -0,0 +1,192 @@
+//! Full-text search over posts and users.
+//!
+//! Both tables have a generated `search_text` column with a GIN index. Posts are matched with
+//! `websearch_to_tsquery`, which accepts anything a user types. Users are matched by prefix, so
+//! people can be found before their handle is typed out.
+
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Nullable, Text, Uuid as SqlUuid};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::post::PostPreview;
+use crate::QueryError;
+
+#[derive(Clone, Debug, PartialEq, Eq, QueryableByName)]
+pub struct UserMatch {
+    #[diesel(sql_type = SqlUuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = Text)]
+    pub handle: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub display_name: Option<String>,
+}
+
+/// Public posts matching `query`, best matches first.
+///
+/// Direct messages and posts scheduled for later are never returned, and neither are posts hidden
+/// from `viewer_id` by blocks and mutes, or by moderators unless `viewer_id` wrote them. Unlisted
+/// posts are left out, and so are posts `viewer_id` can't see because they don't follow the
+/// author.
+pub fn posts(
+    conn: &mut PgConnection,
+    viewer_id: Uuid,
+    query: &str,
+    offset: i64,
+    limit: i64,
+) -> Result<Vec<PostPreview>, QueryError> {
+    Ok(diesel::sql_query(
+        "SELECT p.id, p.user_id AS author_id, u.handle AS author_handle, \
+                post_text(p.content) AS text, p.time_posted, p.edited_at, \
+                p.deleted_at \
+         FROM posts p \
+         JOIN users u ON u.id = p.user_id, \
+              websearch_to_tsquery('english', $1) q \
+         WHERE p.search_text @@ q \
+           AND p.direct_message_to IS NULL \
+           AND p.time_posted <= statement_timestamp() \
+           AND (p.hidden_at IS NULL OR p.user_id = $4) \
+           AND NOT hidden_from($4, p.user_id) \
+           AND p.visibility <> 'unlisted' \
+           AND can_see_post($4, p.user_id, p.visibility) \
+         ORDER BY ts_rank(p.search_text, q) DESC, p.time_posted DESC, p.id \
+         LIMIT $2 OFFSET $3",
+    )
+    .bind::<Text, _>(query)
+    .bind::<BigInt, _>(limit)
+    .bind::<BigInt, _>(offset)
+    .bind::<SqlUuid, _>(viewer_id)
+    .load(conn)?)
+}
+
+/// Users whose handle or display name has words starting with the words in `query`, best
+/// matches first. Users who blocked `viewer_id` or were blocked by them aren't returned, and
+/// neither is [`crate::user::DELETED_USER_ID`].
+pub fn users(
+    conn: &mut PgConnection,
+    viewer_id: Uuid,
+    query: &str,
+    offset: i64,
+    limit: i64,
+) -> Result<Vec<UserMatch>, QueryError> {
+    let Some(tsquery) = prefix_query(query) else {
+        return Ok(vec![]);
+    };
+
+    Ok(diesel::sql_query(
+        "SELECT u.id, u.handle, u.display_name \
+         FROM users u, to_tsquery('simple', $1) q \
+         WHERE u.search_text @@ q \
+           AND NOT blocked_between($4, u.id) \
+           AND u.id <> $5 \
+         ORDER BY ts_rank(u.search_text, q) DESC, u.handle \
+         LIMIT $2 OFFSET $3",
+    )
+    .bind::<Text, _>(tsquery)
+    .bind::<BigInt, _>(limit)
+    .bind::<BigInt, _>(offset)
+    .bind::<SqlUuid, _>(viewer_id)
+    .bind::<SqlUuid, _>(crate::user::DELETED_USER_ID)
+    .load(conn)?)
+}
+
+/// Builds a `tsquery` which matches every word of `query` as a prefix. Anything other than
+/// letters and digits separates words, just like the `simple` parser does, so the result is
+/// always valid syntax.
+fn prefix_query(query: &str) -> Option<String> {
+    let words: Vec<_> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("{}:*", word.to_lowercase()))
+        .collect();
+
+    if words.is_empty() {
+        None
+    } else {
+        Some(words.join(" & "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post::tests::{new_post_at, new_public_post};
+    use crate::test_db::new_connection;
+    use crate::user::tests::new_user;
+    use chrono::{Duration, Utc};
+    use serde_json::json;
+
+    #[test]
+    fn builds_prefix_queries() {
+        assert_eq!(prefix_query("Ali"), Some("ali:*".to_string()));
+        assert_eq!(
+            prefix_query("  bob_smith & 'x'"),
+            Some("bob:* & smith:* & x:*".to_string())
+        );
+        assert_eq!(prefix_query(" !:* "), None);
+    }
+
+    #[test]
+    fn finds_public_posts() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let reader = new_user(&mut conn, "reader");
+        let now = Utc::now();
+
+        let public = new_public_post(&mut conn, author, "Learning about lifetimes");
+        crate::post::new(
+            &mut conn,
+            &crate::post::NewPost {
+                user_id: author,
+                content: json!({ "Chat": { "message": "A secret lifetime" } }),
+                time_posted: now,
+                direct_message_to: Some(reader),
+                reply_to: None,
+                visibility: Default::default(),
+            },
+        )
+        .unwrap();
+        new_post_at(
+            &mut conn,
+            author,
+            "Scheduled lifetime",
+            now + Duration::hours(1),
+        );
+
+        let found = posts(&mut conn, reader, "lifetime", 0, 10).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, public);
+        assert_eq!(found[0].author_handle, "author");
+        assert_eq!(found[0].text, "Learning about lifetimes");
+
+        assert!(posts(&mut conn, reader, "lifetime", 1, 10)
+            .unwrap()
+            .is_empty());
+
+        crate::mute::mute(&mut conn, reader, author).unwrap();
+        assert!(posts(&mut conn, reader, "lifetime", 0, 10)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            posts(&mut conn, author, "lifetime", 0, 10).unwrap().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn finds_users_by_prefix() {
+        let mut conn = new_connection();
+        let alice = new_user(&mut conn, "alice_rust");
+        let bob = new_user(&mut conn, "bob");
+
+        let found = users(&mut conn, bob, "Ali", 0, 10).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, alice);
+
+        assert!(users(&mut conn, bob, "alice go", 0, 10).unwrap().is_empty());
+        assert!(users(&mut conn, bob, "", 0, 10).unwrap().is_empty());
+
+        crate::block::block(&mut conn, alice, bob).unwrap();
+        assert!(users(&mut conn, bob, "Ali", 0, 10).unwrap().is_empty());
+    }
+}
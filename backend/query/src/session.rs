@@ -0,0 +1,186 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Timestamptz};
+use diesel::PgConnection;
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+
+use crate::QueryError;
+
+#[derive(Clone, Debug, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::web)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub fingerprint: JsonValue,
+}
+
+/// Creates a new session for the device identified by `fingerprint`.
+///
+/// Only one session is allowed per device, so an existing session for the same device gets
+/// replaced.
+pub fn new(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    expires_at: DateTime<Utc>,
+    fingerprint: JsonValue,
+) -> Result<Session, QueryError> {
+    use crate::schema::web::{self, columns};
+
+    let session_id = Uuid::new_v4();
+
+    Ok(diesel::insert_into(web::table)
+        .values((
+            columns::id.eq(session_id),
+            columns::user_id.eq(user_id),
+            columns::expires_at.eq(expires_at),
+            columns::fingerprint.eq(&fingerprint),
+        ))
+        .on_conflict((columns::user_id, columns::fingerprint))
+        .do_update()
+        .set((
+            columns::id.eq(session_id),
+            columns::expires_at.eq(expires_at),
+        ))
+        .returning(web::all_columns)
+        .get_result(conn)?)
+}
+
+/// Gets a session if it hasn't expired yet.
+pub fn get(conn: &mut PgConnection, session_id: Uuid) -> Result<Option<Session>, QueryError> {
+    use crate::schema::web::dsl::*;
+
+    Ok(web
+        .filter(id.eq(session_id))
+        .filter(expires_at.gt(Utc::now()))
+        .select(Session::as_select())
+        .get_result(conn)
+        .optional()?)
+}
+
+/// Deletes up to `limit` sessions which expired at or before `now`. Expired sessions still occupy
+/// their device slot, see [`new`], so they are deleted in batches to keep the locks short.
+///
+/// Returns the number of deleted sessions, fewer than `limit` once none are left.
+pub fn delete_expired(
+    conn: &mut PgConnection,
+    now: DateTime<Utc>,
+    limit: i64,
+) -> Result<usize, QueryError> {
+    Ok(diesel::sql_query(
+        "DELETE FROM web WHERE id IN ( \
+             SELECT id FROM web WHERE expires_at <= $1 LIMIT $2 FOR UPDATE SKIP LOCKED)",
+    )
+    .bind::<Timestamptz, _>(now)
+    .bind::<BigInt, _>(limit)
+    .execute(conn)?)
+}
+
+/// Deletes the sessions of `user_id`, or of every user if it is `None`, which were created
+/// before `created_before`, or at any time if it is `None`. Logs the users out on those devices.
+///
+/// Returns the number of deleted sessions.
+pub fn purge(
+    conn: &mut PgConnection,
+    user_id: Option<Uuid>,
+    created_before: Option<DateTime<Utc>>,
+) -> Result<usize, QueryError> {
+    use crate::schema::web;
+
+    let mut query = diesel::delete(web::table).into_boxed();
+    if let Some(user_id) = user_id {
+        query = query.filter(web::user_id.eq(user_id));
+    }
+    if let Some(created_before) = created_before {
+        query = query.filter(web::created_at.lt(created_before));
+    }
+    Ok(query.execute(conn)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db::new_connection;
+    use crate::user::tests::new_user;
+
+    #[test]
+    fn creates_and_gets_session() {
+        let mut conn = new_connection();
+        let user_id = new_user(&mut conn, "test_user");
+        let expires = Utc::now() + chrono::Duration::days(1);
+
+        let session = new(&mut conn, user_id, expires, serde_json::json!({})).unwrap();
+        let found = get(&mut conn, session.id)
+            .unwrap()
+            .expect("missing session");
+        assert_eq!(found.user_id, user_id);
+    }
+
+    #[test]
+    fn replaces_session_for_same_device() {
+        let mut conn = new_connection();
+        let user_id = new_user(&mut conn, "test_user");
+        let expires = Utc::now() + chrono::Duration::days(1);
+
+        let first = new(&mut conn, user_id, expires, serde_json::json!({})).unwrap();
+        let second = new(&mut conn, user_id, expires, serde_json::json!({})).unwrap();
+        assert_ne!(first.id, second.id);
+        assert!(get(&mut conn, first.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn ignores_expired_sessions() {
+        let mut conn = new_connection();
+        let user_id = new_user(&mut conn, "test_user");
+        let expires = Utc::now() - chrono::Duration::seconds(1);
+
+        let session = new(&mut conn, user_id, expires, serde_json::json!({})).unwrap();
+        assert!(get(&mut conn, session.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn deletes_expired_sessions_in_batches() {
+        let mut conn = new_connection();
+        let user_id = new_user(&mut conn, "test_user");
+        let expired = Utc::now() - chrono::Duration::seconds(1);
+        for device in 0..3 {
+            new(
+                &mut conn,
+                user_id,
+                expired,
+                serde_json::json!({ "device": device }),
+            )
+            .unwrap();
+        }
+        let active = new(
+            &mut conn,
+            user_id,
+            Utc::now() + chrono::Duration::days(1),
+            serde_json::json!({ "device": "active" }),
+        )
+        .unwrap();
+
+        assert_eq!(delete_expired(&mut conn, Utc::now(), 2).unwrap(), 2);
+        assert_eq!(delete_expired(&mut conn, Utc::now(), 2).unwrap(), 1);
+        assert_eq!(delete_expired(&mut conn, Utc::now(), 2).unwrap(), 0);
+        assert!(get(&mut conn, active.id).unwrap().is_some());
+    }
+
+    #[test]
+    fn purges_sessions_by_user() {
+        let mut conn = new_connection();
+        let user_id = new_user(&mut conn, "test_user");
+        let other_id = new_user(&mut conn, "other_user");
+        let expires = Utc::now() + chrono::Duration::days(1);
+        new(&mut conn, user_id, expires, serde_json::json!({})).unwrap();
+        let other = new(&mut conn, other_id, expires, serde_json::json!({})).unwrap();
+
+        // sessions created now aren't older than now minus a day
+        let day_ago = Utc::now() - chrono::Duration::days(1);
+        assert_eq!(purge(&mut conn, None, Some(day_ago)).unwrap(), 0);
+        assert_eq!(purge(&mut conn, Some(user_id), None).unwrap(), 1);
+        assert!(get(&mut conn, other.id).unwrap().is_some());
+    }
+}
@@ -0,0 +1,200 @@
+//! Hashtags of public posts.
+//!
+//! `post_hashtags` has a copy of the time each post was published, so timelines and trends are
+//! read from its indexes without scanning `posts`.
+
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Nullable, Text, Timestamptz, Uuid as SqlUuid};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::post::{Cursor, PostPreview};
+use crate::QueryError;
+
+#[derive(Clone, Debug, PartialEq, Eq, QueryableByName)]
+pub struct TrendingTag {
+    #[diesel(sql_type = Text)]
+    pub tag: String,
+    /// Posts with the tag in the time window.
+    #[diesel(sql_type = BigInt)]
+    pub posts: i64,
+}
+
+/// Tags `post_id` with normalized hashtags, creating the ones which are new.
+pub(crate) fn attach(
+    conn: &mut PgConnection,
+    post_id: Uuid,
+    time_posted: DateTime<Utc>,
+    tags: &[String],
+) -> Result<(), QueryError> {
+    use crate::schema::{hashtags, post_hashtags};
+
+    if tags.is_empty() {
+        return Ok(());
+    }
+
+    let new_tags: Vec<_> = tags
+        .iter()
+        .map(|tag| (hashtags::id.eq(Uuid::new_v4()), hashtags::tag.eq(tag)))
+        .collect();
+    diesel::insert_into(hashtags::table)
+        .values(&new_tags)
+        .on_conflict(hashtags::tag)
+        .do_nothing()
+        .execute(conn)?;
+
+    let hashtag_ids: Vec<Uuid> = hashtags::table
+        .filter(hashtags::tag.eq_any(tags))
+        .select(hashtags::id)
+        .load(conn)?;
+    let rows: Vec<_> = hashtag_ids
+        .into_iter()
+        .map(|hashtag_id| {
+            (
+                post_hashtags::post_id.eq(post_id),
+                post_hashtags::hashtag_id.eq(hashtag_id),
+                post_hashtags::time_posted.eq(time_posted),
+            )
+        })
+        .collect();
+    diesel::insert_into(post_hashtags::table)
+        .values(&rows)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Published posts tagged with the normalized `tag`, newest first, starting before `before`.
+/// Posts hidden from `viewer_id` by blocks and mutes are left out, and so are posts hidden by
+/// moderators unless `viewer_id` wrote them. Unlisted posts are left out, and so are posts
+/// `viewer_id` can't see because they don't follow the author.
+pub fn timeline(
+    conn: &mut PgConnection,
+    viewer_id: Uuid,
+    tag: &str,
+    before: Option<Cursor>,
+    limit: i64,
+) -> Result<Vec<PostPreview>, QueryError> {
+    Ok(diesel::sql_query(
+        "SELECT p.id, p.user_id AS author_id, u.handle AS author_handle, \
+                post_text(p.content) AS text, p.time_posted, p.edited_at, \
+                p.deleted_at \
+         FROM hashtags h \
+         JOIN post_hashtags ph ON ph.hashtag_id = h.id \
+         JOIN posts p ON p.id = ph.post_id \
+         JOIN users u ON u.id = p.user_id \
+         WHERE h.tag = $1 \
+           AND ph.time_posted <= statement_timestamp() \
+           AND ($2::timestamptz IS NULL OR (ph.time_posted, ph.post_id) < ($2, $3)) \
+           AND (p.hidden_at IS NULL OR p.user_id = $5) \
+           AND NOT hidden_from($5, p.user_id) \
+           AND p.visibility <> 'unlisted' \
+           AND can_see_post($5, p.user_id, p.visibility) \
+         ORDER BY ph.time_posted DESC, ph.post_id DESC \
+         LIMIT $4",
+    )
+    .bind::<Text, _>(tag)
+    .bind::<Nullable<Timestamptz>, _>(before.map(|c| c.time_posted))
+    .bind::<Nullable<SqlUuid>, _>(before.map(|c| c.id))
+    .bind::<BigInt, _>(limit)
+    .bind::<SqlUuid, _>(viewer_id)
+    .load(conn)?)
+}
+
+/// Tags used by the most posts published since `since`. Only public posts of public users count,
+/// and posts hidden by moderators don't.
+pub fn trending(
+    conn: &mut PgConnection,
+    since: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<TrendingTag>, QueryError> {
+    Ok(diesel::sql_query(
+        "SELECT h.tag, count(*) AS posts \
+         FROM post_hashtags ph \
+         JOIN hashtags h ON h.id = ph.hashtag_id \
+         JOIN posts p ON p.id = ph.post_id \
+         JOIN users u ON u.id = p.user_id \
+         WHERE ph.time_posted > $1 AND ph.time_posted <= statement_timestamp() \
+           AND p.hidden_at IS NULL \
+           AND p.visibility = 'public' \
+           AND NOT u.private \
+         GROUP BY h.tag \
+         ORDER BY posts DESC, h.tag \
+         LIMIT $2",
+    )
+    .bind::<Timestamptz, _>(since)
+    .bind::<BigInt, _>(limit)
+    .load(conn)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post::tests::{new_post_at, new_public_post};
+    use crate::test_db::new_connection;
+    use crate::user::tests::new_user;
+    use chrono::Duration;
+
+    #[test]
+    fn pages_through_tag_timelines() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+        let now = Utc::now();
+
+        let first = new_post_at(
+            &mut conn,
+            author,
+            "#Rust is fun",
+            now - Duration::minutes(2),
+        );
+        let second = new_post_at(
+            &mut conn,
+            author,
+            "#rust #rust again",
+            now - Duration::minutes(1),
+        );
+        new_post_at(&mut conn, author, "#go", now);
+
+        let page = timeline(&mut conn, author, "rust", None, 1).unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].id, second);
+
+        let cursor = Cursor {
+            time_posted: page[0].time_posted,
+            id: page[0].id,
+        };
+        let page = timeline(&mut conn, author, "rust", Some(cursor), 10).unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].id, first);
+    }
+
+    #[test]
+    fn ranks_trending_tags() {
+        let mut conn = new_connection();
+        let author = new_user(&mut conn, "author");
+
+        new_public_post(&mut conn, author, "#rust");
+        new_public_post(&mut conn, author, "#rust #go");
+        new_public_post(&mut conn, author, "#zig");
+
+        let trends = trending(&mut conn, Utc::now() - Duration::hours(1), 2).unwrap();
+        assert_eq!(
+            trends,
+            vec![
+                TrendingTag {
+                    tag: "rust".to_string(),
+                    posts: 2,
+                },
+                TrendingTag {
+                    tag: "go".to_string(),
+                    posts: 1,
+                },
+            ]
+        );
+
+        assert!(trending(&mut conn, Utc::now() + Duration::hours(1), 2)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,356 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::QueryError;
+
+#[derive(Clone, Debug, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::user_totp)]
+pub struct UserTotp {
+    pub user_id: Uuid,
+    /// Encrypted TOTP secret.
+    pub secret: Vec<u8>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub last_used_step: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserTotp {
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed_at.is_some()
+    }
+}
+
+#[derive(Clone, Debug, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::mfa_challenges)]
+pub struct MfaChallenge {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub expires_at: DateTime<Utc>,
+    pub failed_attempts: i16,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Stores a new (unconfirmed) secret for the user, replacing any pending enrollment.
+///
+/// Returns [`QueryError::UniqueViolation`] if two-factor authentication is already enabled.
+pub fn begin_enrollment(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    encrypted_secret: &[u8],
+) -> Result<(), QueryError> {
+    use crate::schema::user_totp::{self, columns};
+
+    conn.transaction(|conn| {
+        if let Some(existing) = get(conn, user_id)? {
+            if existing.is_confirmed() {
+                return Err(QueryError::UniqueViolation);
+            }
+        }
+
+        diesel::insert_into(user_totp::table)
+            .values((
+                columns::user_id.eq(user_id),
+                columns::secret.eq(encrypted_secret),
+            ))
+            .on_conflict(columns::user_id)
+            .do_update()
+            .set((
+                columns::secret.eq(encrypted_secret),
+                columns::last_used_step.eq(0),
+                columns::created_at.eq(Utc::now()),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+pub fn get(conn: &mut PgConnection, user_id: Uuid) -> Result<Option<UserTotp>, QueryError> {
+    use crate::schema::user_totp::dsl;
+
+    Ok(dsl::user_totp
+        .filter(dsl::user_id.eq(user_id))
+        .select(UserTotp::as_select())
+        .get_result(conn)
+        .optional()?)
+}
+
+/// Whether the user has confirmed two-factor authentication.
+pub fn is_enabled(conn: &mut PgConnection, user_id: Uuid) -> Result<bool, QueryError> {
+    Ok(matches!(get(conn, user_id)?, Some(totp) if totp.is_confirmed()))
+}
+
+/// Enables two-factor authentication and replaces the recovery codes.
+pub fn confirm(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    step: i64,
+    recovery_code_hashes: &[String],
+) -> Result<(), QueryError> {
+    use crate::schema::{totp_recovery_codes, user_totp};
+
+    conn.transaction(|conn| {
+        let updated = diesel::update(user_totp::table)
+            .filter(user_totp::user_id.eq(user_id))
+            .filter(user_totp::confirmed_at.is_null())
+            .set((
+                user_totp::confirmed_at.eq(Utc::now()),
+                user_totp::last_used_step.eq(step),
+            ))
+            .execute(conn)?;
+        if updated == 0 {
+            return Err(QueryError::NotFound);
+        }
+
+        diesel::delete(totp_recovery_codes::table)
+            .filter(totp_recovery_codes::user_id.eq(user_id))
+            .execute(conn)?;
+
+        let codes: Vec<_> = recovery_code_hashes
+            .iter()
+            .map(|hash| {
+                (
+                    totp_recovery_codes::user_id.eq(user_id),
+                    totp_recovery_codes::code_hash.eq(hash),
+                )
+            })
+            .collect();
+        diesel::insert_into(totp_recovery_codes::table)
+            .values(codes)
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+/// Records that the code for `step` was used.
+///
+/// Returns `false` if this step (or a later one) was already used, which means the code is being
+/// replayed.
+pub fn record_step(conn: &mut PgConnection, user_id: Uuid, step: i64) -> Result<bool, QueryError> {
+    use crate::schema::user_totp::dsl;
+
+    let updated = diesel::update(dsl::user_totp)
+        .filter(dsl::user_id.eq(user_id))
+        .filter(dsl::last_used_step.lt(step))
+        .set(dsl::last_used_step.eq(step))
+        .execute(conn)?;
+
+    Ok(updated == 1)
+}
+
+/// Marks a recovery code as used. Returns `false` if the code doesn't exist or was already used.
+pub fn use_recovery_code(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    code_hash: &str,
+) -> Result<bool, QueryError> {
+    use crate::schema::totp_recovery_codes::dsl;
+
+    let updated = diesel::update(dsl::totp_recovery_codes)
+        .filter(dsl::user_id.eq(user_id))
+        .filter(dsl::code_hash.eq(code_hash))
+        .filter(dsl::used_at.is_null())
+        .set(dsl::used_at.eq(Utc::now()))
+        .execute(conn)?;
+
+    Ok(updated == 1)
+}
+
+pub fn remaining_recovery_codes(conn: &mut PgConnection, user_id: Uuid) -> Result<i64, QueryError> {
+    use crate::schema::totp_recovery_codes::dsl;
+
+    Ok(dsl::totp_recovery_codes
+        .filter(dsl::user_id.eq(user_id))
+        .filter(dsl::used_at.is_null())
+        .count()
+        .get_result(conn)?)
+}
+
+/// Removes two-factor authentication, including recovery codes and pending login challenges.
+pub fn disable(conn: &mut PgConnection, user_id: Uuid) -> Result<(), QueryError> {
+    use crate::schema::{mfa_challenges, totp_recovery_codes, user_totp};
+
+    conn.transaction(|conn| {
+        diesel::delete(user_totp::table)
+            .filter(user_totp::user_id.eq(user_id))
+            .execute(conn)?;
+        diesel::delete(totp_recovery_codes::table)
+            .filter(totp_recovery_codes::user_id.eq(user_id))
+            .execute(conn)?;
+        diesel::delete(mfa_challenges::table)
+            .filter(mfa_challenges::user_id.eq(user_id))
+            .execute(conn)?;
+        Ok(())
+    })
+}
+
+pub fn new_challenge(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    expires_at: DateTime<Utc>,
+) -> Result<Uuid, QueryError> {
+    use crate::schema::mfa_challenges::{self, columns};
+
+    let challenge_id = Uuid::new_v4();
+
+    diesel::insert_into(mfa_challenges::table)
+        .values((
+            columns::id.eq(challenge_id),
+            columns::user_id.eq(user_id),
+            columns::expires_at.eq(expires_at),
+        ))
+        .execute(conn)?;
+
+    Ok(challenge_id)
+}
+
+/// Gets a login challenge if it hasn't expired yet.
+pub fn get_challenge(
+    conn: &mut PgConnection,
+    challenge_id: Uuid,
+) -> Result<Option<MfaChallenge>, QueryError> {
+    use crate::schema::mfa_challenges::dsl::*;
+
+    Ok(mfa_challenges
+        .filter(id.eq(challenge_id))
+        .filter(expires_at.gt(Utc::now()))
+        .select(MfaChallenge::as_select())
+        .get_result(conn)
+        .optional()?)
+}
+
+/// Returns the total number of failed attempts for the challenge.
+pub fn record_failed_attempt(
+    conn: &mut PgConnection,
+    challenge_id: Uuid,
+) -> Result<i16, QueryError> {
+    use crate::schema::mfa_challenges::dsl::*;
+
+    Ok(diesel::update(mfa_challenges)
+        .filter(id.eq(challenge_id))
+        .set(failed_attempts.eq(failed_attempts + 1))
+        .returning(failed_attempts)
+        .get_result(conn)?)
+}
+
+pub fn delete_challenge(conn: &mut PgConnection, challenge_id: Uuid) -> Result<(), QueryError> {
+    use crate::schema::mfa_challenges::dsl::*;
+
+    diesel::delete(mfa_challenges)
+        .filter(id.eq(challenge_id))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db::new_connection;
+    use crate::user::tests::new_user;
+
+    fn hashes(codes: &[&str]) -> Vec<String> {
+        codes.iter().map(|code| code.to_string()).collect()
+    }
+
+    #[test]
+    fn enrollment_is_disabled_until_confirmed() {
+        let mut conn = new_connection();
+        let user_id = new_user(&mut conn, "test_user");
+
+        begin_enrollment(&mut conn, user_id, b"secret").unwrap();
+        assert!(!is_enabled(&mut conn, user_id).unwrap());
+
+        confirm(&mut conn, user_id, 1, &hashes(&["a", "b"])).unwrap();
+        assert!(is_enabled(&mut conn, user_id).unwrap());
+        assert_eq!(remaining_recovery_codes(&mut conn, user_id).unwrap(), 2);
+    }
+
+    #[test]
+    fn cannot_reenroll_when_enabled() {
+        let mut conn = new_connection();
+        let user_id = new_user(&mut conn, "test_user");
+
+        begin_enrollment(&mut conn, user_id, b"secret").unwrap();
+        confirm(&mut conn, user_id, 1, &[]).unwrap();
+
+        assert!(matches!(
+            begin_enrollment(&mut conn, user_id, b"other"),
+            Err(QueryError::UniqueViolation)
+        ));
+    }
+
+    #[test]
+    fn rejects_replayed_steps() {
+        let mut conn = new_connection();
+        let user_id = new_user(&mut conn, "test_user");
+
+        begin_enrollment(&mut conn, user_id, b"secret").unwrap();
+        confirm(&mut conn, user_id, 10, &[]).unwrap();
+
+        assert!(!record_step(&mut conn, user_id, 10).unwrap());
+        assert!(record_step(&mut conn, user_id, 11).unwrap());
+        assert!(!record_step(&mut conn, user_id, 11).unwrap());
+    }
+
+    #[test]
+    fn recovery_codes_are_single_use() {
+        let mut conn = new_connection();
+        let user_id = new_user(&mut conn, "test_user");
+
+        begin_enrollment(&mut conn, user_id, b"secret").unwrap();
+        confirm(&mut conn, user_id, 1, &hashes(&["a", "b"])).unwrap();
+
+        assert!(use_recovery_code(&mut conn, user_id, "a").unwrap());
+        assert!(!use_recovery_code(&mut conn, user_id, "a").unwrap());
+        assert!(!use_recovery_code(&mut conn, user_id, "missing").unwrap());
+        assert_eq!(remaining_recovery_codes(&mut conn, user_id).unwrap(), 1);
+    }
+
+    #[test]
+    fn disable_removes_everything() {
+        let mut conn = new_connection();
+        let user_id = new_user(&mut conn, "test_user");
+
+        begin_enrollment(&mut conn, user_id, b"secret").unwrap();
+        confirm(&mut conn, user_id, 1, &hashes(&["a"])).unwrap();
+        let challenge_id = new_challenge(
+            &mut conn,
+            user_id,
+            Utc::now() + chrono::Duration::minutes(5),
+        )
+        .unwrap();
+
+        disable(&mut conn, user_id).unwrap();
+        assert!(get(&mut conn, user_id).unwrap().is_none());
+        assert_eq!(remaining_recovery_codes(&mut conn, user_id).unwrap(), 0);
+        assert!(get_challenge(&mut conn, challenge_id).unwrap().is_none());
+    }
+
+    #[test]
+    fn tracks_challenge_attempts() {
+        let mut conn = new_connection();
+        let user_id = new_user(&mut conn, "test_user");
+        let expires = Utc::now() + chrono::Duration::minutes(5);
+
+        let challenge_id = new_challenge(&mut conn, user_id, expires).unwrap();
+        assert_eq!(record_failed_attempt(&mut conn, challenge_id).unwrap(), 1);
+        assert_eq!(record_failed_attempt(&mut conn, challenge_id).unwrap(), 2);
+
+        delete_challenge(&mut conn, challenge_id).unwrap();
+        assert!(get_challenge(&mut conn, challenge_id).unwrap().is_none());
+    }
+
+    #[test]
+    fn ignores_expired_challenges() {
+        let mut conn = new_connection();
+        let user_id = new_user(&mut conn, "test_user");
+        let expired = Utc::now() - chrono::Duration::seconds(1);
+
+        let challenge_id = new_challenge(&mut conn, user_id, expired).unwrap();
+        assert!(get_challenge(&mut conn, challenge_id).unwrap().is_none());
+    }
+}
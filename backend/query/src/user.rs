@@ -0,0 +1,254 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use password_hash::PasswordHashString;
+use uuid::Uuid;
+
+use crate::QueryError;
+
+/// Author of the posts of deleted accounts which other posts reply to, see [`purge_deleted`].
+/// Nobody can log in as this user.
+pub const DELETED_USER_ID: Uuid = Uuid::nil();
+
+#[derive(Clone, Debug, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::users)]
+pub struct User {
+    pub id: Uuid,
+    pub email: Option<String>,
+    pub email_confirmed: Option<DateTime<Utc>>,
+    pub password_hash: String,
+    pub display_name: Option<String>,
+    pub handle: String,
+    pub created_at: DateTime<Utc>,
+    pub profile_image: Option<String>,
+    /// Suspended users can't log in.
+    pub suspended_at: Option<DateTime<Utc>>,
+    /// Posts of private users are only visible to their followers.
+    pub private: bool,
+    /// The account is deleted once this has passed, unless the user cancels the deletion.
+    pub delete_after: Option<DateTime<Utc>>,
+}
+
+pub fn new<T: AsRef<str>>(
+    conn: &mut PgConnection,
+    hash: PasswordHashString,
+    handle: T,
+) -> Result<Uuid, QueryError> {
+    use crate::schema::users::{self, columns};
+
+    let user_id = Uuid::new_v4();
+
+    diesel::insert_into(users::table)
+        .values((
+            columns::id.eq(user_id),
+            columns::password_hash.eq(hash.as_str()),
+            columns::handle.eq(handle.as_ref()),
+        ))
+        .execute(conn)?;
+
+    Ok(user_id)
+}
+
+/// Returns the user id and the serialized password hash for `handle`.
+pub fn get_password_hash<T: AsRef<str>>(
+    conn: &mut PgConnection,
+    user_handle: T,
+) -> Result<(Uuid, String), QueryError> {
+    use crate::schema::users::dsl::*;
+
+    Ok(users
+        .filter(handle.eq(user_handle.as_ref()))
+        .filter(id.ne(DELETED_USER_ID))
+        .select((id, password_hash))
+        .get_result(conn)?)
+}
+
+pub fn find(conn: &mut PgConnection, user_id: Uuid) -> Result<User, QueryError> {
+    use crate::schema::users::dsl::*;
+
+    Ok(users
+        .filter(id.eq(user_id))
+        .select(User::as_select())
+        .get_result(conn)?)
+}
+
+pub fn find_by_handle<T: AsRef<str>>(
+    conn: &mut PgConnection,
+    user_handle: T,
+) -> Result<User, QueryError> {
+    use crate::schema::users::dsl::*;
+
+    Ok(users
+        .filter(handle.eq(user_handle.as_ref()))
+        .select(User::as_select())
+        .get_result(conn)?)
+}
+
+/// Deletes the account of `user_id` at `delete_after`, see [`purge_deleted`].
+pub fn schedule_deletion(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    delete_after: DateTime<Utc>,
+) -> Result<(), QueryError> {
+    use crate::schema::users;
+
+    diesel::update(users::table.find(user_id))
+        .set(users::delete_after.eq(delete_after))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Keeps the account of `user_id` after all.
+pub fn cancel_deletion(conn: &mut PgConnection, user_id: Uuid) -> Result<(), QueryError> {
+    use crate::schema::users;
+
+    diesel::update(users::table.find(user_id))
+        .set(users::delete_after.eq(None::<DateTime<Utc>>))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Deletes the accounts which were scheduled for deletion before `now`, together with everything
+/// they posted. Posts which other posts reply to stay as deleted placeholders by
+/// [`DELETED_USER_ID`], like after [`crate::post::delete`], so the replies keep their thread.
+///
+/// Returns the ids of the deleted users.
+pub fn purge_deleted(conn: &mut PgConnection, now: DateTime<Utc>) -> Result<Vec<Uuid>, QueryError> {
+    use crate::schema::{mentions, post_hashtags, post_revisions, posts, users};
+    use diesel::sql_types::{Nullable, Timestamptz};
+
+    conn.transaction(|conn| {
+        let purged: Vec<Uuid> = users::table
+            .filter(users::delete_after.le(now))
+            .select(users::id)
+            .for_update()
+            .load(conn)?;
+        if purged.is_empty() {
+            return Ok(purged);
+        }
+
+        let post_ids: Vec<Option<Uuid>> = posts::table
+            .filter(posts::user_id.eq_any(&purged))
+            .select(posts::id.nullable())
+            .load(conn)?;
+        let replied_to: Vec<Uuid> = posts::table
+            .filter(posts::reply_to.eq_any(post_ids))
+            .select(posts::reply_to.assume_not_null())
+            .distinct()
+            .load(conn)?;
+        diesel::update(posts::table.filter(posts::id.eq_any(&replied_to)))
+            .set((
+                posts::user_id.eq(DELETED_USER_ID),
+                posts::content.eq(serde_json::Value::Object(Default::default())),
+                posts::deleted_at.eq(diesel::dsl::sql::<Nullable<Timestamptz>>(
+                    "coalesce(deleted_at, statement_timestamp())",
+                )),
+            ))
+            .execute(conn)?;
+        diesel::delete(post_hashtags::table.filter(post_hashtags::post_id.eq_any(&replied_to)))
+            .execute(conn)?;
+        diesel::delete(mentions::table.filter(mentions::post_id.eq_any(&replied_to)))
+            .execute(conn)?;
+        diesel::delete(post_revisions::table.filter(post_revisions::post_id.eq_any(&replied_to)))
+            .execute(conn)?;
+
+        diesel::delete(users::table.filter(users::id.eq_any(&purged))).execute(conn)?;
+        Ok(purged)
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::test_db::new_connection;
+
+    pub fn new_user(conn: &mut PgConnection, handle: &str) -> Uuid {
+        let hash = uchat_crypto::hash_password("password").unwrap();
+        super::new(conn, hash, handle).unwrap()
+    }
+
+    #[test]
+    fn creates_and_finds_user() {
+        let mut conn = new_connection();
+        let user_id = new_user(&mut conn, "test_user");
+
+        let user = find(&mut conn, user_id).unwrap();
+        assert_eq!(user.handle, "test_user");
+
+        let user = find_by_handle(&mut conn, "test_user").unwrap();
+        assert_eq!(user.id, user_id);
+    }
+
+    #[test]
+    fn rejects_duplicate_handles() {
+        let mut conn = new_connection();
+        new_user(&mut conn, "test_user");
+
+        let hash = uchat_crypto::hash_password("password").unwrap();
+        assert!(matches!(
+            super::new(&mut conn, hash, "test_user"),
+            Err(QueryError::UniqueViolation)
+        ));
+    }
+
+    #[test]
+    fn gets_password_hash() {
+        let mut conn = new_connection();
+        let user_id = new_user(&mut conn, "test_user");
+
+        let (id, hash) = get_password_hash(&mut conn, "test_user").unwrap();
+        assert_eq!(id, user_id);
+        let hash = uchat_crypto::password::deserialize_hash(&hash).unwrap();
+        assert!(uchat_crypto::verify_password("password", &hash).is_ok());
+    }
+
+    #[test]
+    fn purges_deleted_accounts_after_grace_period() {
+        let mut conn = new_connection();
+        let leaving = new_user(&mut conn, "leaving");
+        let staying = new_user(&mut conn, "staying");
+        let post_id = crate::post::tests::new_public_post(&mut conn, leaving, "bye");
+        let reply_id = crate::post::new(
+            &mut conn,
+            &crate::post::NewPost {
+                user_id: staying,
+                content: serde_json::json!({ "Chat": { "message": "see you" } }),
+                time_posted: Utc::now(),
+                direct_message_to: None,
+                reply_to: Some(post_id),
+                visibility: Default::default(),
+            },
+        )
+        .unwrap();
+
+        let later = Utc::now() + chrono::Duration::days(30);
+        schedule_deletion(&mut conn, leaving, later).unwrap();
+        schedule_deletion(&mut conn, staying, later).unwrap();
+        cancel_deletion(&mut conn, staying).unwrap();
+        assert!(purge_deleted(&mut conn, Utc::now()).unwrap().is_empty());
+
+        assert_eq!(purge_deleted(&mut conn, later).unwrap(), vec![leaving]);
+        assert!(matches!(
+            find(&mut conn, leaving),
+            Err(QueryError::NotFound)
+        ));
+        assert!(find(&mut conn, staying).unwrap().delete_after.is_none());
+        assert!(crate::post::get(&mut conn, staying, reply_id).is_ok());
+
+        // the reply keeps its parent, which stays as a placeholder
+        let reply_to: Option<Uuid> = crate::schema::posts::table
+            .find(reply_id)
+            .select(crate::schema::posts::reply_to)
+            .get_result(&mut conn)
+            .unwrap();
+        assert_eq!(reply_to, Some(post_id));
+        let placeholder = crate::post::get(&mut conn, staying, post_id).unwrap();
+        assert_eq!(placeholder.author_id, DELETED_USER_ID);
+        assert!(placeholder.deleted_at.is_some());
+        assert!(placeholder.text.is_empty());
+        assert!(matches!(
+            get_password_hash(&mut conn, placeholder.author_handle),
+            Err(QueryError::NotFound)
+        ));
+    }
+}
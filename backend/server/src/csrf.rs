@@ -0,0 +1,230 @@
+//! Cross-site request forgery protection.
+//!
+//! Sessions live in cookies, which browsers attach to cross-site requests too. Every session
+//! therefore gets a CSRF token: a signature over the session id which the frontend keeps in a
+//! cookie and echoes back in the [`CSRF_HEADER`] header. Other sites can't read the cookie, so
+//! they can't produce the header. Tokens are derived from the session, so no server-side state is
+//! needed to check them.
+//!
+//! Only the [`AllowedOrigins`] of the frontend get CORS access with credentials, and requests
+//! which the CSRF token can't cover, like WebSocket upgrades, check the `Origin` header instead.
+
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use tower_http::cors::AllowOrigin;
+use uchat_api::CSRF_HEADER;
+use uchat_cookie::SESSION_ID;
+use uchat_crypto::sign::Keys;
+use url::{Origin, Url};
+use uuid::Uuid;
+
+use crate::error::ApiError;
+
+const TOKEN_CONTEXT: &[u8] = b"uchat-csrf:";
+
+/// Origins the frontend is served from, the only sites which may call the API from a browser.
+#[derive(Clone, Debug)]
+pub struct AllowedOrigins(Arc<[HeaderValue]>);
+
+impl AllowedOrigins {
+    pub fn new(origins: Vec<HeaderValue>) -> Self {
+        Self(origins.into())
+    }
+
+    /// Serializes the origin of `url`, as browsers send it in the `Origin` header. Fails for urls
+    /// without a host, whose origin is opaque.
+    pub fn parse(url: &str) -> Result<HeaderValue, String> {
+        let url = Url::parse(url).map_err(|e| e.to_string())?;
+        match url.origin() {
+            origin @ Origin::Tuple(..) => {
+                HeaderValue::from_str(&origin.ascii_serialization()).map_err(|e| e.to_string())
+            }
+            Origin::Opaque(_) => Err(format!("{url} has no origin")),
+        }
+    }
+
+    pub fn contains(&self, origin: &HeaderValue) -> bool {
+        self.0.contains(origin)
+    }
+
+    /// CORS policy which only lets these origins through.
+    pub fn cors(&self) -> AllowOrigin {
+        AllowOrigin::list(self.0.iter().cloned())
+    }
+
+    /// Rejects requests sent by pages of other origins. Browsers name the page in the `Origin`
+    /// header of cross-origin requests and WebSocket upgrades, so requests without one come from
+    /// other clients, or are navigations which can't read the response.
+    pub fn check(&self, headers: &HeaderMap) -> Result<(), ApiError> {
+        match headers.get(header::ORIGIN) {
+            Some(origin) if !self.contains(origin) => Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "requests from this origin are not allowed",
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn token_message(session_id: Uuid) -> Vec<u8> {
+    [TOKEN_CONTEXT, session_id.as_bytes()].concat()
+}
+
+/// Creates the CSRF token for a session.
+pub fn new_token(keys: &Keys, session_id: Uuid) -> String {
+    let mut rng = uchat_crypto::new_rng();
+    let signature = keys.sign(&mut rng, &token_message(session_id));
+    uchat_crypto::encode_base64(signature)
+}
+
+pub fn verify_token(keys: &Keys, session_id: Uuid, token: &str) -> bool {
+    uchat_crypto::decode_base64(token.trim())
+        .ok()
+        .and_then(|token| uchat_crypto::sign::signature_from_bytes(token).ok())
+        .map(|signature| keys.verify(&token_message(session_id), signature).is_ok())
+        .unwrap_or(false)
+}
+
+fn is_state_changing(method: &Method) -> bool {
+    !matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    )
+}
+
+/// Middleware which rejects state-changing requests that carry a session cookie but no valid
+/// CSRF token.
+///
+/// Requests without a session cookie are passed through since they can't act on behalf of a
+/// user.
+pub async fn require_token<B>(
+    State(keys): State<Keys>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if !is_state_changing(req.method()) {
+        return next.run(req).await;
+    }
+
+    let session_id = req
+        .headers()
+        .get(header::COOKIE)
+        .and_then(|cookies| cookies.to_str().ok())
+        .and_then(|cookies| uchat_cookie::get(cookies, SESSION_ID))
+        .and_then(|id| Uuid::parse_str(&id).ok());
+
+    let Some(session_id) = session_id else {
+        return next.run(req).await;
+    };
+
+    let token = req
+        .headers()
+        .get(CSRF_HEADER)
+        .and_then(|token| token.to_str().ok());
+
+    match token {
+        Some(token) if verify_token(&keys, session_id, token) => next.run(req).await,
+        _ => ApiError::new(StatusCode::FORBIDDEN, "missing or invalid CSRF token").into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::routing::post;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    fn keys() -> Keys {
+        let mut rng = uchat_crypto::new_rng();
+        Keys::generate(&mut rng).unwrap().1
+    }
+
+    #[test]
+    fn verifies_tokens_for_session() {
+        let keys = keys();
+        let session_id = Uuid::new_v4();
+        let token = new_token(&keys, session_id);
+
+        assert!(verify_token(&keys, session_id, &token));
+        assert!(!verify_token(&keys, Uuid::new_v4(), &token));
+        assert!(!verify_token(&keys, session_id, "garbage"));
+    }
+
+    #[test]
+    fn checks_origins() {
+        let frontend = AllowedOrigins::parse("https://uchat.example/app/").unwrap();
+        assert_eq!(frontend, "https://uchat.example");
+        assert!(AllowedOrigins::parse("data:text/plain,hi").is_err());
+
+        let origins = AllowedOrigins::new(vec![frontend.clone()]);
+        let headers = |origin: Option<&'static str>| {
+            let mut headers = HeaderMap::new();
+            if let Some(origin) = origin {
+                headers.insert(header::ORIGIN, HeaderValue::from_static(origin));
+            }
+            headers
+        };
+        assert!(origins
+            .check(&headers(Some("https://uchat.example")))
+            .is_ok());
+        assert!(origins.check(&headers(None)).is_ok());
+        let err = origins
+            .check(&headers(Some("https://evil.example")))
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+        assert!(origins.check(&headers(Some("null"))).is_err());
+    }
+
+    #[tokio::test]
+    async fn middleware_checks_cookie_authenticated_requests() {
+        let keys = keys();
+        let session_id = Uuid::new_v4();
+        let token = new_token(&keys, session_id);
+
+        let app = Router::new()
+            .route("/", post(|| async { "ok" }).get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(
+                keys.clone(),
+                require_token,
+            ));
+
+        let request = |method: Method, token: Option<&str>| {
+            let mut request = Request::builder()
+                .method(method)
+                .uri("/")
+                .header(header::COOKIE, format!("{SESSION_ID}={session_id}"));
+            if let Some(token) = token {
+                request = request.header(CSRF_HEADER, token);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+
+        let status = |request| {
+            let app = app.clone();
+            async move { app.oneshot(request).await.unwrap().status() }
+        };
+
+        assert_eq!(
+            status(request(Method::POST, None)).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(request(Method::POST, Some("garbage"))).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(request(Method::POST, Some(&token))).await,
+            StatusCode::OK
+        );
+        assert_eq!(status(request(Method::GET, None)).await, StatusCode::OK);
+
+        let anonymous = Request::post("/").body(Body::empty()).unwrap();
+        assert_eq!(status(anonymous).await, StatusCode::OK);
+    }
+}
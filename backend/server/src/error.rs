@@ -0,0 +1,189 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use color_eyre::Report;
+use tracing::error;
+use uchat_api::{ErrorCode, RequestFailed};
+use uchat_query::QueryError;
+
+pub type ApiResult<T> = std::result::Result<T, ApiError>;
+
+/// Error returned from request handlers.
+///
+/// Errors with a client error status code are reported to the client. Everything else is logged
+/// and reported as a generic server error. [`QueryError`]s are reported with a fixed message, so
+/// nothing from the database reaches the client. Errors without a status code get one from
+/// [`QueryError`], if they are one, and are server errors otherwise.
+#[derive(Debug)]
+pub struct ApiError {
+    pub code: Option<StatusCode>,
+    /// Overrides the [`ErrorCode`] derived from `code`.
+    pub error_code: Option<ErrorCode>,
+    pub err: Report,
+}
+
+impl ApiError {
+    pub fn new<M>(code: StatusCode, msg: M) -> Self
+    where
+        M: std::fmt::Display + std::fmt::Debug + Send + Sync + 'static,
+    {
+        Self {
+            code: Some(code),
+            error_code: None,
+            err: Report::msg(msg),
+        }
+    }
+
+    pub fn with_error_code(mut self, error_code: ErrorCode) -> Self {
+        self.error_code = Some(error_code);
+        self
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.code.unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+impl<E> From<E> for ApiError
+where
+    E: Into<Report>,
+{
+    fn from(err: E) -> Self {
+        let err = err.into();
+        let code = err
+            .downcast_ref::<QueryError>()
+            .and_then(query_error_status);
+        Self {
+            code,
+            error_code: None,
+            err,
+        }
+    }
+}
+
+fn query_error_status(err: &QueryError) -> Option<StatusCode> {
+    match err {
+        QueryError::NotFound => Some(StatusCode::NOT_FOUND),
+        QueryError::UniqueViolation => Some(StatusCode::CONFLICT),
+        // the request refers to a row which doesn't exist (anymore)
+        QueryError::ForeignKeyViolation => Some(StatusCode::BAD_REQUEST),
+        QueryError::CheckViolation => Some(StatusCode::BAD_REQUEST),
+        QueryError::Blocked => Some(StatusCode::FORBIDDEN),
+        QueryError::EditWindowClosed => Some(StatusCode::FORBIDDEN),
+        QueryError::PollHasVotes => Some(StatusCode::CONFLICT),
+        QueryError::QuotaExceeded => Some(StatusCode::PAYLOAD_TOO_LARGE),
+        QueryError::Pool(_) | QueryError::Connection(_) => Some(StatusCode::SERVICE_UNAVAILABLE),
+        QueryError::Database(_) | QueryError::LeaseLost => None,
+    }
+}
+
+fn query_error_message(err: &QueryError) -> &'static str {
+    match err {
+        QueryError::NotFound => "not found",
+        QueryError::UniqueViolation => "already exists",
+        QueryError::ForeignKeyViolation => "refers to something which doesn't exist",
+        QueryError::CheckViolation => "invalid value",
+        QueryError::Blocked => "blocked",
+        QueryError::EditWindowClosed => "the post can't be edited anymore",
+        QueryError::PollHasVotes => "poll choices can't be changed once there are votes",
+        QueryError::QuotaExceeded => "the media quota is used up",
+        QueryError::Pool(_)
+        | QueryError::Connection(_)
+        | QueryError::Database(_)
+        | QueryError::LeaseLost => "server error",
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let code = self
+            .error_code
+            .unwrap_or_else(|| ErrorCode::from_status(status.as_u16()));
+
+        let msg = if status.is_server_error() {
+            error!(target: "uchat_server", err = ?self.err, %status, "server error");
+            "server error".to_string()
+        } else if let Some(err) = self.err.downcast_ref::<QueryError>() {
+            query_error_message(err).to_string()
+        } else {
+            self.err.to_string()
+        };
+
+        (status, Json(RequestFailed { code, msg })).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn into_parts(err: ApiError) -> (StatusCode, RequestFailed) {
+        let response = err.into_response();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn reports_client_errors() {
+        let err = ApiError::new(StatusCode::CONFLICT, "handle is already taken");
+        let (status, failed) = into_parts(err).await;
+
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(failed.code, ErrorCode::Conflict);
+        assert_eq!(failed.msg, "handle is already taken");
+    }
+
+    #[tokio::test]
+    async fn hides_server_error_details() {
+        let err = ApiError::from(color_eyre::eyre::eyre!("secret details"));
+        let (status, failed) = into_parts(err).await;
+
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(failed.code, ErrorCode::Internal);
+        assert_eq!(failed.msg, "server error");
+    }
+
+    #[tokio::test]
+    async fn maps_query_errors() {
+        let status = |err: QueryError| ApiError::from(err).status();
+
+        assert_eq!(status(QueryError::NotFound), StatusCode::NOT_FOUND);
+        assert_eq!(status(QueryError::UniqueViolation), StatusCode::CONFLICT);
+        assert_eq!(status(QueryError::CheckViolation), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            status(QueryError::ForeignKeyViolation),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(status(QueryError::Blocked), StatusCode::FORBIDDEN);
+        assert_eq!(status(QueryError::EditWindowClosed), StatusCode::FORBIDDEN);
+        assert_eq!(status(QueryError::PollHasVotes), StatusCode::CONFLICT);
+        assert_eq!(
+            status(QueryError::Pool("timed out".to_string())),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        let (status, failed) = into_parts(QueryError::Connection("refused".into()).into()).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(failed.code, ErrorCode::Unavailable);
+        assert_eq!(failed.msg, "server error");
+    }
+
+    #[tokio::test]
+    async fn reports_query_errors_with_fixed_messages() {
+        let err = color_eyre::Report::new(QueryError::ForeignKeyViolation)
+            .wrap_err("insert or update on table \"posts\" violates foreign key constraint");
+        let (status, failed) = into_parts(err.into()).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(failed.code, ErrorCode::InvalidInput);
+        assert_eq!(failed.msg, "refers to something which doesn't exist");
+    }
+
+    #[test]
+    fn overrides_error_code() {
+        let err = ApiError::new(StatusCode::FORBIDDEN, "nope").with_error_code(ErrorCode::Unknown);
+        assert_eq!(err.error_code, Some(ErrorCode::Unknown));
+    }
+}
@@ -0,0 +1,566 @@
+//! Fan-out of real-time events to connected clients.
+//!
+//! Handlers [`publish`](EventHub::publish) events for a set of recipients and every WebSocket
+//! connection of those users receives them. Each connection has a bounded queue. A connection
+//! which doesn't keep up is disconnected instead of slowing down publishers or buffering without
+//! limit, and the client catches up from the backlog of recent events when it reconnects.
+//!
+//! A single server delivers events in-process. With a [`relay`], events go through the database
+//! instead, so clients connected to other instances receive them as well.
+
+pub mod relay;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use uchat_api::event::{Event, EventMessage};
+use uuid::Uuid;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HubConfig {
+    /// Events queued per connection before it is considered lagging.
+    pub queue_len: usize,
+    /// Recent events kept for clients which reconnect.
+    pub backlog_len: usize,
+}
+
+impl Default for HubConfig {
+    fn default() -> Self {
+        Self {
+            queue_len: 64,
+            backlog_len: 4096,
+        }
+    }
+}
+
+/// Event with the users it is meant for.
+#[derive(Clone, Debug)]
+struct Published {
+    id: u64,
+    recipients: HashSet<Uuid>,
+    event: Event,
+}
+
+struct Subscriber {
+    id: u64,
+    sender: mpsc::Sender<EventMessage>,
+}
+
+struct Inner {
+    config: HubConfig,
+    /// Id for the next event published without a relay.
+    next_local_id: u64,
+    /// Newest event delivered so far.
+    latest_id: Option<u64>,
+    next_subscriber_id: u64,
+    backlog: VecDeque<Published>,
+    subscribers: HashMap<Uuid, Vec<Subscriber>>,
+}
+
+impl Inner {
+    /// Events after `since` for `user_id`, or [`EventMessage::Resync`] if some of them may be
+    /// missing from the backlog.
+    fn replay(&self, user_id: Uuid, since: u64) -> Vec<EventMessage> {
+        let Some(latest_id) = self.latest_id else {
+            // nothing was delivered since the hub started, so anything before is unknown
+            return vec![EventMessage::Resync { id: since }];
+        };
+        if since == latest_id {
+            return vec![];
+        }
+
+        // events only enter the backlog in the order of their ids
+        let oldest_id = self
+            .backlog
+            .front()
+            .map(|published| published.id)
+            .unwrap_or(latest_id + 1);
+        // `since` itself may have been evicted, only later events are needed
+        if since < latest_id && since + 1 >= oldest_id {
+            self.backlog
+                .iter()
+                .filter(|published| published.id > since && published.recipients.contains(&user_id))
+                .map(|published| EventMessage::Event {
+                    id: published.id,
+                    event: published.event.clone(),
+                })
+                .collect()
+        } else {
+            // missed events were evicted, or `since` is from before a restart
+            vec![EventMessage::Resync { id: latest_id }]
+        }
+    }
+
+    fn deliver(&mut self, id: u64, mut recipients: HashSet<Uuid>, event: Event) {
+        match self.latest_id {
+            Some(latest_id) if id < latest_id => {
+                let delivered = self
+                    .backlog
+                    .iter()
+                    .find(|published| published.id == id)
+                    .is_some_and(|published| recipients.is_subset(&published.recipients));
+                if !delivered {
+                    warn!(target: "uchat_server", id, latest_id, "event arrived out of order");
+                    self.resync_all();
+                }
+                return;
+            }
+            Some(latest_id) if id == latest_id => {
+                // another part of the latest event
+                if let Some(published) = self.backlog.back_mut().filter(|last| last.id == id) {
+                    recipients.retain(|recipient| !published.recipients.contains(recipient));
+                    published.recipients.extend(&recipients);
+                    self.send(id, &recipients, &event);
+                    return;
+                }
+            }
+            _ => self.latest_id = Some(id),
+        }
+
+        self.send(id, &recipients, &event);
+        if self.config.backlog_len > 0 {
+            if self.backlog.len() == self.config.backlog_len {
+                self.backlog.pop_front();
+            }
+            self.backlog.push_back(Published {
+                id,
+                recipients,
+                event,
+            });
+        }
+    }
+
+    fn send(&mut self, id: u64, recipients: &HashSet<Uuid>, event: &Event) {
+        for recipient in recipients {
+            let Some(subscribers) = self.subscribers.get_mut(recipient) else {
+                continue;
+            };
+            subscribers.retain(|subscriber| {
+                let message = EventMessage::Event {
+                    id,
+                    event: event.clone(),
+                };
+                match subscriber.sender.try_send(message) {
+                    Ok(()) => true,
+                    Err(mpsc::error::TrySendError::Full(_)) => {
+                        info!(target: "uchat_server", user_id = %recipient, "dropping lagging event subscriber");
+                        false
+                    }
+                    Err(mpsc::error::TrySendError::Closed(_)) => false,
+                }
+            });
+            if subscribers.is_empty() {
+                self.subscribers.remove(recipient);
+            }
+        }
+    }
+
+    fn resync_all(&mut self) {
+        self.backlog.clear();
+
+        let Some(latest_id) = self.latest_id else {
+            // nobody can have seen an event yet
+            return;
+        };
+        for subscribers in self.subscribers.values_mut() {
+            // lagging connections are dropped and resync when they reconnect
+            subscribers.retain(|subscriber| {
+                subscriber
+                    .sender
+                    .try_send(EventMessage::Resync { id: latest_id })
+                    .is_ok()
+            });
+        }
+        self.subscribers
+            .retain(|_, subscribers| !subscribers.is_empty());
+    }
+}
+
+/// Event waiting to be sent through the relay.
+#[derive(Clone, Debug)]
+pub struct Outgoing {
+    pub recipients: Vec<Uuid>,
+    pub event: Event,
+}
+
+/// Routes events to the connections of their recipients.
+#[derive(Clone)]
+pub struct EventHub {
+    inner: Arc<Mutex<Inner>>,
+    relay: Option<mpsc::Sender<Outgoing>>,
+}
+
+impl Default for EventHub {
+    fn default() -> Self {
+        Self::new(HubConfig::default())
+    }
+}
+
+impl EventHub {
+    pub fn new(config: HubConfig) -> Self {
+        // ids continue to grow across restarts, so ids from before a restart are never mistaken
+        // for new ones
+        let first_id = chrono::Utc::now().timestamp_micros().max(1) as u64;
+        Self::with_first_id(config, first_id)
+    }
+
+    fn with_first_id(config: HubConfig, first_id: u64) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                config,
+                next_local_id: first_id,
+                latest_id: None,
+                next_subscriber_id: 0,
+                backlog: VecDeque::with_capacity(config.backlog_len),
+                subscribers: HashMap::new(),
+            })),
+            relay: None,
+        }
+    }
+
+    /// Sends events through `relay` instead of delivering them directly. The relay is expected to
+    /// [`deliver`](Self::deliver) them to every instance, this one included.
+    pub fn with_relay(mut self, relay: mpsc::Sender<Outgoing>) -> Self {
+        self.relay = Some(relay);
+        self
+    }
+
+    /// Sends `event` to every connection of the `recipients`.
+    pub fn publish<I>(&self, recipients: I, event: Event)
+    where
+        I: IntoIterator<Item = Uuid>,
+    {
+        let Some(relay) = &self.relay else {
+            // ids are taken and delivered under one lock, so they are delivered in order
+            let mut inner = self.inner.lock().expect("poisoned event hub");
+            let id = inner.next_local_id;
+            inner.next_local_id += 1;
+            inner.deliver(id, recipients.into_iter().collect(), event);
+            return;
+        };
+
+        let outgoing = Outgoing {
+            recipients: recipients.into_iter().collect(),
+            event,
+        };
+        if let Err(e) = relay.try_send(outgoing) {
+            // clients resync once they notice the gap
+            warn!(target: "uchat_server", error = %e, "dropping event, relay is unavailable");
+        }
+    }
+
+    /// Sends an event with a known id to the local connections of the `recipients`.
+    ///
+    /// Events are expected in the order of their ids. Large events may arrive in several parts
+    /// with the same id, each with some of the recipients, and parts which were delivered before
+    /// are ignored. An event older than the latest one resyncs every connection instead, since
+    /// clients may already have moved past it.
+    pub fn deliver<I>(&self, id: u64, recipients: I, event: Event)
+    where
+        I: IntoIterator<Item = Uuid>,
+    {
+        let mut inner = self.inner.lock().expect("poisoned event hub");
+        inner.deliver(id, recipients.into_iter().collect(), event);
+    }
+
+    /// Tells every connection that events may have been missed, e.g. after the relay lost its
+    /// connection. The backlog is cleared, so reconnecting clients resync as well.
+    pub fn resync_all(&self) {
+        let mut inner = self.inner.lock().expect("poisoned event hub");
+        inner.resync_all();
+    }
+
+    /// Starts receiving events for `user_id`.
+    ///
+    /// With `since`, the events after it are queued first, or [`EventMessage::Resync`] if they
+    /// aren't in the backlog anymore. The receiver is closed when the connection falls behind.
+    pub fn subscribe(&self, user_id: Uuid, since: Option<u64>) -> Subscription {
+        let mut inner = self.inner.lock().expect("poisoned event hub");
+
+        let replay = since.map_or_else(Vec::new, |since| inner.replay(user_id, since));
+        let (sender, receiver) = mpsc::channel(inner.config.queue_len.max(replay.len()).max(1));
+        for message in replay {
+            sender.try_send(message).expect("queue fits the replay");
+        }
+
+        let id = inner.next_subscriber_id;
+        inner.next_subscriber_id += 1;
+        inner
+            .subscribers
+            .entry(user_id)
+            .or_default()
+            .push(Subscriber { id, sender });
+
+        Subscription {
+            hub: self.clone(),
+            user_id,
+            id,
+            receiver,
+        }
+    }
+
+    /// Number of open connections.
+    pub fn subscriber_count(&self) -> usize {
+        let inner = self.inner.lock().expect("poisoned event hub");
+        inner.subscribers.values().map(Vec::len).sum()
+    }
+
+    fn unsubscribe(&self, user_id: Uuid, id: u64) {
+        let mut inner = self.inner.lock().expect("poisoned event hub");
+        if let Some(subscribers) = inner.subscribers.get_mut(&user_id) {
+            subscribers.retain(|subscriber| subscriber.id != id);
+            if subscribers.is_empty() {
+                inner.subscribers.remove(&user_id);
+            }
+        }
+    }
+}
+
+/// Events for one connection. Unsubscribes when dropped.
+pub struct Subscription {
+    hub: EventHub,
+    user_id: Uuid,
+    id: u64,
+    receiver: mpsc::Receiver<EventMessage>,
+}
+
+impl Subscription {
+    /// Next event, or `None` if the connection fell behind and was dropped by the hub.
+    pub async fn recv(&mut self) -> Option<EventMessage> {
+        self.receiver.recv().await
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.hub.unsubscribe(self.user_id, self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_post() -> Event {
+        Event::NewPost {
+            post_id: Uuid::new_v4(),
+            author_id: Uuid::new_v4(),
+        }
+    }
+
+    fn hub(queue_len: usize, backlog_len: usize) -> EventHub {
+        EventHub::with_first_id(
+            HubConfig {
+                queue_len,
+                backlog_len,
+            },
+            1,
+        )
+    }
+
+    fn drain(subscription: &mut Subscription) -> Vec<EventMessage> {
+        std::iter::from_fn(|| subscription.receiver.try_recv().ok()).collect()
+    }
+
+    #[tokio::test]
+    async fn delivers_events_to_recipients_only() {
+        let hub = hub(8, 8);
+        let alice = Uuid::new_v4();
+        let bob = Uuid::new_v4();
+        let mut alice_sub = hub.subscribe(alice, None);
+        let mut bob_sub = hub.subscribe(bob, None);
+
+        let event = new_post();
+        hub.publish([alice], event.clone());
+
+        assert_eq!(
+            alice_sub.recv().await,
+            Some(EventMessage::Event { id: 1, event })
+        );
+        assert!(drain(&mut bob_sub).is_empty());
+    }
+
+    #[tokio::test]
+    async fn sends_events_through_relay() {
+        let (sender, mut relayed) = mpsc::channel(1);
+        let hub = hub(8, 8).with_relay(sender);
+        let user = Uuid::new_v4();
+        let mut subscription = hub.subscribe(user, None);
+
+        hub.publish([user], new_post());
+        assert!(drain(&mut subscription).is_empty());
+
+        let outgoing = relayed.recv().await.unwrap();
+        assert_eq!(outgoing.recipients, vec![user]);
+        hub.deliver(10, outgoing.recipients, outgoing.event);
+        assert_eq!(subscription.recv().await.unwrap().id(), 10);
+    }
+
+    #[tokio::test]
+    async fn drops_lagging_subscribers() {
+        let hub = hub(2, 8);
+        let user = Uuid::new_v4();
+        let mut subscription = hub.subscribe(user, None);
+
+        for _ in 0..3 {
+            hub.publish([user], new_post());
+        }
+
+        assert_eq!(drain(&mut subscription).len(), 2);
+        assert_eq!(subscription.recv().await, None);
+        assert_eq!(hub.subscriber_count(), 0);
+    }
+
+    #[test]
+    fn replays_missed_events() {
+        let hub = hub(8, 8);
+        let user = Uuid::new_v4();
+        hub.deliver(1, [user], new_post());
+        hub.deliver(2, [Uuid::new_v4()], new_post());
+        hub.deliver(3, [user], new_post());
+
+        let mut subscription = hub.subscribe(user, Some(1));
+        let ids: Vec<_> = drain(&mut subscription)
+            .iter()
+            .map(EventMessage::id)
+            .collect();
+        assert_eq!(ids, vec![3]);
+
+        let mut subscription = hub.subscribe(user, Some(3));
+        assert!(drain(&mut subscription).is_empty());
+    }
+
+    #[test]
+    fn keeps_one_backlog_entry_per_event() {
+        let hub = hub(8, 2);
+        let alice = Uuid::new_v4();
+        let bob = Uuid::new_v4();
+        let mut alice_sub = hub.subscribe(alice, None);
+        hub.deliver(1, [alice, bob, alice, Uuid::new_v4()], new_post());
+        hub.deliver(2, [alice], new_post());
+
+        // duplicate recipients get the event once
+        assert_eq!(drain(&mut alice_sub).len(), 2);
+
+        let mut bob_sub = hub.subscribe(bob, Some(0));
+        let ids: Vec<_> = drain(&mut bob_sub).iter().map(EventMessage::id).collect();
+        assert_eq!(ids, vec![1]);
+    }
+
+    #[test]
+    fn delivers_parts_of_an_event_once() {
+        let hub = hub(8, 8);
+        let alice = Uuid::new_v4();
+        let bob = Uuid::new_v4();
+        let mut alice_sub = hub.subscribe(alice, None);
+        let event = new_post();
+        hub.deliver(1, [alice], event.clone());
+        hub.deliver(1, [alice, bob], event.clone());
+        hub.deliver(1, [bob], event);
+
+        assert_eq!(drain(&mut alice_sub).len(), 1);
+        let mut bob_sub = hub.subscribe(bob, Some(0));
+        let ids: Vec<_> = drain(&mut bob_sub).iter().map(EventMessage::id).collect();
+        assert_eq!(ids, vec![1]);
+    }
+
+    #[test]
+    fn resyncs_when_events_arrive_out_of_order() {
+        let hub = hub(8, 8);
+        let user = Uuid::new_v4();
+        let mut subscription = hub.subscribe(user, None);
+        hub.deliver(1, [user], new_post());
+        hub.deliver(3, [user], new_post());
+        // a repeated event is ignored
+        hub.deliver(1, [user], new_post());
+        assert_eq!(drain(&mut subscription).len(), 2);
+
+        // the client already moved past 2, so it reloads instead
+        hub.deliver(2, [user], new_post());
+        assert_eq!(
+            drain(&mut subscription),
+            vec![EventMessage::Resync { id: 3 }]
+        );
+        let mut subscription = hub.subscribe(user, Some(1));
+        assert_eq!(
+            drain(&mut subscription),
+            vec![EventMessage::Resync { id: 3 }]
+        );
+
+        hub.deliver(4, [user], new_post());
+        let mut subscription = hub.subscribe(user, Some(3));
+        let ids: Vec<_> = drain(&mut subscription)
+            .iter()
+            .map(EventMessage::id)
+            .collect();
+        assert_eq!(ids, vec![4]);
+    }
+
+    #[test]
+    fn asks_for_resync_when_events_were_evicted() {
+        let hub = hub(8, 2);
+        let user = Uuid::new_v4();
+        for id in 1..=4 {
+            hub.deliver(id, [user], new_post());
+        }
+
+        let mut subscription = hub.subscribe(user, Some(1));
+        assert_eq!(
+            drain(&mut subscription),
+            vec![EventMessage::Resync { id: 4 }]
+        );
+
+        // an id from before a restart
+        let mut subscription = hub.subscribe(user, Some(100));
+        assert_eq!(
+            drain(&mut subscription),
+            vec![EventMessage::Resync { id: 4 }]
+        );
+    }
+
+    #[test]
+    fn asks_for_resync_before_first_event() {
+        let hub = hub(8, 8);
+        let mut subscription = hub.subscribe(Uuid::new_v4(), Some(5));
+        assert_eq!(
+            drain(&mut subscription),
+            vec![EventMessage::Resync { id: 5 }]
+        );
+    }
+
+    #[test]
+    fn resyncs_everyone_after_a_gap() {
+        let hub = hub(8, 8);
+        let user = Uuid::new_v4();
+        hub.deliver(1, [user], new_post());
+        let mut subscription = hub.subscribe(user, None);
+
+        hub.resync_all();
+        assert_eq!(
+            drain(&mut subscription),
+            vec![EventMessage::Resync { id: 1 }]
+        );
+
+        // event 2 was lost
+        hub.deliver(3, [user], new_post());
+        let mut subscription = hub.subscribe(user, Some(1));
+        assert_eq!(
+            drain(&mut subscription),
+            vec![EventMessage::Resync { id: 3 }]
+        );
+    }
+
+    #[test]
+    fn unsubscribes_on_drop() {
+        let hub = hub(8, 8);
+        let user = Uuid::new_v4();
+        let first = hub.subscribe(user, None);
+        let _second = hub.subscribe(user, None);
+        assert_eq!(hub.subscriber_count(), 2);
+
+        drop(first);
+        assert_eq!(hub.subscriber_count(), 1);
+    }
+}
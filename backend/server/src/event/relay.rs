@@ -0,0 +1,165 @@
+//! Delivery of events to every server instance using Postgres `LISTEN`/`NOTIFY`.
+//!
+//! Published events are numbered with a database sequence and sent as notifications on
+//! [`EVENT_CHANNEL`], which arrive in the order of their ids. Every instance, including the one
+//! which published the event, listens on the channel and delivers the events to its own
+//! connections. Event ids are therefore the same on every instance, so clients can resume on any
+//! of them.
+
+use std::time::Duration;
+
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+use uchat_api::event::Event;
+use uchat_query::notify::{Listener, MAX_PAYLOAD_LEN};
+use uchat_query::AsyncConnectionPool;
+use uuid::Uuid;
+
+use super::{EventHub, Outgoing};
+
+pub const EVENT_CHANNEL: &str = "uchat_events";
+
+/// Events waiting to be sent before new ones are dropped.
+const OUTGOING_QUEUE_LEN: usize = 1024;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Payload of a notification.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+struct Relayed {
+    id: u64,
+    recipients: Vec<Uuid>,
+    event: Event,
+}
+
+/// Starts relaying the events of `hub` through the database. Returns the hub to publish to from
+/// now on.
+///
+/// Fails if the listener can't connect to `database_url`, see
+/// [`check_listener_url`](uchat_query::notify::check_listener_url).
+pub fn start(
+    hub: EventHub,
+    db_pool: AsyncConnectionPool,
+    database_url: String,
+) -> Result<EventHub> {
+    uchat_query::notify::check_listener_url(&database_url)?;
+
+    let (sender, outgoing) = mpsc::channel(OUTGOING_QUEUE_LEN);
+    tokio::spawn(send_notifications(db_pool, outgoing));
+    tokio::spawn(receive_notifications(hub.clone(), database_url));
+    Ok(hub.with_relay(sender))
+}
+
+async fn send_notifications(db_pool: AsyncConnectionPool, mut outgoing: mpsc::Receiver<Outgoing>) {
+    while let Some(Outgoing { recipients, event }) = outgoing.recv().await {
+        if let Err(e) = notify(&db_pool, &recipients, &event).await {
+            error!(target: "uchat_server", error = %e, "failed to relay event");
+        }
+    }
+}
+
+async fn notify(db_pool: &AsyncConnectionPool, recipients: &[Uuid], event: &Event) -> Result<()> {
+    let mut conn = db_pool.get().await?;
+    uchat_query::notify::notify_numbered(&mut conn, EVENT_CHANNEL, |id| {
+        encode(id as u64, recipients, event)
+    })?;
+    Ok(())
+}
+
+async fn receive_notifications(hub: EventHub, database_url: String) {
+    let mut reconnecting = false;
+    loop {
+        match Listener::connect(&database_url, &[EVENT_CHANNEL]).await {
+            Ok(mut listener) => {
+                info!(target: "uchat_server", channel = EVENT_CHANNEL, "listening for events");
+                if reconnecting {
+                    // events sent while the connection was down are lost
+                    hub.resync_all();
+                }
+                reconnecting = true;
+
+                while let Some(notification) = listener.recv().await {
+                    match serde_json::from_str::<Relayed>(&notification.payload) {
+                        Ok(relayed) => hub.deliver(relayed.id, relayed.recipients, relayed.event),
+                        Err(e) => {
+                            warn!(target: "uchat_server", error = %e, "invalid event notification")
+                        }
+                    }
+                }
+                warn!(target: "uchat_server", "lost event listener connection");
+            }
+            Err(e) => {
+                error!(target: "uchat_server", error = %e, "failed to listen for events");
+                reconnecting = true;
+            }
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Splits an event into notification payloads which fit the size limit of Postgres. Every
+/// payload has a share of the recipients.
+fn encode(id: u64, recipients: &[Uuid], event: &Event) -> Result<Vec<String>> {
+    let mut chunk_len = recipients.len().max(1);
+    loop {
+        let payloads = recipients
+            .chunks(chunk_len)
+            .map(|recipients| {
+                serde_json::to_string(&Relayed {
+                    id,
+                    recipients: recipients.to_vec(),
+                    event: event.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if payloads
+            .iter()
+            .all(|payload| payload.len() <= MAX_PAYLOAD_LEN)
+        {
+            return Ok(payloads);
+        }
+        if chunk_len == 1 {
+            color_eyre::eyre::bail!("event is too large for a notification");
+        }
+        chunk_len = chunk_len.div_ceil(2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_large_recipient_lists() {
+        let recipients: Vec<_> = (0..1000).map(|_| Uuid::new_v4()).collect();
+        let event = Event::NewPost {
+            post_id: Uuid::new_v4(),
+            author_id: Uuid::new_v4(),
+        };
+
+        let payloads = encode(7, &recipients, &event).unwrap();
+        assert!(payloads.len() > 1);
+
+        let mut decoded = vec![];
+        for payload in payloads {
+            assert!(payload.len() <= MAX_PAYLOAD_LEN);
+            let relayed: Relayed = serde_json::from_str(&payload).unwrap();
+            assert_eq!(relayed.id, 7);
+            assert_eq!(relayed.event, event);
+            decoded.extend(relayed.recipients);
+        }
+        assert_eq!(decoded, recipients);
+    }
+
+    #[test]
+    fn skips_events_without_recipients() {
+        let event = Event::DirectMessage {
+            post_id: Uuid::new_v4(),
+            from: Uuid::new_v4(),
+        };
+        assert!(encode(1, &[], &event).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,233 @@
+//! Data exports, see [`uchat_query::export`].
+//!
+//! Archives are written to the [`ExportDir`] by a background job and downloaded through signed
+//! links, so downloads work without a session. A link is a signature over the export id and the
+//! time the link expires.
+//!
+//! The archive is written by whichever instance runs the job and may be downloaded from any
+//! other, so instances behind the same load balancer have to share the [`ExportDir`].
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use uchat_api::export::DownloadDataExport;
+use uchat_api::Endpoint;
+use uchat_crypto::sign::Keys;
+use uchat_query::{AsyncConnectionPool, QueryError};
+use url::form_urlencoded;
+use uuid::Uuid;
+
+const LINK_CONTEXT: &[u8] = b"uchat-export:";
+
+/// Directory where archives are stored, shared by all instances.
+#[derive(Clone, Debug)]
+pub struct ExportDir(pub PathBuf);
+
+impl ExportDir {
+    pub fn archive_path(&self, export_id: Uuid) -> PathBuf {
+        self.0.join(format!("{export_id}.zip"))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error(transparent)]
+    Query(#[from] QueryError),
+
+    #[error("failed to write archive: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("failed to write archive: {0}")]
+    Zip(#[from] zip::result::ZipError),
+
+    #[error("failed to serialize data: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Writes the archive of `export_id` with the data of `user_id`, then marks the export as
+/// complete. Exports whose job is dead-lettered are marked as failed by the job worker, so the
+/// user can try again.
+pub async fn build(
+    db_pool: &AsyncConnectionPool,
+    dir: &ExportDir,
+    export_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), ExportError> {
+    let mut conn = db_pool.get().await?;
+    let files = uchat_query::export::collect(&mut conn, user_id)?;
+
+    let path = dir.archive_path(export_id);
+    tokio::task::spawn_blocking(move || write_archive(&path, &files))
+        .await
+        .expect("archive writer panicked")?;
+
+    uchat_query::export::complete(&mut conn, export_id)?;
+    Ok(())
+}
+
+/// Writes `files` into a zip archive at `path`. The archive only appears at `path` once it is
+/// complete, so it is never downloaded halfway.
+pub fn write_archive(path: &Path, files: &[(&str, Value)]) -> Result<(), ExportError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let partial = path.with_extension("partial");
+
+    let mut zip = zip::ZipWriter::new(File::create(&partial)?);
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (name, data) in files {
+        zip.start_file(*name, options)?;
+        serde_json::to_writer_pretty(&mut zip, data)?;
+    }
+    zip.finish()?;
+
+    std::fs::rename(partial, path)?;
+    Ok(())
+}
+
+/// Deletes the exports requested before `before` with their archives, and archives which lost
+/// their export, e.g. because the account was deleted.
+///
+/// Returns the number of deleted archives.
+pub async fn remove_stale(
+    db_pool: &AsyncConnectionPool,
+    dir: &ExportDir,
+    before: DateTime<Utc>,
+) -> Result<usize, ExportError> {
+    let mut conn = db_pool.get().await?;
+    uchat_query::export::delete_older_than(&mut conn, before)?;
+
+    let entries = match std::fs::read_dir(&dir.0) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    let mut archives = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        let export_id = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| Uuid::parse_str(stem).ok());
+        if let Some(export_id) = export_id {
+            archives.push((export_id, path));
+        }
+    }
+
+    let export_ids: Vec<Uuid> = archives.iter().map(|(export_id, _)| *export_id).collect();
+    let existing = uchat_query::export::existing(&mut conn, &export_ids)?;
+    let mut removed = 0;
+    for (export_id, path) in archives {
+        if !existing.contains(&export_id) {
+            std::fs::remove_file(path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+fn link_message(export_id: Uuid, expires: i64) -> Vec<u8> {
+    [LINK_CONTEXT, export_id.as_bytes(), &expires.to_be_bytes()].concat()
+}
+
+/// Link to download the archive of `export_id` until `expires_at`, relative to the API url.
+pub fn download_url(keys: &Keys, export_id: Uuid, expires_at: DateTime<Utc>) -> String {
+    let expires = expires_at.timestamp();
+    let mut rng = uchat_crypto::new_rng();
+    let signature = keys.sign(&mut rng, &link_message(export_id, expires));
+
+    let query = form_urlencoded::Serializer::new(String::new())
+        .append_pair("id", &export_id.to_string())
+        .append_pair("expires", &expires.to_string())
+        .append_pair("signature", &uchat_crypto::encode_base64(signature))
+        .finish();
+    format!("{}?{query}", DownloadDataExport::URL)
+}
+
+/// Whether a download link was created by [`download_url`] and hasn't expired at `now`.
+pub fn verify_link(keys: &Keys, link: &DownloadDataExport, now: DateTime<Utc>) -> bool {
+    if link.expires <= now.timestamp() {
+        return false;
+    }
+    uchat_crypto::decode_base64(link.signature.trim())
+        .ok()
+        .and_then(|signature| uchat_crypto::sign::signature_from_bytes(signature).ok())
+        .map(|signature| {
+            keys.verify(&link_message(link.id, link.expires), signature)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn keys() -> Keys {
+        let mut rng = uchat_crypto::new_rng();
+        Keys::generate(&mut rng).unwrap().1
+    }
+
+    fn parse_link(url: &str) -> DownloadDataExport {
+        let (_, query) = url.split_once('?').unwrap();
+        let mut link = DownloadDataExport {
+            id: Uuid::nil(),
+            expires: 0,
+            signature: String::new(),
+        };
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            match &*key {
+                "id" => link.id = value.parse().unwrap(),
+                "expires" => link.expires = value.parse().unwrap(),
+                "signature" => link.signature = value.into_owned(),
+                _ => panic!("unexpected parameter {key}"),
+            }
+        }
+        link
+    }
+
+    #[test]
+    fn verifies_download_links() {
+        let keys = keys();
+        let export_id = Uuid::new_v4();
+        let now = Utc::now();
+        let url = download_url(&keys, export_id, now + chrono::Duration::hours(1));
+        assert!(url.starts_with(DownloadDataExport::URL));
+
+        let mut link = parse_link(&url);
+        assert_eq!(link.id, export_id);
+        assert!(verify_link(&keys, &link, now));
+        assert!(!verify_link(&keys, &link, now + chrono::Duration::hours(2)));
+
+        link.expires += 3600;
+        assert!(!verify_link(&keys, &link, now));
+        link.expires -= 3600;
+        link.id = Uuid::new_v4();
+        assert!(!verify_link(&keys, &link, now));
+    }
+
+    #[test]
+    fn writes_archives() {
+        let dir = ExportDir(std::env::temp_dir().join(format!("uchat-test-{}", Uuid::new_v4())));
+        let path = dir.archive_path(Uuid::new_v4());
+        let files = [("profile.json", serde_json::json!({ "handle": "user" }))];
+        write_archive(&path, &files).unwrap();
+
+        let mut archive = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let mut profile = String::new();
+        archive
+            .by_name("profile.json")
+            .unwrap()
+            .read_to_string(&mut profile)
+            .unwrap();
+        let profile: Value = serde_json::from_str(&profile).unwrap();
+        assert_eq!(profile["handle"], "user");
+        assert!(!path.with_extension("partial").exists());
+
+        std::fs::remove_dir_all(&dir.0).unwrap();
+    }
+}
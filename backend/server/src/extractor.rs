@@ -0,0 +1,93 @@
+use axum::async_trait;
+use axum::extract::{FromRef, FromRequestParts};
+use axum::http::{header, request::Parts, StatusCode};
+use std::ops::{Deref, DerefMut};
+use uchat_cookie::{SESSION_ID, SESSION_SIGNATURE};
+use uchat_query::{AsyncConnectionPool, OwnedAsyncConnection};
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::AppState;
+
+/// Database connection checked out of the pool for the duration of a request.
+pub struct DbConnection(pub OwnedAsyncConnection);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for DbConnection
+where
+    AsyncConnectionPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(_parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let pool = AsyncConnectionPool::from_ref(state);
+        let connection = pool.get_owned().await?;
+        Ok(Self(connection))
+    }
+}
+
+impl Deref for DbConnection {
+    type Target = OwnedAsyncConnection;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for DbConnection {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// Session of the logged in user.
+///
+/// Requests without a valid session are rejected with `401 Unauthorized`.
+#[derive(Clone, Copy, Debug)]
+pub struct UserSession {
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for UserSession
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let unauthorized = || ApiError::new(StatusCode::UNAUTHORIZED, "not logged in");
+        let state = AppState::from_ref(state);
+
+        let cookies = parts
+            .headers
+            .get(header::COOKIE)
+            .and_then(|cookies| cookies.to_str().ok())
+            .ok_or_else(unauthorized)?;
+
+        let session_id = uchat_cookie::get(cookies, SESSION_ID)
+            .and_then(|id| Uuid::parse_str(&id).ok())
+            .ok_or_else(unauthorized)?;
+
+        let signature = uchat_cookie::get(cookies, SESSION_SIGNATURE)
+            .and_then(|sig| uchat_crypto::decode_base64(sig).ok())
+            .and_then(|sig| uchat_crypto::sign::signature_from_bytes(sig).ok())
+            .ok_or_else(unauthorized)?;
+
+        state
+            .signing_keys
+            .verify(session_id.as_bytes(), signature)
+            .map_err(|_| unauthorized())?;
+
+        let mut conn = state.db_pool.get().await?;
+        let session = uchat_query::session::get(&mut conn, session_id)?.ok_or_else(unauthorized)?;
+
+        Ok(Self {
+            session_id,
+            user_id: session.user_id,
+        })
+    }
+}
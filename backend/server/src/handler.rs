@@ -0,0 +1,17 @@
+pub mod block;
+pub mod event;
+pub mod export;
+pub mod follow;
+pub mod media;
+pub mod moderation;
+pub mod notification;
+pub mod post;
+pub mod search;
+pub mod tag;
+pub mod totp;
+pub mod user;
+
+/// Current time as seconds since the unix epoch.
+pub(crate) fn unix_now() -> u64 {
+    chrono::Utc::now().timestamp().max(0) as u64
+}
@@ -0,0 +1,82 @@
+use axum::http::StatusCode;
+use axum::Json;
+use tracing::info;
+use uchat_api::block::{
+    BlockUser, BlockUserOk, GetBlockedUsers, GetBlockedUsersOk, MuteUser, MuteUserOk,
+    RestrictedUser, UnblockUser, UnblockUserOk, UnmuteUser, UnmuteUserOk,
+};
+use uuid::Uuid;
+
+use crate::error::{ApiError, ApiResult};
+use crate::extractor::{DbConnection, UserSession};
+
+pub async fn block(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<BlockUser>,
+) -> ApiResult<Json<BlockUserOk>> {
+    reject_self(&session, req.user_id, "block")?;
+    uchat_query::block::block(&mut conn, session.user_id, req.user_id)?;
+
+    info!(target: "uchat_server", user_id = %session.user_id, blocked_id = %req.user_id, "user blocked");
+    Ok(Json(BlockUserOk))
+}
+
+pub async fn unblock(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<UnblockUser>,
+) -> ApiResult<Json<UnblockUserOk>> {
+    uchat_query::block::unblock(&mut conn, session.user_id, req.user_id)?;
+    Ok(Json(UnblockUserOk))
+}
+
+pub async fn mute(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<MuteUser>,
+) -> ApiResult<Json<MuteUserOk>> {
+    reject_self(&session, req.user_id, "mute")?;
+    uchat_query::mute::mute(&mut conn, session.user_id, req.user_id)?;
+    Ok(Json(MuteUserOk))
+}
+
+pub async fn unmute(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<UnmuteUser>,
+) -> ApiResult<Json<UnmuteUserOk>> {
+    uchat_query::mute::unmute(&mut conn, session.user_id, req.user_id)?;
+    Ok(Json(UnmuteUserOk))
+}
+
+pub async fn list(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(_req): Json<GetBlockedUsers>,
+) -> ApiResult<Json<GetBlockedUsersOk>> {
+    let to_api = |users: Vec<(Uuid, String)>| {
+        users
+            .into_iter()
+            .map(|(id, handle)| RestrictedUser { id, handle })
+            .collect()
+    };
+
+    Ok(Json(GetBlockedUsersOk {
+        blocked: to_api(uchat_query::block::blocked_users(
+            &mut conn,
+            session.user_id,
+        )?),
+        muted: to_api(uchat_query::mute::muted_users(&mut conn, session.user_id)?),
+    }))
+}
+
+pub(super) fn reject_self(session: &UserSession, user_id: Uuid, action: &str) -> ApiResult<()> {
+    if session.user_id == user_id {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("you can't {action} yourself"),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use std::borrow::Cow;
+use std::time::Duration;
+
+use axum::extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::response::Response;
+use tracing::{debug, warn};
+use uchat_api::event::{EventMessage, SubscribeEvents, CLOSE_LAGGED};
+use uuid::Uuid;
+
+use crate::csrf::AllowedOrigins;
+use crate::error::ApiResult;
+use crate::event::EventHub;
+use crate::extractor::UserSession;
+
+/// Keeps idle connections from being closed by proxies.
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Streams events for the logged in user over a WebSocket.
+///
+/// Upgrades are GET requests without a CSRF token, so the `Origin` header is checked instead and
+/// pages of other sites can't open this socket on behalf of the user.
+pub async fn subscribe(
+    State(hub): State<EventHub>,
+    State(origins): State<AllowedOrigins>,
+    headers: HeaderMap,
+    session: UserSession,
+    Query(req): Query<SubscribeEvents>,
+    ws: WebSocketUpgrade,
+) -> ApiResult<Response> {
+    origins.check(&headers)?;
+    Ok(ws.on_upgrade(move |socket| stream_events(socket, hub, session.user_id, req.since)))
+}
+
+async fn stream_events(mut socket: WebSocket, hub: EventHub, user_id: Uuid, since: Option<u64>) {
+    let mut subscription = hub.subscribe(user_id, since);
+    debug!(target: "uchat_server", %user_id, ?since, "event stream opened");
+
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    ping.tick().await;
+
+    loop {
+        tokio::select! {
+            message = subscription.recv() => {
+                let Some(message) = message else {
+                    let frame = CloseFrame {
+                        code: CLOSE_LAGGED,
+                        reason: Cow::from("lagging behind"),
+                    };
+                    let _ = socket.send(Message::Close(Some(frame))).await;
+                    break;
+                };
+                if send(&mut socket, &message).await.is_err() {
+                    break;
+                }
+            }
+            incoming = socket.recv() => match incoming {
+                // clients don't send anything but control frames
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => (),
+            },
+            _ = ping.tick() => {
+                if socket.send(Message::Ping(Vec::new())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    debug!(target: "uchat_server", %user_id, "event stream closed");
+}
+
+async fn send(socket: &mut WebSocket, message: &EventMessage) -> Result<(), axum::Error> {
+    let json = match serde_json::to_string(message) {
+        Ok(json) => json,
+        Err(e) => {
+            warn!(target: "uchat_server", error = %e, "failed to serialize event");
+            return Ok(());
+        }
+    };
+    socket.send(Message::Text(json)).await
+}
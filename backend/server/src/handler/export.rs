@@ -0,0 +1,107 @@
+use axum::body::{self, Body};
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderValue, Request, StatusCode};
+use axum::response::Response;
+use axum::Json;
+use chrono::{Duration, Utc};
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+use tracing::info;
+use uchat_api::export::{
+    DataExport, DownloadDataExport, GetDataExport, GetDataExportOk, RequestDataExport,
+    RequestDataExportOk,
+};
+use uchat_crypto::sign::Keys;
+
+use crate::csrf::AllowedOrigins;
+use crate::error::{ApiError, ApiResult};
+use crate::export::ExportDir;
+use crate::extractor::{DbConnection, UserSession};
+
+const EXPORT_INTERVAL_HOURS: i64 = 24;
+const DOWNLOAD_LINK_LIFETIME_MINUTES: i64 = 60;
+
+pub async fn request(
+    State(keys): State<Keys>,
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(_req): Json<RequestDataExport>,
+) -> ApiResult<Json<RequestDataExportOk>> {
+    let since = Utc::now() - Duration::hours(EXPORT_INTERVAL_HOURS);
+    let Some(export_id) = uchat_query::export::request(&mut conn, session.user_id, since)? else {
+        return Err(ApiError::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            "you can request one export per day",
+        ));
+    };
+    info!(target: "uchat_server", %export_id, user_id = %session.user_id, "data export requested");
+
+    let export = uchat_query::export::find(&mut conn, export_id)?;
+    Ok(Json(RequestDataExportOk {
+        export: export_to_api(&keys, export),
+    }))
+}
+
+pub async fn status(
+    State(keys): State<Keys>,
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(_req): Json<GetDataExport>,
+) -> ApiResult<Json<GetDataExportOk>> {
+    let export = uchat_query::export::latest(&mut conn, session.user_id)?;
+    Ok(Json(GetDataExportOk {
+        export: export.map(|export| export_to_api(&keys, export)),
+    }))
+}
+
+/// Streams an archive to whoever has a valid link, see [`crate::export::download_url`].
+///
+/// Downloads are GET requests without a CSRF token, so like the event stream they are refused if
+/// the `Origin` header names another site.
+pub async fn download(
+    State(keys): State<Keys>,
+    State(export_dir): State<ExportDir>,
+    State(origins): State<AllowedOrigins>,
+    Query(link): Query<DownloadDataExport>,
+    request: Request<Body>,
+) -> ApiResult<Response> {
+    origins.check(request.headers())?;
+    if !crate::export::verify_link(&keys, &link, Utc::now()) {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "this download link is invalid or expired",
+        ));
+    }
+
+    let path = export_dir.archive_path(link.id);
+    let mut response = match ServeFile::new(path).oneshot(request).await {
+        Ok(response) => response,
+        Err(never) => match never {},
+    };
+    if response.status() == StatusCode::NOT_FOUND {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            "this export is no longer available",
+        ));
+    }
+    response.headers_mut().insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_static("attachment; filename=\"uchat-export.zip\""),
+    );
+    Ok(response.map(body::boxed))
+}
+
+fn export_to_api(keys: &Keys, export: uchat_query::export::DataExport) -> DataExport {
+    let download_expires = export
+        .completed_at
+        .map(|_| Utc::now() + Duration::minutes(DOWNLOAD_LINK_LIFETIME_MINUTES));
+    DataExport {
+        id: export.id,
+        requested_at: export.requested_at,
+        completed_at: export.completed_at,
+        failed: export.failed_at.is_some(),
+        download_url: download_expires
+            .map(|expires_at| crate::export::download_url(keys, export.id, expires_at)),
+        download_expires,
+    }
+}
@@ -0,0 +1,66 @@
+use axum::Json;
+use tracing::info;
+use uchat_api::follow::{
+    AnswerFollowRequest, AnswerFollowRequestOk, FollowRequest, FollowStatus, FollowUser,
+    FollowUserOk, GetFollowRequests, GetFollowRequestsOk, SetPrivate, SetPrivateOk, UnfollowUser,
+    UnfollowUserOk,
+};
+use uchat_query::follow as query;
+
+use super::block::reject_self;
+use crate::error::ApiResult;
+use crate::extractor::{DbConnection, UserSession};
+
+pub async fn follow(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<FollowUser>,
+) -> ApiResult<Json<FollowUserOk>> {
+    reject_self(&session, req.user_id, "follow")?;
+    let status = match query::follow(&mut conn, session.user_id, req.user_id)? {
+        query::FollowStatus::Following => FollowStatus::Following,
+        query::FollowStatus::Requested => FollowStatus::Requested,
+    };
+    Ok(Json(FollowUserOk { status }))
+}
+
+pub async fn unfollow(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<UnfollowUser>,
+) -> ApiResult<Json<UnfollowUserOk>> {
+    query::unfollow(&mut conn, session.user_id, req.user_id)?;
+    Ok(Json(UnfollowUserOk))
+}
+
+pub async fn requests(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(_req): Json<GetFollowRequests>,
+) -> ApiResult<Json<GetFollowRequestsOk>> {
+    let requests = query::requests(&mut conn, session.user_id)?
+        .into_iter()
+        .map(|(user_id, handle)| FollowRequest { user_id, handle })
+        .collect();
+    Ok(Json(GetFollowRequestsOk { requests }))
+}
+
+pub async fn answer(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<AnswerFollowRequest>,
+) -> ApiResult<Json<AnswerFollowRequestOk>> {
+    query::answer_request(&mut conn, session.user_id, req.user_id, req.approve)?;
+    Ok(Json(AnswerFollowRequestOk))
+}
+
+pub async fn set_private(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<SetPrivate>,
+) -> ApiResult<Json<SetPrivateOk>> {
+    query::set_private(&mut conn, session.user_id, req.private)?;
+
+    info!(target: "uchat_server", user_id = %session.user_id, private = req.private, "privacy changed");
+    Ok(Json(SetPrivateOk))
+}
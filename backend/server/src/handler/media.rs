@@ -0,0 +1,73 @@
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use tracing::info;
+use uchat_api::media::{DeleteMedia, DeleteMediaOk, GetMediaUsage, GetMediaUsageOk, UploadMediaOk};
+
+use crate::error::{ApiError, ApiResult};
+use crate::extractor::{DbConnection, UserSession};
+use crate::media::{Media, MediaKey, MediaType};
+
+/// Takes the image as the raw body, see [`uchat_api::media::UploadMedia`]. The size of the body
+/// is limited by the router.
+pub async fn upload(
+    State(media): State<Media>,
+    mut conn: DbConnection,
+    session: UserSession,
+    data: Bytes,
+) -> ApiResult<Json<UploadMediaOk>> {
+    let Some(media_type) = MediaType::sniff(&data) else {
+        return Err(ApiError::new(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "only PNG, JPEG, GIF and WebP images can be uploaded",
+        ));
+    };
+    let key = MediaKey::for_content(&data, media_type);
+    let size = data.len() as i64;
+
+    // recorded first, so the file counts towards the quota even if storing it fails halfway.
+    // Uploading it again fixes that.
+    uchat_query::media::add(
+        &mut conn,
+        session.user_id,
+        key.as_str(),
+        media_type.as_mime(),
+        size,
+        media.quota,
+    )?;
+    media.store.put(&key, data, media_type).await?;
+    info!(target: "uchat_server", user_id = %session.user_id, %key, size, "media uploaded");
+
+    Ok(Json(UploadMediaOk {
+        url: media.store.url(&key),
+        key: key.to_string(),
+        content_type: media_type.as_mime().to_string(),
+        size,
+    }))
+}
+
+/// The file stays in the store until [`crate::media::remove_orphans`] runs.
+pub async fn delete(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<DeleteMedia>,
+) -> ApiResult<Json<DeleteMediaOk>> {
+    let Some(key) = MediaKey::parse(&req.key) else {
+        return Err(ApiError::new(StatusCode::NOT_FOUND, "no such upload"));
+    };
+    uchat_query::media::remove(&mut conn, session.user_id, key.as_str())?;
+    Ok(Json(DeleteMediaOk))
+}
+
+pub async fn usage(
+    State(media): State<Media>,
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(_req): Json<GetMediaUsage>,
+) -> ApiResult<Json<GetMediaUsageOk>> {
+    Ok(Json(GetMediaUsageOk {
+        used: uchat_query::media::usage(&mut conn, session.user_id)?,
+        quota: media.quota,
+    }))
+}
@@ -0,0 +1,319 @@
+use axum::http::StatusCode;
+use axum::Json;
+use tracing::info;
+use uchat_api::moderation::{
+    AuditAction, AuditCursor, AuditEntry, GetAuditLog, GetAuditLogOk, GetReports, GetReportsOk,
+    HidePost, HidePostOk, Report, ReportContent, ReportContentOk, ReportCursor, ReportReason,
+    ReportStatus, ReportTarget, ResolveReport, ResolveReportOk, Role, SetRole, SetRoleOk,
+    SuspendUser, SuspendUserOk, UnhidePost, UnhidePostOk, UnsuspendUser, UnsuspendUserOk,
+    PAGE_SIZE,
+};
+use uchat_query::moderation as query;
+use uchat_query::{audit, report, QueryError};
+use uuid::Uuid;
+
+use crate::error::{ApiError, ApiResult};
+use crate::extractor::{DbConnection, UserSession};
+
+pub async fn report(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<ReportContent>,
+) -> ApiResult<Json<ReportContentOk>> {
+    uchat_api::moderation::validate_report_details(&req.details)
+        .map_err(|msg| ApiError::new(StatusCode::BAD_REQUEST, msg))?;
+
+    let target = match req.target {
+        ReportTarget::User(user_id) => report::Target::User(user_id),
+        ReportTarget::Post(post_id) => report::Target::Post(post_id),
+    };
+    let reason = match req.reason {
+        ReportReason::Spam => report::ReportReason::Spam,
+        ReportReason::Harassment => report::ReportReason::Harassment,
+        ReportReason::Hate => report::ReportReason::Hate,
+        ReportReason::Violence => report::ReportReason::Violence,
+        ReportReason::Other => report::ReportReason::Other,
+    };
+
+    let report_id = match report::new(
+        &mut conn,
+        session.user_id,
+        target,
+        reason,
+        req.details.trim(),
+    ) {
+        Ok(report_id) => report_id,
+        Err(QueryError::UniqueViolation) => {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                "you already reported this",
+            ))
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    info!(target: "uchat_server", %report_id, reporter_id = %session.user_id, "new report");
+    Ok(Json(ReportContentOk { report_id }))
+}
+
+pub async fn reports(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<GetReports>,
+) -> ApiResult<Json<GetReportsOk>> {
+    require_role(&mut conn, &session, Role::Moderator)?;
+
+    let status = match req.status {
+        ReportStatus::Open => report::ReportStatus::Open,
+        ReportStatus::Resolved => report::ReportStatus::Resolved,
+        ReportStatus::Dismissed => report::ReportStatus::Dismissed,
+    };
+    let after = req.after.map(|after| report::Cursor {
+        created_at: after.created_at,
+        id: after.id,
+    });
+
+    // one more than needed shows whether there is another page
+    let mut reports = report::list(&mut conn, status, after, PAGE_SIZE + 1)?;
+    let next = if reports.len() as i64 > PAGE_SIZE {
+        reports.truncate(PAGE_SIZE as usize);
+        reports.last().map(|last| ReportCursor {
+            created_at: last.created_at,
+            id: last.id,
+        })
+    } else {
+        None
+    };
+
+    Ok(Json(GetReportsOk {
+        reports: reports.into_iter().map(report_to_api).collect(),
+        next,
+    }))
+}
+
+pub async fn resolve(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<ResolveReport>,
+) -> ApiResult<Json<ResolveReportOk>> {
+    require_role(&mut conn, &session, Role::Moderator)?;
+    report::close(&mut conn, Some(session.user_id), req.report_id, req.dismiss)?;
+
+    info!(target: "uchat_server", report_id = %req.report_id, moderator_id = %session.user_id, dismissed = req.dismiss, "report closed");
+    Ok(Json(ResolveReportOk))
+}
+
+pub async fn hide_post(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<HidePost>,
+) -> ApiResult<Json<HidePostOk>> {
+    require_role(&mut conn, &session, Role::Moderator)?;
+    query::hide_post(&mut conn, Some(session.user_id), req.post_id, req.report_id)?;
+
+    info!(target: "uchat_server", post_id = %req.post_id, moderator_id = %session.user_id, "post hidden");
+    Ok(Json(HidePostOk))
+}
+
+pub async fn unhide_post(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<UnhidePost>,
+) -> ApiResult<Json<UnhidePostOk>> {
+    require_role(&mut conn, &session, Role::Moderator)?;
+    query::unhide_post(&mut conn, Some(session.user_id), req.post_id)?;
+
+    info!(target: "uchat_server", post_id = %req.post_id, moderator_id = %session.user_id, "post unhidden");
+    Ok(Json(UnhidePostOk))
+}
+
+pub async fn suspend(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<SuspendUser>,
+) -> ApiResult<Json<SuspendUserOk>> {
+    let role = require_role(&mut conn, &session, Role::Moderator)?;
+    uchat_api::moderation::validate_suspension_reason(&req.reason)
+        .map_err(|msg| ApiError::new(StatusCode::BAD_REQUEST, msg))?;
+    require_authority(&mut conn, &session, role, req.user_id, "suspend")?;
+
+    query::suspend(
+        &mut conn,
+        Some(session.user_id),
+        req.user_id,
+        req.reason.trim(),
+        req.report_id,
+    )?;
+
+    info!(target: "uchat_server", user_id = %req.user_id, moderator_id = %session.user_id, "user suspended");
+    Ok(Json(SuspendUserOk))
+}
+
+pub async fn unsuspend(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<UnsuspendUser>,
+) -> ApiResult<Json<UnsuspendUserOk>> {
+    let role = require_role(&mut conn, &session, Role::Moderator)?;
+    require_authority(&mut conn, &session, role, req.user_id, "unsuspend")?;
+    query::unsuspend(&mut conn, Some(session.user_id), req.user_id)?;
+
+    info!(target: "uchat_server", user_id = %req.user_id, moderator_id = %session.user_id, "user unsuspended");
+    Ok(Json(UnsuspendUserOk))
+}
+
+pub async fn set_role(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<SetRole>,
+) -> ApiResult<Json<SetRoleOk>> {
+    require_role(&mut conn, &session, Role::Admin)?;
+    // keeps the last admin from locking everyone out
+    if req.user_id == session.user_id {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "you can't change your own role",
+        ));
+    }
+    query::set_role(
+        &mut conn,
+        Some(session.user_id),
+        req.user_id,
+        role_from_api(req.role),
+    )?;
+
+    info!(target: "uchat_server", user_id = %req.user_id, admin_id = %session.user_id, role = ?req.role, "role changed");
+    Ok(Json(SetRoleOk))
+}
+
+pub async fn audit_log(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<GetAuditLog>,
+) -> ApiResult<Json<GetAuditLogOk>> {
+    require_role(&mut conn, &session, Role::Moderator)?;
+
+    let before = req.before.map(|before| audit::Cursor {
+        created_at: before.created_at,
+        id: before.id,
+    });
+
+    // one more than needed shows whether there is another page
+    let mut entries = audit::list(&mut conn, before, PAGE_SIZE + 1)?;
+    let next = if entries.len() as i64 > PAGE_SIZE {
+        entries.truncate(PAGE_SIZE as usize);
+        entries.last().map(|last| AuditCursor {
+            created_at: last.created_at,
+            id: last.id,
+        })
+    } else {
+        None
+    };
+
+    Ok(Json(GetAuditLogOk {
+        entries: entries.into_iter().map(audit_entry_to_api).collect(),
+        next,
+    }))
+}
+
+/// Rejects users with a lower role than `min` with `403 Forbidden`, and returns the role
+/// otherwise.
+fn require_role(conn: &mut DbConnection, session: &UserSession, min: Role) -> ApiResult<Role> {
+    let role = role_to_api(query::role(conn, session.user_id)?);
+    if role < min {
+        let msg = match min {
+            Role::Admin => "only admins can do this",
+            _ => "only moderators can do this",
+        };
+        return Err(ApiError::new(StatusCode::FORBIDDEN, msg));
+    }
+    Ok(role)
+}
+
+/// Moderators can only act on regular users, admins on everyone but themselves.
+fn require_authority(
+    conn: &mut DbConnection,
+    session: &UserSession,
+    role: Role,
+    user_id: Uuid,
+    action: &str,
+) -> ApiResult<()> {
+    if user_id == session.user_id {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("you can't {action} yourself"),
+        ));
+    }
+    let target_role = role_to_api(query::role(conn, user_id)?);
+    if target_role > Role::User && role < Role::Admin {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            format!("only admins can {action} moderators and admins"),
+        ));
+    }
+    Ok(())
+}
+
+pub(super) fn role_to_api(role: query::Role) -> Role {
+    match role {
+        query::Role::User => Role::User,
+        query::Role::Moderator => Role::Moderator,
+        query::Role::Admin => Role::Admin,
+    }
+}
+
+fn role_from_api(role: Role) -> query::Role {
+    match role {
+        Role::User => query::Role::User,
+        Role::Moderator => query::Role::Moderator,
+        Role::Admin => query::Role::Admin,
+    }
+}
+
+fn report_to_api(report: report::Report) -> Report {
+    Report {
+        id: report.id,
+        reporter_handle: report.reporter_handle,
+        user_id: report.user_id,
+        user_handle: report.user_handle,
+        post_id: report.post_id,
+        post_text: report.post_text,
+        reason: match report.reason {
+            report::ReportReason::Spam => ReportReason::Spam,
+            report::ReportReason::Harassment => ReportReason::Harassment,
+            report::ReportReason::Hate => ReportReason::Hate,
+            report::ReportReason::Violence => ReportReason::Violence,
+            report::ReportReason::Other => ReportReason::Other,
+        },
+        details: report.details,
+        status: match report.status {
+            report::ReportStatus::Open => ReportStatus::Open,
+            report::ReportStatus::Resolved => ReportStatus::Resolved,
+            report::ReportStatus::Dismissed => ReportStatus::Dismissed,
+        },
+        resolved_by_handle: report.resolved_by_handle,
+        resolved_at: report.resolved_at,
+        created_at: report.created_at,
+    }
+}
+
+fn audit_entry_to_api(entry: audit::Entry) -> AuditEntry {
+    AuditEntry {
+        id: entry.id,
+        actor_handle: entry.actor_handle,
+        action: match entry.action {
+            audit::AuditAction::ResolveReport => AuditAction::ResolveReport,
+            audit::AuditAction::DismissReport => AuditAction::DismissReport,
+            audit::AuditAction::HidePost => AuditAction::HidePost,
+            audit::AuditAction::UnhidePost => AuditAction::UnhidePost,
+            audit::AuditAction::SuspendUser => AuditAction::SuspendUser,
+            audit::AuditAction::UnsuspendUser => AuditAction::UnsuspendUser,
+            audit::AuditAction::SetRole => AuditAction::SetRole,
+        },
+        user_id: entry.user_id,
+        post_id: entry.post_id,
+        report_id: entry.report_id,
+        details: entry.details,
+        created_at: entry.created_at,
+    }
+}
@@ -0,0 +1,95 @@
+use axum::http::StatusCode;
+use axum::Json;
+use uchat_api::notification::{
+    GetNotifications, GetNotificationsOk, GetUnreadNotifications, GetUnreadNotificationsOk,
+    MarkNotificationsRead, MarkNotificationsReadOk, Notification, NotificationCursor,
+    NotificationKind, PAGE_SIZE,
+};
+use uchat_query::notification::{self as query, Cursor};
+
+use crate::error::{ApiError, ApiResult};
+use crate::extractor::{DbConnection, UserSession};
+
+/// Most notifications which can be marked as read in one request.
+const MAX_MARK_READ: usize = 500;
+
+pub async fn list(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<GetNotifications>,
+) -> ApiResult<Json<GetNotificationsOk>> {
+    let after = req.after.map(|after| Cursor {
+        created_at: after.created_at,
+        id: after.id,
+    });
+    // one more than needed shows whether there is another page
+    let mut notifications = query::list(&mut conn, session.user_id, after, PAGE_SIZE + 1)?;
+    let next = if notifications.len() as i64 > PAGE_SIZE {
+        notifications.truncate(PAGE_SIZE as usize);
+        notifications.last().map(|last| NotificationCursor {
+            created_at: last.created_at,
+            id: last.id,
+        })
+    } else {
+        None
+    };
+
+    Ok(Json(GetNotificationsOk {
+        notifications: notifications.into_iter().map(to_api).collect(),
+        next,
+        unread: query::unread_count(&mut conn, session.user_id)?,
+    }))
+}
+
+pub async fn unread(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(_req): Json<GetUnreadNotifications>,
+) -> ApiResult<Json<GetUnreadNotificationsOk>> {
+    Ok(Json(GetUnreadNotificationsOk {
+        unread: query::unread_count(&mut conn, session.user_id)?,
+    }))
+}
+
+pub async fn mark_read(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<MarkNotificationsRead>,
+) -> ApiResult<Json<MarkNotificationsReadOk>> {
+    if req
+        .ids
+        .as_ref()
+        .is_some_and(|ids| ids.len() > MAX_MARK_READ)
+    {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("at most {MAX_MARK_READ} notifications can be marked at once"),
+        ));
+    }
+
+    query::mark_read(&mut conn, session.user_id, req.ids.as_deref())?;
+
+    Ok(Json(MarkNotificationsReadOk {
+        unread: query::unread_count(&mut conn, session.user_id)?,
+    }))
+}
+
+fn to_api(notification: query::Notification) -> Notification {
+    Notification {
+        id: notification.id,
+        kind: match notification.kind {
+            query::NotificationKind::Follow => NotificationKind::Follow,
+            query::NotificationKind::Reply => NotificationKind::Reply,
+            query::NotificationKind::Boost => NotificationKind::Boost,
+            query::NotificationKind::Reaction => NotificationKind::Reaction,
+            query::NotificationKind::PollVote => NotificationKind::PollVote,
+            query::NotificationKind::Mention => NotificationKind::Mention,
+            query::NotificationKind::FollowRequest => NotificationKind::FollowRequest,
+        },
+        actor_id: notification.actor_id,
+        actor_handle: notification.actor_handle,
+        post_id: notification.post_id,
+        read: notification.read_at.is_some(),
+        created_at: notification.created_at,
+    }
+}
@@ -0,0 +1,265 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::Utc;
+use tracing::warn;
+use uchat_api::event::{Event, PollChoiceVotes};
+use uchat_api::post::{
+    Boost, BoostOk, DeletePost, DeletePostOk, EditPost, EditPostOk, GetPost, GetPostOk,
+    GetPostRevisions, GetPostRevisionsOk, NewPost, NewPostOk, PostPreview, PostRevision, React,
+    ReactOk, Visibility, VotePoll, VotePollOk,
+};
+use uchat_query::post::Audience;
+use uchat_query::QueryError;
+use uuid::Uuid;
+
+use crate::error::{ApiError, ApiResult};
+use crate::event::EventHub;
+use crate::extractor::{DbConnection, UserSession};
+use crate::EditWindow;
+
+pub async fn new(
+    mut conn: DbConnection,
+    session: UserSession,
+    State(hub): State<EventHub>,
+    Json(req): Json<NewPost>,
+) -> ApiResult<Json<NewPostOk>> {
+    let post_id = uchat_query::post::new(
+        &mut conn,
+        &uchat_query::post::NewPost {
+            user_id: session.user_id,
+            content: req.content,
+            time_posted: req.time_posted.unwrap_or_else(Utc::now),
+            direct_message_to: req.direct_message_to,
+            reply_to: req.reply_to,
+            visibility: visibility_from_api(req.visibility),
+        },
+    )?;
+    announce(
+        &hub,
+        post_id,
+        uchat_query::post::audience(&mut conn, post_id),
+    );
+    Ok(Json(NewPostOk { post_id }))
+}
+
+/// Tells the audience of post `post_id` about it once it is saved. Nothing happens for scheduled
+/// posts, which are announced by the job publishing them.
+///
+/// The post exists either way, so failing to find the audience only costs the live update and is
+/// logged instead of failing the request or job.
+pub(crate) fn announce(
+    hub: &EventHub,
+    post_id: Uuid,
+    audience: Result<Option<Audience>, QueryError>,
+) {
+    match audience {
+        Ok(Some(Audience {
+            author_id,
+            direct_message_to: Some(recipient),
+            ..
+        })) => hub.publish(
+            [recipient],
+            Event::DirectMessage {
+                post_id,
+                from: author_id,
+            },
+        ),
+        Ok(Some(Audience {
+            author_id,
+            followers,
+            ..
+        })) => hub.publish(followers, Event::NewPost { post_id, author_id }),
+        Ok(None) => (),
+        Err(e) => {
+            warn!(target: "uchat_server", %post_id, err = ?e, "failed to announce post");
+        }
+    }
+}
+
+pub async fn react(
+    mut conn: DbConnection,
+    session: UserSession,
+    State(hub): State<EventHub>,
+    Json(req): Json<React>,
+) -> ApiResult<Json<ReactOk>> {
+    if !(-1..=1).contains(&req.like_status) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "like status must be -1, 0 or 1",
+        ));
+    }
+
+    let author_id =
+        uchat_query::reaction::react(&mut conn, session.user_id, req.post_id, req.like_status)?;
+    if author_id != session.user_id {
+        hub.publish(
+            [author_id],
+            Event::Reaction {
+                post_id: req.post_id,
+                user_id: session.user_id,
+                like_status: req.like_status,
+            },
+        );
+    }
+    Ok(Json(ReactOk))
+}
+
+pub async fn boost(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<Boost>,
+) -> ApiResult<Json<BoostOk>> {
+    if req.boosted {
+        uchat_query::boost::boost(&mut conn, session.user_id, req.post_id)?;
+    } else {
+        uchat_query::boost::unboost(&mut conn, session.user_id, req.post_id)?;
+    }
+    Ok(Json(BoostOk))
+}
+
+pub async fn vote(
+    mut conn: DbConnection,
+    session: UserSession,
+    State(hub): State<EventHub>,
+    Json(req): Json<VotePoll>,
+) -> ApiResult<Json<VotePollOk>> {
+    let tally = uchat_query::poll::vote(&mut conn, session.user_id, req.post_id, req.choice_id)?;
+    let votes: Vec<_> = tally
+        .votes
+        .into_iter()
+        .map(|choice| PollChoiceVotes {
+            choice_id: choice.choice_id,
+            votes: choice.votes,
+        })
+        .collect();
+
+    let recipients = std::iter::once(tally.author_id).chain(tally.voter_ids);
+    hub.publish(
+        recipients,
+        Event::PollTally {
+            post_id: req.post_id,
+            votes: votes.clone(),
+        },
+    );
+    Ok(Json(VotePollOk { votes }))
+}
+
+pub async fn get(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<GetPost>,
+) -> ApiResult<Json<GetPostOk>> {
+    let post = uchat_query::post::get(&mut conn, session.user_id, req.post_id)?;
+    Ok(Json(GetPostOk {
+        post: preview_to_api(post),
+    }))
+}
+
+pub async fn edit(
+    mut conn: DbConnection,
+    session: UserSession,
+    State(EditWindow(window)): State<EditWindow>,
+    Json(req): Json<EditPost>,
+) -> ApiResult<Json<EditPostOk>> {
+    uchat_query::post::edit(
+        &mut conn,
+        session.user_id,
+        req.post_id,
+        &req.content,
+        Utc::now() - window,
+    )?;
+    Ok(Json(EditPostOk))
+}
+
+pub async fn revisions(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<GetPostRevisions>,
+) -> ApiResult<Json<GetPostRevisionsOk>> {
+    let revisions = uchat_query::post::revisions(&mut conn, session.user_id, req.post_id)?
+        .into_iter()
+        .map(|revision| PostRevision {
+            text: uchat_query::post::text(&revision.content),
+            revised_at: revision.revised_at,
+        })
+        .collect();
+    Ok(Json(GetPostRevisionsOk { revisions }))
+}
+
+pub async fn delete(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<DeletePost>,
+) -> ApiResult<Json<DeletePostOk>> {
+    uchat_query::post::delete(&mut conn, session.user_id, req.post_id)?;
+    Ok(Json(DeletePostOk))
+}
+
+fn visibility_from_api(visibility: Visibility) -> uchat_query::post::Visibility {
+    match visibility {
+        Visibility::Public => uchat_query::post::Visibility::Public,
+        Visibility::Followers => uchat_query::post::Visibility::Followers,
+        Visibility::Unlisted => uchat_query::post::Visibility::Unlisted,
+    }
+}
+
+pub(super) fn preview_to_api(post: uchat_query::post::PostPreview) -> PostPreview {
+    PostPreview {
+        id: post.id,
+        author_id: post.author_id,
+        author_handle: post.author_handle,
+        text: post.text,
+        time_posted: post.time_posted,
+        edited_at: post.edited_at,
+        deleted_at: post.deleted_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uchat_api::event::EventMessage;
+
+    fn next_event(message: Option<EventMessage>) -> Event {
+        match message {
+            Some(EventMessage::Event { event, .. }) => event,
+            other => panic!("expected an event, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn announces_posts_to_their_audience() {
+        let hub = EventHub::default();
+        let author_id = Uuid::new_v4();
+        let follower = Uuid::new_v4();
+        let mut subscription = hub.subscribe(follower, None);
+
+        let post_id = Uuid::new_v4();
+        let audience = Audience {
+            author_id,
+            direct_message_to: None,
+            followers: vec![follower],
+        };
+        announce(&hub, post_id, Ok(Some(audience)));
+        assert_eq!(
+            next_event(subscription.recv().await),
+            Event::NewPost { post_id, author_id }
+        );
+
+        let message_id = Uuid::new_v4();
+        let audience = Audience {
+            author_id,
+            direct_message_to: Some(follower),
+            followers: vec![],
+        };
+        announce(&hub, message_id, Ok(Some(audience)));
+        assert_eq!(
+            next_event(subscription.recv().await),
+            Event::DirectMessage {
+                post_id: message_id,
+                from: author_id
+            }
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use axum::http::StatusCode;
+use axum::Json;
+use uchat_api::search::{Search, SearchHits, SearchKind, SearchOk, UserHit, PAGE_SIZE};
+use uchat_query::search as query;
+
+use super::post::preview_to_api;
+use crate::error::{ApiError, ApiResult};
+use crate::extractor::{DbConnection, UserSession};
+
+/// Offsets beyond this are rejected, since every skipped result still has to be ranked.
+const MAX_OFFSET: i64 = 1000;
+
+pub async fn search(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<Search>,
+) -> ApiResult<Json<SearchOk>> {
+    uchat_api::search::validate_query(&req.query)
+        .map_err(|msg| ApiError::new(StatusCode::BAD_REQUEST, msg))?;
+    if !(0..=MAX_OFFSET).contains(&req.offset) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("offset must be between 0 and {MAX_OFFSET}"),
+        ));
+    }
+
+    // one more than needed shows whether there is another page
+    let limit = PAGE_SIZE + 1;
+    let mut hits = match req.kind {
+        SearchKind::Posts => SearchHits::Posts(
+            query::posts(&mut conn, session.user_id, &req.query, req.offset, limit)?
+                .into_iter()
+                .map(preview_to_api)
+                .collect(),
+        ),
+        SearchKind::Users => SearchHits::Users(
+            query::users(&mut conn, session.user_id, &req.query, req.offset, limit)?
+                .into_iter()
+                .map(|user| UserHit {
+                    id: user.id,
+                    handle: user.handle,
+                    display_name: user.display_name,
+                })
+                .collect(),
+        ),
+    };
+
+    let next_offset = if hits.len() as i64 > PAGE_SIZE {
+        match &mut hits {
+            SearchHits::Posts(hits) => hits.truncate(PAGE_SIZE as usize),
+            SearchHits::Users(hits) => hits.truncate(PAGE_SIZE as usize),
+        }
+        Some(req.offset + PAGE_SIZE)
+    } else {
+        None
+    };
+
+    Ok(Json(SearchOk { hits, next_offset }))
+}
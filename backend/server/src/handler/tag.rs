@@ -0,0 +1,66 @@
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::{Duration, Utc};
+use uchat_api::post::PostCursor;
+use uchat_api::tag::{
+    GetTagTimeline, GetTagTimelineOk, GetTrendingTags, GetTrendingTagsOk, TrendingTag, PAGE_SIZE,
+};
+use uchat_query::post::Cursor;
+
+use super::post::preview_to_api;
+use crate::error::{ApiError, ApiResult};
+use crate::extractor::{DbConnection, UserSession};
+
+/// Posts from this long ago count towards trends.
+const TRENDING_WINDOW_HOURS: i64 = 24;
+const TRENDING_TAGS: i64 = 10;
+
+pub async fn timeline(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<GetTagTimeline>,
+) -> ApiResult<Json<GetTagTimelineOk>> {
+    let tag = uchat_api::tag::normalize_tag(&req.tag)
+        .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "invalid hashtag"))?;
+    let before = req.before.map(|before| Cursor {
+        time_posted: before.time_posted,
+        id: before.id,
+    });
+
+    // one more than needed shows whether there is another page
+    let mut posts =
+        uchat_query::tag::timeline(&mut conn, session.user_id, &tag, before, PAGE_SIZE + 1)?;
+    let next = if posts.len() as i64 > PAGE_SIZE {
+        posts.truncate(PAGE_SIZE as usize);
+        posts.last().map(|last| PostCursor {
+            time_posted: last.time_posted,
+            id: last.id,
+        })
+    } else {
+        None
+    };
+
+    Ok(Json(GetTagTimelineOk {
+        posts: posts.into_iter().map(preview_to_api).collect(),
+        next,
+    }))
+}
+
+pub async fn trending(
+    mut conn: DbConnection,
+    _session: UserSession,
+    Json(_req): Json<GetTrendingTags>,
+) -> ApiResult<Json<GetTrendingTagsOk>> {
+    let since = Utc::now() - Duration::hours(TRENDING_WINDOW_HOURS);
+    let tags = uchat_query::tag::trending(&mut conn, since, TRENDING_TAGS)?;
+
+    Ok(Json(GetTrendingTagsOk {
+        tags: tags
+            .into_iter()
+            .map(|trend| TrendingTag {
+                tag: trend.tag,
+                posts: trend.posts,
+            })
+            .collect(),
+    }))
+}
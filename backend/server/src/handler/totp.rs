@@ -0,0 +1,139 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use tracing::info;
+use uchat_api::user::{
+    ConfirmTotp, ConfirmTotpOk, DisableTotp, DisableTotpOk, EnrollTotp, EnrollTotpOk,
+};
+use uchat_crypto::totp::{
+    generate_recovery_codes, hash_recovery_code, is_recovery_code, TotpSecret,
+};
+use uchat_query::totp::UserTotp;
+use uchat_query::QueryError;
+use uuid::Uuid;
+
+use super::unix_now;
+use crate::error::{ApiError, ApiResult};
+use crate::extractor::{DbConnection, UserSession};
+use crate::AppState;
+
+/// Issuer shown in authenticator apps.
+pub const TOTP_ISSUER: &str = "uChat";
+
+const RECOVERY_CODE_COUNT: usize = 10;
+
+pub async fn enroll(
+    State(state): State<AppState>,
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(_req): Json<EnrollTotp>,
+) -> ApiResult<Json<EnrollTotpOk>> {
+    let user = uchat_query::user::find(&mut conn, session.user_id)?;
+
+    let mut rng = uchat_crypto::new_rng();
+    let secret = TotpSecret::generate(&mut rng);
+    let encrypted = state.encryption_key.encrypt(&mut rng, secret.as_bytes())?;
+
+    match uchat_query::totp::begin_enrollment(&mut conn, session.user_id, &encrypted) {
+        Ok(()) => (),
+        Err(QueryError::UniqueViolation) => {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                "two-factor authentication is already enabled",
+            ))
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    Ok(Json(EnrollTotpOk {
+        provisioning_uri: secret.provisioning_uri(TOTP_ISSUER, &user.handle),
+        secret: secret.to_base32(),
+    }))
+}
+
+pub async fn confirm(
+    State(state): State<AppState>,
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<ConfirmTotp>,
+) -> ApiResult<Json<ConfirmTotpOk>> {
+    let totp = uchat_query::totp::get(&mut conn, session.user_id)?
+        .filter(|totp| !totp.is_confirmed())
+        .ok_or_else(|| {
+            ApiError::new(StatusCode::BAD_REQUEST, "no pending two-factor enrollment")
+        })?;
+
+    let secret = decrypt_secret(&state, &totp)?;
+    let step = secret
+        .verify(&req.code, unix_now())
+        .map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, "invalid code"))?;
+
+    let mut rng = uchat_crypto::new_rng();
+    let recovery_codes = generate_recovery_codes(&mut rng, RECOVERY_CODE_COUNT);
+    let hashes: Vec<_> = recovery_codes.iter().map(hash_recovery_code).collect();
+
+    uchat_query::totp::confirm(&mut conn, session.user_id, step as i64, &hashes)?;
+    info!(target: "uchat_server", user_id = %session.user_id, "two-factor authentication enabled");
+
+    Ok(Json(ConfirmTotpOk { recovery_codes }))
+}
+
+pub async fn disable(
+    State(state): State<AppState>,
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<DisableTotp>,
+) -> ApiResult<Json<DisableTotpOk>> {
+    verify_second_factor(&state, &mut conn, session.user_id, &req.code)?;
+
+    uchat_query::totp::disable(&mut conn, session.user_id)?;
+    info!(target: "uchat_server", user_id = %session.user_id, "two-factor authentication disabled");
+
+    Ok(Json(DisableTotpOk))
+}
+
+/// Checks a TOTP code or recovery code for a user with two-factor authentication enabled.
+///
+/// Accepted codes are consumed: TOTP codes can't be replayed and recovery codes are single-use.
+pub(crate) fn verify_second_factor(
+    state: &AppState,
+    conn: &mut DbConnection,
+    user_id: Uuid,
+    code: &str,
+) -> ApiResult<()> {
+    let invalid_code = || ApiError::new(StatusCode::UNAUTHORIZED, "invalid code");
+
+    let totp = uchat_query::totp::get(conn, user_id)?
+        .filter(UserTotp::is_confirmed)
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "two-factor authentication is not enabled",
+            )
+        })?;
+
+    if is_recovery_code(code) {
+        let hash = hash_recovery_code(code);
+        if !uchat_query::totp::use_recovery_code(conn, user_id, &hash)? {
+            return Err(invalid_code());
+        }
+        let remaining = uchat_query::totp::remaining_recovery_codes(conn, user_id)?;
+        info!(target: "uchat_server", %user_id, remaining, "recovery code used");
+        return Ok(());
+    }
+
+    let secret = decrypt_secret(state, &totp)?;
+    let step = secret
+        .verify(code, unix_now())
+        .map_err(|_| invalid_code())?;
+    if !uchat_query::totp::record_step(conn, user_id, step as i64)? {
+        return Err(invalid_code());
+    }
+
+    Ok(())
+}
+
+fn decrypt_secret(state: &AppState, totp: &UserTotp) -> ApiResult<TotpSecret> {
+    let secret = state.encryption_key.decrypt(&totp.secret)?;
+    Ok(TotpSecret::from_bytes(secret))
+}
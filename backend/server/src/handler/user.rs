@@ -0,0 +1,212 @@
+use axum::extract::State;
+use axum::headers::UserAgent;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json, TypedHeader};
+use chrono::{Duration, Utc};
+use tracing::{info, warn};
+use uchat_api::user::{
+    CancelAccountDeletion, CancelAccountDeletionOk, CreateUser, DeleteAccount, DeleteAccountOk,
+    Login, LoginMfa, LoginOk, SessionInfo, Whoami, WhoamiOk,
+};
+use uchat_query::QueryError;
+use uuid::Uuid;
+
+use crate::error::{ApiError, ApiResult};
+use crate::extractor::{DbConnection, UserSession};
+use crate::rate_limit::{AttemptedHandle, SessionIssued};
+use crate::AppState;
+
+const SESSION_LIFETIME_WEEKS: i64 = 3;
+const MFA_CHALLENGE_LIFETIME_MINUTES: i64 = 5;
+const MAX_MFA_ATTEMPTS: i16 = 5;
+const ACCOUNT_DELETION_GRACE_DAYS: i64 = 30;
+
+pub async fn create_user(
+    State(state): State<AppState>,
+    mut conn: DbConnection,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    Json(req): Json<CreateUser>,
+) -> ApiResult<(StatusCode, Extension<SessionIssued>, Json<SessionInfo>)> {
+    uchat_api::user::validate_handle(&req.handle)
+        .map_err(|msg| ApiError::new(StatusCode::BAD_REQUEST, msg))?;
+    uchat_api::user::validate_password(&req.password)
+        .map_err(|msg| ApiError::new(StatusCode::BAD_REQUEST, msg))?;
+
+    let hash = uchat_crypto::hash_password(&req.password)?;
+    let user_id = match uchat_query::user::new(&mut conn, hash, &req.handle) {
+        Ok(user_id) => user_id,
+        Err(QueryError::UniqueViolation) => {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                "handle is already taken",
+            ))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    info!(target: "uchat_server", %user_id, handle = %req.handle, "new user");
+
+    let session = new_session(&state, &mut conn, user_id, fingerprint(user_agent))?;
+    Ok((StatusCode::CREATED, Extension(SessionIssued), Json(session)))
+}
+
+pub async fn login(
+    State(state): State<AppState>,
+    mut conn: DbConnection,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    Json(req): Json<Login>,
+) -> ApiResult<Response> {
+    let invalid_login = || ApiError::new(StatusCode::UNAUTHORIZED, "invalid handle or password");
+
+    let (user_id, hash) = match uchat_query::user::get_password_hash(&mut conn, &req.handle) {
+        Ok(found) => found,
+        Err(QueryError::NotFound) => return Err(invalid_login()),
+        Err(e) => return Err(e.into()),
+    };
+    let hash = uchat_crypto::password::deserialize_hash(&hash)?;
+    uchat_crypto::verify_password(&req.password, &hash).map_err(|_| invalid_login())?;
+
+    if uchat_query::totp::is_enabled(&mut conn, user_id)? {
+        let expires_at = Utc::now() + Duration::minutes(MFA_CHALLENGE_LIFETIME_MINUTES);
+        let challenge_id = uchat_query::totp::new_challenge(&mut conn, user_id, expires_at)?;
+        info!(target: "uchat_server", %user_id, "login requires second factor");
+
+        return Ok(Json(LoginOk::MfaRequired {
+            challenge_id,
+            expires_at,
+        })
+        .into_response());
+    }
+
+    let session = new_session(&state, &mut conn, user_id, fingerprint(user_agent))?;
+    Ok((Extension(SessionIssued), Json(LoginOk::Session(session))).into_response())
+}
+
+pub async fn login_mfa(
+    State(state): State<AppState>,
+    mut conn: DbConnection,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    Json(req): Json<LoginMfa>,
+) -> ApiResult<Response> {
+    let challenge =
+        uchat_query::totp::get_challenge(&mut conn, req.challenge_id)?.ok_or_else(|| {
+            ApiError::new(StatusCode::UNAUTHORIZED, "login expired, please try again")
+        })?;
+    // the request only names the challenge, this lets the rate limit count against the handle
+    let attempted = AttemptedHandle(uchat_query::user::find(&mut conn, challenge.user_id)?.handle);
+
+    let verified =
+        super::totp::verify_second_factor(&state, &mut conn, challenge.user_id, &req.code);
+    if let Err(e) = verified {
+        let attempts = uchat_query::totp::record_failed_attempt(&mut conn, challenge.id)?;
+        if attempts >= MAX_MFA_ATTEMPTS {
+            warn!(target: "uchat_server", user_id = %challenge.user_id, "too many failed second factor attempts");
+            uchat_query::totp::delete_challenge(&mut conn, challenge.id)?;
+        }
+        return Ok((Extension(attempted), e).into_response());
+    }
+
+    uchat_query::totp::delete_challenge(&mut conn, challenge.id)?;
+
+    let session = new_session(
+        &state,
+        &mut conn,
+        challenge.user_id,
+        fingerprint(user_agent),
+    )?;
+    Ok((
+        Extension(SessionIssued),
+        Extension(attempted),
+        Json(session),
+    )
+        .into_response())
+}
+
+pub async fn whoami(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(_req): Json<Whoami>,
+) -> ApiResult<Json<WhoamiOk>> {
+    let user = uchat_query::user::find(&mut conn, session.user_id)?;
+    let role = uchat_query::moderation::role(&mut conn, session.user_id)?;
+
+    Ok(Json(WhoamiOk {
+        user_id: user.id,
+        handle: user.handle,
+        display_name: user.display_name,
+        role: super::moderation::role_to_api(role),
+        private: user.private,
+        delete_after: user.delete_after,
+    }))
+}
+
+pub async fn delete_account(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(req): Json<DeleteAccount>,
+) -> ApiResult<Json<DeleteAccountOk>> {
+    let user = uchat_query::user::find(&mut conn, session.user_id)?;
+    let hash = uchat_crypto::password::deserialize_hash(&user.password_hash)?;
+    uchat_crypto::verify_password(&req.password, &hash)
+        .map_err(|_| ApiError::new(StatusCode::FORBIDDEN, "wrong password"))?;
+
+    let delete_after = Utc::now() + Duration::days(ACCOUNT_DELETION_GRACE_DAYS);
+    uchat_query::user::schedule_deletion(&mut conn, session.user_id, delete_after)?;
+    info!(target: "uchat_server", user_id = %session.user_id, %delete_after, "account deletion scheduled");
+
+    Ok(Json(DeleteAccountOk { delete_after }))
+}
+
+pub async fn cancel_deletion(
+    mut conn: DbConnection,
+    session: UserSession,
+    Json(_req): Json<CancelAccountDeletion>,
+) -> ApiResult<Json<CancelAccountDeletionOk>> {
+    uchat_query::user::cancel_deletion(&mut conn, session.user_id)?;
+    info!(target: "uchat_server", user_id = %session.user_id, "account deletion cancelled");
+    Ok(Json(CancelAccountDeletionOk))
+}
+
+/// Creates a new signed session for the user. Fails with `403 Forbidden` for suspended users.
+pub(crate) fn new_session(
+    state: &AppState,
+    conn: &mut DbConnection,
+    user_id: Uuid,
+    fingerprint: serde_json::Value,
+) -> ApiResult<SessionInfo> {
+    let user = uchat_query::user::find(conn, user_id)?;
+    if user.suspended_at.is_some() {
+        warn!(target: "uchat_server", %user_id, "login of suspended user");
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "this account is suspended",
+        ));
+    }
+    let role = uchat_query::moderation::role(conn, user_id)?;
+
+    let expires_at = Utc::now() + Duration::weeks(SESSION_LIFETIME_WEEKS);
+    let session = uchat_query::session::new(conn, user_id, expires_at, fingerprint)?;
+
+    let mut rng = uchat_crypto::new_rng();
+    let signature = state.signing_keys.sign(&mut rng, session.id.as_bytes());
+
+    info!(target: "uchat_server", %user_id, session_id = %session.id, "new session");
+
+    Ok(SessionInfo {
+        session_id: session.id,
+        session_signature: uchat_crypto::encode_base64(signature),
+        session_expires: session.expires_at,
+        csrf_token: crate::csrf::new_token(&state.signing_keys, session.id),
+        user_id,
+        handle: user.handle,
+        display_name: user.display_name,
+        role: super::moderation::role_to_api(role),
+        private: user.private,
+        delete_after: user.delete_after,
+    })
+}
+
+fn fingerprint(user_agent: Option<TypedHeader<UserAgent>>) -> serde_json::Value {
+    let user_agent = user_agent.map(|TypedHeader(user_agent)| user_agent.to_string());
+    serde_json::json!({ "user_agent": user_agent })
+}
@@ -0,0 +1,217 @@
+//! Workers running the background jobs of [`uchat_query::job`].
+//!
+//! Every worker claims one job at a time and polls for new ones when the queue is empty. Workers
+//! stop between jobs once shutdown is signalled, so jobs which already started finish first.
+//! Periodic maintenance, like deleting expired sessions, runs as recurring jobs, so only one
+//! instance does it at a time. Emails are sent by the [`Mailer`] of the workers.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tracing::{debug, error, info, warn};
+use uchat_query::job::{Job, Task};
+use uchat_query::{AsyncConnection, AsyncConnectionPool, QueryError};
+
+use crate::event::EventHub;
+use crate::export::{self, ExportDir, ExportError};
+use crate::mail::{MailError, Mailer};
+use crate::metrics::Metrics;
+
+/// How long a worker may run a job before other workers consider it abandoned.
+const LEASE_MINUTES: i64 = 15;
+/// Expired sessions deleted per statement.
+const SESSION_BATCH_SIZE: i64 = 1000;
+/// Tasks which keep running as long as the server is deployed.
+const RECURRING: [Task; 2] = [Task::PurgeDeletedAccounts, Task::DeleteExpiredSessions];
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum JobError {
+    #[error(transparent)]
+    Query(#[from] QueryError),
+
+    #[error(transparent)]
+    Export(#[from] ExportError),
+
+    #[error(transparent)]
+    Mail(#[from] MailError),
+
+    #[error("invalid payload: {0}")]
+    Payload(#[from] serde_json::Error),
+
+    #[error("the job was abandoned by its workers too often")]
+    Abandoned,
+}
+
+#[derive(Clone)]
+pub struct Worker {
+    db_pool: AsyncConnectionPool,
+    export_dir: ExportDir,
+    event_hub: EventHub,
+    metrics: Metrics,
+    mailer: Arc<dyn Mailer>,
+}
+
+impl Worker {
+    pub fn new(
+        db_pool: AsyncConnectionPool,
+        export_dir: ExportDir,
+        event_hub: EventHub,
+        metrics: Metrics,
+        mailer: Arc<dyn Mailer>,
+    ) -> Self {
+        Self {
+            db_pool,
+            export_dir,
+            event_hub,
+            metrics,
+            mailer,
+        }
+    }
+
+    /// Runs jobs until `shutdown` becomes `true` or its sender is dropped.
+    pub async fn run(self, mut shutdown: watch::Receiver<bool>) {
+        loop {
+            let idle = match self.run_next().await {
+                Ok(ran) => !ran,
+                Err(e) => {
+                    error!(target: "uchat_server", err = ?e, "failed to run job");
+                    true
+                }
+            };
+            if *shutdown.borrow() {
+                break;
+            }
+            if idle {
+                tokio::select! {
+                    changed = shutdown.changed() => if changed.is_err() || *shutdown.borrow() {
+                        break;
+                    },
+                    _ = tokio::time::sleep(POLL_INTERVAL) => (),
+                }
+            }
+        }
+    }
+
+    /// Runs the next due job. Returns whether there was one.
+    async fn run_next(&self) -> Result<bool, QueryError> {
+        let job = {
+            let mut conn = self.db_pool.get().await?;
+            uchat_query::job::claim(&mut conn, chrono::Duration::minutes(LEASE_MINUTES))?
+        };
+        let Some(job) = job else {
+            return Ok(false);
+        };
+
+        let result = if job.is_abandoned() {
+            Err(JobError::Abandoned)
+        } else {
+            self.execute(&job).await
+        };
+
+        let mut conn = self.db_pool.get().await?;
+        match result {
+            Ok(()) => match uchat_query::job::complete(&mut conn, &job) {
+                Ok(()) => {
+                    debug!(target: "uchat_server", job_id = %job.id, kind = job.kind, "job done");
+                }
+                Err(QueryError::LeaseLost) => lease_lost(&job),
+                Err(e) => return Err(e),
+            },
+            Err(e) => match uchat_query::job::fail(&mut conn, &job, &e.to_string()) {
+                Ok(Some(run_at)) => {
+                    warn!(target: "uchat_server", job_id = %job.id, kind = job.kind, attempts = job.attempts, %run_at, err = ?e, "job failed, retrying");
+                }
+                Ok(None) => {
+                    error!(target: "uchat_server", job_id = %job.id, kind = job.kind, attempts = job.attempts, err = ?e, "job failed, giving up");
+                    if let Ok(task) = job.task() {
+                        given_up(&mut conn, &task)?;
+                    }
+                }
+                Err(QueryError::LeaseLost) => lease_lost(&job),
+                Err(e) => return Err(e),
+            },
+        }
+        Ok(true)
+    }
+
+    async fn execute(&self, job: &Job) -> Result<(), JobError> {
+        match job.task()? {
+            Task::PublishPost { post_id } => {
+                let mut conn = self.db_pool.get().await?;
+                uchat_query::post::publish(&mut conn, post_id)?;
+                let audience = uchat_query::post::audience(&mut conn, post_id);
+                crate::handler::post::announce(&self.event_hub, post_id, audience);
+            }
+            Task::BuildExport { export_id, user_id } => {
+                export::build(&self.db_pool, &self.export_dir, export_id, user_id).await?;
+                info!(target: "uchat_server", %export_id, %user_id, "data export ready");
+            }
+            Task::PurgeDeletedAccounts => {
+                let mut conn = self.db_pool.get().await?;
+                let user_ids = uchat_query::user::purge_deleted(&mut conn, chrono::Utc::now())?;
+                for user_id in user_ids {
+                    info!(target: "uchat_server", %user_id, "account deleted");
+                }
+            }
+            Task::DeleteExpiredSessions => {
+                let deleted = self.delete_expired_sessions().await?;
+                if deleted > 0 {
+                    self.metrics.add_sessions_deleted(deleted);
+                    info!(target: "uchat_server", deleted, "deleted expired sessions");
+                }
+            }
+            Task::SendEmail { to, subject, body } => {
+                self.mailer.send(&to, &subject, &body).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes expired sessions in batches. Returns the number of deleted sessions.
+    async fn delete_expired_sessions(&self) -> Result<u64, QueryError> {
+        let now = chrono::Utc::now();
+        let mut deleted = 0;
+        loop {
+            let mut conn = self.db_pool.get().await?;
+            let batch = uchat_query::session::delete_expired(&mut conn, now, SESSION_BATCH_SIZE)?;
+            deleted += batch as u64;
+            if (batch as i64) < SESSION_BATCH_SIZE {
+                return Ok(deleted);
+            }
+            // let requests have the connection in between
+            drop(conn);
+            tokio::task::yield_now().await;
+        }
+    }
+}
+
+/// Queues the recurring tasks unless another instance already did.
+pub async fn schedule_recurring(db_pool: &AsyncConnectionPool) -> Result<(), QueryError> {
+    let mut conn = db_pool.get().await?;
+    for task in &RECURRING {
+        if uchat_query::job::schedule_recurring(&mut conn, task)? {
+            info!(target: "uchat_server", kind = task.kind(), "scheduled recurring job");
+        }
+    }
+    Ok(())
+}
+
+/// Cleans up after `task` was dead-lettered.
+fn given_up(conn: &mut AsyncConnection<'_>, task: &Task) -> Result<(), QueryError> {
+    match task {
+        Task::PublishPost { .. } => Ok(()),
+        // lets the user request another export
+        Task::BuildExport { export_id, .. } => uchat_query::export::fail(conn, *export_id),
+        // queued again once a server starts, see `schedule_recurring`
+        Task::PurgeDeletedAccounts | Task::DeleteExpiredSessions => Ok(()),
+        Task::SendEmail { .. } => Ok(()),
+    }
+}
+
+/// The job took longer than its lease and another worker claimed it, whose result counts instead.
+fn lease_lost(job: &Job) {
+    warn!(target: "uchat_server", job_id = %job.id, kind = job.kind, "job outlived its lease and was claimed again");
+}
@@ -1,2 +1,41 @@
+pub mod csrf;
+pub mod error;
+pub mod event;
+pub mod export;
+pub mod extractor;
+pub mod handler;
+pub mod job;
+pub mod logging;
+pub mod mail;
+pub mod media;
+pub mod metrics;
+pub mod rate_limit;
+pub mod router;
 
+use axum::extract::FromRef;
+use uchat_crypto::encrypt::EncryptionKey;
+use uchat_crypto::sign::Keys;
+use uchat_query::AsyncConnectionPool;
 
+use crate::csrf::AllowedOrigins;
+use crate::event::EventHub;
+use crate::export::ExportDir;
+use crate::media::Media;
+use crate::rate_limit::LoginLimiter;
+
+#[derive(Clone, FromRef)]
+pub struct AppState {
+    pub db_pool: AsyncConnectionPool,
+    pub signing_keys: Keys,
+    pub encryption_key: EncryptionKey,
+    pub login_limiter: LoginLimiter,
+    pub event_hub: EventHub,
+    pub edit_window: EditWindow,
+    pub export_dir: ExportDir,
+    pub media: Media,
+    pub allowed_origins: AllowedOrigins,
+}
+
+/// How long after publishing authors can edit their posts.
+#[derive(Clone, Copy, Debug)]
+pub struct EditWindow(pub chrono::Duration);
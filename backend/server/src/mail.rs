@@ -0,0 +1,32 @@
+//! Email sent by the workers of [`crate::job`].
+//!
+//! Emails are queued as [`uchat_query::job::Task::SendEmail`] jobs, so failed deliveries are
+//! retried with backoff like other jobs. The server only comes with [`LogMailer`], which logs
+//! emails instead of delivering them. Deployments which deliver email implement [`Mailer`] for
+//! their provider and pass it to [`crate::job::Worker::new`].
+
+use axum::async_trait;
+use tracing::info;
+
+#[derive(Debug, thiserror::Error)]
+#[error("failed to send email: {0}")]
+pub struct MailError(pub String);
+
+/// Delivers emails.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), MailError>;
+}
+
+/// Logs the recipient and subject of emails without delivering them. The body is left out since
+/// it may contain links which sign users in.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, to: &str, subject: &str, _body: &str) -> Result<(), MailError> {
+        info!(target: "uchat_server", to, subject, "email not delivered, no mailer is configured");
+        Ok(())
+    }
+}
@@ -0,0 +1,449 @@
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::HeaderValue;
+use clap::{Parser, Subcommand};
+use color_eyre::{eyre::Context, Help, Result};
+use tokio::sync::watch;
+use tracing::{debug, error, info};
+use uchat_crypto::encrypt::EncryptionKey;
+use uchat_crypto::sign::{encode_private_key, Keys};
+use uchat_crypto::sigv4::Credentials;
+use uchat_query::moderation::Role;
+use uchat_query::AsyncConnectionPool;
+use uchat_server::csrf::AllowedOrigins;
+use uchat_server::event::{relay, EventHub};
+use uchat_server::export::{self, ExportDir};
+use uchat_server::job::{self, Worker};
+use uchat_server::logging::{self, Verbosity};
+use uchat_server::mail::{LogMailer, Mailer};
+use uchat_server::media::local::LocalMediaStore;
+use uchat_server::media::s3::S3MediaStore;
+use uchat_server::media::{self, Media};
+use uchat_server::metrics::{self, Metrics};
+use uchat_server::rate_limit::{InMemoryAttemptStore, LoginLimiter};
+use uchat_server::{router, AppState, EditWindow};
+use url::Url;
+
+const LOGIN_ATTEMPT_PRUNE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const EXPORT_RETENTION_DAYS: i64 = 7;
+
+#[derive(Debug, Parser)]
+#[command(author, version, about, subcommand_negates_reqs = true)]
+struct Cli {
+    #[arg(short, long, default_value = "127.0.0.1:8070", env = "API_BIND")]
+    bind: String,
+
+    #[arg(short, long, env = "API_DATABASE_URL", required = true)]
+    database_url: Option<String>,
+
+    /// private key used to sign sessions
+    #[arg(long, env = "API_PRIVATE_KEY", hide_env_values = true, required = true)]
+    private_key: Option<String>,
+
+    /// key used to encrypt secrets stored in the database
+    #[arg(
+        long,
+        env = "API_ENCRYPTION_KEY",
+        hide_env_values = true,
+        required = true
+    )]
+    encryption_key: Option<String>,
+
+    /// origins the frontend is served from, comma separated, no other site can use the API from a
+    /// browser
+    #[arg(
+        long,
+        default_value = "http://localhost:8080,http://127.0.0.1:8080",
+        env = "API_ALLOWED_ORIGINS",
+        value_delimiter = ',',
+        value_parser = AllowedOrigins::parse
+    )]
+    allowed_origins: Vec<HeaderValue>,
+
+    /// addresses of load balancers in front of the server, comma separated, whose
+    /// X-Forwarded-For header names the client
+    #[arg(long, env = "API_TRUSTED_PROXIES", value_delimiter = ',')]
+    trusted_proxies: Vec<IpAddr>,
+
+    /// deliver real-time events through the database, needed when running multiple instances
+    #[arg(long, env = "API_EVENT_RELAY")]
+    event_relay: bool,
+
+    /// directory where data exports are stored until they are downloaded, multiple instances need
+    /// to share it, e.g. on a network volume
+    #[arg(long, default_value = "exports", env = "API_EXPORT_DIR")]
+    export_dir: PathBuf,
+
+    /// where uploaded media is stored
+    #[arg(long, default_value = "local", value_parser = ["local", "s3"], env = "API_MEDIA_STORE")]
+    media_store: String,
+
+    /// directory of the local media store
+    #[arg(long, default_value = "media", env = "API_MEDIA_DIR")]
+    media_dir: PathBuf,
+
+    /// megabytes every user can upload
+    #[arg(long, default_value_t = 100, env = "API_MEDIA_QUOTA_MB")]
+    media_quota_mb: u32,
+
+    #[command(flatten)]
+    s3: S3Args,
+
+    /// address serving metrics in the Prometheus text format, metrics are off without it
+    #[arg(long, env = "API_METRICS_BIND")]
+    metrics_bind: Option<String>,
+
+    /// number of background jobs run at the same time
+    #[arg(long, default_value_t = 2, env = "API_JOB_WORKERS")]
+    job_workers: usize,
+
+    /// minutes after publishing during which authors can edit their posts
+    #[arg(long, default_value_t = 15, env = "API_EDIT_WINDOW_MINUTES")]
+    edit_window_minutes: u32,
+
+    #[command(flatten)]
+    verbosity: Verbosity,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Object storage used by the `s3` media store.
+#[derive(Debug, clap::Args)]
+struct S3Args {
+    /// url of the S3-compatible storage, e.g. https://s3.eu-central-1.amazonaws.com
+    #[arg(long, env = "API_S3_ENDPOINT")]
+    s3_endpoint: Option<Url>,
+
+    #[arg(long, env = "API_S3_BUCKET")]
+    s3_bucket: Option<String>,
+
+    #[arg(long, default_value = "us-east-1", env = "API_S3_REGION")]
+    s3_region: String,
+
+    #[arg(long, env = "API_S3_ACCESS_KEY", hide_env_values = true)]
+    s3_access_key: Option<String>,
+
+    #[arg(long, env = "API_S3_SECRET_KEY", hide_env_values = true)]
+    s3_secret_key: Option<String>,
+
+    /// url clients download media from, defaults to the url of the bucket
+    #[arg(long, env = "API_S3_PUBLIC_URL")]
+    s3_public_url: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// generate new signing and encryption keys
+    GenKey,
+    /// change the role of a user, e.g. to appoint the first admin
+    SetRole {
+        handle: String,
+        #[arg(value_parser = ["user", "moderator", "admin"])]
+        role: String,
+    },
+    /// log users out by deleting their sessions
+    #[command(group(clap::ArgGroup::new("sessions").required(true).multiple(true)))]
+    PurgeSessions {
+        /// only delete the sessions of this user
+        #[arg(long, group = "sessions")]
+        handle: Option<String>,
+        /// only delete sessions created more than this many days ago
+        #[arg(long, group = "sessions")]
+        older_than_days: Option<u32>,
+        /// delete the sessions of every user, unless other options narrow them down
+        #[arg(long, group = "sessions")]
+        all: bool,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    color_eyre::install()?;
+
+    let use_dotenv = dotenvy::dotenv();
+
+    let args = Cli::parse();
+
+    logging::setup(args.verbosity);
+
+    if let Ok(path) = use_dotenv {
+        debug!(target: "uchat_server", dot_env_found = true, path = %path.to_string_lossy());
+    }
+
+    match args.command {
+        Some(Command::GenKey) => return gen_keys(),
+        Some(Command::SetRole { handle, role }) => {
+            let database_url = args
+                .database_url
+                .ok_or_else(|| color_eyre::eyre::eyre!("missing database url"))
+                .suggestion("set API_DATABASE_URL or pass --database-url")?;
+            return set_role(&database_url, &handle, &role).await;
+        }
+        Some(Command::PurgeSessions {
+            handle,
+            older_than_days,
+            all: _,
+        }) => {
+            let database_url = args
+                .database_url
+                .ok_or_else(|| color_eyre::eyre::eyre!("missing database url"))
+                .suggestion("set API_DATABASE_URL or pass --database-url")?;
+            return purge_sessions(&database_url, handle.as_deref(), older_than_days).await;
+        }
+        None => (),
+    }
+
+    // required args are guaranteed by clap when no subcommand is given
+    let database_url = args.database_url.expect("missing database url");
+    let private_key = args.private_key.expect("missing private key");
+    let encryption_key = args.encryption_key.expect("missing encryption key");
+
+    let db_pool = AsyncConnectionPool::new(&database_url)
+        .await
+        .with_suggestion(|| "check database URL")
+        .with_suggestion(|| "ensure correct database access rights")
+        .with_suggestion(|| "make sure database exists")?;
+
+    let event_hub = if args.event_relay {
+        relay::start(EventHub::default(), db_pool.clone(), database_url)
+            .wrap_err("failed to start the event relay")?
+    } else {
+        EventHub::default()
+    };
+
+    let state = AppState {
+        db_pool,
+        signing_keys: Keys::from_encoded(private_key)
+            .wrap_err("failed to decode private key")
+            .suggestion("generate a new key with the `gen-key` subcommand")?,
+        encryption_key: EncryptionKey::from_encoded(encryption_key)
+            .wrap_err("failed to decode encryption key")
+            .suggestion("generate a new key with the `gen-key` subcommand")?,
+        login_limiter: LoginLimiter::new(InMemoryAttemptStore::default(), Default::default())
+            .with_trusted_proxies(args.trusted_proxies),
+        event_hub,
+        edit_window: EditWindow(chrono::Duration::minutes(args.edit_window_minutes.into())),
+        export_dir: ExportDir(args.export_dir),
+        media: build_media(
+            &args.media_store,
+            args.media_dir,
+            args.s3,
+            args.media_quota_mb,
+        )?,
+        allowed_origins: AllowedOrigins::new(args.allowed_origins),
+    };
+
+    {
+        let login_limiter = state.login_limiter.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(LOGIN_ATTEMPT_PRUNE_INTERVAL);
+            loop {
+                interval.tick().await;
+                login_limiter.prune(chrono::Utc::now()).await;
+            }
+        });
+    }
+
+    let metrics = Metrics::default();
+    if let Some(metrics_bind) = args.metrics_bind {
+        let metrics_addr: SocketAddr = metrics_bind
+            .parse()
+            .wrap_err("failed to parse metrics bind address")?;
+        let server = axum::Server::try_bind(&metrics_addr)
+            .wrap_err("failed to bind metrics address")?
+            .serve(metrics::new_router(metrics.clone()).into_make_service());
+        info!(target: "uchat_server", metrics_addr = %metrics_addr, "serving metrics");
+        tokio::spawn(async move {
+            if let Err(e) = server.await {
+                error!(target: "uchat_server", err = ?e, "metrics server error");
+            }
+        });
+    }
+
+    {
+        let db_pool = state.db_pool.clone();
+        let export_dir = state.export_dir.clone();
+        let media = state.media.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+            loop {
+                interval.tick().await;
+                match media::remove_orphans(&db_pool, &media).await {
+                    Ok(0) => (),
+                    Ok(removed) => {
+                        info!(target: "uchat_server", removed, "removed unused media files")
+                    }
+                    Err(e) => {
+                        error!(target: "uchat_server", err = ?e, "failed to remove unused media files")
+                    }
+                }
+                let before = chrono::Utc::now() - chrono::Duration::days(EXPORT_RETENTION_DAYS);
+                match export::remove_stale(&db_pool, &export_dir, before).await {
+                    Ok(0) => (),
+                    Ok(removed) => {
+                        info!(target: "uchat_server", removed, "removed old data exports")
+                    }
+                    Err(e) => {
+                        error!(target: "uchat_server", err = ?e, "failed to remove old data exports")
+                    }
+                }
+            }
+        });
+    }
+
+    job::schedule_recurring(&state.db_pool)
+        .await
+        .wrap_err("failed to schedule recurring jobs")?;
+    let (stop_workers, shutdown) = watch::channel(false);
+    let mailer: Arc<dyn Mailer> = Arc::new(LogMailer);
+    let workers: Vec<_> = (0..args.job_workers)
+        .map(|_| {
+            let worker = Worker::new(
+                state.db_pool.clone(),
+                state.export_dir.clone(),
+                state.event_hub.clone(),
+                metrics.clone(),
+                mailer.clone(),
+            );
+            tokio::spawn(worker.run(shutdown.clone()))
+        })
+        .collect();
+
+    let router = router::new_router(state);
+
+    let bind_addr: SocketAddr = args.bind.parse().wrap_err("failed to parse bind address")?;
+
+    info!(target: "uchat_server", bind_addr = %bind_addr, "listening");
+
+    axum::Server::bind(&bind_addr)
+        .serve(router.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .wrap_err("server error")?;
+
+    info!(target: "uchat_server", "shutting down, waiting for running jobs");
+    // receivers are gone once every worker stopped
+    let _ = stop_workers.send(true);
+    for worker in workers {
+        worker.await.wrap_err("job worker panicked")?;
+    }
+
+    Ok(())
+}
+
+/// Completes on Ctrl-C, or when the process is asked to terminate.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!(target: "uchat_server", err = ?e, "failed to listen for Ctrl-C");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                error!(target: "uchat_server", err = ?e, "failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => (),
+        _ = terminate => (),
+    }
+}
+
+fn build_media(store: &str, dir: PathBuf, s3: S3Args, quota_mb: u32) -> Result<Media> {
+    let quota = i64::from(quota_mb) * 1024 * 1024;
+    if store == "local" {
+        return Ok(Media::new(LocalMediaStore::new(dir), quota));
+    }
+
+    let missing = |arg: &str| {
+        color_eyre::eyre::eyre!("missing {arg} for the s3 media store").suggestion(format!(
+            "pass --{arg} or set API_{}",
+            arg.to_uppercase().replace('-', "_")
+        ))
+    };
+    let credentials = Credentials {
+        access_key: s3.s3_access_key.ok_or_else(|| missing("s3-access-key"))?,
+        secret_key: s3.s3_secret_key.ok_or_else(|| missing("s3-secret-key"))?,
+        region: s3.s3_region,
+    };
+    let store = S3MediaStore::new(
+        s3.s3_endpoint.ok_or_else(|| missing("s3-endpoint"))?,
+        s3.s3_bucket.ok_or_else(|| missing("s3-bucket"))?,
+        credentials,
+        s3.s3_public_url,
+    );
+    Ok(Media::new(store, quota))
+}
+
+async fn purge_sessions(
+    database_url: &str,
+    handle: Option<&str>,
+    older_than_days: Option<u32>,
+) -> Result<()> {
+    let db_pool = AsyncConnectionPool::new(database_url)
+        .await
+        .with_suggestion(|| "check database URL")?;
+    let mut conn = db_pool.get().await?;
+
+    let user_id = match handle {
+        Some(handle) => Some(
+            uchat_query::user::find_by_handle(&mut conn, handle)
+                .wrap_err_with(|| format!("failed to find user @{handle}"))?
+                .id,
+        ),
+        None => None,
+    };
+    let created_before =
+        older_than_days.map(|days| chrono::Utc::now() - chrono::Duration::days(days.into()));
+    let deleted = uchat_query::session::purge(&mut conn, user_id, created_before)?;
+
+    info!(target: "uchat_server", deleted, user_id = ?user_id, created_before = ?created_before, "sessions purged from the command line");
+    Ok(())
+}
+
+async fn set_role(database_url: &str, handle: &str, role: &str) -> Result<()> {
+    let role = Role::parse(role).expect("roles are checked by clap");
+
+    let db_pool = AsyncConnectionPool::new(database_url)
+        .await
+        .with_suggestion(|| "check database URL")?;
+    let mut conn = db_pool.get().await?;
+
+    let user = uchat_query::user::find_by_handle(&mut conn, handle)
+        .wrap_err_with(|| format!("failed to find user @{handle}"))?;
+    uchat_query::moderation::set_role(&mut conn, None, user.id, role)?;
+
+    info!(target: "uchat_server", user_id = %user.id, role = role.as_str(), "role changed from the command line");
+    Ok(())
+}
+
+fn gen_keys() -> Result<()> {
+    let mut rng = uchat_crypto::new_rng();
+
+    let (private_key, _) = Keys::generate(&mut rng)?;
+    let private_key = encode_private_key(private_key)?;
+    let encryption_key = EncryptionKey::generate(&mut rng);
+
+    println!("API_PRIVATE_KEY={}", private_key.as_str());
+    println!("API_ENCRYPTION_KEY={}", encryption_key.encode());
+
+    Ok(())
+}
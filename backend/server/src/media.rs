@@ -0,0 +1,256 @@
+//! Storage for files uploaded by users, see [`uchat_query::media`].
+//!
+//! Files are kept in a [`MediaStore`], either on the local filesystem of the API server or in an
+//! S3-compatible object storage. They are named after the SHA-256 of their content, so identical
+//! uploads share a file and files never change once they are stored. Only images whose type is
+//! recognized from their first bytes are accepted, the type the client claims is ignored.
+//! Removing an upload only forgets it, the file is deleted later by [`remove_orphans`].
+
+pub mod local;
+pub mod s3;
+
+use std::path::Path;
+use std::sync::Arc;
+
+use axum::async_trait;
+use axum::body::Bytes;
+use axum::http::StatusCode;
+use tracing::warn;
+use uchat_query::{AsyncConnection, AsyncConnectionPool, QueryError};
+
+/// Where files of the local store are served, relative to the API url.
+pub const MEDIA_FILES_URL: &str = "/media/files";
+
+#[derive(Debug, thiserror::Error)]
+pub enum MediaError {
+    #[error(transparent)]
+    Query(#[from] QueryError),
+
+    #[error("failed to access media: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("object storage request failed: {0}")]
+    Http(#[from] hyper::Error),
+
+    #[error("object storage responded with {status}: {body}")]
+    Status { status: StatusCode, body: String },
+}
+
+/// Image types which can be uploaded. SVG is left out since it can contain scripts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MediaType {
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+}
+
+impl MediaType {
+    /// Recognizes the type of a file from its first bytes.
+    pub fn sniff(data: &[u8]) -> Option<Self> {
+        match data {
+            [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n', ..] => Some(Self::Png),
+            [0xff, 0xd8, 0xff, ..] => Some(Self::Jpeg),
+            [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some(Self::Gif),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some(Self::Webp),
+            _ => None,
+        }
+    }
+
+    pub fn as_mime(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Gif => "image/gif",
+            Self::Webp => "image/webp",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Gif => "gif",
+            Self::Webp => "webp",
+        }
+    }
+}
+
+/// Name of a file in a [`MediaStore`]: the hex encoded SHA-256 of the content with the extension
+/// of its type. Keys only contain characters which are safe in paths and urls.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MediaKey(String);
+
+impl MediaKey {
+    pub fn for_content(data: &[u8], media_type: MediaType) -> Self {
+        Self(format!(
+            "{}.{}",
+            uchat_crypto::sha256_hex(data),
+            media_type.extension()
+        ))
+    }
+
+    /// Checks a key sent by a client.
+    pub fn parse(key: &str) -> Option<Self> {
+        let (hash, extension) = key.split_once('.')?;
+        let valid_hash = hash.len() == 64
+            && hash
+                .bytes()
+                .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b));
+        let valid_extension = [
+            MediaType::Png,
+            MediaType::Jpeg,
+            MediaType::Gif,
+            MediaType::Webp,
+        ]
+        .iter()
+        .any(|media_type| media_type.extension() == extension);
+        (valid_hash && valid_extension).then(|| Self(key.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for MediaKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Storage for the files of uploads.
+#[async_trait]
+pub trait MediaStore: Send + Sync {
+    /// Stores `data` at `key`, replacing what was stored there.
+    async fn put(
+        &self,
+        key: &MediaKey,
+        data: Bytes,
+        media_type: MediaType,
+    ) -> Result<(), MediaError>;
+
+    /// The file at `key`, `None` if there is none.
+    async fn get(&self, key: &MediaKey) -> Result<Option<Bytes>, MediaError>;
+
+    /// Deletes the file at `key`. Succeeds if there is none.
+    async fn delete(&self, key: &MediaKey) -> Result<(), MediaError>;
+
+    /// Where clients download the file at `key`. Relative to the API url for files which are
+    /// served by the API server.
+    fn url(&self, key: &MediaKey) -> String;
+
+    /// Directory to serve at [`MEDIA_FILES_URL`], for stores which keep files on the API server.
+    fn local_dir(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// The media store with the limits of uploads.
+#[derive(Clone)]
+pub struct Media {
+    pub store: Arc<dyn MediaStore>,
+    /// Bytes every user can upload.
+    pub quota: i64,
+}
+
+impl Media {
+    pub fn new<S>(store: S, quota: i64) -> Self
+    where
+        S: MediaStore + 'static,
+    {
+        Self {
+            store: Arc::new(store),
+            quota,
+        }
+    }
+}
+
+/// Deletes the files of removed uploads and of users who deleted their account, unless someone
+/// else uploaded them too. Every file is checked and deleted under the lock of its key, see
+/// [`uchat_query::media::lock`], so an upload of the same file in between doesn't lose it.
+///
+/// Returns the number of deleted files.
+pub async fn remove_orphans(
+    db_pool: &AsyncConnectionPool,
+    media: &Media,
+) -> Result<usize, MediaError> {
+    let keys = {
+        let mut conn = db_pool.get().await?;
+        uchat_query::media::orphaned_keys(&mut conn)?
+    };
+    let mut removed = 0;
+    for key in keys {
+        let mut conn = db_pool.get().await?;
+        let Some(media_key) = MediaKey::parse(&key) else {
+            warn!(target: "uchat_server", %key, "forgetting invalid media key");
+            uchat_query::media::forget_orphans(&mut conn, &key)?;
+            continue;
+        };
+        uchat_query::media::lock(&mut conn, &key)?;
+        let result = remove_orphan(&mut conn, media, &media_key).await;
+        uchat_query::media::unlock(&mut conn, &key)?;
+        if result? {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Deletes the file at `key` unless it is uploaded, then forgets its orphaned uploads. Returns
+/// whether the file was deleted. The caller holds the lock of the key.
+async fn remove_orphan(
+    conn: &mut AsyncConnection<'_>,
+    media: &Media,
+    key: &MediaKey,
+) -> Result<bool, MediaError> {
+    let uploaded = uchat_query::media::is_uploaded(conn, key.as_str())?;
+    if !uploaded {
+        // the uploads are only forgotten afterwards, so a failed delete is retried
+        media.store.delete(key).await?;
+    }
+    uchat_query::media::forget_orphans(conn, key.as_str())?;
+    Ok(!uploaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_image_types() {
+        assert_eq!(
+            MediaType::sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some(MediaType::Png)
+        );
+        assert_eq!(
+            MediaType::sniff(b"\xff\xd8\xff\xe0\0\x10JFIF"),
+            Some(MediaType::Jpeg)
+        );
+        assert_eq!(MediaType::sniff(b"GIF89a\x01\0"), Some(MediaType::Gif));
+        assert_eq!(
+            MediaType::sniff(b"RIFF\x24\0\0\0WEBPVP8 "),
+            Some(MediaType::Webp)
+        );
+        assert_eq!(
+            MediaType::sniff(b"<svg xmlns=\"http://www.w3.org/2000/svg\">"),
+            None
+        );
+        assert_eq!(MediaType::sniff(b"\x89PNG"), None);
+    }
+
+    #[test]
+    fn derives_keys_from_content() {
+        let key = MediaKey::for_content(b"", MediaType::Png);
+        assert_eq!(
+            key.as_str(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855.png"
+        );
+        assert_eq!(MediaKey::parse(key.as_str()), Some(key));
+
+        assert!(MediaKey::parse("../../etc/passwd").is_none());
+        assert!(MediaKey::parse(&format!("{}.svg", "a".repeat(64))).is_none());
+        assert!(MediaKey::parse(&format!("{}.png", "A".repeat(64))).is_none());
+        assert!(MediaKey::parse(&format!("{}.png/x", "a".repeat(64))).is_none());
+    }
+}
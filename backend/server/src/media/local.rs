@@ -0,0 +1,90 @@
+//! Media stored in a directory of the API server, which serves it at [`MEDIA_FILES_URL`].
+
+use std::path::{Path, PathBuf};
+
+use axum::async_trait;
+use axum::body::Bytes;
+use uuid::Uuid;
+
+use super::{MediaError, MediaKey, MediaStore, MediaType, MEDIA_FILES_URL};
+
+#[derive(Clone, Debug)]
+pub struct LocalMediaStore {
+    dir: PathBuf,
+}
+
+impl LocalMediaStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, key: &MediaKey) -> PathBuf {
+        self.dir.join(key.as_str())
+    }
+}
+
+#[async_trait]
+impl MediaStore for LocalMediaStore {
+    async fn put(
+        &self,
+        key: &MediaKey,
+        data: Bytes,
+        _media_type: MediaType,
+    ) -> Result<(), MediaError> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        // the file only appears at its path once it is complete, so it is never served halfway.
+        // Other users may upload the same file at the same time, so partial files are unique.
+        let path = self.path(key);
+        let partial = self.dir.join(format!("{key}.{}.partial", Uuid::new_v4()));
+        tokio::fs::write(&partial, &data).await?;
+        tokio::fs::rename(partial, path).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &MediaKey) -> Result<Option<Bytes>, MediaError> {
+        match tokio::fs::read(self.path(key)).await {
+            Ok(data) => Ok(Some(data.into())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &MediaKey) -> Result<(), MediaError> {
+        match tokio::fs::remove_file(self.path(key)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn url(&self, key: &MediaKey) -> String {
+        format!("{MEDIA_FILES_URL}/{key}")
+    }
+
+    fn local_dir(&self) -> Option<&Path> {
+        Some(&self.dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stores_files() {
+        let dir = std::env::temp_dir().join(format!("uchat-test-{}", Uuid::new_v4()));
+        let store = LocalMediaStore::new(dir.clone());
+        let data = Bytes::from_static(b"GIF89a\x01\0");
+        let key = MediaKey::for_content(&data, MediaType::Gif);
+
+        assert_eq!(store.get(&key).await.unwrap(), None);
+        store.put(&key, data.clone(), MediaType::Gif).await.unwrap();
+        assert_eq!(store.get(&key).await.unwrap(), Some(data));
+        assert_eq!(store.url(&key), format!("/media/files/{key}"));
+
+        store.delete(&key).await.unwrap();
+        store.delete(&key).await.unwrap();
+        assert_eq!(store.get(&key).await.unwrap(), None);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
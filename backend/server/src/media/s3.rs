@@ -0,0 +1,300 @@
+//! Media stored in an S3-compatible object storage, like Amazon S3 or MinIO.
+//!
+//! Objects are addressed path-style, as `{endpoint}/{bucket}/{key}`, which every implementation
+//! supports. Requests are signed with [`uchat_crypto::sigv4`]. Clients download objects directly
+//! from the storage or a CDN in front of it, so the bucket has to allow public reads.
+
+use axum::async_trait;
+use axum::body::{Body, Bytes};
+use axum::http::{header, Method, Request, Response, StatusCode};
+use chrono::Utc;
+use hyper::client::HttpConnector;
+use hyper::Client;
+use hyper_rustls::HttpsConnector;
+use uchat_crypto::sigv4::{self, Credentials};
+use url::Url;
+
+use super::{MediaError, MediaKey, MediaStore, MediaType};
+
+#[derive(Clone, Debug)]
+pub struct S3MediaStore {
+    client: Client<HttpsConnector<HttpConnector>>,
+    endpoint: Url,
+    bucket: String,
+    credentials: Credentials,
+    public_url: String,
+}
+
+impl S3MediaStore {
+    /// Stores objects in `bucket` of the storage at `endpoint`, e.g.
+    /// `https://s3.eu-central-1.amazonaws.com`. Clients download them from `public_url`, which
+    /// defaults to the url of the bucket.
+    pub fn new(
+        endpoint: Url,
+        bucket: String,
+        credentials: Credentials,
+        public_url: Option<String>,
+    ) -> Self {
+        let connector = hyper_rustls::HttpsConnectorBuilder::new()
+            .with_webpki_roots()
+            .https_or_http()
+            .enable_http1()
+            .build();
+        let public_url = public_url
+            .unwrap_or_else(|| format!("{}/{bucket}", endpoint.as_str().trim_end_matches('/')));
+        Self {
+            client: Client::builder().build(connector),
+            endpoint,
+            bucket,
+            credentials,
+            public_url: public_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        key: &MediaKey,
+        body: Bytes,
+        content_type: Option<&str>,
+    ) -> Result<Response<Body>, MediaError> {
+        let host = self.endpoint.host_str().unwrap_or_default();
+        let host = match self.endpoint.port() {
+            Some(port) => format!("{host}:{port}"),
+            None => host.to_string(),
+        };
+        let path = format!(
+            "{}/{}/{key}",
+            self.endpoint.path().trim_end_matches('/'),
+            self.bucket
+        );
+        let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let payload_hash = uchat_crypto::sha256_hex(&body);
+
+        let mut headers = vec![
+            ("host", host.as_str()),
+            ("x-amz-content-sha256", payload_hash.as_str()),
+            ("x-amz-date", amz_date.as_str()),
+        ];
+        if let Some(content_type) = content_type {
+            headers.push(("content-type", content_type));
+        }
+        let authorization = sigv4::authorization(
+            &self.credentials,
+            "s3",
+            &amz_date,
+            &sigv4::Request {
+                method: method.as_str(),
+                path: &path,
+                query: "",
+                headers: &headers,
+                payload_hash: &payload_hash,
+            },
+        );
+
+        let mut request = Request::builder()
+            .method(method)
+            .uri(format!("{}://{host}{path}", self.endpoint.scheme()));
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        let request = request
+            .header(header::AUTHORIZATION, authorization)
+            .body(Body::from(body))
+            .expect("media keys and the endpoint make a valid uri");
+        Ok(self.client.request(request).await?)
+    }
+}
+
+/// Error for a response which isn't successful.
+async fn status_error(response: Response<Body>) -> MediaError {
+    let status = response.status();
+    let body = match hyper::body::to_bytes(response.into_body()).await {
+        Ok(body) => String::from_utf8_lossy(&body).into_owned(),
+        Err(e) => return e.into(),
+    };
+    MediaError::Status { status, body }
+}
+
+#[async_trait]
+impl MediaStore for S3MediaStore {
+    async fn put(
+        &self,
+        key: &MediaKey,
+        data: Bytes,
+        media_type: MediaType,
+    ) -> Result<(), MediaError> {
+        let response = self
+            .send(Method::PUT, key, data, Some(media_type.as_mime()))
+            .await?;
+        if !response.status().is_success() {
+            return Err(status_error(response).await);
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &MediaKey) -> Result<Option<Bytes>, MediaError> {
+        let response = self.send(Method::GET, key, Bytes::new(), None).await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => {
+                Ok(Some(hyper::body::to_bytes(response.into_body()).await?))
+            }
+            _ => Err(status_error(response).await),
+        }
+    }
+
+    async fn delete(&self, key: &MediaKey) -> Result<(), MediaError> {
+        let response = self.send(Method::DELETE, key, Bytes::new(), None).await?;
+        // S3 answers `204 No Content` for missing objects as well, others may not
+        if !response.status().is_success() && response.status() != StatusCode::NOT_FOUND {
+            return Err(status_error(response).await);
+        }
+        Ok(())
+    }
+
+    fn url(&self, key: &MediaKey) -> String {
+        format!("{}/{key}", self.public_url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::State;
+    use axum::http::{HeaderMap, Uri};
+    use axum::response::IntoResponse;
+    use axum::Router;
+    use std::collections::HashMap;
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::{Arc, Mutex};
+
+    /// In-memory stand-in for an object storage, which checks signatures like S3 does.
+    #[derive(Clone)]
+    struct StandIn {
+        credentials: Credentials,
+        objects: Arc<Mutex<HashMap<String, (String, Bytes)>>>,
+    }
+
+    async fn object(
+        State(stand_in): State<StandIn>,
+        method: Method,
+        uri: Uri,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> axum::response::Response {
+        let authorization = headers[header::AUTHORIZATION].to_str().unwrap();
+        let signed_headers = authorization
+            .split(", ")
+            .find_map(|part| part.strip_prefix("SignedHeaders="))
+            .unwrap();
+        let amz_date = headers["x-amz-date"].to_str().unwrap();
+        let signed: Vec<_> = signed_headers
+            .split(';')
+            .map(|name| (name, headers[name].to_str().unwrap()))
+            .collect();
+        let payload_hash = uchat_crypto::sha256_hex(&body);
+        let expected = sigv4::authorization(
+            &stand_in.credentials,
+            "s3",
+            amz_date,
+            &sigv4::Request {
+                method: method.as_str(),
+                path: uri.path(),
+                query: "",
+                headers: &signed,
+                payload_hash: &payload_hash,
+            },
+        );
+        if authorization != expected || headers["x-amz-content-sha256"] != payload_hash {
+            return (StatusCode::FORBIDDEN, "SignatureDoesNotMatch").into_response();
+        }
+
+        let mut objects = stand_in.objects.lock().unwrap();
+        match method {
+            Method::PUT => {
+                let content_type = headers[header::CONTENT_TYPE].to_str().unwrap();
+                objects.insert(uri.path().to_string(), (content_type.to_string(), body));
+                StatusCode::OK.into_response()
+            }
+            Method::GET => match objects.get(uri.path()) {
+                Some((content_type, body)) => {
+                    ([(header::CONTENT_TYPE, content_type.clone())], body.clone()).into_response()
+                }
+                None => (StatusCode::NOT_FOUND, "NoSuchKey").into_response(),
+            },
+            Method::DELETE => {
+                objects.remove(uri.path());
+                StatusCode::NO_CONTENT.into_response()
+            }
+            _ => StatusCode::METHOD_NOT_ALLOWED.into_response(),
+        }
+    }
+
+    fn credentials(secret_key: &str) -> Credentials {
+        Credentials {
+            access_key: "uchat".into(),
+            secret_key: secret_key.into(),
+            region: "us-east-1".into(),
+        }
+    }
+
+    fn start_stand_in() -> (SocketAddr, StandIn) {
+        let stand_in = StandIn {
+            credentials: credentials("secret"),
+            objects: Default::default(),
+        };
+        let app = Router::new().fallback(object).with_state(stand_in.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service());
+        tokio::spawn(server);
+        (addr, stand_in)
+    }
+
+    #[tokio::test]
+    async fn stores_objects() {
+        let (addr, stand_in) = start_stand_in();
+        let endpoint = Url::parse(&format!("http://{addr}")).unwrap();
+        let store = S3MediaStore::new(endpoint, "media".into(), credentials("secret"), None);
+        let data = Bytes::from_static(b"\x89PNG\r\n\x1a\n");
+        let key = MediaKey::for_content(&data, MediaType::Png);
+
+        assert_eq!(store.get(&key).await.unwrap(), None);
+        store.put(&key, data.clone(), MediaType::Png).await.unwrap();
+        assert_eq!(store.get(&key).await.unwrap(), Some(data));
+        let path = format!("/media/{key}");
+        assert_eq!(
+            stand_in.objects.lock().unwrap()[&path].0,
+            MediaType::Png.as_mime()
+        );
+        assert_eq!(store.url(&key), format!("http://{addr}{path}"));
+
+        store.delete(&key).await.unwrap();
+        assert_eq!(store.get(&key).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn fails_with_wrong_credentials() {
+        let (addr, _) = start_stand_in();
+        let endpoint = Url::parse(&format!("http://{addr}")).unwrap();
+        let store = S3MediaStore::new(
+            endpoint,
+            "media".into(),
+            credentials("wrong"),
+            Some("https://cdn.example.com/".into()),
+        );
+        let key = MediaKey::for_content(b"GIF89a", MediaType::Gif);
+
+        match store
+            .put(&key, Bytes::from_static(b"GIF89a"), MediaType::Gif)
+            .await
+        {
+            Err(MediaError::Status { status, .. }) => assert_eq!(status, StatusCode::FORBIDDEN),
+            other => panic!("unexpected result {other:?}"),
+        }
+        assert_eq!(store.url(&key), format!("https://cdn.example.com/{key}"));
+    }
+}
@@ -0,0 +1,80 @@
+//! Counters for monitoring, served in the Prometheus text format.
+//!
+//! Metrics are served on their own address, see `--metrics-bind`, so they aren't reachable
+//! through the public API.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+
+pub const METRICS_URL: &str = "/metrics";
+
+#[derive(Clone, Debug, Default)]
+pub struct Metrics(Arc<Counters>);
+
+#[derive(Debug, Default)]
+struct Counters {
+    sessions_deleted: AtomicU64,
+}
+
+impl Metrics {
+    /// Counts sessions deleted by the periodic cleanup.
+    pub fn add_sessions_deleted(&self, deleted: u64) {
+        self.0
+            .sessions_deleted
+            .fetch_add(deleted, Ordering::Relaxed);
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        counter(
+            &mut out,
+            "uchat_expired_sessions_deleted_total",
+            "Expired sessions deleted by the periodic cleanup.",
+            self.0.sessions_deleted.load(Ordering::Relaxed),
+        );
+        out
+    }
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    // writing to a string can't fail
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} counter");
+    let _ = writeln!(out, "{name} {value}");
+}
+
+pub fn new_router(metrics: Metrics) -> Router {
+    Router::new()
+        .route(METRICS_URL, get(render))
+        .with_state(metrics)
+}
+
+async fn render(State(metrics): State<Metrics>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.render(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_counters() {
+        let metrics = Metrics::default();
+        metrics.add_sessions_deleted(3);
+        metrics.add_sessions_deleted(2);
+
+        let rendered = metrics.render();
+        assert!(rendered.contains("# TYPE uchat_expired_sessions_deleted_total counter\n"));
+        assert!(rendered.contains("\nuchat_expired_sessions_deleted_total 5\n"));
+    }
+}
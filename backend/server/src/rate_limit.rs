@@ -0,0 +1,773 @@
+//! Throttling for login endpoints.
+//!
+//! Failed logins are tracked per client IP and per handle. Every failure doubles the time the
+//! client has to wait before the next attempt, and too many failures lock the key out for a
+//! while. Failure records live in an [`AttemptStore`] so the in-memory default can be replaced
+//! with a shared (database-backed) store when running multiple servers.
+//!
+//! Each attempt counts as a failure from the moment it is let through until the response shows
+//! otherwise, so concurrent attempts can't all slip past the check. Only issuing a session
+//! forgets the failures of a handle, and the failures of an IP are only forgotten over time.
+//!
+//! Behind a load balancer every connection comes from the balancer, so its address has to be
+//! configured as a trusted proxy. The client IP is then taken from `X-Forwarded-For`, see
+//! [`client_ip`]. Otherwise one client could lock everyone out.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use axum::async_trait;
+use axum::body::{Body, Bytes};
+use axum::extract::ConnectInfo;
+use axum::http::{header, request::Parts, HeaderValue, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use tower::{Layer, Service};
+use tracing::{error, info, warn};
+use uchat_api::{ErrorCode, RequestFailed};
+
+/// Login request bodies are tiny, anything bigger is rejected before buffering.
+const MAX_BODY_SIZE: usize = 16 * 1024;
+
+const FORWARDED_FOR: &str = "x-forwarded-for";
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AttemptKey {
+    Ip(IpAddr),
+    Handle(String),
+}
+
+impl std::fmt::Display for AttemptKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ip(ip) => write!(f, "ip:{ip}"),
+            Self::Handle(handle) => write!(f, "handle:{handle}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FailureRecord {
+    pub failures: u32,
+    pub last_failure: DateTime<Utc>,
+}
+
+/// Outcome of [`AttemptStore::acquire`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Acquired {
+    /// The attempt may go ahead and was counted as a failure.
+    Reserved(FailureRecord),
+    /// The key is blocked until the given time.
+    Blocked(DateTime<Utc>),
+}
+
+/// Response extension of handlers which issued a session, see [`LoginRateLimit`].
+#[derive(Clone, Copy, Debug)]
+pub struct SessionIssued;
+
+/// Response extension of handlers naming the handle a login attempt was for, when the request
+/// doesn't contain it.
+#[derive(Clone, Debug)]
+pub struct AttemptedHandle(pub String);
+
+/// Storage for failed login attempts.
+#[async_trait]
+pub trait AttemptStore: Send + Sync {
+    async fn get(&self, key: &AttemptKey) -> color_eyre::Result<Option<FailureRecord>>;
+
+    /// Checks whether `key` is blocked under `policy` and, if it isn't, adds a failure for the
+    /// attempt in the same step. Expired records are reset first.
+    async fn acquire(
+        &self,
+        key: &AttemptKey,
+        now: DateTime<Utc>,
+        policy: &BackoffPolicy,
+    ) -> color_eyre::Result<Acquired>;
+
+    /// Takes back a failure added by [`AttemptStore::acquire`].
+    async fn release(&self, key: &AttemptKey) -> color_eyre::Result<()>;
+
+    /// Adds a failure and returns the updated record.
+    async fn record_failure(
+        &self,
+        key: &AttemptKey,
+        at: DateTime<Utc>,
+    ) -> color_eyre::Result<FailureRecord>;
+
+    async fn clear(&self, key: &AttemptKey) -> color_eyre::Result<()>;
+
+    /// Removes records whose last failure happened before `before`.
+    async fn prune(&self, before: DateTime<Utc>) -> color_eyre::Result<()>;
+}
+
+/// Keeps failure records in process memory.
+#[derive(Debug, Default)]
+pub struct InMemoryAttemptStore {
+    records: Mutex<HashMap<AttemptKey, FailureRecord>>,
+}
+
+#[async_trait]
+impl AttemptStore for InMemoryAttemptStore {
+    async fn get(&self, key: &AttemptKey) -> color_eyre::Result<Option<FailureRecord>> {
+        Ok(self.records.lock().unwrap().get(key).copied())
+    }
+
+    async fn acquire(
+        &self,
+        key: &AttemptKey,
+        now: DateTime<Utc>,
+        policy: &BackoffPolicy,
+    ) -> color_eyre::Result<Acquired> {
+        let mut records = self.records.lock().unwrap();
+        let record = records.entry(key.clone()).or_insert(FailureRecord {
+            failures: 0,
+            last_failure: now,
+        });
+        if policy.is_expired(record, now) {
+            record.failures = 0;
+        }
+        if let Some(until) = policy.blocked_until(record).filter(|until| *until > now) {
+            return Ok(Acquired::Blocked(until));
+        }
+        record.failures += 1;
+        record.last_failure = now;
+        Ok(Acquired::Reserved(*record))
+    }
+
+    async fn release(&self, key: &AttemptKey) -> color_eyre::Result<()> {
+        let mut records = self.records.lock().unwrap();
+        if let Some(record) = records.get_mut(key) {
+            record.failures = record.failures.saturating_sub(1);
+            if record.failures == 0 {
+                records.remove(key);
+            }
+        }
+        Ok(())
+    }
+
+    async fn record_failure(
+        &self,
+        key: &AttemptKey,
+        at: DateTime<Utc>,
+    ) -> color_eyre::Result<FailureRecord> {
+        let mut records = self.records.lock().unwrap();
+        let record = records.entry(key.clone()).or_insert(FailureRecord {
+            failures: 0,
+            last_failure: at,
+        });
+        record.failures += 1;
+        record.last_failure = at;
+        Ok(*record)
+    }
+
+    async fn clear(&self, key: &AttemptKey) -> color_eyre::Result<()> {
+        self.records.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    async fn prune(&self, before: DateTime<Utc>) -> color_eyre::Result<()> {
+        self.records
+            .lock()
+            .unwrap()
+            .retain(|_, record| record.last_failure >= before);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BackoffPolicy {
+    /// Delay after the first failure. Doubles with each further failure.
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Number of failures which trigger a lockout.
+    pub max_failures: u32,
+    pub lockout: Duration,
+    /// Failures are forgotten after this much time without new failures.
+    pub reset_after: Duration,
+}
+
+impl BackoffPolicy {
+    /// Time until which further attempts are rejected.
+    pub fn blocked_until(&self, record: &FailureRecord) -> Option<DateTime<Utc>> {
+        if record.failures == 0 {
+            return None;
+        }
+
+        let wait = if record.failures >= self.max_failures {
+            self.lockout
+        } else {
+            let exponent = (record.failures - 1).min(31);
+            self.base_delay
+                .saturating_mul(2_u32.saturating_pow(exponent))
+                .min(self.max_delay)
+        };
+
+        Some(record.last_failure + to_chrono(wait))
+    }
+
+    pub fn is_expired(&self, record: &FailureRecord, now: DateTime<Utc>) -> bool {
+        now - record.last_failure > to_chrono(self.reset_after)
+    }
+
+    pub fn is_locked_out(&self, record: &FailureRecord) -> bool {
+        record.failures >= self.max_failures
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RateLimitConfig {
+    pub per_handle: BackoffPolicy,
+    /// Usually more lenient than `per_handle` since many users may share an address.
+    pub per_ip: BackoffPolicy,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_handle: BackoffPolicy {
+                base_delay: Duration::from_secs(1),
+                max_delay: Duration::from_secs(60),
+                max_failures: 10,
+                lockout: Duration::from_secs(15 * 60),
+                reset_after: Duration::from_secs(60 * 60),
+            },
+            per_ip: BackoffPolicy {
+                base_delay: Duration::from_millis(250),
+                max_delay: Duration::from_secs(30),
+                max_failures: 50,
+                lockout: Duration::from_secs(15 * 60),
+                reset_after: Duration::from_secs(60 * 60),
+            },
+        }
+    }
+}
+
+fn to_chrono(duration: Duration) -> chrono::Duration {
+    // configured durations are nowhere near chrono's limits
+    chrono::Duration::from_std(duration).expect("duration out of range")
+}
+
+/// Tracks failed logins and decides whether new attempts are allowed.
+#[derive(Clone)]
+pub struct LoginLimiter {
+    store: Arc<dyn AttemptStore>,
+    config: RateLimitConfig,
+    trusted_proxies: Arc<[IpAddr]>,
+}
+
+impl LoginLimiter {
+    pub fn new<S>(store: S, config: RateLimitConfig) -> Self
+    where
+        S: AttemptStore + 'static,
+    {
+        Self {
+            store: Arc::new(store),
+            config,
+            trusted_proxies: Arc::new([]),
+        }
+    }
+
+    /// Takes the client IP of requests from `proxies` from `X-Forwarded-For`, see [`client_ip`].
+    pub fn with_trusted_proxies(mut self, proxies: Vec<IpAddr>) -> Self {
+        self.trusted_proxies = proxies.into();
+        self
+    }
+
+    fn policy(&self, key: &AttemptKey) -> &BackoffPolicy {
+        match key {
+            AttemptKey::Ip(_) => &self.config.per_ip,
+            AttemptKey::Handle(_) => &self.config.per_handle,
+        }
+    }
+
+    /// Lets an attempt for `keys` through unless one of them is blocked, in which case it returns
+    /// how long the client has to wait. The attempt counts as a failure until it is settled with
+    /// [`LoginLimiter::record_failure`], [`LoginLimiter::record_success`] or
+    /// [`LoginLimiter::release`].
+    ///
+    /// Store errors are logged and the key is skipped, so a broken store doesn't lock everyone
+    /// out.
+    pub async fn acquire(
+        &self,
+        keys: &[AttemptKey],
+        now: DateTime<Utc>,
+    ) -> Result<Reservation, Duration> {
+        let mut reservation = Reservation::default();
+        let mut retry_after = None;
+
+        for key in keys {
+            match self.store.acquire(key, now, self.policy(key)).await {
+                Ok(Acquired::Reserved(record)) => reservation.records.push((key.clone(), record)),
+                Ok(Acquired::Blocked(until)) => {
+                    let wait = (until - now).to_std().unwrap_or_default();
+                    retry_after = retry_after.max(Some(wait));
+                }
+                Err(e) => {
+                    error!(target: "uchat_server", %key, err = ?e, "failed to acquire login attempt");
+                }
+            }
+        }
+
+        match retry_after {
+            Some(retry_after) => {
+                self.release(reservation).await;
+                Err(retry_after)
+            }
+            None => Ok(reservation),
+        }
+    }
+
+    /// The attempt failed, so its failures stay. `more` are keys which only the response
+    /// revealed, they get a failure as well.
+    pub async fn record_failure(
+        &self,
+        reservation: Reservation,
+        more: &[AttemptKey],
+        now: DateTime<Utc>,
+    ) {
+        let mut records = reservation.records;
+        for key in more {
+            match self.store.record_failure(key, now).await {
+                Ok(record) => records.push((key.clone(), record)),
+                Err(e) => {
+                    error!(target: "uchat_server", %key, err = ?e, "failed to record login attempt");
+                }
+            }
+        }
+
+        for (key, record) in records {
+            if self.policy(&key).is_locked_out(&record) {
+                warn!(target: "uchat_server", %key, failures = record.failures, "login locked out");
+            } else {
+                info!(target: "uchat_server", %key, failures = record.failures, "failed login");
+            }
+        }
+    }
+
+    /// The attempt issued a session: the failures of the handles are forgotten. Failures of the
+    /// IP are kept, otherwise logging into one account would reset the limit for guessing others.
+    pub async fn record_success(&self, reservation: Reservation, more: &[AttemptKey]) {
+        for (key, _) in reservation.records {
+            match key {
+                AttemptKey::Ip(_) => self.release_key(&key).await,
+                AttemptKey::Handle(_) => self.clear(&key).await,
+            }
+        }
+        for key in more
+            .iter()
+            .filter(|key| matches!(key, AttemptKey::Handle(_)))
+        {
+            self.clear(key).await;
+        }
+    }
+
+    /// The attempt neither failed nor issued a session, e.g. it needs a second factor.
+    pub async fn release(&self, reservation: Reservation) {
+        for (key, _) in reservation.records {
+            self.release_key(&key).await;
+        }
+    }
+
+    /// Drops records which have expired under both policies.
+    pub async fn prune(&self, now: DateTime<Utc>) {
+        let reset_after = self
+            .config
+            .per_handle
+            .reset_after
+            .max(self.config.per_ip.reset_after);
+        if let Err(e) = self.store.prune(now - to_chrono(reset_after)).await {
+            error!(target: "uchat_server", err = ?e, "failed to prune login attempts");
+        }
+    }
+
+    async fn clear(&self, key: &AttemptKey) {
+        if let Err(e) = self.store.clear(key).await {
+            error!(target: "uchat_server", %key, err = ?e, "failed to clear login attempts");
+        }
+    }
+
+    async fn release_key(&self, key: &AttemptKey) {
+        if let Err(e) = self.store.release(key).await {
+            error!(target: "uchat_server", %key, err = ?e, "failed to release login attempt");
+        }
+    }
+}
+
+/// Attempts let through by [`LoginLimiter::acquire`], which count as failures until settled.
+#[derive(Debug, Default)]
+#[must_use]
+pub struct Reservation {
+    records: Vec<(AttemptKey, FailureRecord)>,
+}
+
+/// Applies a [`LoginLimiter`] to login routes.
+///
+/// `401 Unauthorized` responses count as failures. Responses with the [`SessionIssued`] extension
+/// reset the counters of the handle, everything else doesn't count. Handlers add an
+/// [`AttemptedHandle`] when the request only identifies the account indirectly.
+#[derive(Clone)]
+pub struct LoginRateLimitLayer {
+    limiter: LoginLimiter,
+}
+
+impl LoginRateLimitLayer {
+    pub fn new(limiter: LoginLimiter) -> Self {
+        Self { limiter }
+    }
+}
+
+impl<S> Layer<S> for LoginRateLimitLayer {
+    type Service = LoginRateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LoginRateLimit {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct LoginRateLimit<S> {
+    inner: S,
+    limiter: LoginLimiter,
+}
+
+impl<S> Service<Request<Body>> for LoginRateLimit<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        // the service that was driven to readiness is the one that gets called
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limiter = self.limiter.clone();
+
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let body = match read_body(body).await {
+                Ok(body) => body,
+                Err(response) => return Ok(response),
+            };
+
+            let keys = attempt_keys(&parts, &body, &limiter.trusted_proxies);
+            let reservation = match limiter.acquire(&keys, Utc::now()).await {
+                Ok(reservation) => reservation,
+                Err(retry_after) => {
+                    info!(target: "uchat_server", ?keys, ?retry_after, "login attempt throttled");
+                    return Ok(too_many_attempts(retry_after));
+                }
+            };
+
+            let response = inner
+                .call(Request::from_parts(parts, Body::from(body)))
+                .await?;
+
+            let more: Vec<_> = response
+                .extensions()
+                .get::<AttemptedHandle>()
+                .map(|AttemptedHandle(handle)| AttemptKey::Handle(handle.clone()))
+                .into_iter()
+                .collect();
+            if response.status() == StatusCode::UNAUTHORIZED {
+                limiter.record_failure(reservation, &more, Utc::now()).await;
+            } else if response.extensions().get::<SessionIssued>().is_some() {
+                limiter.record_success(reservation, &more).await;
+            } else {
+                limiter.release(reservation).await;
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+async fn read_body(body: Body) -> Result<Bytes, Response> {
+    let body = http_body::Limited::new(body, MAX_BODY_SIZE);
+    hyper::body::to_bytes(body)
+        .await
+        .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE.into_response())
+}
+
+/// Address of the client which sent a request. Proxies in `trusted_proxies` append the address
+/// they got the request from to `X-Forwarded-For`, so the last address there which isn't a
+/// trusted proxy is the client. Anything before it may be made up by the client.
+pub fn client_ip(parts: &Parts, trusted_proxies: &[IpAddr]) -> Option<IpAddr> {
+    let peer = parts.extensions.get::<ConnectInfo<SocketAddr>>()?.0.ip();
+    if !trusted_proxies.contains(&peer) {
+        return Some(peer);
+    }
+
+    let forwarded: Vec<&str> = parts
+        .headers
+        .get_all(FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect();
+    let mut client = peer;
+    for hop in forwarded.into_iter().rev() {
+        let Ok(ip) = hop.trim().parse() else {
+            break;
+        };
+        client = ip;
+        if !trusted_proxies.contains(&ip) {
+            break;
+        }
+    }
+    Some(client)
+}
+
+fn attempt_keys(parts: &Parts, body: &[u8], trusted_proxies: &[IpAddr]) -> Vec<AttemptKey> {
+    #[derive(Deserialize)]
+    struct HandleField {
+        handle: Option<String>,
+    }
+
+    let ip = client_ip(parts, trusted_proxies).map(AttemptKey::Ip);
+
+    let handle = serde_json::from_slice::<HandleField>(body)
+        .ok()
+        .and_then(|field| field.handle)
+        .map(AttemptKey::Handle);
+
+    ip.into_iter().chain(handle).collect()
+}
+
+fn too_many_attempts(retry_after: Duration) -> Response {
+    // round up so clients never retry too early
+    let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    let payload = RequestFailed {
+        code: ErrorCode::RateLimited,
+        msg: format!("too many login attempts, try again in {secs} seconds"),
+    };
+
+    let mut response = (StatusCode::TOO_MANY_REQUESTS, Json(payload)).into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::ServiceExt;
+
+    fn policy() -> BackoffPolicy {
+        BackoffPolicy {
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(8),
+            max_failures: 5,
+            lockout: Duration::from_secs(60),
+            reset_after: Duration::from_secs(600),
+        }
+    }
+
+    fn record(failures: u32, last_failure: DateTime<Utc>) -> FailureRecord {
+        FailureRecord {
+            failures,
+            last_failure,
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_and_caps() {
+        let policy = policy();
+        let now = Utc::now();
+        let wait = |failures| policy.blocked_until(&record(failures, now)).unwrap() - now;
+
+        assert!(policy.blocked_until(&record(0, now)).is_none());
+        assert_eq!(wait(1), chrono::Duration::seconds(1));
+        assert_eq!(wait(2), chrono::Duration::seconds(2));
+        assert_eq!(wait(3), chrono::Duration::seconds(4));
+        assert_eq!(wait(4), chrono::Duration::seconds(8));
+        assert_eq!(wait(5), chrono::Duration::seconds(60));
+        assert!(policy.is_locked_out(&record(5, now)));
+    }
+
+    #[test]
+    fn records_expire() {
+        let policy = policy();
+        let now = Utc::now();
+        assert!(!policy.is_expired(&record(3, now - chrono::Duration::seconds(599)), now));
+        assert!(policy.is_expired(&record(3, now - chrono::Duration::seconds(601)), now));
+    }
+
+    fn limiter() -> LoginLimiter {
+        let config = RateLimitConfig {
+            per_handle: policy(),
+            per_ip: policy(),
+        };
+        LoginLimiter::new(InMemoryAttemptStore::default(), config)
+    }
+
+    #[tokio::test]
+    async fn blocks_keys_after_failures() {
+        let limiter = limiter();
+        let keys = [AttemptKey::Handle("test".into())];
+        let now = Utc::now();
+
+        let reservation = limiter.acquire(&keys, now).await.unwrap();
+        limiter.record_failure(reservation, &[], now).await;
+        let later = now + chrono::Duration::seconds(1);
+        let reservation = limiter.acquire(&keys, later).await.unwrap();
+        limiter.record_failure(reservation, &[], later).await;
+        let retry_after = limiter.acquire(&keys, later).await.unwrap_err();
+        assert_eq!(retry_after, Duration::from_secs(2));
+
+        // backoff has passed
+        let reservation = limiter
+            .acquire(&keys, later + chrono::Duration::seconds(2))
+            .await
+            .unwrap();
+        limiter.record_success(reservation, &[]).await;
+        let reservation = limiter.acquire(&keys, later).await.unwrap();
+        limiter.release(reservation).await;
+    }
+
+    #[tokio::test]
+    async fn pending_attempts_block_concurrent_ones() {
+        let limiter = limiter();
+        let keys = [AttemptKey::Handle("test".into())];
+        let now = Utc::now();
+
+        let reservation = limiter.acquire(&keys, now).await.unwrap();
+        assert!(limiter.acquire(&keys, now).await.is_err());
+
+        limiter.release(reservation).await;
+        let reservation = limiter.acquire(&keys, now).await.unwrap();
+        limiter.release(reservation).await;
+    }
+
+    #[tokio::test]
+    async fn sessions_only_reset_handles() {
+        let limiter = limiter();
+        let ip = [AttemptKey::Ip([127, 0, 0, 1].into())];
+        let handle = [AttemptKey::Handle("test".into())];
+        let now = Utc::now();
+
+        let reservation = limiter.acquire(&ip, now).await.unwrap();
+        limiter.record_failure(reservation, &handle, now).await;
+
+        let later = now + chrono::Duration::seconds(1);
+        let reservation = limiter.acquire(&ip, later).await.unwrap();
+        limiter.record_success(reservation, &handle).await;
+
+        let store = &limiter.store;
+        assert!(store.get(&handle[0]).await.unwrap().is_none());
+        assert_eq!(store.get(&ip[0]).await.unwrap().unwrap().failures, 1);
+    }
+
+    #[tokio::test]
+    async fn prunes_expired_records() {
+        let store = InMemoryAttemptStore::default();
+        let now = Utc::now();
+        let old = AttemptKey::Handle("old".into());
+        let new = AttemptKey::Handle("new".into());
+
+        store
+            .record_failure(&old, now - chrono::Duration::hours(2))
+            .await
+            .unwrap();
+        store.record_failure(&new, now).await.unwrap();
+        store.prune(now - chrono::Duration::hours(1)).await.unwrap();
+
+        assert!(store.get(&old).await.unwrap().is_none());
+        assert!(store.get(&new).await.unwrap().is_some());
+    }
+
+    #[test]
+    fn takes_client_ips_from_trusted_proxies() {
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let parts = |peer: IpAddr, forwarded: Option<&str>| {
+            let mut request = Request::get("/");
+            if let Some(forwarded) = forwarded {
+                request = request.header(FORWARDED_FOR, forwarded);
+            }
+            let (mut parts, _) = request.body(()).unwrap().into_parts();
+            parts
+                .extensions
+                .insert(ConnectInfo(SocketAddr::new(peer, 443)));
+            parts
+        };
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+
+        let client = ip("203.0.113.7");
+        let forwarded = Some("1.2.3.4, 203.0.113.7, 10.0.0.1");
+        assert_eq!(client_ip(&parts(proxy, forwarded), &[proxy]), Some(client));
+        // only trusted proxies can set the client ip
+        assert_eq!(client_ip(&parts(client, forwarded), &[proxy]), Some(client));
+        assert_eq!(client_ip(&parts(proxy, forwarded), &[]), Some(proxy));
+        assert_eq!(client_ip(&parts(proxy, None), &[proxy]), Some(proxy));
+        assert_eq!(
+            client_ip(&parts(proxy, Some("nonsense, 203.0.113.7")), &[proxy]),
+            Some(client)
+        );
+    }
+
+    #[tokio::test]
+    async fn layer_throttles_unauthorized_responses() {
+        let limiter = LoginLimiter::new(InMemoryAttemptStore::default(), Default::default());
+        let service = LoginRateLimitLayer::new(limiter).layer(tower::service_fn(
+            |_req: Request<Body>| async {
+                Ok::<_, std::convert::Infallible>(StatusCode::UNAUTHORIZED.into_response())
+            },
+        ));
+
+        let request = || {
+            Request::post("/account/login")
+                .body(Body::from(r#"{"handle":"test","password":"wrong"}"#))
+                .unwrap()
+        };
+
+        let response = service.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = service.oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "1");
+    }
+
+    #[tokio::test]
+    async fn layer_keeps_failures_without_a_session() {
+        let limiter = LoginLimiter::new(InMemoryAttemptStore::default(), Default::default());
+        let handle = AttemptKey::Handle("test".into());
+        limiter
+            .store
+            .record_failure(&handle, Utc::now() - chrono::Duration::minutes(1))
+            .await
+            .unwrap();
+        // a correct password which still needs a second factor
+        let service = LoginRateLimitLayer::new(limiter.clone()).layer(tower::service_fn(
+            |_req: Request<Body>| async {
+                Ok::<_, std::convert::Infallible>(StatusCode::OK.into_response())
+            },
+        ));
+
+        let request = Request::post("/account/login")
+            .body(Body::from(r#"{"handle":"test","password":"right"}"#))
+            .unwrap();
+        let response = service.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            limiter.store.get(&handle).await.unwrap().unwrap().failures,
+            1
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use axum::extract::DefaultBodyLimit;
+use axum::http::header::{self, CONTENT_TYPE};
+use axum::http::{HeaderName, HeaderValue, Method};
+use axum::middleware::from_fn_with_state;
+use axum::routing::{get, post};
+use axum::Router;
+use tower::ServiceBuilder;
+use tower_http::cors::CorsLayer;
+use tower_http::services::ServeDir;
+use tower_http::set_header::SetResponseHeaderLayer;
+use tower_http::trace::{DefaultMakeSpan, DefaultOnRequest, DefaultOnResponse, TraceLayer};
+use tower_http::LatencyUnit;
+use tracing::Level;
+use uchat_api::block::{BlockUser, GetBlockedUsers, MuteUser, UnblockUser, UnmuteUser};
+use uchat_api::event::SubscribeEvents;
+use uchat_api::export::{DownloadDataExport, GetDataExport, RequestDataExport};
+use uchat_api::follow::{
+    AnswerFollowRequest, FollowUser, GetFollowRequests, SetPrivate, UnfollowUser,
+};
+use uchat_api::media::{DeleteMedia, GetMediaUsage, UploadMedia, MAX_UPLOAD_SIZE};
+use uchat_api::moderation::{
+    GetAuditLog, GetReports, HidePost, ReportContent, ResolveReport, SetRole, SuspendUser,
+    UnhidePost, UnsuspendUser,
+};
+use uchat_api::notification::{GetNotifications, GetUnreadNotifications, MarkNotificationsRead};
+use uchat_api::post::{
+    Boost, DeletePost, EditPost, GetPost, GetPostRevisions, NewPost, React, VotePoll,
+};
+use uchat_api::search::Search;
+use uchat_api::tag::{GetTagTimeline, GetTrendingTags};
+use uchat_api::user::{
+    CancelAccountDeletion, ConfirmTotp, CreateUser, DeleteAccount, DisableTotp, EnrollTotp, Login,
+    LoginMfa, Whoami,
+};
+use uchat_api::{Endpoint, CSRF_HEADER};
+
+use crate::csrf;
+use crate::handler;
+use crate::media::MEDIA_FILES_URL;
+use crate::rate_limit::LoginRateLimitLayer;
+use crate::AppState;
+
+pub fn new_router(state: AppState) -> Router {
+    let public_routes = Router::new()
+        .route(Login::URL, post(handler::user::login))
+        .route(LoginMfa::URL, post(handler::user::login_mfa))
+        .route(CreateUser::URL, post(handler::user::create_user))
+        .route_layer(LoginRateLimitLayer::new(state.login_limiter.clone()));
+
+    let authorized_routes = Router::new()
+        .route(Whoami::URL, post(handler::user::whoami))
+        .route(EnrollTotp::URL, post(handler::totp::enroll))
+        .route(ConfirmTotp::URL, post(handler::totp::confirm))
+        .route(DisableTotp::URL, post(handler::totp::disable))
+        .route(DeleteAccount::URL, post(handler::user::delete_account))
+        .route(
+            CancelAccountDeletion::URL,
+            post(handler::user::cancel_deletion),
+        )
+        .route(RequestDataExport::URL, post(handler::export::request))
+        .route(GetDataExport::URL, post(handler::export::status))
+        .route(DownloadDataExport::URL, get(handler::export::download))
+        .route(
+            UploadMedia::URL,
+            post(handler::media::upload).layer(DefaultBodyLimit::max(MAX_UPLOAD_SIZE)),
+        )
+        .route(DeleteMedia::URL, post(handler::media::delete))
+        .route(GetMediaUsage::URL, post(handler::media::usage))
+        .route(SubscribeEvents::URL, get(handler::event::subscribe))
+        .route(GetNotifications::URL, post(handler::notification::list))
+        .route(
+            GetUnreadNotifications::URL,
+            post(handler::notification::unread),
+        )
+        .route(
+            MarkNotificationsRead::URL,
+            post(handler::notification::mark_read),
+        )
+        .route(NewPost::URL, post(handler::post::new))
+        .route(React::URL, post(handler::post::react))
+        .route(Boost::URL, post(handler::post::boost))
+        .route(VotePoll::URL, post(handler::post::vote))
+        .route(GetPost::URL, post(handler::post::get))
+        .route(EditPost::URL, post(handler::post::edit))
+        .route(GetPostRevisions::URL, post(handler::post::revisions))
+        .route(DeletePost::URL, post(handler::post::delete))
+        .route(Search::URL, post(handler::search::search))
+        .route(GetTagTimeline::URL, post(handler::tag::timeline))
+        .route(GetTrendingTags::URL, post(handler::tag::trending))
+        .route(BlockUser::URL, post(handler::block::block))
+        .route(UnblockUser::URL, post(handler::block::unblock))
+        .route(MuteUser::URL, post(handler::block::mute))
+        .route(UnmuteUser::URL, post(handler::block::unmute))
+        .route(GetBlockedUsers::URL, post(handler::block::list))
+        .route(FollowUser::URL, post(handler::follow::follow))
+        .route(UnfollowUser::URL, post(handler::follow::unfollow))
+        .route(GetFollowRequests::URL, post(handler::follow::requests))
+        .route(AnswerFollowRequest::URL, post(handler::follow::answer))
+        .route(SetPrivate::URL, post(handler::follow::set_private))
+        .route(ReportContent::URL, post(handler::moderation::report))
+        .route(GetReports::URL, post(handler::moderation::reports))
+        .route(ResolveReport::URL, post(handler::moderation::resolve))
+        .route(HidePost::URL, post(handler::moderation::hide_post))
+        .route(UnhidePost::URL, post(handler::moderation::unhide_post))
+        .route(SuspendUser::URL, post(handler::moderation::suspend))
+        .route(UnsuspendUser::URL, post(handler::moderation::unsuspend))
+        .route(SetRole::URL, post(handler::moderation::set_role))
+        .route(GetAuditLog::URL, post(handler::moderation::audit_log));
+
+    let mut router = Router::new().merge(public_routes).merge(authorized_routes);
+    if let Some(dir) = state.media.store.local_dir() {
+        // files never change since they are named after their content, and browsers must not
+        // guess another type for them
+        let media_files = ServiceBuilder::new()
+            .layer(SetResponseHeaderLayer::overriding(
+                header::CACHE_CONTROL,
+                HeaderValue::from_static("public, max-age=31536000, immutable"),
+            ))
+            .layer(SetResponseHeaderLayer::overriding(
+                header::X_CONTENT_TYPE_OPTIONS,
+                HeaderValue::from_static("nosniff"),
+            ))
+            .service(ServeDir::new(dir));
+        router = router.nest_service(MEDIA_FILES_URL, media_files);
+    }
+
+    router
+        .layer(from_fn_with_state(
+            state.signing_keys.clone(),
+            csrf::require_token,
+        ))
+        .layer(
+            ServiceBuilder::new()
+                .layer(
+                    TraceLayer::new_for_http()
+                        .make_span_with(DefaultMakeSpan::new().include_headers(true))
+                        .on_request(DefaultOnRequest::new().level(Level::INFO))
+                        .on_response(
+                            DefaultOnResponse::new()
+                                .level(Level::INFO)
+                                .latency_unit(LatencyUnit::Micros),
+                        ),
+                )
+                .layer(
+                    CorsLayer::new()
+                        .allow_methods([
+                            Method::GET,
+                            Method::POST,
+                            Method::PUT,
+                            Method::DELETE,
+                            Method::OPTIONS,
+                        ])
+                        .allow_headers([CONTENT_TYPE, HeaderName::from_static(CSRF_HEADER)])
+                        .allow_credentials(true)
+                        .allow_origin(state.allowed_origins.cors()),
+                ),
+        )
+        .with_state(state)
+}
@@ -1,9 +1,126 @@
 #![allow(non_snake_case)]
 
 use dioxus::prelude::*;
+use dioxus_router::{use_router, Route, Router};
 use fermi::use_init_atom_root;
 
+use crate::component::{NotificationBell, ToastRoot};
+use crate::page;
+use crate::state::{
+    load_local_user, stream_live_events, use_connectivity, use_live_events, use_local_user,
+    watch_connectivity, LocalUser,
+};
+
 pub fn App(cx: Scope) -> Element {
     use_init_atom_root(cx);
+
+    cx.render(rsx! {
+        Router {
+            Init {}
+            EventStream {}
+            OfflineBanner {}
+            ToastRoot {}
+            NotificationBell {}
+            Route { to: page::ACCOUNT_LOGIN, page::Login {} }
+            Route { to: page::ACCOUNT_REGISTER, page::Register {} }
+            Route { to: page::MODERATION, RequireAuth { page::Moderation {} } }
+            Route { to: page::NOTIFICATIONS, RequireAuth { page::Notifications {} } }
+            Route { to: page::POST, RequireAuth { page::Post {} } }
+            Route { to: page::SETTINGS_ACCOUNT, RequireAuth { page::Account {} } }
+            Route { to: page::SETTINGS_BLOCKED, RequireAuth { page::Blocked {} } }
+            Route { to: page::SETTINGS_EXPORT, RequireAuth { page::Export {} } }
+            Route { to: page::SETTINGS_PRIVACY, RequireAuth { page::Privacy {} } }
+            Route { to: page::SEARCH, RequireAuth { page::Search {} } }
+            Route { to: page::TAG, RequireAuth { page::Tag {} } }
+            Route { to: page::HOME, RequireAuth { page::Home {} } }
+        }
+    })
+}
+
+/// Loads the local user and starts watching the connection when the app starts.
+fn Init(cx: Scope) -> Element {
+    let local_user = use_local_user(cx);
+    let connectivity = use_connectivity(cx);
+
+    cx.use_hook(|| watch_connectivity(connectivity.clone()));
+
+    use_future(cx, (), |_| {
+        to_owned![local_user];
+        async move { load_local_user(&local_user).await }
+    });
+
+    None
+}
+
+/// Keeps the event stream open while a user is logged in.
+fn EventStream(cx: Scope) -> Element {
+    let local_user = use_local_user(cx);
+    let live_events = use_live_events(cx);
+
+    let user_id = match &*local_user.read() {
+        LocalUser::LoggedIn(user) => Some(user.user_id),
+        _ => None,
+    };
+
+    // restarted when another user logs in, which closes the old stream
+    use_future(cx, (&user_id,), |(user_id,)| {
+        to_owned![live_events];
+        async move {
+            if user_id.is_some() {
+                stream_live_events(live_events).await
+            }
+        }
+    });
+
     None
 }
+
+fn OfflineBanner(cx: Scope) -> Element {
+    let connectivity = use_connectivity(cx);
+
+    if connectivity.read().is_online() {
+        return None;
+    }
+
+    cx.render(rsx! {
+        div {
+            class: "fixed top-0 inset-x-0 bg-yellow-200 text-center py-1",
+            "You're offline. Changes will be sent once the connection is back."
+        }
+    })
+}
+
+#[derive(Props)]
+pub struct RequireAuthProps<'a> {
+    children: Element<'a>,
+}
+
+/// Renders its children only for logged in users. Everyone else is redirected to the login page.
+pub fn RequireAuth<'a>(cx: Scope<'a, RequireAuthProps<'a>>) -> Element<'a> {
+    let router = use_router(cx);
+    let local_user = use_local_user(cx);
+
+    let (logged_in, logged_out) = {
+        let local_user = local_user.read();
+        (
+            local_user.is_logged_in(),
+            *local_user == LocalUser::LoggedOut,
+        )
+    };
+
+    // navigating is a side effect, so it must not happen while rendering
+    use_effect(cx, (&logged_out,), |(logged_out,)| {
+        to_owned![router];
+        async move {
+            if logged_out {
+                router.navigate_to(page::ACCOUNT_LOGIN);
+            }
+        }
+    });
+    // nothing is shown while the session is being checked
+    if !logged_in {
+        return None;
+    }
+
+    cx.render(rsx! { &cx.props.children })
+}
@@ -0,0 +1,13 @@
+pub mod notification_bell;
+pub mod post_text;
+pub mod report_button;
+pub mod toaster;
+pub mod trending_tags;
+pub mod user_actions;
+
+pub use notification_bell::NotificationBell;
+pub use post_text::PostText;
+pub use report_button::ReportButton;
+pub use toaster::{use_toaster, ToastRoot, Toaster};
+pub use trending_tags::TrendingTags;
+pub use user_actions::UserActions;
@@ -0,0 +1,67 @@
+use dioxus::prelude::*;
+use dioxus_router::Link;
+use uchat_api::notification::{GetUnreadNotifications, GetUnreadNotificationsOk};
+
+use crate::fetch_json;
+use crate::page;
+use crate::state::{use_live_events, use_local_user, use_unread_notifications};
+use crate::util::ApiClient;
+
+/// How often the unread count is refreshed without real-time events.
+const REFRESH_INTERVAL_MS: u32 = 60_000;
+
+/// Link to the notifications page with the number of unread notifications.
+pub fn NotificationBell(cx: Scope) -> Element {
+    let api_client = ApiClient::global();
+    let local_user = use_local_user(cx);
+    let live_events = use_live_events(cx);
+    let unread = use_unread_notifications(cx);
+
+    let logged_in = local_user.read().is_logged_in();
+    // new events usually come with a notification, and resyncs mean some may have been missed
+    let events = {
+        let live_events = live_events.read();
+        (live_events.received(), live_events.resyncs())
+    };
+
+    use_future(cx, (&logged_in, &events), |(logged_in, _)| {
+        to_owned![unread];
+        async move {
+            if !logged_in {
+                *unread.write() = None;
+                return;
+            }
+            loop {
+                match fetch_json!(<GetUnreadNotificationsOk>, api_client, GetUnreadNotifications) {
+                    Ok(res) => *unread.write() = Some(res.unread),
+                    Err(e) => log::warn!("failed to load unread notifications: {e}"),
+                }
+                gloo_timers::future::TimeoutFuture::new(REFRESH_INTERVAL_MS).await;
+            }
+        }
+    });
+
+    if !logged_in {
+        return None;
+    }
+
+    let count = match *unread.read() {
+        Some(count) if count > 99 => "99+".to_string(),
+        Some(count) if count > 0 => count.to_string(),
+        _ => String::new(),
+    };
+
+    cx.render(rsx! {
+        Link {
+            class: "fixed top-3 right-5 flex items-center gap-1",
+            to: page::NOTIFICATIONS,
+            "🔔"
+            (!count.is_empty()).then(|| rsx! {
+                span {
+                    class: "bg-red-600 text-white text-xs rounded-full px-2",
+                    "{count}"
+                }
+            })
+        }
+    })
+}
@@ -0,0 +1,34 @@
+use dioxus::prelude::*;
+use dioxus_router::Link;
+use uchat_api::search::SearchKind;
+use uchat_api::tag::Segment;
+
+use crate::page;
+
+#[derive(Props, PartialEq)]
+pub struct PostTextProps {
+    text: String,
+}
+
+/// Text of a post with hashtags linked to their timeline and mentions linked to the user.
+pub fn PostText(cx: Scope<PostTextProps>) -> Element {
+    let segments = uchat_api::tag::segments(&cx.props.text)
+        .into_iter()
+        .map(|segment| {
+            let (url, label) = match segment {
+                Segment::Text(text) => return rsx! { span { "{text}" } },
+                Segment::Hashtag(tag) => (page::tag_url(tag), format!("#{tag}")),
+                Segment::Mention(handle) => (
+                    page::search_url(handle, SearchKind::Users),
+                    format!("@{handle}"),
+                ),
+            };
+            rsx! {
+                Link { class: "text-blue-600", to: "{url}", "{label}" }
+            }
+        });
+
+    cx.render(rsx! {
+        p { class: "whitespace-pre-wrap break-words", segments }
+    })
+}
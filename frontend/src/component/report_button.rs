@@ -0,0 +1,111 @@
+use dioxus::prelude::*;
+use uchat_api::moderation::{ReportContent, ReportContentOk, ReportReason, ReportTarget};
+
+use crate::component::use_toaster;
+use crate::util::cancel::use_request_scope;
+use crate::util::ApiClient;
+use crate::{async_handler, fetch_json};
+
+const REASONS: [(ReportReason, &str, &str); 5] = [
+    (ReportReason::Spam, "spam", "Spam"),
+    (ReportReason::Harassment, "harassment", "Harassment"),
+    (ReportReason::Hate, "hate", "Hate"),
+    (ReportReason::Violence, "violence", "Violence"),
+    (ReportReason::Other, "other", "Something else"),
+];
+
+#[derive(Props, PartialEq)]
+pub struct ReportButtonProps {
+    target: ReportTarget,
+}
+
+/// Button which opens a form to report a post or user to the moderators.
+pub fn ReportButton(cx: Scope<ReportButtonProps>) -> Element {
+    let api_client = ApiClient::global();
+    let toaster = use_toaster(cx);
+    let cancel = use_request_scope(cx).token();
+
+    let open = use_state(cx, || false);
+    let reason = use_state(cx, || ReportReason::Spam);
+    let details = use_state(cx, String::new);
+    let busy = use_state(cx, || false);
+    let target = cx.props.target;
+
+    let submit = async_handler!(
+        &cx,
+        [api_client, cancel, toaster, open, reason, details, busy, target],
+        move |_| async move {
+            if let Err(msg) = uchat_api::moderation::validate_report_details(&details) {
+                toaster.write().error(msg);
+                return;
+            }
+            busy.set(true);
+            let request = ReportContent {
+                target,
+                reason: *reason.current(),
+                details: details.current().to_string(),
+            };
+            let response = cancel
+                .run(async { fetch_json!(<ReportContentOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(_) => {
+                    toaster
+                        .write()
+                        .success("Thanks, the moderators will take a look");
+                    open.set(false);
+                    details.set(String::new());
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            busy.set(false);
+        }
+    );
+
+    if !**open {
+        return cx.render(rsx! {
+            button { class: "btn text-sm", onclick: move |_| open.set(true), "Report" }
+        });
+    }
+
+    let options = REASONS.iter().map(|(value, key, label)| {
+        rsx! {
+            option { key: "{key}", value: "{key}", selected: *value == **reason, "{label}" }
+        }
+    });
+
+    cx.render(rsx! {
+        form {
+            class: "flex flex-col gap-2",
+            prevent_default: "onsubmit",
+            onsubmit: submit,
+            select {
+                class: "input-field",
+                oninput: move |ev| {
+                    if let Some((value, _, _)) = REASONS.iter().find(|(_, key, _)| *key == ev.value) {
+                        reason.set(*value);
+                    }
+                },
+                options
+            }
+            textarea {
+                class: "input-field",
+                placeholder: "Anything the moderators should know (optional)",
+                value: "{details}",
+                oninput: move |ev| details.set(ev.value.clone()),
+            }
+            div {
+                class: "flex gap-2",
+                button {
+                    class: "btn",
+                    r#type: "button",
+                    onclick: move |_| open.set(false),
+                    "Cancel"
+                }
+                button { class: "btn", r#type: "submit", disabled: "{busy}", "Send report" }
+            }
+        }
+    })
+}
@@ -0,0 +1,181 @@
+//! Short-lived notifications shown in a corner of the screen.
+
+use chrono::{DateTime, Duration, Utc};
+use dioxus::prelude::*;
+use fermi::{use_atom_ref, AtomRef, UseAtomRef};
+use std::collections::BTreeMap;
+use uchat_api::ErrorCode;
+
+use crate::util::RequestError;
+
+/// How long toasts are shown.
+pub const TOAST_DURATION_SECS: i64 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToastKind {
+    Info,
+    Success,
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Toast {
+    pub kind: ToastKind,
+    pub message: String,
+    pub expires: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+pub struct Toaster {
+    toasts: BTreeMap<usize, Toast>,
+    next_id: usize,
+}
+
+impl Toaster {
+    fn push(&mut self, kind: ToastKind, message: impl Into<String>) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.toasts.insert(
+            id,
+            Toast {
+                kind,
+                message: message.into(),
+                expires: Utc::now() + Duration::seconds(TOAST_DURATION_SECS),
+            },
+        );
+        id
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) -> usize {
+        self.push(ToastKind::Info, message)
+    }
+
+    pub fn success(&mut self, message: impl Into<String>) -> usize {
+        self.push(ToastKind::Success, message)
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) -> usize {
+        self.push(ToastKind::Error, message)
+    }
+
+    /// Shows a failed request. Cancelled requests are ignored since nobody is waiting for them.
+    pub fn api_error(&mut self, error: &RequestError) -> Option<usize> {
+        let message = match error {
+            RequestError::Cancelled => return None,
+            e if e.is_network_error() => "Unable to reach the server. Please try again.".into(),
+            RequestError::BadRequest(failed) => failed.msg.clone(),
+            e => match e.code() {
+                Some(ErrorCode::RateLimited) => "Too many requests. Please slow down.".into(),
+                Some(ErrorCode::Unavailable) => "The server is busy. Please try again.".into(),
+                Some(ErrorCode::Internal) => "Something went wrong on our end.".into(),
+                _ => e.to_string(),
+            },
+        };
+        Some(self.error(message))
+    }
+
+    pub fn remove(&mut self, id: usize) {
+        self.toasts.remove(&id);
+    }
+
+    pub fn remove_expired(&mut self, now: DateTime<Utc>) {
+        self.toasts.retain(|_, toast| toast.expires > now);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&usize, &Toast)> {
+        self.toasts.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.toasts.is_empty()
+    }
+}
+
+pub static TOASTER: AtomRef<Toaster> = |_| Toaster::default();
+
+pub fn use_toaster(cx: &ScopeState) -> &UseAtomRef<Toaster> {
+    use_atom_ref(cx, TOASTER)
+}
+
+/// Renders the toasts. Place once near the root of the app.
+pub fn ToastRoot(cx: Scope) -> Element {
+    let toaster = use_toaster(cx);
+
+    use_future(cx, (), |_| {
+        to_owned![toaster];
+        async move {
+            loop {
+                gloo_timers::future::TimeoutFuture::new(200).await;
+                // only write when needed, so subscribers aren't re-rendered for nothing
+                let now = Utc::now();
+                if toaster.read().iter().any(|(_, toast)| toast.expires <= now) {
+                    toaster.write().remove_expired(now);
+                }
+            }
+        }
+    });
+
+    let toasts = toaster.read();
+    let toasts = toasts.iter().map(|(&id, toast)| {
+        let class = match toast.kind {
+            ToastKind::Info => "bg-slate-200",
+            ToastKind::Success => "bg-green-200",
+            ToastKind::Error => "bg-red-200",
+        };
+        rsx! {
+            div {
+                key: "{id}",
+                class: "{class} rounded px-4 py-2 shadow cursor-pointer",
+                onclick: move |_| toaster.write().remove(id),
+                "{toast.message}"
+            }
+        }
+    });
+
+    cx.render(rsx! {
+        div {
+            class: "fixed bottom-5 right-5 flex flex-col gap-2 max-w-xs z-50",
+            toasts
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uchat_api::RequestFailed;
+
+    #[test]
+    fn removes_expired_toasts() {
+        let mut toaster = Toaster::default();
+        let first = toaster.info("first");
+        let second = toaster.info("second");
+        assert_ne!(first, second);
+
+        toaster.remove(first);
+        assert_eq!(toaster.iter().count(), 1);
+
+        toaster.remove_expired(Utc::now() + Duration::seconds(TOAST_DURATION_SECS + 1));
+        assert!(toaster.is_empty());
+    }
+
+    #[test]
+    fn shows_server_messages() {
+        let mut toaster = Toaster::default();
+        toaster.api_error(&RequestError::BadRequest(RequestFailed {
+            code: ErrorCode::Conflict,
+            msg: "handle is already taken".into(),
+        }));
+
+        let (_, toast) = toaster.iter().next().unwrap();
+        assert_eq!(toast.kind, ToastKind::Error);
+        assert_eq!(toast.message, "handle is already taken");
+    }
+
+    #[test]
+    fn ignores_cancelled_requests() {
+        let mut toaster = Toaster::default();
+        assert!(toaster.api_error(&RequestError::Cancelled).is_none());
+        assert!(toaster.is_empty());
+    }
+}
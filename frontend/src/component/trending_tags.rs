@@ -0,0 +1,58 @@
+use dioxus::prelude::*;
+use dioxus_router::Link;
+use uchat_api::tag::{GetTrendingTags, GetTrendingTagsOk, TrendingTag};
+
+use crate::fetch_json;
+use crate::page;
+use crate::util::ApiClient;
+
+/// How often trends are refreshed.
+const REFRESH_INTERVAL_MS: u32 = 5 * 60_000;
+
+/// Most used hashtags of the last day.
+pub fn TrendingTags(cx: Scope) -> Element {
+    let api_client = ApiClient::global();
+    let tags = use_ref(cx, Vec::<TrendingTag>::new);
+
+    use_future(cx, (), |_| {
+        to_owned![tags];
+        async move {
+            loop {
+                match fetch_json!(<GetTrendingTagsOk>, api_client, GetTrendingTags) {
+                    Ok(res) => *tags.write() = res.tags,
+                    Err(e) => log::warn!("failed to load trending tags: {e}"),
+                }
+                gloo_timers::future::TimeoutFuture::new(REFRESH_INTERVAL_MS).await;
+            }
+        }
+    });
+
+    let tags = tags.read();
+    if tags.is_empty() {
+        return None;
+    }
+    let items = tags.iter().map(|trend| {
+        let url = page::tag_url(&trend.tag);
+        let posts = if trend.posts == 1 {
+            "1 post".to_string()
+        } else {
+            format!("{} posts", trend.posts)
+        };
+        rsx! {
+            li {
+                key: "{trend.tag}",
+                class: "flex justify-between",
+                Link { class: "text-blue-600", to: "{url}", "#{trend.tag}" }
+                span { class: "text-xs text-gray-500", "{posts}" }
+            }
+        }
+    });
+
+    cx.render(rsx! {
+        section {
+            class: "flex flex-col gap-1",
+            h2 { class: "text-lg", "Trending" }
+            ul { items }
+        }
+    })
+}
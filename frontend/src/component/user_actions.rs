@@ -0,0 +1,157 @@
+use dioxus::prelude::*;
+use uchat_api::block::{BlockUser, BlockUserOk, MuteUser, MuteUserOk};
+use uchat_api::follow::{FollowStatus, FollowUser, FollowUserOk, UnfollowUser, UnfollowUserOk};
+use uchat_api::moderation::ReportTarget;
+use uuid::Uuid;
+
+use crate::component::{use_toaster, ReportButton};
+use crate::state::{use_local_user, LocalUser};
+use crate::util::cancel::use_request_scope;
+use crate::util::ApiClient;
+use crate::{async_handler, fetch_json};
+
+#[derive(Props, PartialEq)]
+pub struct UserActionsProps {
+    user_id: Uuid,
+    handle: String,
+}
+
+/// Buttons to follow, block, mute or report a user. Nothing is shown for the logged in user.
+pub fn UserActions(cx: Scope<UserActionsProps>) -> Element {
+    let api_client = ApiClient::global();
+    let toaster = use_toaster(cx);
+    let local_user = use_local_user(cx);
+    let request_scope = use_request_scope(cx);
+    let cancel = request_scope.token();
+
+    let user_id = cx.props.user_id;
+    let handle = cx.props.handle.clone();
+    let busy = use_state(cx, || false);
+    let blocked = use_state(cx, || false);
+    // only known once the user was followed from here
+    let follow_status = use_state(cx, || None::<FollowStatus>);
+
+    let block = async_handler!(
+        &cx,
+        [api_client, cancel, toaster, user_id, handle, busy, blocked],
+        move |_| async move {
+            busy.set(true);
+            let request = BlockUser { user_id };
+            let response = cancel
+                .run(async { fetch_json!(<BlockUserOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(_) => {
+                    toaster.write().success(format!("Blocked @{handle}"));
+                    blocked.set(true);
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            busy.set(false);
+        }
+    );
+
+    // handlers take ownership of their captures
+    let cancel = request_scope.token();
+    let handle = cx.props.handle.clone();
+    let mute = async_handler!(
+        &cx,
+        [api_client, cancel, toaster, user_id, handle, busy],
+        move |_| async move {
+            busy.set(true);
+            let request = MuteUser { user_id };
+            let response = cancel
+                .run(async { fetch_json!(<MuteUserOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(_) => {
+                    toaster.write().success(format!("Muted @{handle}"));
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            busy.set(false);
+        }
+    );
+
+    let cancel = request_scope.token();
+    let follow = async_handler!(
+        &cx,
+        [api_client, cancel, toaster, user_id, busy, follow_status],
+        move |_| async move {
+            busy.set(true);
+            let request = FollowUser { user_id };
+            let response = cancel
+                .run(async { fetch_json!(<FollowUserOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(res) => {
+                    follow_status.set(Some(res.status));
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            busy.set(false);
+        }
+    );
+
+    let cancel = request_scope.token();
+    let unfollow = async_handler!(
+        &cx,
+        [api_client, cancel, toaster, user_id, busy, follow_status],
+        move |_| async move {
+            busy.set(true);
+            let request = UnfollowUser { user_id };
+            let response = cancel
+                .run(async { fetch_json!(<UnfollowUserOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(_) => {
+                    follow_status.set(None);
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            busy.set(false);
+        }
+    );
+
+    if let LocalUser::LoggedIn(user) = &*local_user.read() {
+        if user.user_id == cx.props.user_id {
+            return None;
+        }
+    }
+
+    if **blocked {
+        return cx.render(rsx! { span { class: "text-sm text-gray-500", "Blocked" } });
+    }
+
+    let following = match **follow_status {
+        None => rsx! { button { class: "btn", disabled: "{busy}", onclick: follow, "Follow" } },
+        Some(status) => {
+            let (label, action) = match status {
+                FollowStatus::Following => ("Following", "Unfollow"),
+                FollowStatus::Requested => ("Requested", "Cancel request"),
+            };
+            rsx! {
+                span { class: "text-sm text-gray-500", "{label}" }
+                button { class: "btn", disabled: "{busy}", onclick: unfollow, "{action}" }
+            }
+        }
+    };
+
+    cx.render(rsx! {
+        div {
+            class: "flex gap-2",
+            following
+            button { class: "btn", disabled: "{busy}", onclick: mute, "Mute" }
+            button { class: "btn", disabled: "{busy}", onclick: block, "Block" }
+            ReportButton { target: ReportTarget::User(cx.props.user_id) }
+        }
+    })
+}
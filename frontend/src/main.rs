@@ -6,10 +6,19 @@
 pub mod util;
 
 pub mod app;
+pub mod component;
+pub mod page;
+pub mod state;
 
 use cfg_if::cfg_if;
 
-pub const ROOT_API_URL: &str = "http://127.0.0.1:8070/";
+/// API base URL set with the `API_URL` environment variable at build time.
+///
+/// Can be overridden when the app is served, see [`util::api_url`].
+pub const DEFAULT_API_URL: &str = match option_env!("API_URL") {
+    Some(url) => url,
+    None => "http://127.0.0.1:8070/",
+};
 
 cfg_if! {
     if #[cfg(feature = "console_log")] {
@@ -24,5 +33,6 @@ cfg_if! {
 
 fn main() {
     init_log();
+    util::ApiClient::init();
     dioxus_web::launch(app::App)
 }
@@ -0,0 +1,38 @@
+pub mod account;
+pub mod blocked;
+pub mod export;
+pub mod home;
+pub mod login;
+pub mod moderation;
+pub mod notifications;
+pub mod post;
+pub mod privacy;
+pub mod register;
+pub mod search;
+pub mod tag;
+
+pub use account::Account;
+pub use blocked::Blocked;
+pub use export::Export;
+pub use home::Home;
+pub use login::Login;
+pub use moderation::Moderation;
+pub use notifications::Notifications;
+pub use post::{post_url, Post};
+pub use privacy::Privacy;
+pub use register::Register;
+pub use search::{search_url, Search};
+pub use tag::{tag_url, Tag};
+
+pub const HOME: &str = "/";
+pub const ACCOUNT_LOGIN: &str = "/account/login";
+pub const ACCOUNT_REGISTER: &str = "/account/register";
+pub const MODERATION: &str = "/moderation";
+pub const NOTIFICATIONS: &str = "/notifications";
+pub const POST: &str = "/post";
+pub const SETTINGS_ACCOUNT: &str = "/settings/account";
+pub const SETTINGS_BLOCKED: &str = "/settings/blocked";
+pub const SETTINGS_EXPORT: &str = "/settings/export";
+pub const SETTINGS_PRIVACY: &str = "/settings/privacy";
+pub const SEARCH: &str = "/search";
+pub const TAG: &str = "/tags";
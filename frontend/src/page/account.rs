@@ -0,0 +1,124 @@
+#![allow(non_snake_case)]
+
+use dioxus::prelude::*;
+use uchat_api::user::{
+    CancelAccountDeletion, CancelAccountDeletionOk, DeleteAccount, DeleteAccountOk,
+};
+
+use crate::component::use_toaster;
+use crate::state::{use_local_user, LocalUser};
+use crate::util::cancel::use_request_scope;
+use crate::util::ApiClient;
+use crate::{async_handler, fetch_json};
+
+/// Deletes the account of the logged in user, or cancels a pending deletion.
+pub fn Account(cx: Scope) -> Element {
+    let api_client = ApiClient::global();
+    let toaster = use_toaster(cx);
+    let local_user = use_local_user(cx);
+    let request_scope = use_request_scope(cx);
+    let cancel = request_scope.token();
+
+    let password = use_state(cx, String::new);
+    let busy = use_state(cx, || false);
+
+    let delete = async_handler!(
+        &cx,
+        [api_client, cancel, toaster, local_user, password, busy],
+        move |_| async move {
+            busy.set(true);
+            let request = DeleteAccount {
+                password: password.current().to_string(),
+            };
+            let response = cancel
+                .run(async { fetch_json!(<DeleteAccountOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(res) => {
+                    password.set(String::new());
+                    if let LocalUser::LoggedIn(user) = &mut *local_user.write() {
+                        user.delete_after = Some(res.delete_after);
+                    }
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            busy.set(false);
+        }
+    );
+
+    // handlers take ownership of their token
+    let cancel = request_scope.token();
+    let keep = async_handler!(
+        &cx,
+        [api_client, cancel, toaster, local_user, busy],
+        move |_| async move {
+            busy.set(true);
+            let response = cancel
+                .run(async {
+                    fetch_json!(<CancelAccountDeletionOk>, api_client, CancelAccountDeletion)
+                })
+                .await;
+            match response {
+                Ok(_) => {
+                    if let LocalUser::LoggedIn(user) = &mut *local_user.write() {
+                        user.delete_after = None;
+                    }
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            busy.set(false);
+        }
+    );
+
+    let delete_after = match &*local_user.read() {
+        LocalUser::LoggedIn(user) => user.delete_after,
+        _ => return None,
+    };
+
+    let content = match delete_after {
+        Some(delete_after) => {
+            let date = delete_after.format("%Y-%m-%d");
+            rsx! {
+                p { "Your account and everything you posted will be deleted on {date}." }
+                button {
+                    class: "btn self-start",
+                    disabled: "{busy}",
+                    onclick: keep,
+                    "Keep my account"
+                }
+            }
+        }
+        None => rsx! {
+            p {
+                "Your account is deleted 30 days after you ask for it. "
+                "You can change your mind until then."
+            }
+            form {
+                class: "flex flex-col gap-3",
+                prevent_default: "onsubmit",
+                onsubmit: delete,
+                input {
+                    class: "input-field",
+                    r#type: "password",
+                    placeholder: "Password",
+                    autocomplete: "current-password",
+                    value: "{password}",
+                    oninput: move |ev| password.set(ev.value.clone()),
+                }
+                button { class: "btn", r#type: "submit", disabled: "{busy}", "Delete my account" }
+            }
+        },
+    };
+
+    cx.render(rsx! {
+        div {
+            class: "max-w-lg mx-auto mt-10 flex flex-col gap-3",
+            h1 { class: "text-2xl", "Delete account" }
+            content
+        }
+    })
+}
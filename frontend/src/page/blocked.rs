@@ -0,0 +1,153 @@
+#![allow(non_snake_case)]
+
+use dioxus::prelude::*;
+use uchat_api::block::{
+    GetBlockedUsers, GetBlockedUsersOk, RestrictedUser, UnblockUser, UnblockUserOk, UnmuteUser,
+    UnmuteUserOk,
+};
+use uuid::Uuid;
+
+use crate::component::use_toaster;
+use crate::util::cancel::use_request_scope;
+use crate::util::ApiClient;
+use crate::{async_handler, fetch_json};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Restriction {
+    Block,
+    Mute,
+}
+
+/// Users the logged in user blocked or muted.
+pub fn Blocked(cx: Scope) -> Element {
+    let api_client = ApiClient::global();
+    let toaster = use_toaster(cx);
+    let cancel = use_request_scope(cx).token();
+
+    let users = use_ref(cx, || None::<GetBlockedUsersOk>);
+
+    use_future(cx, (), |_| {
+        to_owned![api_client, cancel, toaster, users];
+        async move {
+            let response = cancel
+                .run(async { fetch_json!(<GetBlockedUsersOk>, api_client, GetBlockedUsers) })
+                .await;
+            match response {
+                Ok(res) => {
+                    *users.write() = Some(res);
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+        }
+    });
+
+    let users = users.read();
+    let Some(users) = &*users else {
+        return None;
+    };
+    let list_section = |title: &'static str, list: &[RestrictedUser], restriction: Restriction| {
+        let items = list.iter().map(move |user| {
+            rsx! {
+                RestrictedRow {
+                    key: "{user.id}",
+                    user_id: user.id,
+                    handle: user.handle.clone(),
+                    restriction: restriction,
+                }
+            }
+        });
+        rsx! {
+            section {
+                class: "flex flex-col gap-1",
+                h2 { class: "text-lg", "{title}" }
+                list.is_empty().then(|| rsx! { p { class: "text-sm text-gray-500", "Nobody." } })
+                ul { items }
+            }
+        }
+    };
+
+    cx.render(rsx! {
+        div {
+            class: "max-w-lg mx-auto mt-10 flex flex-col gap-3",
+            h1 { class: "text-2xl", "Blocked and muted users" }
+            list_section("Blocked", &users.blocked, Restriction::Block)
+            list_section("Muted", &users.muted, Restriction::Mute)
+        }
+    })
+}
+
+#[derive(Props, PartialEq)]
+struct RestrictedRowProps {
+    user_id: Uuid,
+    handle: String,
+    restriction: Restriction,
+}
+
+fn RestrictedRow(cx: Scope<RestrictedRowProps>) -> Element {
+    let api_client = ApiClient::global();
+    let toaster = use_toaster(cx);
+    let cancel = use_request_scope(cx).token();
+
+    let user_id = cx.props.user_id;
+    let restriction = cx.props.restriction;
+    // the row stays until the page is reloaded, so it can't jump away from the pointer
+    let lifted = use_state(cx, || false);
+    let busy = use_state(cx, || false);
+
+    let lift = async_handler!(
+        &cx,
+        [
+            api_client,
+            cancel,
+            toaster,
+            user_id,
+            restriction,
+            lifted,
+            busy
+        ],
+        move |_| async move {
+            busy.set(true);
+            let response = cancel
+                .run(async {
+                    match restriction {
+                        Restriction::Block => {
+                            fetch_json!(<UnblockUserOk>, api_client, UnblockUser { user_id })
+                                .map(|_| ())
+                        }
+                        Restriction::Mute => {
+                            fetch_json!(<UnmuteUserOk>, api_client, UnmuteUser { user_id })
+                                .map(|_| ())
+                        }
+                    }
+                })
+                .await;
+            match response {
+                Ok(()) => {
+                    lifted.set(true);
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            busy.set(false);
+        }
+    );
+
+    let (action, done) = match restriction {
+        Restriction::Block => ("Unblock", "Unblocked"),
+        Restriction::Mute => ("Unmute", "Unmuted"),
+    };
+
+    cx.render(rsx! {
+        li {
+            class: "p-3 border-b flex justify-between items-center",
+            span { "@{cx.props.handle}" }
+            lifted.then(|| rsx! { span { class: "text-sm text-gray-500", "{done}" } })
+            (!**lifted).then(|| rsx! {
+                button { class: "btn", disabled: "{busy}", onclick: lift, "{action}" }
+            })
+        }
+    })
+}
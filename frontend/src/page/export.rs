@@ -0,0 +1,120 @@
+#![allow(non_snake_case)]
+
+use dioxus::prelude::*;
+use uchat_api::export::{
+    DataExport, GetDataExport, GetDataExportOk, RequestDataExport, RequestDataExportOk,
+};
+
+use crate::component::use_toaster;
+use crate::util::api_url::api_url;
+use crate::util::cancel::use_request_scope;
+use crate::util::ApiClient;
+use crate::{async_handler, fetch_json};
+
+/// Archive of everything stored about the logged in user.
+pub fn Export(cx: Scope) -> Element {
+    let api_client = ApiClient::global();
+    let toaster = use_toaster(cx);
+    let request_scope = use_request_scope(cx);
+    let cancel = request_scope.token();
+
+    let export = use_state(cx, || None::<DataExport>);
+    let loaded = use_state(cx, || false);
+    let busy = use_state(cx, || false);
+    // bumped to check whether the archive is ready
+    let checks = use_state(cx, || 0_u32);
+
+    use_future(cx, (checks.get(),), |_| {
+        to_owned![api_client, cancel, toaster, export, loaded];
+        async move {
+            let response = cancel
+                .run(async { fetch_json!(<GetDataExportOk>, api_client, GetDataExport) })
+                .await;
+            match response {
+                Ok(res) => {
+                    export.set(res.export);
+                    loaded.set(true);
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+        }
+    });
+
+    // handlers take ownership of their token
+    let cancel = request_scope.token();
+    let request = async_handler!(
+        &cx,
+        [api_client, cancel, toaster, export, busy],
+        move |_| async move {
+            busy.set(true);
+            let response = cancel
+                .run(async { fetch_json!(<RequestDataExportOk>, api_client, RequestDataExport) })
+                .await;
+            match response {
+                Ok(res) => export.set(Some(res.export)),
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            busy.set(false);
+        }
+    );
+
+    if !**loaded {
+        return None;
+    }
+
+    let request_button = rsx! {
+        button {
+            class: "btn self-start",
+            disabled: "{busy}",
+            onclick: request,
+            "Request an archive"
+        }
+    };
+    let status = match export.get() {
+        None => request_button,
+        Some(export) if export.failed => rsx! {
+            p { "Preparing your last archive failed. Please try again." }
+            request_button
+        },
+        Some(DataExport {
+            download_url: Some(url),
+            download_expires: Some(expires),
+            ..
+        }) => {
+            let href = api_url()
+                .and_then(|api_url| api_url.join(url.trim_start_matches('/')))
+                .map(|url| url.to_string())
+                .unwrap_or_default();
+            let expires = expires.format("%H:%M");
+            rsx! {
+                a { class: "text-blue-600", href: "{href}", "Download your archive" }
+                p { class: "text-sm text-gray-500", "The link works until {expires}." }
+                request_button
+            }
+        }
+        Some(_) => rsx! {
+            p { "Your archive is being prepared." }
+            button {
+                class: "btn self-start",
+                onclick: move |_| checks.modify(|n| n + 1),
+                "Check again"
+            }
+        },
+    };
+
+    cx.render(rsx! {
+        div {
+            class: "max-w-lg mx-auto mt-10 flex flex-col gap-3",
+            h1 { class: "text-2xl", "Download your data" }
+            p {
+                "The archive has your profile, posts, direct messages, reactions, bookmarks, "
+                "poll votes, follows and sessions. You can request one archive per day."
+            }
+            status
+        }
+    })
+}
@@ -0,0 +1,37 @@
+#![allow(non_snake_case)]
+
+use dioxus::prelude::*;
+use dioxus_router::Link;
+use uchat_api::moderation::Role;
+
+use crate::component::TrendingTags;
+use crate::page;
+use crate::state::{use_local_user, LocalUser};
+
+pub fn Home(cx: Scope) -> Element {
+    let local_user = use_local_user(cx);
+
+    let (name, role) = match &*local_user.read() {
+        LocalUser::LoggedIn(user) => (
+            user.display_name
+                .clone()
+                .unwrap_or_else(|| user.handle.clone()),
+            user.role,
+        ),
+        _ => return None,
+    };
+
+    cx.render(rsx! {
+        div {
+            class: "max-w-sm mx-auto mt-10 flex flex-col gap-3",
+            h1 { class: "text-2xl", "Welcome, {name}" }
+            Link { to: page::SEARCH, "Search posts and people" }
+            Link { to: page::SETTINGS_BLOCKED, "Blocked and muted users" }
+            Link { to: page::SETTINGS_PRIVACY, "Privacy and follow requests" }
+            Link { to: page::SETTINGS_EXPORT, "Download your data" }
+            Link { to: page::SETTINGS_ACCOUNT, "Delete account" }
+            (role >= Role::Moderator).then(|| rsx! { Link { to: page::MODERATION, "Moderation" } })
+            TrendingTags {}
+        }
+    })
+}
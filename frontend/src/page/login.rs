@@ -0,0 +1,135 @@
+#![allow(non_snake_case)]
+
+use dioxus::prelude::*;
+use dioxus_router::{use_router, Link};
+use uchat_api::user::{Login as LoginRequest, LoginMfa, LoginOk, SessionInfo};
+use uuid::Uuid;
+
+use crate::component::use_toaster;
+use crate::page;
+use crate::state::{start_session, use_local_user};
+use crate::util::cancel::use_request_scope;
+use crate::util::ApiClient;
+use crate::{async_handler, fetch_json};
+
+pub fn Login(cx: Scope) -> Element {
+    let api_client = ApiClient::global();
+    let router = use_router(cx);
+    let local_user = use_local_user(cx);
+    let toaster = use_toaster(cx);
+    let cancel = use_request_scope(cx).token();
+
+    let handle = use_state(cx, String::new);
+    let password = use_state(cx, String::new);
+    // set once the password was accepted and a second factor is required
+    let challenge_id = use_state(cx, || None::<Uuid>);
+    let code = use_state(cx, String::new);
+
+    let form_onsubmit = async_handler!(
+        &cx,
+        [
+            api_client,
+            cancel,
+            router,
+            local_user,
+            toaster,
+            handle,
+            password,
+            challenge_id,
+            code
+        ],
+        move |_| async move {
+            let response = match *challenge_id.current() {
+                None => {
+                    let request = LoginRequest {
+                        handle: handle.current().to_string(),
+                        password: password.current().to_string(),
+                    };
+                    let response = cancel
+                        .run(async { fetch_json!(<LoginOk>, api_client, request) })
+                        .await;
+                    match response {
+                        Ok(LoginOk::Session(session)) => Ok(Some(session)),
+                        Ok(LoginOk::MfaRequired {
+                            challenge_id: id, ..
+                        }) => {
+                            challenge_id.set(Some(id));
+                            Ok(None)
+                        }
+                        Err(e) => Err(e),
+                    }
+                }
+                Some(id) => {
+                    let request = LoginMfa {
+                        challenge_id: id,
+                        code: code.current().to_string(),
+                    };
+                    cancel
+                        .run(async { fetch_json!(<SessionInfo>, api_client, request) })
+                        .await
+                        .map(Some)
+                }
+            };
+
+            match response {
+                Ok(Some(session)) => {
+                    start_session(&local_user, session);
+                    router.navigate_to(page::HOME);
+                }
+                Ok(None) => (),
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+        }
+    );
+
+    let fields = if challenge_id.is_none() {
+        rsx! {
+            input {
+                class: "input-field",
+                r#type: "text",
+                placeholder: "Handle",
+                autocomplete: "username",
+                value: "{handle}",
+                oninput: move |ev| handle.set(ev.value.clone()),
+            }
+            input {
+                class: "input-field",
+                r#type: "password",
+                placeholder: "Password",
+                autocomplete: "current-password",
+                value: "{password}",
+                oninput: move |ev| password.set(ev.value.clone()),
+            }
+        }
+    } else {
+        rsx! {
+            input {
+                class: "input-field",
+                r#type: "text",
+                placeholder: "Authenticator or recovery code",
+                autocomplete: "one-time-code",
+                value: "{code}",
+                oninput: move |ev| code.set(ev.value.clone()),
+            }
+        }
+    };
+
+    cx.render(rsx! {
+        form {
+            class: "flex flex-col gap-5 max-w-sm mx-auto mt-10",
+            prevent_default: "onsubmit",
+            onsubmit: form_onsubmit,
+
+            h1 { class: "text-2xl", "Log in" }
+            fields
+            button {
+                class: "btn",
+                r#type: "submit",
+                "Log in"
+            }
+            Link { to: page::ACCOUNT_REGISTER, "Create an account" }
+        }
+    })
+}
@@ -0,0 +1,291 @@
+#![allow(non_snake_case)]
+
+use dioxus::prelude::*;
+use uchat_api::moderation::{
+    AuditAction, AuditEntry, GetAuditLog, GetAuditLogOk, GetReports, GetReportsOk, HidePost,
+    HidePostOk, Report, ReportCursor, ReportReason, ResolveReport, ResolveReportOk, SuspendUser,
+    SuspendUserOk,
+};
+
+use crate::component::use_toaster;
+use crate::util::cancel::use_request_scope;
+use crate::util::ApiClient;
+use crate::{async_handler, fetch_json};
+
+/// Open reports, oldest first, and the latest moderation actions. The server only answers
+/// moderators.
+pub fn Moderation(cx: Scope) -> Element {
+    let api_client = ApiClient::global();
+    let toaster = use_toaster(cx);
+    let cancel = use_request_scope(cx).token();
+
+    let reports = use_ref(cx, Vec::<Report>::new);
+    // `None` once the last page was loaded
+    let next = use_state(cx, || None::<ReportCursor>);
+    let audit_log = use_ref(cx, Vec::<AuditEntry>::new);
+    let loading = use_state(cx, || true);
+
+    use_future(cx, (), |_| {
+        to_owned![api_client, cancel, toaster, reports, next, audit_log, loading];
+        async move {
+            let request = GetReports::default();
+            let response = cancel
+                .run(async { fetch_json!(<GetReportsOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(page) => {
+                    *reports.write() = page.reports;
+                    next.set(page.next);
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            loading.set(false);
+
+            let request = GetAuditLog::default();
+            let response = cancel
+                .run(async { fetch_json!(<GetAuditLogOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(page) => *audit_log.write() = page.entries,
+                Err(e) => log::warn!("failed to load audit log: {e}"),
+            }
+        }
+    });
+
+    let load_more = async_handler!(
+        &cx,
+        [api_client, cancel, toaster, reports, next, loading],
+        move |_| async move {
+            let Some(after) = *next.current() else {
+                return;
+            };
+            loading.set(true);
+            let request = GetReports {
+                after: Some(after),
+                ..Default::default()
+            };
+            let response = cancel
+                .run(async { fetch_json!(<GetReportsOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(page) => {
+                    reports.write().extend(page.reports);
+                    next.set(page.next);
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            loading.set(false);
+        }
+    );
+
+    let reports = reports.read();
+    let items = reports.iter().map(|report| {
+        rsx! {
+            ReportItem { key: "{report.id}", report: report.clone() }
+        }
+    });
+    let is_empty = reports.is_empty();
+
+    let audit_log = audit_log.read();
+    let entries = audit_log.iter().map(|entry| {
+        let text = describe(entry);
+        let time = entry.created_at.format("%Y-%m-%d %H:%M");
+        rsx! {
+            li {
+                key: "{entry.id}",
+                class: "p-2 border-b text-sm",
+                p { "{text}" }
+                p { class: "text-xs text-gray-500", "{time}" }
+            }
+        }
+    });
+
+    cx.render(rsx! {
+        div {
+            class: "max-w-lg mx-auto mt-10 flex flex-col gap-3",
+            h1 { class: "text-2xl", "Moderation" }
+            (is_empty && !**loading).then(|| rsx! { p { "No open reports." } })
+            ul { items }
+            next.is_some().then(|| rsx! {
+                button {
+                    class: "btn",
+                    disabled: "{loading}",
+                    onclick: load_more,
+                    "Load more"
+                }
+            })
+            h2 { class: "text-lg mt-6", "Recent actions" }
+            ul { entries }
+        }
+    })
+}
+
+#[derive(Props, PartialEq)]
+struct ReportItemProps {
+    report: Report,
+}
+
+/// Open report with the actions moderators can take on it. The report stays in the list once it
+/// was closed, so the list doesn't jump while the queue is worked through.
+fn ReportItem(cx: Scope<ReportItemProps>) -> Element {
+    let api_client = ApiClient::global();
+    let toaster = use_toaster(cx);
+    let request_scope = use_request_scope(cx);
+    let cancel = request_scope.token();
+
+    let closed = use_state(cx, || None::<&'static str>);
+    let busy = use_state(cx, || false);
+    let suspension_reason = use_state(cx, String::new);
+    let report_id = cx.props.report.id;
+    let user_id = cx.props.report.user_id;
+    let post_id = cx.props.report.post_id;
+
+    // only captures references, so both the dismiss and resolve buttons can use it
+    let resolve = move |dismiss: bool| {
+        let cancel = request_scope.token();
+        to_owned![toaster, closed, busy];
+        cx.spawn(async move {
+            busy.set(true);
+            let request = ResolveReport { report_id, dismiss };
+            let response = cancel
+                .run(async { fetch_json!(<ResolveReportOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(_) => closed.set(Some(if dismiss { "Dismissed" } else { "Resolved" })),
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            busy.set(false);
+        });
+    };
+
+    let hide_post = async_handler!(
+        &cx,
+        [api_client, cancel, toaster, closed, busy, post_id],
+        move |_| async move {
+            let Some(post_id) = post_id else {
+                return;
+            };
+            busy.set(true);
+            let request = HidePost {
+                post_id,
+                report_id: Some(report_id),
+            };
+            let response = cancel
+                .run(async { fetch_json!(<HidePostOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(_) => closed.set(Some("Post hidden")),
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            busy.set(false);
+        }
+    );
+
+    // handlers take ownership of their token
+    let cancel = request_scope.token();
+    let suspend = async_handler!(
+        &cx,
+        [api_client, cancel, toaster, closed, busy, suspension_reason],
+        move |_| async move {
+            let reason = suspension_reason.current().trim().to_string();
+            if let Err(msg) = uchat_api::moderation::validate_suspension_reason(&reason) {
+                toaster.write().error(msg);
+                return;
+            }
+            busy.set(true);
+            let request = SuspendUser {
+                user_id,
+                reason,
+                report_id: Some(report_id),
+            };
+            let response = cancel
+                .run(async { fetch_json!(<SuspendUserOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(_) => closed.set(Some("User suspended")),
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            busy.set(false);
+        }
+    );
+
+    let report = &cx.props.report;
+    let time = report.created_at.format("%Y-%m-%d %H:%M");
+    let reason = reason_label(report.reason);
+    let actions = match **closed {
+        Some(outcome) => rsx! { p { class: "text-sm font-semibold", "{outcome}" } },
+        None => rsx! {
+            div {
+                class: "flex flex-wrap gap-2",
+                button { class: "btn", disabled: "{busy}", onclick: move |_| resolve(true), "Dismiss" }
+                button { class: "btn", disabled: "{busy}", onclick: move |_| resolve(false), "Resolve" }
+                post_id.is_some().then(|| rsx! {
+                    button { class: "btn", disabled: "{busy}", onclick: hide_post, "Hide post" }
+                })
+            }
+            form {
+                class: "flex gap-2",
+                prevent_default: "onsubmit",
+                onsubmit: suspend,
+                input {
+                    class: "input-field",
+                    placeholder: "Reason for suspending @{report.user_handle}",
+                    value: "{suspension_reason}",
+                    oninput: move |ev| suspension_reason.set(ev.value.clone()),
+                }
+                button { class: "btn", r#type: "submit", disabled: "{busy}", "Suspend" }
+            }
+        },
+    };
+
+    cx.render(rsx! {
+        li {
+            class: "p-3 border-b flex flex-col gap-2",
+            p {
+                class: "text-sm text-gray-500",
+                "@{report.reporter_handle} reported @{report.user_handle} for {reason} · {time}"
+            }
+            report.post_text.as_ref().map(|text| rsx! { blockquote { class: "border-l-4 pl-2", "{text}" } })
+            (!report.details.is_empty()).then(|| rsx! { p { "{report.details}" } })
+            actions
+        }
+    })
+}
+
+fn reason_label(reason: ReportReason) -> &'static str {
+    match reason {
+        ReportReason::Spam => "spam",
+        ReportReason::Harassment => "harassment",
+        ReportReason::Hate => "hate",
+        ReportReason::Violence => "violence",
+        ReportReason::Other => "something else",
+    }
+}
+
+fn describe(entry: &AuditEntry) -> String {
+    let actor = match &entry.actor_handle {
+        Some(handle) => format!("@{handle}"),
+        None => "The command line".to_string(),
+    };
+    let action = match entry.action {
+        AuditAction::ResolveReport => "resolved a report",
+        AuditAction::DismissReport => "dismissed a report",
+        AuditAction::HidePost => "hid a post",
+        AuditAction::UnhidePost => "unhid a post",
+        AuditAction::SuspendUser => "suspended a user",
+        AuditAction::UnsuspendUser => "lifted a suspension",
+        AuditAction::SetRole => "changed a role",
+        AuditAction::Unknown => "did something",
+    };
+    format!("{actor} {action}")
+}
@@ -0,0 +1,163 @@
+#![allow(non_snake_case)]
+
+use dioxus::prelude::*;
+use uchat_api::notification::{
+    GetNotifications, GetNotificationsOk, MarkNotificationsRead, MarkNotificationsReadOk,
+    Notification, NotificationCursor, NotificationKind,
+};
+
+use crate::component::use_toaster;
+use crate::state::{run_when_online, use_connectivity, use_unread_notifications};
+use crate::util::cancel::use_request_scope;
+use crate::util::ApiClient;
+use crate::{async_handler, fetch_json};
+
+pub fn Notifications(cx: Scope) -> Element {
+    let api_client = ApiClient::global();
+    let toaster = use_toaster(cx);
+    let unread = use_unread_notifications(cx);
+    let connectivity = use_connectivity(cx);
+    let request_scope = use_request_scope(cx);
+    let cancel = request_scope.token();
+
+    let notifications = use_ref(cx, Vec::<Notification>::new);
+    // `None` once the last page was loaded
+    let next = use_state(cx, || None::<NotificationCursor>);
+    let loading = use_state(cx, || true);
+
+    use_future(cx, (), |_| {
+        to_owned![
+            api_client,
+            cancel,
+            toaster,
+            unread,
+            notifications,
+            next,
+            loading
+        ];
+        async move {
+            let request = GetNotifications { after: None };
+            let response = cancel
+                .run(async { fetch_json!(<GetNotificationsOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(page) => {
+                    *unread.write() = Some(page.unread);
+                    *notifications.write() = page.notifications;
+                    next.set(page.next);
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            loading.set(false);
+        }
+    });
+
+    let load_more = async_handler!(
+        &cx,
+        [api_client, cancel, toaster, notifications, next, loading],
+        move |_| async move {
+            let Some(after) = *next.current() else {
+                return;
+            };
+            loading.set(true);
+            let request = GetNotifications { after: Some(after) };
+            let response = cancel
+                .run(async { fetch_json!(<GetNotificationsOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(page) => {
+                    notifications.write().extend(page.notifications);
+                    next.set(page.next);
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            loading.set(false);
+        }
+    );
+
+    // shown as read right away. The request is sent once the app is online and finishes even if
+    // the page is left in the meantime.
+    let mark_all_read = move |_| {
+        for notification in notifications.write().iter_mut() {
+            notification.read = true;
+        }
+        *unread.write() = Some(0);
+
+        to_owned![api_client, toaster, unread];
+        run_when_online(connectivity, move || {
+            wasm_bindgen_futures::spawn_local(async move {
+                let request = MarkNotificationsRead { ids: None };
+                match fetch_json!(<MarkNotificationsReadOk>, api_client, request) {
+                    Ok(res) => *unread.write() = Some(res.unread),
+                    Err(e) => {
+                        toaster.write().api_error(&e);
+                    }
+                }
+            })
+        });
+    };
+
+    let notifications = notifications.read();
+    let items = notifications.iter().map(|notification| {
+        let class = if notification.read {
+            "p-3 border-b"
+        } else {
+            "p-3 border-b bg-blue-50 font-semibold"
+        };
+        let text = describe(notification);
+        let time = notification.created_at.format("%Y-%m-%d %H:%M");
+        rsx! {
+            li {
+                key: "{notification.id}",
+                class: "{class}",
+                p { "{text}" }
+                p { class: "text-xs text-gray-500", "{time}" }
+            }
+        }
+    });
+    let is_empty = notifications.is_empty();
+    let all_read = unread.read().unwrap_or(0) == 0;
+
+    cx.render(rsx! {
+        div {
+            class: "max-w-lg mx-auto mt-10 flex flex-col gap-3",
+            div {
+                class: "flex justify-between items-center",
+                h1 { class: "text-2xl", "Notifications" }
+                button {
+                    class: "btn",
+                    disabled: "{all_read}",
+                    onclick: mark_all_read,
+                    "Mark all as read"
+                }
+            }
+            (is_empty && !**loading).then(|| rsx! { p { "Nothing here yet." } })
+            ul { items }
+            next.is_some().then(|| rsx! {
+                button {
+                    class: "btn",
+                    disabled: "{loading}",
+                    onclick: load_more,
+                    "Load more"
+                }
+            })
+        }
+    })
+}
+
+fn describe(notification: &Notification) -> String {
+    let actor = &notification.actor_handle;
+    match notification.kind {
+        NotificationKind::Follow => format!("@{actor} followed you"),
+        NotificationKind::Reply => format!("@{actor} replied to your post"),
+        NotificationKind::Boost => format!("@{actor} boosted your post"),
+        NotificationKind::Reaction => format!("@{actor} reacted to your post"),
+        NotificationKind::PollVote => format!("@{actor} voted in your poll"),
+        NotificationKind::Mention => format!("@{actor} mentioned you"),
+        NotificationKind::FollowRequest => format!("@{actor} asked to follow you"),
+    }
+}
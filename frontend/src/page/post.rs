@@ -0,0 +1,188 @@
+#![allow(non_snake_case)]
+
+use chrono::Utc;
+use dioxus::prelude::*;
+use dioxus_router::use_route;
+use uchat_api::moderation::ReportTarget;
+use uchat_api::post::{
+    DeletePost, DeletePostOk, GetPost, GetPostOk, GetPostRevisions, GetPostRevisionsOk,
+    PostPreview, PostRevision,
+};
+use uchat_api::ErrorCode;
+use url::form_urlencoded;
+use uuid::Uuid;
+
+use crate::component::{use_toaster, PostText, ReportButton};
+use crate::fetch_json;
+use crate::page;
+use crate::state::{use_local_user, LocalUser};
+use crate::util::cancel::use_request_scope;
+use crate::util::ApiClient;
+
+/// Link to a single post, which also works for unlisted posts.
+pub fn post_url(post_id: Uuid) -> String {
+    let params = form_urlencoded::Serializer::new(String::new())
+        .append_pair("id", &post_id.to_string())
+        .finish();
+    format!("{}?{params}", page::POST)
+}
+
+/// The post with the id in the URL.
+pub fn Post(cx: Scope) -> Element {
+    let api_client = ApiClient::global();
+    let route = use_route(cx);
+    let toaster = use_toaster(cx);
+    let local_user = use_local_user(cx);
+    let request_scope = use_request_scope(cx);
+    let cancel = request_scope.token();
+
+    let post_id = route
+        .query_param("id")
+        .and_then(|id| id.parse::<Uuid>().ok());
+
+    let post = use_state(cx, || None::<PostPreview>);
+    let missing = use_state(cx, || false);
+    let revisions = use_state(cx, || None::<Vec<PostRevision>>);
+
+    use_future(cx, (&post_id,), |(post_id,)| {
+        to_owned![api_client, cancel, toaster, post, missing, revisions];
+        async move {
+            post.set(None);
+            revisions.set(None);
+            let Some(post_id) = post_id else {
+                missing.set(true);
+                return;
+            };
+            let response = cancel
+                .run(async { fetch_json!(<GetPostOk>, api_client, GetPost { post_id }) })
+                .await;
+            match response {
+                Ok(res) => {
+                    missing.set(false);
+                    post.set(Some(res.post));
+                }
+                Err(e) if e.code() == Some(ErrorCode::NotFound) => missing.set(true),
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+        }
+    });
+
+    let delete_post = move |post_id: Uuid| {
+        let cancel = request_scope.token();
+        to_owned![toaster, post, revisions];
+        cx.spawn(async move {
+            let response = cancel
+                .run(async { fetch_json!(<DeletePostOk>, api_client, DeletePost { post_id }) })
+                .await;
+            match response {
+                Ok(_) => {
+                    // deleted posts have no text or earlier versions left
+                    post.with_mut(|post| {
+                        if let Some(post) = post {
+                            post.text.clear();
+                            post.deleted_at = Some(Utc::now());
+                        }
+                    });
+                    revisions.set(None);
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+        });
+    };
+
+    if **missing {
+        return cx.render(rsx! {
+            p { class: "max-w-lg mx-auto mt-10", "This post doesn't exist, or you can't see it." }
+        });
+    }
+    let Some(post) = post.get() else {
+        return None;
+    };
+    let time = post.time_posted.format("%Y-%m-%d %H:%M");
+
+    let show_history = move |post_id: Uuid| {
+        let cancel = request_scope.token();
+        to_owned![toaster, revisions];
+        cx.spawn(async move {
+            let request = GetPostRevisions { post_id };
+            let response = cancel
+                .run(async { fetch_json!(<GetPostRevisionsOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(res) => revisions.set(Some(res.revisions)),
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+        });
+    };
+    let is_author = match &*local_user.read() {
+        LocalUser::LoggedIn(user) => user.user_id == post.author_id,
+        _ => false,
+    };
+    let body = match post.deleted_at {
+        Some(_) => rsx! { p { class: "italic text-gray-500", "This post was deleted." } },
+        None => {
+            let post_id = post.id;
+            rsx! {
+                PostText { text: post.text.clone() }
+                div {
+                    class: "flex gap-2",
+                    ReportButton { target: ReportTarget::Post(post_id) }
+                    is_author.then(|| rsx! {
+                        button { class: "btn", onclick: move |_| delete_post(post_id), "Delete" }
+                    })
+                }
+            }
+        }
+    };
+    let history = match (post.edited_at, revisions.get()) {
+        _ if post.deleted_at.is_some() => None,
+        (None, _) => None,
+        (Some(_), None) => {
+            let post_id = post.id;
+            Some(rsx! {
+                button {
+                    class: "btn self-start",
+                    onclick: move |_| show_history(post_id),
+                    "Show earlier versions"
+                }
+            })
+        }
+        (Some(_), Some(revisions)) => {
+            let items = revisions.iter().map(|revision| {
+                let time = revision.revised_at.format("%Y-%m-%d %H:%M");
+                rsx! {
+                    li {
+                        class: "p-3 border-b",
+                        p { class: "text-sm text-gray-500", "Replaced {time}" }
+                        PostText { text: revision.text.clone() }
+                    }
+                }
+            });
+            Some(rsx! {
+                section {
+                    h2 { class: "text-lg", "Earlier versions" }
+                    ul { items }
+                }
+            })
+        }
+    };
+
+    cx.render(rsx! {
+        div {
+            class: "max-w-lg mx-auto mt-10 flex flex-col gap-3",
+            p {
+                class: "text-sm text-gray-500",
+                "@{post.author_handle} · {time}"
+                post.edited_at.is_some().then(|| rsx! { span { " (edited)" } })
+            }
+            body
+            history
+        }
+    })
+}
@@ -0,0 +1,170 @@
+#![allow(non_snake_case)]
+
+use dioxus::prelude::*;
+use uchat_api::follow::{
+    AnswerFollowRequest, AnswerFollowRequestOk, FollowRequest, GetFollowRequests,
+    GetFollowRequestsOk, SetPrivate, SetPrivateOk,
+};
+use uuid::Uuid;
+
+use crate::component::use_toaster;
+use crate::state::{use_local_user, LocalUser};
+use crate::util::cancel::use_request_scope;
+use crate::util::ApiClient;
+use crate::{async_handler, fetch_json};
+
+/// Whether the posts of the logged in user are private, and the follow requests waiting for
+/// their approval.
+pub fn Privacy(cx: Scope) -> Element {
+    let api_client = ApiClient::global();
+    let toaster = use_toaster(cx);
+    let local_user = use_local_user(cx);
+    let request_scope = use_request_scope(cx);
+    let cancel = request_scope.token();
+
+    let requests = use_ref(cx, Vec::<FollowRequest>::new);
+    let busy = use_state(cx, || false);
+
+    use_future(cx, (), |_| {
+        to_owned![api_client, cancel, toaster, requests];
+        async move {
+            let response = cancel
+                .run(async { fetch_json!(<GetFollowRequestsOk>, api_client, GetFollowRequests) })
+                .await;
+            match response {
+                Ok(res) => {
+                    *requests.write() = res.requests;
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+        }
+    });
+
+    // handlers take ownership of their token
+    let cancel = request_scope.token();
+    let toggle = async_handler!(
+        &cx,
+        [api_client, cancel, toaster, local_user, requests, busy],
+        move |_| async move {
+            let private = match &*local_user.read() {
+                LocalUser::LoggedIn(user) => !user.private,
+                _ => return,
+            };
+            busy.set(true);
+            let response = cancel
+                .run(async { fetch_json!(<SetPrivateOk>, api_client, SetPrivate { private }) })
+                .await;
+            match response {
+                Ok(_) => {
+                    if let LocalUser::LoggedIn(user) = &mut *local_user.write() {
+                        user.private = private;
+                    }
+                    // the server approved every pending request
+                    if !private {
+                        requests.write().clear();
+                    }
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            busy.set(false);
+        }
+    );
+
+    let private = match &*local_user.read() {
+        LocalUser::LoggedIn(user) => user.private,
+        _ => return None,
+    };
+    let requests = requests.read();
+    let items = requests.iter().map(|request| {
+        rsx! {
+            RequestRow {
+                key: "{request.user_id}",
+                user_id: request.user_id,
+                handle: request.handle.clone(),
+            }
+        }
+    });
+
+    cx.render(rsx! {
+        div {
+            class: "max-w-lg mx-auto mt-10 flex flex-col gap-3",
+            h1 { class: "text-2xl", "Privacy" }
+            label {
+                class: "flex gap-2 items-center",
+                input {
+                    r#type: "checkbox",
+                    checked: "{private}",
+                    disabled: "{busy}",
+                    onclick: toggle,
+                }
+                "Only approved followers can see my posts"
+            }
+            section {
+                class: "flex flex-col gap-1",
+                h2 { class: "text-lg", "Follow requests" }
+                requests.is_empty().then(|| rsx! { p { class: "text-sm text-gray-500", "Nobody." } })
+                ul { items }
+            }
+        }
+    })
+}
+
+#[derive(Props, PartialEq)]
+struct RequestRowProps {
+    user_id: Uuid,
+    handle: String,
+}
+
+fn RequestRow(cx: Scope<RequestRowProps>) -> Element {
+    let api_client = ApiClient::global();
+    let toaster = use_toaster(cx);
+    let request_scope = use_request_scope(cx);
+
+    let user_id = cx.props.user_id;
+    // the row stays until the page is reloaded, so it can't jump away from the pointer
+    let answered = use_state(cx, || None::<&'static str>);
+    let busy = use_state(cx, || false);
+
+    // only captures references, so both the approve and deny buttons can use it
+    let answer = move |approve: bool| {
+        let cancel = request_scope.token();
+        to_owned![toaster, answered, busy];
+        cx.spawn(async move {
+            busy.set(true);
+            let request = AnswerFollowRequest { user_id, approve };
+            let response = cancel
+                .run(async { fetch_json!(<AnswerFollowRequestOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(_) => answered.set(Some(if approve { "Approved" } else { "Denied" })),
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            busy.set(false);
+        });
+    };
+
+    let actions = match **answered {
+        Some(outcome) => rsx! { span { class: "text-sm text-gray-500", "{outcome}" } },
+        None => rsx! {
+            div {
+                class: "flex gap-2",
+                button { class: "btn", disabled: "{busy}", onclick: move |_| answer(true), "Approve" }
+                button { class: "btn", disabled: "{busy}", onclick: move |_| answer(false), "Deny" }
+            }
+        },
+    };
+
+    cx.render(rsx! {
+        li {
+            class: "p-3 border-b flex justify-between items-center",
+            span { "@{cx.props.handle}" }
+            actions
+        }
+    })
+}
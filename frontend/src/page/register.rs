@@ -0,0 +1,113 @@
+#![allow(non_snake_case)]
+
+use dioxus::prelude::*;
+use dioxus_router::{use_router, Link};
+use uchat_api::user::{validate_handle, validate_password, CreateUser, SessionInfo};
+
+use crate::component::use_toaster;
+use crate::page;
+use crate::state::{start_session, use_local_user};
+use crate::util::cancel::use_request_scope;
+use crate::util::ApiClient;
+use crate::{async_handler, fetch_json};
+
+pub fn Register(cx: Scope) -> Element {
+    let api_client = ApiClient::global();
+    let router = use_router(cx);
+    let local_user = use_local_user(cx);
+    let toaster = use_toaster(cx);
+    let cancel = use_request_scope(cx).token();
+
+    let handle = use_state(cx, String::new);
+    let password = use_state(cx, String::new);
+    let password_confirmation = use_state(cx, String::new);
+    let error = use_state(cx, || None::<String>);
+
+    let form_onsubmit = async_handler!(
+        &cx,
+        [
+            api_client,
+            cancel,
+            router,
+            local_user,
+            toaster,
+            handle,
+            password,
+            password_confirmation,
+            error
+        ],
+        move |_| async move {
+            let request = CreateUser {
+                handle: handle.current().to_string(),
+                password: password.current().to_string(),
+            };
+
+            let invalid = validate_handle(&request.handle)
+                .and_then(|_| validate_password(&request.password))
+                .err()
+                .or_else(|| {
+                    (*password_confirmation.current() != request.password)
+                        .then(|| "passwords don't match".to_string())
+                });
+            if invalid.is_some() {
+                error.set(invalid);
+                return;
+            }
+
+            let response = cancel
+                .run(async { fetch_json!(<SessionInfo>, api_client, request) })
+                .await;
+            match response {
+                Ok(session) => {
+                    error.set(None);
+                    start_session(&local_user, session);
+                    router.navigate_to(page::HOME);
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+        }
+    );
+
+    cx.render(rsx! {
+        form {
+            class: "flex flex-col gap-5 max-w-sm mx-auto mt-10",
+            prevent_default: "onsubmit",
+            onsubmit: form_onsubmit,
+
+            h1 { class: "text-2xl", "Create an account" }
+            input {
+                class: "input-field",
+                r#type: "text",
+                placeholder: "Handle",
+                autocomplete: "username",
+                value: "{handle}",
+                oninput: move |ev| handle.set(ev.value.clone()),
+            }
+            input {
+                class: "input-field",
+                r#type: "password",
+                placeholder: "Password",
+                autocomplete: "new-password",
+                value: "{password}",
+                oninput: move |ev| password.set(ev.value.clone()),
+            }
+            input {
+                class: "input-field",
+                r#type: "password",
+                placeholder: "Confirm password",
+                autocomplete: "new-password",
+                value: "{password_confirmation}",
+                oninput: move |ev| password_confirmation.set(ev.value.clone()),
+            }
+            error.as_ref().map(|e| rsx! { p { class: "text-red-600", "{e}" } })
+            button {
+                class: "btn",
+                r#type: "submit",
+                "Sign up"
+            }
+            Link { to: page::ACCOUNT_LOGIN, "Already have an account? Log in" }
+        }
+    })
+}
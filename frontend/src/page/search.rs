@@ -0,0 +1,256 @@
+#![allow(non_snake_case)]
+
+use dioxus::prelude::*;
+use dioxus_router::{use_route, use_router, Link};
+use uchat_api::moderation::ReportTarget;
+use uchat_api::search::{Search as SearchRequest, SearchHits, SearchKind, SearchOk};
+use url::form_urlencoded;
+
+use crate::component::{use_toaster, PostText, ReportButton, UserActions};
+use crate::page;
+use crate::util::cancel::use_request_scope;
+use crate::util::ApiClient;
+use crate::{async_handler, fetch_json};
+
+/// Link to the results of a search. The search page runs the search in its URL, so results can
+/// be linked to and survive reloads.
+pub fn search_url(query: &str, kind: SearchKind) -> String {
+    let kind = match kind {
+        SearchKind::Posts => "posts",
+        SearchKind::Users => "users",
+    };
+    let params = form_urlencoded::Serializer::new(String::new())
+        .append_pair("q", query)
+        .append_pair("kind", kind)
+        .finish();
+    format!("{}?{params}", page::SEARCH)
+}
+
+pub fn Search(cx: Scope) -> Element {
+    let api_client = ApiClient::global();
+    let router = use_router(cx);
+    let route = use_route(cx);
+    let toaster = use_toaster(cx);
+    let cancel = use_request_scope(cx).token();
+
+    let searched = route.query_param("q").map(|q| q.into_owned());
+    let searched_kind = match route.query_param("kind").as_deref() {
+        Some("users") => SearchKind::Users,
+        _ => SearchKind::Posts,
+    };
+
+    let query = use_state(cx, || searched.clone().unwrap_or_default());
+    let kind = use_state(cx, || searched_kind);
+    let hits = use_ref(cx, || None::<SearchHits>);
+    let next_offset = use_state(cx, || None::<i64>);
+    let loading = use_state(cx, || false);
+
+    // runs whenever the search in the URL changes
+    use_future(
+        cx,
+        (&searched, &searched_kind),
+        |(searched, searched_kind)| {
+            to_owned![
+                api_client,
+                cancel,
+                toaster,
+                query,
+                kind,
+                hits,
+                next_offset,
+                loading
+            ];
+            async move {
+                *hits.write() = None;
+                next_offset.set(None);
+                let Some(searched) = searched else {
+                    return;
+                };
+                query.set(searched.clone());
+                kind.set(searched_kind);
+                if uchat_api::search::validate_query(&searched).is_err() {
+                    return;
+                }
+
+                loading.set(true);
+                let request = SearchRequest {
+                    query: searched,
+                    kind: searched_kind,
+                    offset: 0,
+                };
+                let response = cancel
+                    .run(async { fetch_json!(<SearchOk>, api_client, request) })
+                    .await;
+                match response {
+                    Ok(page) => {
+                        *hits.write() = Some(page.hits);
+                        next_offset.set(page.next_offset);
+                    }
+                    Err(e) => {
+                        toaster.write().api_error(&e);
+                    }
+                }
+                loading.set(false);
+            }
+        },
+    );
+
+    let form_onsubmit = move |_| {
+        let trimmed = query.trim();
+        if let Err(msg) = uchat_api::search::validate_query(trimmed) {
+            toaster.write().error(msg);
+            return;
+        }
+        router.navigate_to(&search_url(trimmed, **kind));
+    };
+
+    let load_more = async_handler!(
+        &cx,
+        [
+            api_client,
+            cancel,
+            toaster,
+            searched,
+            searched_kind,
+            hits,
+            next_offset,
+            loading
+        ],
+        move |_| async move {
+            let (Some(query), Some(offset)) = (searched, *next_offset.current()) else {
+                return;
+            };
+            loading.set(true);
+            let request = SearchRequest {
+                query,
+                kind: searched_kind,
+                offset,
+            };
+            let response = cancel
+                .run(async { fetch_json!(<SearchOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(page) => {
+                    let appended = match (hits.write().as_mut(), page.hits) {
+                        (Some(SearchHits::Posts(hits)), SearchHits::Posts(more)) => {
+                            hits.extend(more);
+                            true
+                        }
+                        (Some(SearchHits::Users(hits)), SearchHits::Users(more)) => {
+                            hits.extend(more);
+                            true
+                        }
+                        // a new search replaced the results in the meantime
+                        _ => false,
+                    };
+                    if appended {
+                        next_offset.set(page.next_offset);
+                    }
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            loading.set(false);
+        }
+    );
+
+    let tab = |tab_kind: SearchKind, label: &'static str| {
+        let class = if **kind == tab_kind {
+            "btn font-semibold underline"
+        } else {
+            "btn"
+        };
+        rsx! {
+            button {
+                class: "{class}",
+                r#type: "button",
+                onclick: move |_| kind.set(tab_kind),
+                "{label}"
+            }
+        }
+    };
+
+    let hits = hits.read();
+    let results = match &*hits {
+        None => None,
+        Some(hits) if hits.is_empty() => Some(rsx! { p { "No results." } }),
+        Some(SearchHits::Posts(posts)) => {
+            let items = posts.iter().map(|post| {
+                let time = post.time_posted.format("%Y-%m-%d %H:%M");
+                let url = page::post_url(post.id);
+                rsx! {
+                    li {
+                        key: "{post.id}",
+                        class: "p-3 border-b",
+                        p {
+                            class: "text-sm text-gray-500",
+                            "@{post.author_handle} · "
+                            Link { class: "text-blue-600", to: "{url}", "{time}" }
+                            post.edited_at.is_some().then(|| rsx! { span { " (edited)" } })
+                        }
+                        PostText { text: post.text.clone() }
+                        ReportButton { target: ReportTarget::Post(post.id) }
+                    }
+                }
+            });
+            Some(rsx! { ul { items } })
+        }
+        Some(SearchHits::Users(users)) => {
+            let items = users.iter().map(|user| {
+                let name = user.display_name.as_deref().unwrap_or_default();
+                rsx! {
+                    li {
+                        key: "{user.id}",
+                        class: "p-3 border-b flex justify-between items-center",
+                        div {
+                            p { class: "font-semibold", "{name}" }
+                            p { class: "text-sm text-gray-500", "@{user.handle}" }
+                        }
+                        UserActions { user_id: user.id, handle: user.handle.clone() }
+                    }
+                }
+            });
+            Some(rsx! { ul { items } })
+        }
+    };
+
+    cx.render(rsx! {
+        div {
+            class: "max-w-lg mx-auto mt-10 flex flex-col gap-3",
+            h1 { class: "text-2xl", "Search" }
+            form {
+                class: "flex flex-col gap-3",
+                prevent_default: "onsubmit",
+                onsubmit: form_onsubmit,
+                input {
+                    class: "input-field",
+                    r#type: "search",
+                    placeholder: "Search posts and people",
+                    value: "{query}",
+                    oninput: move |ev| query.set(ev.value.clone()),
+                }
+                div {
+                    class: "flex gap-3",
+                    tab(SearchKind::Posts, "Posts")
+                    tab(SearchKind::Users, "People")
+                    button {
+                        class: "btn ml-auto",
+                        r#type: "submit",
+                        disabled: "{loading}",
+                        "Search"
+                    }
+                }
+            }
+            results
+            next_offset.is_some().then(|| rsx! {
+                button {
+                    class: "btn",
+                    disabled: "{loading}",
+                    onclick: load_more,
+                    "Load more"
+                }
+            })
+        }
+    })
+}
@@ -0,0 +1,131 @@
+#![allow(non_snake_case)]
+
+use dioxus::prelude::*;
+use dioxus_router::{use_route, Link};
+use uchat_api::moderation::ReportTarget;
+use uchat_api::post::{PostCursor, PostPreview};
+use uchat_api::tag::{GetTagTimeline, GetTagTimelineOk};
+use url::form_urlencoded;
+
+use crate::component::{use_toaster, PostText, ReportButton};
+use crate::page;
+use crate::util::cancel::use_request_scope;
+use crate::util::ApiClient;
+use crate::{async_handler, fetch_json};
+
+/// Link to the timeline of a hashtag, given without the `#`.
+pub fn tag_url(tag: &str) -> String {
+    let params = form_urlencoded::Serializer::new(String::new())
+        .append_pair("tag", &tag.to_lowercase())
+        .finish();
+    format!("{}?{params}", page::TAG)
+}
+
+/// Public posts with the hashtag in the URL, newest first.
+pub fn Tag(cx: Scope) -> Element {
+    let api_client = ApiClient::global();
+    let route = use_route(cx);
+    let toaster = use_toaster(cx);
+    let cancel = use_request_scope(cx).token();
+
+    let tag = route
+        .query_param("tag")
+        .map(|tag| tag.into_owned())
+        .unwrap_or_default();
+
+    let posts = use_ref(cx, Vec::<PostPreview>::new);
+    // `None` once the last page was loaded
+    let next = use_state(cx, || None::<PostCursor>);
+    let loading = use_state(cx, || true);
+
+    use_future(cx, (&tag,), |(tag,)| {
+        to_owned![api_client, cancel, toaster, posts, next, loading];
+        async move {
+            posts.write().clear();
+            next.set(None);
+            loading.set(true);
+            let request = GetTagTimeline { tag, before: None };
+            let response = cancel
+                .run(async { fetch_json!(<GetTagTimelineOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(page) => {
+                    *posts.write() = page.posts;
+                    next.set(page.next);
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            loading.set(false);
+        }
+    });
+
+    // `load_more` takes ownership of the tag
+    let title = format!("#{tag}");
+    let load_more = async_handler!(
+        &cx,
+        [api_client, cancel, toaster, tag, posts, next, loading],
+        move |_| async move {
+            let Some(before) = *next.current() else {
+                return;
+            };
+            loading.set(true);
+            let request = GetTagTimeline {
+                tag,
+                before: Some(before),
+            };
+            let response = cancel
+                .run(async { fetch_json!(<GetTagTimelineOk>, api_client, request) })
+                .await;
+            match response {
+                Ok(page) => {
+                    posts.write().extend(page.posts);
+                    next.set(page.next);
+                }
+                Err(e) => {
+                    toaster.write().api_error(&e);
+                }
+            }
+            loading.set(false);
+        }
+    );
+
+    let posts = posts.read();
+    let items = posts.iter().map(|post| {
+        let time = post.time_posted.format("%Y-%m-%d %H:%M");
+        let url = page::post_url(post.id);
+        rsx! {
+            li {
+                key: "{post.id}",
+                class: "p-3 border-b",
+                p {
+                    class: "text-sm text-gray-500",
+                    "@{post.author_handle} · "
+                    Link { class: "text-blue-600", to: "{url}", "{time}" }
+                    post.edited_at.is_some().then(|| rsx! { span { " (edited)" } })
+                }
+                PostText { text: post.text.clone() }
+                ReportButton { target: ReportTarget::Post(post.id) }
+            }
+        }
+    });
+    let is_empty = posts.is_empty();
+
+    cx.render(rsx! {
+        div {
+            class: "max-w-lg mx-auto mt-10 flex flex-col gap-3",
+            h1 { class: "text-2xl", "{title}" }
+            (is_empty && !**loading).then(|| rsx! { p { "No posts with this tag yet." } })
+            ul { items }
+            next.is_some().then(|| rsx! {
+                button {
+                    class: "btn",
+                    disabled: "{loading}",
+                    onclick: load_more,
+                    "Load more"
+                }
+            })
+        }
+    })
+}
@@ -0,0 +1,189 @@
+use dioxus::prelude::ScopeState;
+use fermi::{use_atom_ref, AtomRef, UseAtomRef};
+use std::collections::VecDeque;
+use uchat_api::event::{Event, EventMessage};
+use uchat_api::user::{SessionInfo, Whoami, WhoamiOk};
+use uchat_api::ErrorCode;
+
+use crate::fetch_json;
+use crate::util::{connectivity, cookie, event_socket, ApiClient};
+
+/// The user of this app.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum LocalUser {
+    /// The session hasn't been checked with the server yet.
+    #[default]
+    Loading,
+    LoggedOut,
+    LoggedIn(WhoamiOk),
+}
+
+impl LocalUser {
+    pub fn is_logged_in(&self) -> bool {
+        matches!(self, Self::LoggedIn(_))
+    }
+}
+
+pub static LOCAL_USER: AtomRef<LocalUser> = |_| LocalUser::default();
+
+pub fn use_local_user(cx: &ScopeState) -> &UseAtomRef<LocalUser> {
+    use_atom_ref(cx, LOCAL_USER)
+}
+
+/// Asks the server who is logged in using the session cookies.
+///
+/// Cookies of sessions which the server rejected are removed.
+pub async fn load_local_user(local_user: &UseAtomRef<LocalUser>) {
+    if !cookie::has_session() {
+        *local_user.write() = LocalUser::LoggedOut;
+        return;
+    }
+
+    let api_client = ApiClient::global();
+    match fetch_json!(<WhoamiOk>, api_client, Whoami) {
+        Ok(user) => *local_user.write() = LocalUser::LoggedIn(user),
+        Err(e) if e.code() == Some(ErrorCode::Unauthorized) => {
+            log::info!("session rejected: {e}");
+            cookie::clear_session();
+            *local_user.write() = LocalUser::LoggedOut;
+        }
+        Err(e) => {
+            log::error!("failed to load local user: {e}");
+            *local_user.write() = LocalUser::LoggedOut;
+        }
+    }
+}
+
+/// Stores a new session after logging in or registering.
+pub fn start_session(local_user: &UseAtomRef<LocalUser>, session: SessionInfo) {
+    cookie::set_session(&session);
+    *local_user.write() = LocalUser::LoggedIn(WhoamiOk {
+        user_id: session.user_id,
+        handle: session.handle,
+        display_name: session.display_name,
+        role: session.role,
+        private: session.private,
+        delete_after: session.delete_after,
+    });
+}
+
+/// Network status of the app.
+pub struct Connectivity {
+    online: bool,
+    /// Actions to run once the app is back online.
+    queued: Vec<Box<dyn FnOnce()>>,
+}
+
+impl Default for Connectivity {
+    fn default() -> Self {
+        Self {
+            online: connectivity::is_online(),
+            queued: Vec::new(),
+        }
+    }
+}
+
+impl Connectivity {
+    pub fn is_online(&self) -> bool {
+        self.online
+    }
+
+    /// Updates the status. Returns the queued actions if the app came back online.
+    fn set_online(&mut self, online: bool) -> Vec<Box<dyn FnOnce()>> {
+        self.online = online;
+        if online {
+            std::mem::take(&mut self.queued)
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+pub static CONNECTIVITY: AtomRef<Connectivity> = |_| Connectivity::default();
+
+pub fn use_connectivity(cx: &ScopeState) -> &UseAtomRef<Connectivity> {
+    use_atom_ref(cx, CONNECTIVITY)
+}
+
+/// Keeps [`CONNECTIVITY`] up to date with the browser. Call once when the app starts.
+pub fn watch_connectivity(connectivity: UseAtomRef<Connectivity>) {
+    connectivity::watch(move |online| {
+        log::info!("connectivity changed: online = {online}");
+        let queued = connectivity.write().set_online(online);
+        for action in queued {
+            action();
+        }
+    });
+}
+
+/// Runs `action` now, or once the app is back online.
+pub fn run_when_online<F>(connectivity: &UseAtomRef<Connectivity>, action: F)
+where
+    F: FnOnce() + 'static,
+{
+    if connectivity.read().is_online() {
+        action();
+    } else {
+        connectivity.write().queued.push(Box::new(action));
+    }
+}
+
+/// Events kept in [`LiveEvents`].
+pub const MAX_LIVE_EVENTS: usize = 100;
+
+/// Real-time events pushed by the server.
+#[derive(Debug, Default)]
+pub struct LiveEvents {
+    /// Most recent events, oldest first.
+    recent: VecDeque<Event>,
+    /// Number of events received so far.
+    received: u64,
+    /// Increased whenever events were missed. Views showing data from the server should reload it
+    /// when this changes.
+    resyncs: u64,
+}
+
+impl LiveEvents {
+    pub fn recent(&self) -> impl Iterator<Item = &Event> {
+        self.recent.iter()
+    }
+
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+
+    pub fn resyncs(&self) -> u64 {
+        self.resyncs
+    }
+
+    fn push(&mut self, message: EventMessage) {
+        match message {
+            EventMessage::Event { event, .. } => {
+                if self.recent.len() == MAX_LIVE_EVENTS {
+                    self.recent.pop_front();
+                }
+                self.recent.push_back(event);
+                self.received += 1;
+            }
+            EventMessage::Resync { .. } => self.resyncs += 1,
+        }
+    }
+}
+
+pub static LIVE_EVENTS: AtomRef<LiveEvents> = |_| LiveEvents::default();
+
+pub fn use_live_events(cx: &ScopeState) -> &UseAtomRef<LiveEvents> {
+    use_atom_ref(cx, LIVE_EVENTS)
+}
+
+/// Streams events into [`LIVE_EVENTS`] until the future is dropped.
+pub async fn stream_live_events(live_events: UseAtomRef<LiveEvents>) {
+    event_socket::run(move |message| live_events.write().push(message)).await
+}
+
+/// Unread notifications of the logged in user, once loaded.
+pub static UNREAD_NOTIFICATIONS: AtomRef<Option<i64>> = |_| None;
+
+pub fn use_unread_notifications(cx: &ScopeState) -> &UseAtomRef<Option<i64>> {
+    use_atom_ref(cx, UNREAD_NOTIFICATIONS)
+}
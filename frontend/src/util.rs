@@ -1,5 +1,11 @@
 pub mod api_client;
+pub mod api_url;
+pub mod cancel;
+pub mod coalesce;
+pub mod connectivity;
 pub mod cookie;
+pub mod event_socket;
+pub mod retry;
 pub use api_client::ApiClient;
 
 use serde::Deserialize;
@@ -13,6 +19,51 @@ pub enum RequestError {
 
     #[error("request timeout")]
     Timeout,
+
+    /// The server rejected the request.
+    #[error("{}", .0.msg)]
+    BadRequest(uchat_api::RequestFailed),
+
+    /// Error response without a [`RequestFailed`](uchat_api::RequestFailed) body.
+    #[error("server responded with {0}")]
+    Status(reqwest::StatusCode),
+
+    #[error("invalid API url: {0}")]
+    Url(#[from] url::ParseError),
+
+    #[error("response is larger than {limit} bytes")]
+    ResponseTooLarge { limit: usize },
+
+    #[error("invalid response: {0}")]
+    Decode(#[from] serde_json::Error),
+
+    #[error("request cancelled")]
+    Cancelled,
+
+    /// Error of a request which was shared by several callers.
+    #[error(transparent)]
+    Coalesced(std::sync::Arc<RequestError>),
+}
+
+impl RequestError {
+    /// Error code of responses from the server.
+    pub fn code(&self) -> Option<uchat_api::ErrorCode> {
+        match self {
+            Self::BadRequest(failed) => Some(failed.code),
+            Self::Status(status) => Some(uchat_api::ErrorCode::from_status(status.as_u16())),
+            Self::Coalesced(e) => e.code(),
+            _ => None,
+        }
+    }
+
+    /// Whether the request never got a response.
+    pub fn is_network_error(&self) -> bool {
+        match self {
+            Self::Request(_) | Self::Timeout => true,
+            Self::Coalesced(e) => e.is_network_error(),
+            _ => false,
+        }
+    }
 }
 
 #[derive(Clone, Deserialize, PartialEq)]
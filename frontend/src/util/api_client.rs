@@ -1,31 +1,172 @@
 use futures::Future;
 use once_cell::sync::OnceCell;
+use reqwest::{multipart, Method, Request, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
 use serde::Serialize;
+use std::time::Duration;
+use uchat_api::RequestFailed;
 
+use super::coalesce::{coalesce, BufferedResponse, RequestKey};
+use super::retry::RetryPolicy;
 use super::RequestError;
 
 pub static API_CLIENT: OnceCell<ApiClient> = OnceCell::new();
 
+/// Default limit for [`read_body`] and [`read_json`].
+pub const MAX_RESPONSE_BYTES: usize = 8 * 1024 * 1024;
+
 #[derive(Clone, Debug, Default)]
 pub struct ApiClient {
     pub inner: reqwest::Client,
+    /// Applies to idempotent requests (`GET`, `PUT` and `DELETE`).
+    pub retry_policy: RetryPolicy,
 }
 
 impl ApiClient {
     pub fn new(client: reqwest::Client) -> Self {
-        Self { inner: client }
+        Self {
+            inner: client,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Sends a `GET` request with `query` serialized into the query string.
+    pub async fn get<Q>(
+        &self,
+        endpoint: &str,
+        query: &Q,
+        timeout: Duration,
+    ) -> Result<Response, RequestError>
+    where
+        Q: Serialize + ?Sized,
+    {
+        let request = self.request(Method::GET, endpoint)?.query(query).build()?;
+        self.send_idempotent(request, timeout).await
+    }
+
+    /// Like [`get`](Self::get), but concurrent identical requests share a single network call.
+    pub async fn get_coalesced<Q>(
+        &self,
+        endpoint: &str,
+        query: &Q,
+        timeout: Duration,
+    ) -> Result<BufferedResponse, RequestError>
+    where
+        Q: Serialize + ?Sized,
+    {
+        let request = self.request(Method::GET, endpoint)?.query(query).build()?;
+        let key = RequestKey::new(&request);
+        let client = self.clone();
+        coalesce(key, async move {
+            let response = client.send_idempotent(request, timeout).await?;
+            BufferedResponse::read(response, MAX_RESPONSE_BYTES).await
+        })
+        .await
     }
 
     pub async fn post_json<T>(
         &self,
         endpoint: &str,
         json: &T,
-        timeout: std::time::Duration,
-    ) -> Result<reqwest::Response, RequestError>
+        timeout: Duration,
+    ) -> Result<Response, RequestError>
     where
         T: Serialize + ?Sized,
     {
-        post_json(self.clone(), endpoint, json, timeout).await
+        let request = self.request(Method::POST, endpoint)?.json(json);
+        make_request(request.send(), timeout).await
+    }
+
+    /// Like [`post_json`](Self::post_json), but concurrent identical requests share a single
+    /// network call. Only use this for requests without side effects.
+    pub async fn post_json_coalesced<T>(
+        &self,
+        endpoint: &str,
+        json: &T,
+        timeout: Duration,
+    ) -> Result<BufferedResponse, RequestError>
+    where
+        T: Serialize + ?Sized,
+    {
+        let request = self.request(Method::POST, endpoint)?.json(json).build()?;
+        let key = RequestKey::new(&request);
+        let client = self.inner.clone();
+        coalesce(key, async move {
+            let response = make_request(client.execute(request), timeout).await?;
+            BufferedResponse::read(response, MAX_RESPONSE_BYTES).await
+        })
+        .await
+    }
+
+    pub async fn put_json<T>(
+        &self,
+        endpoint: &str,
+        json: &T,
+        timeout: Duration,
+    ) -> Result<Response, RequestError>
+    where
+        T: Serialize + ?Sized,
+    {
+        let request = self.request(Method::PUT, endpoint)?.json(json).build()?;
+        self.send_idempotent(request, timeout).await
+    }
+
+    pub async fn delete(
+        &self,
+        endpoint: &str,
+        timeout: Duration,
+    ) -> Result<Response, RequestError> {
+        let request = self.request(Method::DELETE, endpoint)?.build()?;
+        self.send_idempotent(request, timeout).await
+    }
+
+    /// Sends a `multipart/form-data` `POST` request, used to upload files.
+    pub async fn post_multipart(
+        &self,
+        endpoint: &str,
+        form: multipart::Form,
+        timeout: Duration,
+    ) -> Result<Response, RequestError> {
+        let request = self.request(Method::POST, endpoint)?.multipart(form);
+        make_request(request.send(), timeout).await
+    }
+
+    /// Sends a request, retrying according to the [`RetryPolicy`].
+    async fn send_idempotent(
+        &self,
+        request: Request,
+        timeout: Duration,
+    ) -> Result<Response, RequestError> {
+        let mut attempt = 0;
+        loop {
+            // only requests with streaming bodies can't be cloned, and those aren't sent here
+            let Some(attempt_request) = request.try_clone() else {
+                return make_request(self.inner.execute(request), timeout).await;
+            };
+            let result = make_request(self.inner.execute(attempt_request), timeout).await;
+
+            let Some(delay) = self.retry_policy.retry_delay(attempt, &result) else {
+                return result;
+            };
+            attempt += 1;
+            log::debug!(
+                "retrying {} {} in {delay:?} (attempt {attempt})",
+                request.method(),
+                request.url()
+            );
+            gloo_timers::future::sleep(delay).await;
+        }
+    }
+
+    fn request(&self, method: Method, endpoint: &str) -> Result<RequestBuilder, RequestError> {
+        let url = make_absolute_url(endpoint)?;
+        let is_safe = method.is_safe();
+        Ok(with_credentials(self.inner.request(method, url), is_safe))
     }
 
     pub fn global() -> &'static ApiClient {
@@ -41,49 +182,87 @@ impl ApiClient {
     }
 }
 
+/// Sends the session cookies along with the request. State-changing requests also get the CSRF
+/// token.
 #[cfg(target_arch = "wasm32")]
-async fn post_json<T>(
-    client: ApiClient,
-    endpoint: &str,
-    json: &T,
-    timeout: std::time::Duration,
-) -> Result<reqwest::Response, RequestError>
+fn with_credentials(request: RequestBuilder, is_safe: bool) -> RequestBuilder {
+    let request = request.fetch_credentials_include();
+    match super::cookie::get_csrf_token() {
+        Some(token) if !is_safe => request.header(uchat_api::CSRF_HEADER, token),
+        _ => request,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn with_credentials(request: RequestBuilder, _is_safe: bool) -> RequestBuilder {
+    request
+}
+
+fn make_absolute_url(endpoint: &str) -> Result<reqwest::Url, RequestError> {
+    // endpoints are joined as relative paths so they stay below the base path of the API
+    let url = super::api_url::api_url()?.join(endpoint.trim_start_matches('/'))?;
+    Ok(url)
+}
+
+/// Reads the response body. Fails with [`RequestError::ResponseTooLarge`] if it is larger than
+/// `limit` bytes.
+pub async fn read_body(response: Response, limit: usize) -> Result<Vec<u8>, RequestError> {
+    if response
+        .content_length()
+        .is_some_and(|len| len > limit as u64)
+    {
+        return Err(RequestError::ResponseTooLarge { limit });
+    }
+    read_limited(response, limit).await
+}
+
+pub async fn read_json<T>(response: Response, limit: usize) -> Result<T, RequestError>
 where
-    T: Serialize + ?Sized,
+    T: DeserializeOwned,
 {
-    let url = make_absolute_url(endpoint);
-
-    let api_request = async {
-        client
-            .inner
-            .post(url)
-            .fetch_credentials_include()
-            .json(json)
-            .send()
-            .await
-    };
-    make_request(api_request, timeout).await
+    let body = read_body(response, limit).await?;
+    Ok(serde_json::from_slice(&body)?)
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-async fn post_json<T>(
-    client: ApiClient,
-    endpoint: &str,
-    json: &T,
-    timeout: std::time::Duration,
-) -> Result<reqwest::Response, RequestError>
+/// Reads the JSON body of a successful response. Error responses are turned into
+/// [`RequestError::BadRequest`], or [`RequestError::Status`] if they don't have a
+/// [`RequestFailed`] body.
+pub async fn read_api_response<T>(response: Response) -> Result<T, RequestError>
 where
-    T: Serialize + ?Sized,
+    T: DeserializeOwned,
 {
-    let url = make_absolute_url(endpoint);
+    let status = response.status();
+    if status.is_success() {
+        return read_json(response, MAX_RESPONSE_BYTES).await;
+    }
 
-    let api_request = async { client.inner.post(url).json(json).send().await };
-    make_request(api_request, timeout).await
+    let body = read_body(response, MAX_RESPONSE_BYTES).await?;
+    match serde_json::from_slice::<RequestFailed>(&body) {
+        Ok(failed) => Err(RequestError::BadRequest(failed)),
+        Err(_) => Err(RequestError::Status(status)),
+    }
 }
 
-fn make_absolute_url(endpoint: &str) -> reqwest::Url {
-    let url = reqwest::Url::parse(crate::ROOT_API_URL).unwrap();
-    url.join(endpoint).unwrap()
+#[cfg(target_arch = "wasm32")]
+async fn read_limited(response: Response, limit: usize) -> Result<Vec<u8>, RequestError> {
+    // `fetch` buffers the body, so bodies without a `Content-Length` are checked after the fact
+    let body = response.bytes().await?;
+    if body.len() > limit {
+        return Err(RequestError::ResponseTooLarge { limit });
+    }
+    Ok(body.to_vec())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn read_limited(mut response: Response, limit: usize) -> Result<Vec<u8>, RequestError> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > limit {
+            return Err(RequestError::ResponseTooLarge { limit });
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
 }
 
 async fn make_request(
@@ -114,20 +293,13 @@ async fn make_request(
 macro_rules! fetch_json {
     (<$target:ty>, $client:ident, $request:expr) => {{
         use uchat_api::Endpoint;
-        use $crate::util::RequestError;
         let duration = std::time::Duration::from_millis(6000);
-        let response = $client
-            .post_json($request.self_url(), &$request, duration)
-            .await;
-        match response {
-            Ok(res) => {
-                if res.status().is_success() {
-                    Ok(res.json::<$target>().await.unwrap())
-                } else {
-                    let err_payload = res.json::<uchat_api::RequestFailed>().await.unwrap();
-                    Err(RequestError::BadRequest(err_payload))
-                }
-            }
+        let request = &$request;
+        match $client
+            .post_json(request.self_url(), request, duration)
+            .await
+        {
+            Ok(res) => $crate::util::api_client::read_api_response::<$target>(res).await,
             Err(e) => Err(e),
         }
     }};
@@ -0,0 +1,116 @@
+//! Location of the API server.
+//!
+//! The build-time [`DEFAULT_API_URL`](crate::DEFAULT_API_URL) can be overridden when serving the
+//! app by adding a `<meta>` tag to `index.html`:
+//!
+//! ```html
+//! <meta name="uchat-api-url" content="https://api.example.com/" />
+//! ```
+//!
+//! Relative URLs are resolved against the page location, so `/api/` targets the API on the same
+//! origin as the app.
+
+use once_cell::sync::OnceCell;
+use url::{ParseError, Url};
+
+/// Name of the `<meta>` tag which overrides the API url.
+pub const API_URL_META: &str = "uchat-api-url";
+
+static API_URL: OnceCell<Url> = OnceCell::new();
+
+/// Base URL of the API, resolved on first use.
+pub fn api_url() -> Result<&'static Url, ParseError> {
+    API_URL.get_or_try_init(|| resolve(&configured(meta_api_url()), page_url().as_deref()))
+}
+
+/// The url from the `<meta>` tag, if there is one, or the build-time default.
+fn configured(meta_api_url: Option<String>) -> String {
+    meta_api_url.unwrap_or_else(|| crate::DEFAULT_API_URL.to_string())
+}
+
+/// Resolves `configured` against the page location (if any). The path always ends with `/` so
+/// endpoints can be joined onto it.
+pub fn resolve(configured: &str, page_url: Option<&str>) -> Result<Url, ParseError> {
+    let mut url = match page_url {
+        Some(page_url) => Url::parse(page_url)?.join(configured)?,
+        None => Url::parse(configured)?,
+    };
+    if !url.path().ends_with('/') {
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
+    }
+    Ok(url)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn meta_api_url() -> Option<String> {
+    super::document()
+        .query_selector(&format!("meta[name=\"{API_URL_META}\"]"))
+        .ok()
+        .flatten()
+        .and_then(|meta| meta.get_attribute("content"))
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn meta_api_url() -> Option<String> {
+    None
+}
+
+#[cfg(target_arch = "wasm32")]
+fn page_url() -> Option<String> {
+    super::window().location().href().ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn page_url() -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn meta_tag_overrides_the_default() {
+        assert_eq!(configured(None), crate::DEFAULT_API_URL);
+
+        let configured = configured(Some("https://api.example.com/v1".into()));
+        assert_eq!(
+            resolve(&configured, Some("https://example.com/home"))
+                .unwrap()
+                .as_str(),
+            "https://api.example.com/v1/"
+        );
+    }
+
+    #[test]
+    fn resolves_relative_urls_against_the_page() {
+        assert_eq!(
+            resolve("/api", Some("https://example.com/posts/1?x=y"))
+                .unwrap()
+                .as_str(),
+            "https://example.com/api/"
+        );
+        assert_eq!(
+            resolve("api/", Some("http://127.0.0.1:8080/app/"))
+                .unwrap()
+                .as_str(),
+            "http://127.0.0.1:8080/app/api/"
+        );
+    }
+
+    #[test]
+    fn relative_urls_need_a_base() {
+        assert_eq!(
+            resolve("/api/", None),
+            Err(ParseError::RelativeUrlWithoutBase)
+        );
+        // pages like `data:` urls can't be a base
+        assert_eq!(
+            resolve("/api/", Some("data:text/html,uchat")),
+            Err(ParseError::RelativeUrlWithCannotBeABaseBase)
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! Cancellation of requests started by a component.
+//!
+//! Dropping a request future aborts the underlying `fetch`. [`use_request_scope`] drops every
+//! request started through its [`CancelToken`]s when the component is unmounted:
+//!
+//! ```ignore
+//! let cancel = use_request_scope(cx).token();
+//! // in an event handler or future
+//! match cancel.run(api_client.get(endpoint, &query, timeout)).await {
+//!     Err(RequestError::Cancelled) => (), // the component is gone
+//!     ...
+//! }
+//! ```
+
+use dioxus::prelude::ScopeState;
+use futures::channel::oneshot;
+use futures::future::{select, Either, FutureExt, Shared};
+use futures::{pin_mut, Future};
+use std::cell::RefCell;
+
+use super::RequestError;
+
+/// Cancels requests when dropped.
+pub struct RequestScope {
+    // dropping the sender resolves the receiver held by the tokens
+    current: RefCell<(oneshot::Sender<()>, CancelToken)>,
+}
+
+impl RequestScope {
+    pub fn new() -> Self {
+        Self {
+            current: RefCell::new(channel()),
+        }
+    }
+
+    /// Token for requests which should be cancelled along with this scope.
+    pub fn token(&self) -> CancelToken {
+        self.current.borrow().1.clone()
+    }
+
+    /// Cancels every request started so far. Tokens handed out afterwards are unaffected.
+    pub fn cancel_all(&self) {
+        self.current.replace(channel());
+    }
+}
+
+impl Default for RequestScope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn channel() -> (oneshot::Sender<()>, CancelToken) {
+    let (sender, receiver) = oneshot::channel();
+    let token = CancelToken {
+        cancelled: receiver.shared(),
+    };
+    (sender, token)
+}
+
+#[derive(Clone)]
+pub struct CancelToken {
+    cancelled: Shared<oneshot::Receiver<()>>,
+}
+
+impl CancelToken {
+    /// Runs `request` until it completes or the scope is cancelled, in which case
+    /// [`RequestError::Cancelled`] is returned.
+    pub async fn run<F, T>(&self, request: F) -> Result<T, RequestError>
+    where
+        F: Future<Output = Result<T, RequestError>>,
+    {
+        let cancelled = self.cancelled.clone();
+        pin_mut!(request);
+
+        match select(request, cancelled).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(RequestError::Cancelled),
+        }
+    }
+}
+
+/// Request scope which is cancelled when the component is unmounted.
+pub fn use_request_scope(cx: &ScopeState) -> &RequestScope {
+    cx.use_hook(RequestScope::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::future::pending;
+
+    #[test]
+    fn runs_requests_to_completion() {
+        let scope = RequestScope::new();
+        let result = block_on(scope.token().run(async { Ok(1) }));
+        assert_eq!(result.unwrap(), 1);
+    }
+
+    #[test]
+    fn cancels_requests_when_dropped() {
+        let scope = RequestScope::new();
+        let token = scope.token();
+        drop(scope);
+
+        let result = block_on(token.run(pending::<Result<(), _>>()));
+        assert!(matches!(result, Err(RequestError::Cancelled)));
+    }
+
+    #[test]
+    fn cancel_all_only_affects_earlier_tokens() {
+        let scope = RequestScope::new();
+        let old = scope.token();
+        scope.cancel_all();
+        let new = scope.token();
+
+        let result = block_on(old.run(pending::<Result<(), _>>()));
+        assert!(matches!(result, Err(RequestError::Cancelled)));
+
+        let result = block_on(new.run(async { Ok(()) }));
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,245 @@
+//! Coalescing of concurrent identical requests into a single network call.
+//!
+//! The first caller starts the request and later callers wait for the same response. The request
+//! is dropped (which aborts the `fetch`) once every caller has gone away.
+
+use futures::future::{FutureExt, LocalBoxFuture, Shared};
+use futures::Future;
+use reqwest::header::HeaderMap;
+use reqwest::{Method, Request, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use super::RequestError;
+
+/// Response which was read into memory, so it can be handed to every caller of a coalesced
+/// request.
+#[derive(Clone, Debug)]
+pub struct BufferedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Rc<[u8]>,
+}
+
+impl BufferedResponse {
+    /// Reads the body of `response`, up to `limit` bytes.
+    pub async fn read(response: Response, limit: usize) -> Result<Self, RequestError> {
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = super::api_client::read_body(response, limit).await?;
+
+        Ok(Self {
+            status,
+            headers,
+            body: body.into(),
+        })
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, RequestError> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+}
+
+/// Requests with the same key are considered identical.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RequestKey {
+    method: Method,
+    url: String,
+    body: Option<Vec<u8>>,
+}
+
+impl RequestKey {
+    pub fn new(request: &Request) -> Self {
+        Self {
+            method: request.method().clone(),
+            url: request.url().to_string(),
+            body: request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map(<[u8]>::to_vec),
+        }
+    }
+}
+
+type InFlight = Shared<LocalBoxFuture<'static, Result<BufferedResponse, Arc<RequestError>>>>;
+
+struct Entry {
+    id: u64,
+    waiters: usize,
+    request: InFlight,
+}
+
+thread_local! {
+    static IN_FLIGHT: RefCell<HashMap<RequestKey, Entry>> = RefCell::default();
+    static NEXT_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Runs `request`, unless an identical request is in flight already. In that case its response
+/// is shared instead.
+pub async fn coalesce<F>(key: RequestKey, request: F) -> Result<BufferedResponse, RequestError>
+where
+    F: Future<Output = Result<BufferedResponse, RequestError>> + 'static,
+{
+    let (in_flight, waiter) = join(key, request);
+    let result = in_flight.await;
+    drop(waiter);
+
+    // the error is only wrapped if other callers still hold on to it
+    result.map_err(|e| Arc::try_unwrap(e).unwrap_or_else(RequestError::Coalesced))
+}
+
+fn join<F>(key: RequestKey, request: F) -> (InFlight, Waiter)
+where
+    F: Future<Output = Result<BufferedResponse, RequestError>> + 'static,
+{
+    IN_FLIGHT.with(|in_flight| {
+        let mut in_flight = in_flight.borrow_mut();
+        let entry = in_flight.entry(key.clone()).or_insert_with(|| Entry {
+            id: NEXT_ID.with(|id| id.replace(id.get() + 1)),
+            waiters: 0,
+            request: request
+                .map(|result| result.map_err(Arc::new))
+                .boxed_local()
+                .shared(),
+        });
+        entry.waiters += 1;
+
+        let waiter = Waiter { key, id: entry.id };
+        (entry.request.clone(), waiter)
+    })
+}
+
+/// Removes the in-flight request once its last caller is done with it.
+struct Waiter {
+    key: RequestKey,
+    id: u64,
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        // the map may already be gone if the thread is shutting down
+        let _ = IN_FLIGHT.try_with(|in_flight| {
+            let mut in_flight = in_flight.borrow_mut();
+            let Some(entry) = in_flight.get_mut(&self.key) else {
+                return;
+            };
+            // a newer request for the same key isn't ours to remove
+            if entry.id != self.id {
+                return;
+            }
+            entry.waiters -= 1;
+            if entry.waiters == 0 {
+                in_flight.remove(&self.key);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::oneshot;
+    use futures::executor::block_on;
+
+    fn key(url: &str) -> RequestKey {
+        RequestKey {
+            method: Method::GET,
+            url: url.to_string(),
+            body: None,
+        }
+    }
+
+    fn response(body: &[u8]) -> BufferedResponse {
+        BufferedResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: body.into(),
+        }
+    }
+
+    fn in_flight_count() -> usize {
+        IN_FLIGHT.with(|in_flight| in_flight.borrow().len())
+    }
+
+    #[test]
+    fn shares_concurrent_identical_requests() {
+        let sent = Rc::new(Cell::new(0));
+        let (tx, rx) = oneshot::channel::<()>();
+
+        let send = |body: &'static [u8]| {
+            let sent = Rc::clone(&sent);
+            async move {
+                sent.set(sent.get() + 1);
+                Ok(response(body))
+            }
+        };
+
+        let first = coalesce(key("/a"), {
+            let request = send(b"first");
+            async move {
+                rx.await.unwrap();
+                request.await
+            }
+        });
+        let second = coalesce(key("/a"), send(b"second"));
+        let other = coalesce(key("/b"), send(b"other"));
+
+        let (first, second, other, _) = block_on(async {
+            futures::join!(first, second, other, async { tx.send(()).unwrap() })
+        });
+
+        assert_eq!(first.unwrap().body(), b"first");
+        assert_eq!(second.unwrap().body(), b"first");
+        assert_eq!(other.unwrap().body(), b"other");
+        assert_eq!(sent.get(), 2);
+        assert_eq!(in_flight_count(), 0);
+    }
+
+    #[test]
+    fn sends_again_after_completion() {
+        let first = block_on(coalesce(key("/c"), async { Ok(response(b"1")) })).unwrap();
+        let second = block_on(coalesce(key("/c"), async { Ok(response(b"2")) })).unwrap();
+
+        assert_eq!(first.body(), b"1");
+        assert_eq!(second.body(), b"2");
+    }
+
+    #[test]
+    fn drops_request_when_every_caller_is_gone() {
+        let (tx, rx) = oneshot::channel::<()>();
+        let request = coalesce(key("/d"), async move {
+            rx.await.ok();
+            Ok(response(b""))
+        });
+
+        // start the request, then give up on it
+        let mut request = Box::pin(request);
+        assert!((&mut request).now_or_never().is_none());
+        assert_eq!(in_flight_count(), 1);
+        drop(request);
+
+        assert_eq!(in_flight_count(), 0);
+        assert!(tx.is_canceled());
+    }
+
+    #[test]
+    fn unwraps_errors_of_single_callers() {
+        let result = block_on(coalesce(key("/e"), async { Err(RequestError::Timeout) }));
+        assert!(matches!(result, Err(RequestError::Timeout)));
+    }
+}
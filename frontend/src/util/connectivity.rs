@@ -0,0 +1,45 @@
+//! Browser online/offline status.
+
+/// Whether the browser thinks it has a network connection.
+#[cfg(target_arch = "wasm32")]
+pub fn is_online() -> bool {
+    super::window().navigator().on_line()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn is_online() -> bool {
+    true
+}
+
+/// Calls `on_change` with the new status whenever the browser goes online or offline.
+///
+/// The listeners stay installed for the lifetime of the page.
+#[cfg(target_arch = "wasm32")]
+pub fn watch<F>(on_change: F)
+where
+    F: Fn(bool) + 'static,
+{
+    use std::rc::Rc;
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::JsCast;
+
+    let on_change = Rc::new(on_change);
+    let window = super::window();
+    for (event, online) in [("online", true), ("offline", false)] {
+        let on_change = Rc::clone(&on_change);
+        let listener = Closure::<dyn Fn()>::new(move || on_change(online));
+        if let Err(e) =
+            window.add_event_listener_with_callback(event, listener.as_ref().unchecked_ref())
+        {
+            log::error!("failed to listen for '{event}' events: {e:?}");
+        }
+        listener.forget();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn watch<F>(_on_change: F)
+where
+    F: Fn(bool) + 'static,
+{
+}
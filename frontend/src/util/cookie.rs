@@ -1,36 +1,69 @@
 #![allow(dead_code)]
 
 use chrono::{DateTime, Utc};
+use uchat_api::user::SessionInfo;
+use uchat_cookie::{Cookie, CookieBuilder, SameSite, CSRF_TOKEN, SESSION_ID, SESSION_SIGNATURE};
 
-#[cfg(not(debug_assertions))]
-fn standard_options() -> &'static str {
-    "SameSite=Strict; Path=/; Secure"
+use super::api_url::api_url;
+
+// `Secure` follows the scheme of the API: Safari drops secure cookies on `http://localhost`, so
+// they would break local development without TLS.
+fn with_standard_options(cookie: CookieBuilder) -> CookieBuilder {
+    let secure = api_url().map_or(true, |url| url.scheme() == "https");
+    cookie.same_site(SameSite::Strict).path("/").secure(secure)
 }
 
-#[cfg(debug_assertions)]
-fn standard_options() -> &'static str {
-    "SameSite=Strict; Path=/;"
+/// Sets a cookie which expires at `expires`.
+pub fn set<N, V>(name: N, value: V, expires: DateTime<Utc>)
+where
+    N: Into<String>,
+    V: Into<String>,
+{
+    let cookie = with_standard_options(Cookie::build(name, value).expires(expires)).finish();
+    set_cookie(&cookie);
 }
 
-fn format_expiration(expires: DateTime<Utc>) -> String {
-    expires.format("expires=%a, %d %b %Y %T GMT").to_string()
+/// Gets the decoded value of a cookie.
+pub fn get(name: &str) -> Option<String> {
+    let cookies = super::document().cookie().ok()?;
+    uchat_cookie::get(&cookies, name)
 }
 
-fn format_kv<K, V>(key: K, value: V) -> String
-where
-    K: AsRef<str>,
-    V: AsRef<str>,
-{
-    let key = key.as_ref();
-    let value = value.as_ref();
-    format!("{key}={value}")
+pub fn remove<N: Into<String>>(name: N) {
+    let cookie = with_standard_options(Cookie::removal(name)).finish();
+    set_cookie(&cookie);
+}
+
+/// Token which must accompany state-changing API requests.
+pub fn get_csrf_token() -> Option<String> {
+    get(CSRF_TOKEN)
+}
+
+/// Stores the session cookies which authenticate API requests.
+pub fn set_session(session: &SessionInfo) {
+    let expires = session.session_expires;
+    set(SESSION_ID, session.session_id.to_string(), expires);
+    set(
+        SESSION_SIGNATURE,
+        session.session_signature.clone(),
+        expires,
+    );
+    set(CSRF_TOKEN, session.csrf_token.clone(), expires);
 }
 
-fn format_cookie<S: AsRef<str>>(payload: S, expires: DateTime<Utc>) -> String {
-    let expires = format_expiration(expires);
-    let options = standard_options();
-    let payload = payload.as_ref();
+/// Whether session cookies are present. The server still has to accept them.
+pub fn has_session() -> bool {
+    get(SESSION_ID).is_some() && get(SESSION_SIGNATURE).is_some()
+}
+
+pub fn clear_session() {
+    remove(SESSION_ID);
+    remove(SESSION_SIGNATURE);
+    remove(CSRF_TOKEN);
+}
 
-    let cookie = format!("{payload}; {expires}; {options}");
-    cookie
+fn set_cookie(cookie: &Cookie) {
+    if let Err(e) = super::document().set_cookie(&cookie.to_string()) {
+        log::error!("failed to set cookie '{}': {e:?}", cookie.name());
+    }
 }
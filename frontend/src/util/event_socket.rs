@@ -0,0 +1,185 @@
+//! Client for the real-time event stream.
+//!
+//! [`run`] keeps a WebSocket open to [`SubscribeEvents`] for as long as its future is alive.
+//! Dropped connections are reopened with backoff, resuming from the last event received.
+
+use std::time::Duration;
+use uchat_api::event::{EventMessage, SubscribeEvents};
+use uchat_api::Endpoint;
+use url::{ParseError, Url};
+
+use super::retry::RetryPolicy;
+
+/// Delays between reconnection attempts.
+pub const RECONNECT_POLICY: RetryPolicy = RetryPolicy {
+    max_retries: u32::MAX,
+    base_delay: Duration::from_millis(500),
+    max_delay: Duration::from_secs(30),
+};
+
+/// WebSocket URL of the event stream below the API base URL.
+pub fn events_url(api_url: &Url, since: Option<u64>) -> Result<Url, ParseError> {
+    let mut url = api_url.join(SubscribeEvents::URL.trim_start_matches('/'))?;
+    let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+    // both are "special" schemes, so switching between them can't fail
+    let _ = url.set_scheme(scheme);
+    if let Some(since) = since {
+        url.query_pairs_mut()
+            .append_pair("since", &since.to_string());
+    }
+    Ok(url)
+}
+
+/// Streams events to `on_message` until the returned future is dropped, which closes the socket.
+#[cfg(target_arch = "wasm32")]
+pub async fn run<F>(mut on_message: F)
+where
+    F: FnMut(EventMessage),
+{
+    use futures::StreamExt;
+    use uchat_api::event::CLOSE_LAGGED;
+
+    let mut since = None;
+    let mut attempt = 0;
+
+    loop {
+        let close_code = match super::api_url::api_url()
+            .and_then(|api_url| events_url(api_url, since))
+            .map_err(|e| format!("{e:?}"))
+            .and_then(|url| connection::Connection::open(url.as_str()))
+        {
+            Ok(mut connection) => {
+                let mut close_code = None;
+                while let Some(frame) = connection.frames.next().await {
+                    match frame {
+                        connection::Frame::Opened => attempt = 0,
+                        connection::Frame::Text(text) => {
+                            match serde_json::from_str::<EventMessage>(&text) {
+                                Ok(message) => {
+                                    since = Some(message.id());
+                                    on_message(message);
+                                }
+                                Err(e) => log::warn!("invalid event message: {e}"),
+                            }
+                        }
+                        connection::Frame::Closed(code) => {
+                            close_code = Some(code);
+                            break;
+                        }
+                    }
+                }
+                close_code
+            }
+            Err(e) => {
+                log::error!("failed to open event stream: {e}");
+                None
+            }
+        };
+
+        // lagging clients can catch up right away
+        if close_code != Some(CLOSE_LAGGED) {
+            let delay = RECONNECT_POLICY.backoff(attempt, super::retry::random());
+            attempt = attempt.saturating_add(1);
+            gloo_timers::future::sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run<F>(_on_message: F)
+where
+    F: FnMut(EventMessage),
+{
+    futures::future::pending::<()>().await
+}
+
+#[cfg(target_arch = "wasm32")]
+mod connection {
+    use futures::channel::mpsc;
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::JsCast;
+    use web_sys::{CloseEvent, MessageEvent, WebSocket};
+
+    pub enum Frame {
+        Opened,
+        Text(String),
+        Closed(u16),
+    }
+
+    /// Open WebSocket. Closed when dropped.
+    pub struct Connection {
+        socket: WebSocket,
+        pub frames: mpsc::UnboundedReceiver<Frame>,
+        // the callbacks must live as long as the socket
+        _on_open: Closure<dyn FnMut()>,
+        _on_message: Closure<dyn FnMut(MessageEvent)>,
+        _on_close: Closure<dyn FnMut(CloseEvent)>,
+    }
+
+    impl Connection {
+        pub fn open(url: &str) -> Result<Self, String> {
+            let socket = WebSocket::new(url).map_err(|e| format!("{e:?}"))?;
+            let (sender, frames) = mpsc::unbounded();
+
+            let on_open = {
+                let sender = sender.clone();
+                Closure::<dyn FnMut()>::new(move || {
+                    let _ = sender.unbounded_send(Frame::Opened);
+                })
+            };
+            let on_message = {
+                let sender = sender.clone();
+                Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                    if let Some(text) = event.data().as_string() {
+                        let _ = sender.unbounded_send(Frame::Text(text));
+                    }
+                })
+            };
+            // errors are always followed by a close event
+            let on_close = Closure::<dyn FnMut(CloseEvent)>::new(move |event: CloseEvent| {
+                let _ = sender.unbounded_send(Frame::Closed(event.code()));
+            });
+
+            socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+            socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+            socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+            Ok(Self {
+                socket,
+                frames,
+                _on_open: on_open,
+                _on_message: on_message,
+                _on_close: on_close,
+            })
+        }
+    }
+
+    impl Drop for Connection {
+        fn drop(&mut self) {
+            self.socket.set_onopen(None);
+            self.socket.set_onmessage(None);
+            self.socket.set_onclose(None);
+            let _ = self.socket.close();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_websocket_urls() {
+        let api_url = Url::parse("https://example.com/api/").unwrap();
+        assert_eq!(
+            events_url(&api_url, None).unwrap().as_str(),
+            "wss://example.com/api/events"
+        );
+
+        let api_url = Url::parse("http://127.0.0.1:8070/").unwrap();
+        assert_eq!(
+            events_url(&api_url, Some(42)).unwrap().as_str(),
+            "ws://127.0.0.1:8070/events?since=42"
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Response, StatusCode};
+use std::time::Duration;
+
+use super::RequestError;
+
+/// When to retry idempotent API requests.
+///
+/// Requests are retried after network errors and `5xx` or `429 Too Many Requests` responses. The
+/// delay grows exponentially with random jitter, unless the server asks for a specific delay with
+/// `Retry-After`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    /// Upper bound for the delay between attempts. Responses asking to wait longer than this are
+    /// returned without retrying.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// How long to wait before the next attempt, or `None` if the request shouldn't be retried.
+    ///
+    /// `attempt` is the number of retries done so far.
+    pub fn retry_delay(
+        &self,
+        attempt: u32,
+        result: &Result<Response, RequestError>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+
+        match result {
+            Ok(response) if is_retryable_status(response.status()) => {
+                match retry_after(response.headers(), Utc::now()) {
+                    Some(delay) if delay > self.max_delay => None,
+                    Some(delay) => Some(delay),
+                    None => Some(self.backoff(attempt, random())),
+                }
+            }
+            Ok(_) => None,
+            Err(RequestError::Request(e)) if !e.is_builder() => {
+                Some(self.backoff(attempt, random()))
+            }
+            Err(RequestError::Timeout) => Some(self.backoff(attempt, random())),
+            Err(_) => None,
+        }
+    }
+
+    /// Exponential backoff with "equal jitter": half of the delay is fixed and the other half is
+    /// random. `jitter` is in `0.0..1.0`.
+    pub fn backoff(&self, attempt: u32, jitter: f64) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        delay / 2 + (delay / 2).mul_f64(jitter.clamp(0.0, 1.0))
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Parses `Retry-After`, which is either a number of seconds or an HTTP date.
+fn retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(super) fn random() -> f64 {
+    js_sys::Math::random()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(super) fn random() -> f64 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    // every `RandomState` is seeded differently, which is plenty for jitter
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use reqwest::header::HeaderValue;
+
+    fn headers(retry_after: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(retry_after).unwrap());
+        headers
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_max() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+        };
+
+        assert_eq!(policy.backoff(0, 1.0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1, 1.0), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2, 1.0), Duration::from_millis(400));
+        assert_eq!(policy.backoff(5, 1.0), Duration::from_secs(1));
+        assert_eq!(policy.backoff(u32::MAX, 1.0), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn jitter_keeps_at_least_half_the_delay() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(0, 0.0), policy.base_delay / 2);
+        assert!(policy.backoff(0, random()) >= policy.base_delay / 2);
+        assert!(policy.backoff(0, random()) <= policy.base_delay);
+    }
+
+    #[test]
+    fn parses_retry_after_seconds() {
+        let now = Utc::now();
+        assert_eq!(
+            retry_after(&headers("120"), now),
+            Some(Duration::from_secs(120))
+        );
+    }
+
+    #[test]
+    fn parses_retry_after_date() {
+        let now = Utc.with_ymd_and_hms(2015, 10, 21, 7, 27, 0).unwrap();
+        assert_eq!(
+            retry_after(&headers("Wed, 21 Oct 2015 07:28:00 GMT"), now),
+            Some(Duration::from_secs(60))
+        );
+
+        let later = Utc.with_ymd_and_hms(2015, 10, 21, 8, 0, 0).unwrap();
+        assert_eq!(
+            retry_after(&headers("Wed, 21 Oct 2015 07:28:00 GMT"), later),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn ignores_invalid_retry_after() {
+        assert_eq!(retry_after(&headers("soon"), Utc::now()), None);
+        assert_eq!(retry_after(&HeaderMap::new(), Utc::now()), None);
+    }
+
+    #[test]
+    fn retries_only_retryable_statuses() {
+        assert!(is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!is_retryable_status(StatusCode::BAD_REQUEST));
+        assert!(!is_retryable_status(StatusCode::UNAUTHORIZED));
+    }
+
+    #[test]
+    fn retries_timeouts_until_limit() {
+        let policy = RetryPolicy::default();
+        assert!(policy.retry_delay(0, &Err(RequestError::Timeout)).is_some());
+        assert!(policy
+            .retry_delay(policy.max_retries, &Err(RequestError::Timeout))
+            .is_none());
+        assert!(RetryPolicy::none()
+            .retry_delay(0, &Err(RequestError::Timeout))
+            .is_none());
+    }
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::route;
+
+/// Blocks a user. Both users stop following each other and can't interact anymore, and neither
+/// sees content of the other.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct BlockUser {
+    pub user_id: Uuid,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct BlockUserOk;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UnblockUser {
+    pub user_id: Uuid,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UnblockUserOk;
+
+/// Mutes a user, which hides their content from the logged in user only.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MuteUser {
+    pub user_id: Uuid,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MuteUserOk;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UnmuteUser {
+    pub user_id: Uuid,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UnmuteUserOk;
+
+/// Lists the users the logged in user blocked or muted.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetBlockedUsers;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RestrictedUser {
+    pub id: Uuid,
+    pub handle: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetBlockedUsersOk {
+    /// Most recent first, like `muted`.
+    pub blocked: Vec<RestrictedUser>,
+    pub muted: Vec<RestrictedUser>,
+}
+
+route!("/users/block" => BlockUser);
+route!("/users/unblock" => UnblockUser);
+route!("/users/mute" => MuteUser);
+route!("/users/unmute" => UnmuteUser);
+route!("/users/blocked" => GetBlockedUsers);
@@ -0,0 +1,101 @@
+//! Real-time events streamed to logged in users over a WebSocket.
+//!
+//! Clients connect to [`SubscribeEvents`] and receive [`EventMessage`]s as JSON text frames. Every
+//! event has an increasing id. After a disconnect, clients reconnect with the last id they saw in
+//! `since` and get the events they missed, or [`EventMessage::Resync`] if those are gone.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::route;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// Someone the user follows published a post.
+    NewPost {
+        post_id: Uuid,
+        author_id: Uuid,
+    },
+    /// Someone reacted to a post of the user.
+    Reaction {
+        post_id: Uuid,
+        user_id: Uuid,
+        /// `1` for likes, `-1` for dislikes and `0` for neither.
+        like_status: i16,
+    },
+    DirectMessage {
+        post_id: Uuid,
+        from: Uuid,
+    },
+    /// Vote counts of a poll the user created or voted in changed.
+    PollTally {
+        post_id: Uuid,
+        votes: Vec<PollChoiceVotes>,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PollChoiceVotes {
+    pub choice_id: Uuid,
+    pub votes: i64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventMessage {
+    Event {
+        id: u64,
+        event: Event,
+    },
+    /// Events were missed and can't be replayed, so anything shown from earlier events should be
+    /// reloaded. Reconnect with `id` as `since` from now on.
+    Resync {
+        id: u64,
+    },
+}
+
+impl EventMessage {
+    /// Id to resume from after this message.
+    pub fn id(&self) -> u64 {
+        match self {
+            Self::Event { id, .. } | Self::Resync { id } => *id,
+        }
+    }
+}
+
+/// Opens the event stream. Sent as the query string of a WebSocket upgrade request.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SubscribeEvents {
+    /// Id of the last event received on an earlier connection.
+    pub since: Option<u64>,
+}
+
+/// WebSocket close code used when a client falls too far behind. Reconnect with `since` to catch
+/// up.
+pub const CLOSE_LAGGED: u16 = 4000;
+
+route!("/events" => SubscribeEvents);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_tagged_messages() {
+        let post_id = Uuid::new_v4();
+        let author_id = Uuid::new_v4();
+        let message = EventMessage::Event {
+            id: 7,
+            event: Event::NewPost { post_id, author_id },
+        };
+
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["type"], "event");
+        assert_eq!(json["event"]["type"], "new_post");
+
+        let parsed: EventMessage = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, message);
+        assert_eq!(parsed.id(), 7);
+    }
+}
@@ -0,0 +1,58 @@
+//! Archives of everything stored about the logged in user.
+//!
+//! An export is prepared in the background after [`RequestDataExport`]. Once it is ready,
+//! [`GetDataExport`] returns a signed link to download the zip archive, which works without a
+//! session until it expires.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::route;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DataExport {
+    pub id: Uuid,
+    pub requested_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub failed: bool,
+    /// Relative to the API url. Only set once the archive is ready.
+    pub download_url: Option<String>,
+    pub download_expires: Option<DateTime<Utc>>,
+}
+
+/// Starts preparing an archive of the data of the logged in user.
+///
+/// Fails with `429 Too Many Requests` if the user already requested an export in the last day.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RequestDataExport;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RequestDataExportOk {
+    pub export: DataExport,
+}
+
+/// Gets the most recent export of the logged in user, with a fresh download link if it is ready.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetDataExport;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetDataExportOk {
+    pub export: Option<DataExport>,
+}
+
+/// Downloads an archive. Sent as the query string of a `GET` request, see
+/// [`DataExport::download_url`].
+///
+/// Fails with `403 Forbidden` if the signature is invalid or expired.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DownloadDataExport {
+    pub id: Uuid,
+    /// Unix timestamp.
+    pub expires: i64,
+    pub signature: String,
+}
+
+route!("/account/export" => RequestDataExport);
+route!("/account/export/status" => GetDataExport);
+route!("/account/export/download" => DownloadDataExport);
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::route;
+
+/// Follows a user. Following a private user sends them a follow request instead, and their posts
+/// stay hidden until they approve it.
+///
+/// Fails with `403 Forbidden` if either user blocked the other.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FollowUser {
+    pub user_id: Uuid,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FollowStatus {
+    Following,
+    /// Waiting for the followed user to approve the request.
+    Requested,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FollowUserOk {
+    pub status: FollowStatus,
+}
+
+/// Stops following a user, or withdraws a pending follow request.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UnfollowUser {
+    pub user_id: Uuid,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UnfollowUserOk;
+
+/// Lists the users waiting for the logged in user to approve their follow.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetFollowRequests;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FollowRequest {
+    pub user_id: Uuid,
+    pub handle: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetFollowRequestsOk {
+    /// Oldest first.
+    pub requests: Vec<FollowRequest>,
+}
+
+/// Approves or denies a follow request to the logged in user.
+///
+/// Fails with `404 Not Found` if the user has no pending request.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AnswerFollowRequest {
+    pub user_id: Uuid,
+    pub approve: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AnswerFollowRequestOk;
+
+/// Makes the posts of the logged in user visible to their followers only, or to everyone again.
+/// Pending follow requests are approved when the account becomes public.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SetPrivate {
+    pub private: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SetPrivateOk;
+
+route!("/users/follow" => FollowUser);
+route!("/users/unfollow" => UnfollowUser);
+route!("/users/requests" => GetFollowRequests);
+route!("/users/requests/answer" => AnswerFollowRequest);
+route!("/account/private" => SetPrivate);
@@ -0,0 +1,101 @@
+pub mod block;
+pub mod event;
+pub mod export;
+pub mod follow;
+pub mod media;
+pub mod moderation;
+pub mod notification;
+pub mod post;
+pub mod search;
+pub mod tag;
+pub mod user;
+
+use serde::{Deserialize, Serialize};
+
+/// Header carrying the CSRF token on state-changing requests.
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Request payload which is sent to a fixed API route.
+pub trait Endpoint {
+    const URL: &'static str;
+
+    fn self_url(&self) -> &'static str {
+        Self::URL
+    }
+}
+
+/// Machine-readable reason for a failed request.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The request was malformed or failed validation.
+    InvalidInput,
+    /// Not logged in, or the credentials are wrong.
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    /// The request conflicts with existing data, e.g. a handle which is already taken.
+    Conflict,
+    RateLimited,
+    Internal,
+    /// The server is temporarily unable to handle the request.
+    Unavailable,
+    /// Sent by a newer server version.
+    #[serde(other)]
+    Unknown,
+}
+
+impl ErrorCode {
+    /// Code which best describes a response with `status`.
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            401 => Self::Unauthorized,
+            403 => Self::Forbidden,
+            404 => Self::NotFound,
+            409 => Self::Conflict,
+            429 => Self::RateLimited,
+            503 => Self::Unavailable,
+            400..=499 => Self::InvalidInput,
+            500..=599 => Self::Internal,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// Body returned by the server whenever a request cannot be completed.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RequestFailed {
+    pub code: ErrorCode,
+    pub msg: String,
+}
+
+macro_rules! route {
+    ($url:literal => $request:ty) => {
+        impl $crate::Endpoint for $request {
+            const URL: &'static str = $url;
+        }
+    };
+}
+pub(crate) use route;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_status_to_error_code() {
+        assert_eq!(ErrorCode::from_status(400), ErrorCode::InvalidInput);
+        assert_eq!(ErrorCode::from_status(422), ErrorCode::InvalidInput);
+        assert_eq!(ErrorCode::from_status(401), ErrorCode::Unauthorized);
+        assert_eq!(ErrorCode::from_status(429), ErrorCode::RateLimited);
+        assert_eq!(ErrorCode::from_status(500), ErrorCode::Internal);
+        assert_eq!(ErrorCode::from_status(503), ErrorCode::Unavailable);
+    }
+
+    #[test]
+    fn deserializes_unknown_error_codes() {
+        let failed: RequestFailed =
+            serde_json::from_str(r#"{"code":"from_the_future","msg":"?"}"#).unwrap();
+        assert_eq!(failed.code, ErrorCode::Unknown);
+    }
+}
@@ -0,0 +1,52 @@
+//! Images uploaded by users, e.g. for profiles and posts.
+//!
+//! Uploads are referred to by their key, which is derived from their content. Every user has a
+//! quota of bytes they can upload.
+
+use serde::{Deserialize, Serialize};
+
+use crate::route;
+
+/// Largest file which can be uploaded, in bytes.
+pub const MAX_UPLOAD_SIZE: usize = 10 * 1024 * 1024;
+
+/// Uploads a PNG, JPEG, GIF or WebP image. The image is sent as the request body instead of JSON,
+/// its type is recognized from its content.
+///
+/// Fails with `415 Unsupported Media Type` for other files, and with `413 Payload Too Large` if
+/// the file is larger than [`MAX_UPLOAD_SIZE`] or the quota of the user is used up.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UploadMedia;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UploadMediaOk {
+    pub key: String,
+    /// Relative to the API url for images served by the API server.
+    pub url: String,
+    pub content_type: String,
+    pub size: i64,
+}
+
+/// Deletes an upload of the logged in user.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DeleteMedia {
+    pub key: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DeleteMediaOk;
+
+/// Gets how much of their quota the logged in user used.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetMediaUsage;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetMediaUsageOk {
+    /// Bytes.
+    pub used: i64,
+    pub quota: i64,
+}
+
+route!("/media/upload" => UploadMedia);
+route!("/media/delete" => DeleteMedia);
+route!("/media/usage" => GetMediaUsage);
@@ -0,0 +1,274 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::route;
+
+/// Reports and audit log entries returned per page.
+pub const PAGE_SIZE: i64 = 50;
+pub const REPORT_DETAILS_MAX_LEN: usize = 1000;
+pub const SUSPENSION_REASON_MAX_LEN: usize = 500;
+
+/// Checks that details of a report are at most [`REPORT_DETAILS_MAX_LEN`] characters long.
+pub fn validate_report_details(details: &str) -> Result<(), String> {
+    if details.chars().count() > REPORT_DETAILS_MAX_LEN {
+        return Err(format!(
+            "details must be at most {REPORT_DETAILS_MAX_LEN} characters long"
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that a suspension has a reason of at most [`SUSPENSION_REASON_MAX_LEN`] characters.
+pub fn validate_suspension_reason(reason: &str) -> Result<(), String> {
+    if reason.trim().is_empty() {
+        return Err("a reason is required".to_string());
+    }
+    if reason.chars().count() > SUSPENSION_REASON_MAX_LEN {
+        return Err(format!(
+            "reason must be at most {SUSPENSION_REASON_MAX_LEN} characters long"
+        ));
+    }
+    Ok(())
+}
+
+/// Roles in ascending order of privileges. Moderators work through reports; admins can also
+/// moderate other moderators and change roles.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    #[default]
+    User,
+    Moderator,
+    Admin,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportReason {
+    Spam,
+    Harassment,
+    Hate,
+    Violence,
+    Other,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportStatus {
+    #[default]
+    Open,
+    Resolved,
+    Dismissed,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind", content = "id", rename_all = "snake_case")]
+pub enum ReportTarget {
+    User(Uuid),
+    Post(Uuid),
+}
+
+/// Reports a post or user to the moderators.
+///
+/// Fails with `409 Conflict` if the same post or user was already reported by the logged in user
+/// and the report is still open.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ReportContent {
+    pub target: ReportTarget,
+    pub reason: ReportReason,
+    #[serde(default)]
+    pub details: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ReportContentOk {
+    pub report_id: Uuid,
+}
+
+/// Position in the moderation queue, see [`GetReportsOk::next`].
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ReportCursor {
+    pub created_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+/// Lists reports, oldest first. Moderators only.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetReports {
+    #[serde(default)]
+    pub status: ReportStatus,
+    pub after: Option<ReportCursor>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Report {
+    pub id: Uuid,
+    pub reporter_handle: String,
+    /// Reported user, or the author of the reported post.
+    pub user_id: Uuid,
+    pub user_handle: String,
+    pub post_id: Option<Uuid>,
+    pub post_text: Option<String>,
+    pub reason: ReportReason,
+    pub details: String,
+    pub status: ReportStatus,
+    pub resolved_by_handle: Option<String>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetReportsOk {
+    pub reports: Vec<Report>,
+    /// Cursor for the next page, unless this is the last one.
+    pub next: Option<ReportCursor>,
+}
+
+/// Closes an open report. Moderators only.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ResolveReport {
+    pub report_id: Uuid,
+    /// Closes the report as dismissed, for reports about nothing wrong.
+    #[serde(default)]
+    pub dismiss: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ResolveReportOk;
+
+/// Hides a post from everyone but its author. Moderators only.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct HidePost {
+    pub post_id: Uuid,
+    /// Report which is resolved by hiding the post.
+    pub report_id: Option<Uuid>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct HidePostOk;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UnhidePost {
+    pub post_id: Uuid,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UnhidePostOk;
+
+/// Suspends a user, which logs them out everywhere until the suspension is lifted. Moderators
+/// only, and only admins can suspend moderators and admins.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SuspendUser {
+    pub user_id: Uuid,
+    pub reason: String,
+    /// Report which is resolved by suspending the user.
+    pub report_id: Option<Uuid>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SuspendUserOk;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UnsuspendUser {
+    pub user_id: Uuid,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UnsuspendUserOk;
+
+/// Changes the role of a user. Admins only, who can't change their own role.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SetRole {
+    pub user_id: Uuid,
+    pub role: Role,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SetRoleOk;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    ResolveReport,
+    DismissReport,
+    HidePost,
+    UnhidePost,
+    SuspendUser,
+    UnsuspendUser,
+    SetRole,
+    /// Sent by a newer server version.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Position in the audit log, see [`GetAuditLogOk::next`].
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AuditCursor {
+    pub created_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+/// Lists moderation actions, newest first. Moderators only.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetAuditLog {
+    pub before: Option<AuditCursor>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct AuditEntry {
+    pub id: Uuid,
+    /// `None` for actions taken from the command line.
+    pub actor_handle: Option<String>,
+    pub action: AuditAction,
+    pub user_id: Option<Uuid>,
+    pub post_id: Option<Uuid>,
+    pub report_id: Option<Uuid>,
+    /// Action specific details, like the reason of a suspension.
+    pub details: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct GetAuditLogOk {
+    pub entries: Vec<AuditEntry>,
+    /// Cursor for the next page, unless this is the last one.
+    pub next: Option<AuditCursor>,
+}
+
+route!("/reports/new" => ReportContent);
+route!("/moderation/reports" => GetReports);
+route!("/moderation/reports/resolve" => ResolveReport);
+route!("/moderation/posts/hide" => HidePost);
+route!("/moderation/posts/unhide" => UnhidePost);
+route!("/moderation/users/suspend" => SuspendUser);
+route!("/moderation/users/unsuspend" => UnsuspendUser);
+route!("/moderation/users/role" => SetRole);
+route!("/moderation/audit" => GetAuditLog);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_roles_by_privileges() {
+        assert!(Role::User < Role::Moderator);
+        assert!(Role::Moderator < Role::Admin);
+    }
+
+    #[test]
+    fn validates_suspension_reasons() {
+        assert!(validate_suspension_reason("spam").is_ok());
+        assert!(validate_suspension_reason(" ").is_err());
+        assert!(validate_suspension_reason(&"a".repeat(SUSPENSION_REASON_MAX_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn serializes_report_targets() {
+        let id = Uuid::new_v4();
+        let target = serde_json::to_value(ReportTarget::Post(id)).unwrap();
+        assert_eq!(target, serde_json::json!({ "kind": "post", "id": id }));
+    }
+}
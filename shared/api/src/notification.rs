@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::route;
+
+/// Notifications returned per page.
+pub const PAGE_SIZE: i64 = 30;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// The actor followed the user.
+    Follow,
+    /// The actor replied to a post of the user.
+    Reply,
+    /// The actor boosted a post of the user.
+    Boost,
+    /// The actor liked or disliked a post of the user.
+    Reaction,
+    /// The actor voted in a poll of the user.
+    PollVote,
+    /// The actor mentioned the user in a post.
+    Mention,
+    /// The actor asked to follow the private user.
+    FollowRequest,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Notification {
+    pub id: Uuid,
+    pub kind: NotificationKind,
+    pub actor_id: Uuid,
+    pub actor_handle: String,
+    pub post_id: Option<Uuid>,
+    pub read: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Position in the inbox, see [`GetNotificationsOk::next`].
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct NotificationCursor {
+    pub created_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+/// Gets a page of the inbox of the logged in user, newest first.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetNotifications {
+    /// Continues after an earlier page. The first page is returned without a cursor.
+    pub after: Option<NotificationCursor>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetNotificationsOk {
+    pub notifications: Vec<Notification>,
+    /// Cursor for the next page, unless this is the last one.
+    pub next: Option<NotificationCursor>,
+    pub unread: i64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetUnreadNotifications;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetUnreadNotificationsOk {
+    pub unread: i64,
+}
+
+/// Marks notifications as read.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MarkNotificationsRead {
+    /// Notifications to mark. Every notification is marked if this is `None`.
+    pub ids: Option<Vec<Uuid>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MarkNotificationsReadOk {
+    pub unread: i64,
+}
+
+route!("/notifications" => GetNotifications);
+route!("/notifications/unread" => GetUnreadNotifications);
+route!("/notifications/read" => MarkNotificationsRead);
@@ -0,0 +1,178 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::route;
+
+/// Public post as shown in lists like search results and tag timelines.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PostPreview {
+    pub id: Uuid,
+    pub author_id: Uuid,
+    pub author_handle: String,
+    /// Text of the post.
+    pub text: String,
+    pub time_posted: DateTime<Utc>,
+    /// When the content was last changed, if it ever was.
+    #[serde(default)]
+    pub edited_at: Option<DateTime<Utc>>,
+    /// Deleted posts stay in threads as placeholders without text.
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Position in a timeline. Pages continue with the posts published before it.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PostCursor {
+    pub time_posted: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+/// Who can see a post, on top of blocks, moderation and private users.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    #[default]
+    Public,
+    /// Only followers of the author can see the post.
+    Followers,
+    /// Anyone can open the post, but it is left out of search results and timelines.
+    Unlisted,
+}
+
+/// Publishes a post by the logged in user. Followers who are online are told about it, or the
+/// recipient for direct messages.
+///
+/// Fails with `403 Forbidden` for replies and direct messages across a block, and with
+/// `404 Not Found` for replies to posts the user can't see.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct NewPost {
+    pub content: serde_json::Value,
+    /// Schedules the post for later. Posts without a time are published right away.
+    #[serde(default)]
+    pub time_posted: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub direct_message_to: Option<Uuid>,
+    #[serde(default)]
+    pub reply_to: Option<Uuid>,
+    #[serde(default)]
+    pub visibility: Visibility,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct NewPostOk {
+    pub post_id: Uuid,
+}
+
+/// Likes or dislikes a post. The author is told about it if they are online.
+///
+/// Fails with `404 Not Found` like [`GetPost`], and with `403 Forbidden` across a block.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct React {
+    pub post_id: Uuid,
+    /// `1` for likes, `-1` for dislikes and `0` to take the reaction back.
+    pub like_status: i16,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ReactOk;
+
+/// Boosts a post of someone else, or takes the boost back. The author is notified about the first
+/// boost of each user.
+///
+/// Fails with `404 Not Found` like [`GetPost`] and for direct messages and posts only visible to
+/// followers, and with `403 Forbidden` across a block.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Boost {
+    pub post_id: Uuid,
+    /// `false` takes the boost back.
+    pub boosted: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct BoostOk;
+
+/// Votes in a poll, replacing an earlier vote of the user. The new counts are sent to the author
+/// and everyone who voted.
+///
+/// Fails with `404 Not Found` like [`GetPost`] or if the choice isn't part of the poll, and with
+/// `403 Forbidden` across a block.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct VotePoll {
+    pub post_id: Uuid,
+    pub choice_id: Uuid,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct VotePollOk {
+    pub votes: Vec<crate::event::PollChoiceVotes>,
+}
+
+/// Opens a single post by its id, which is the only way to see unlisted posts.
+///
+/// Fails with `404 Not Found` if the post doesn't exist or the logged in user can't see it.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetPost {
+    pub post_id: Uuid,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetPostOk {
+    pub post: PostPreview,
+}
+
+/// Replaces the content of a post by the logged in user. The previous content stays visible in
+/// the history of the post.
+///
+/// Fails with `404 Not Found` if the user didn't write the post, `403 Forbidden` once the post is
+/// too old to edit, and `409 Conflict` when changing the choices of a poll which has votes.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct EditPost {
+    pub post_id: Uuid,
+    /// New content, in the same format as the post was written in.
+    pub content: serde_json::Value,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct EditPostOk;
+
+/// Lists the earlier versions of a post.
+///
+/// Fails with `404 Not Found` like [`GetPost`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetPostRevisions {
+    pub post_id: Uuid,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PostRevision {
+    /// Text of the post before the edit.
+    pub text: String,
+    pub revised_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetPostRevisionsOk {
+    /// Newest first.
+    pub revisions: Vec<PostRevision>,
+}
+
+/// Deletes a post by the logged in user. Replies to it stay, under a placeholder.
+///
+/// Fails with `404 Not Found` if the user didn't write the post or already deleted it.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DeletePost {
+    pub post_id: Uuid,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DeletePostOk;
+
+route!("/posts/new" => NewPost);
+route!("/posts/react" => React);
+route!("/posts/boost" => Boost);
+route!("/posts/vote" => VotePoll);
+route!("/posts/get" => GetPost);
+route!("/posts/edit" => EditPost);
+route!("/posts/revisions" => GetPostRevisions);
+route!("/posts/delete" => DeletePost);
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::post::PostPreview;
+use crate::route;
+
+/// Results returned per page.
+pub const PAGE_SIZE: i64 = 20;
+pub const QUERY_MAX_LEN: usize = 200;
+
+/// Checks that a search query has something to search for and is at most [`QUERY_MAX_LEN`]
+/// characters long.
+pub fn validate_query(query: &str) -> Result<(), String> {
+    if query.trim().is_empty() {
+        return Err("search query is empty".to_string());
+    }
+    if query.chars().count() > QUERY_MAX_LEN {
+        return Err(format!(
+            "search query must be at most {QUERY_MAX_LEN} characters long"
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchKind {
+    #[default]
+    Posts,
+    Users,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UserHit {
+    pub id: Uuid,
+    pub handle: String,
+    pub display_name: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind", content = "hits", rename_all = "snake_case")]
+pub enum SearchHits {
+    /// Public posts. Direct messages and scheduled posts never match.
+    Posts(Vec<PostPreview>),
+    Users(Vec<UserHit>),
+}
+
+impl SearchHits {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Posts(hits) => hits.len(),
+            Self::Users(hits) => hits.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Searches posts or users, best matches first.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Search {
+    pub query: String,
+    pub kind: SearchKind,
+    /// Number of results to skip. Earlier pages return the offset of the next page.
+    #[serde(default)]
+    pub offset: i64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SearchOk {
+    pub hits: SearchHits,
+    /// Offset of the next page, unless this is the last one.
+    pub next_offset: Option<i64>,
+}
+
+route!("/search" => Search);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_queries() {
+        assert!(validate_query("rust").is_ok());
+        assert!(validate_query("  ").is_err());
+        assert!(validate_query(&"a".repeat(QUERY_MAX_LEN + 1)).is_err());
+    }
+}
@@ -0,0 +1,47 @@
+//! Hashtags (`#rust`) and mentions (`@handle`) inside post text.
+//!
+//! The server and the frontend share the parser of [`uchat_text`], so the links shown to users are
+//! exactly the tags and mentions which were stored.
+
+use serde::{Deserialize, Serialize};
+pub use uchat_text::{hashtags, mentions, normalize_tag, segments, Segment, TAG_MAX_LEN};
+
+use crate::post::{PostCursor, PostPreview};
+use crate::route;
+
+/// Posts returned per page of a tag timeline.
+pub const PAGE_SIZE: i64 = 30;
+
+/// Public posts with a hashtag, newest first.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetTagTimeline {
+    pub tag: String,
+    /// Continues after an earlier page. The first page is returned without a cursor.
+    pub before: Option<PostCursor>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetTagTimelineOk {
+    pub posts: Vec<PostPreview>,
+    /// Cursor for the next page, unless this is the last one.
+    pub next: Option<PostCursor>,
+}
+
+/// Hashtags used by the most posts recently.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetTrendingTags;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TrendingTag {
+    pub tag: String,
+    pub posts: i64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetTrendingTagsOk {
+    /// Most used first.
+    pub tags: Vec<TrendingTag>,
+}
+
+route!("/tags/timeline" => GetTagTimeline);
+route!("/tags/trending" => GetTrendingTags);
@@ -0,0 +1,173 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub use uchat_text::{validate_handle, HANDLE_MAX_LEN, HANDLE_MIN_LEN};
+
+use crate::moderation::Role;
+use crate::route;
+
+/// Session details. The frontend stores these in cookies.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SessionInfo {
+    pub session_id: Uuid,
+    pub session_signature: String,
+    pub session_expires: DateTime<Utc>,
+    /// Sent back in the [`CSRF_HEADER`](crate::CSRF_HEADER) header on state-changing requests.
+    pub csrf_token: String,
+    pub user_id: Uuid,
+    pub handle: String,
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub role: Role,
+    /// Whether posts are only visible to followers.
+    #[serde(default)]
+    pub private: bool,
+    /// When the account will be deleted, if the user asked for it.
+    #[serde(default)]
+    pub delete_after: Option<DateTime<Utc>>,
+}
+
+pub const PASSWORD_MIN_LEN: usize = 8;
+
+pub fn validate_password(password: &str) -> Result<(), String> {
+    if password.chars().count() < PASSWORD_MIN_LEN {
+        return Err(format!(
+            "password must be at least {PASSWORD_MIN_LEN} characters long"
+        ));
+    }
+    Ok(())
+}
+
+/// Registers a new account and logs in.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CreateUser {
+    pub handle: String,
+    pub password: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Login {
+    pub handle: String,
+    pub password: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum LoginOk {
+    Session(SessionInfo),
+    /// Two-factor authentication is enabled: complete the login with [`LoginMfa`].
+    MfaRequired {
+        challenge_id: Uuid,
+        expires_at: DateTime<Utc>,
+    },
+}
+
+/// Gets the logged in user. Fails with `401 Unauthorized` if the session is missing or invalid.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Whoami;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct WhoamiOk {
+    pub user_id: Uuid,
+    pub handle: String,
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub role: Role,
+    /// Whether posts are only visible to followers.
+    #[serde(default)]
+    pub private: bool,
+    /// When the account will be deleted, if the user asked for it.
+    #[serde(default)]
+    pub delete_after: Option<DateTime<Utc>>,
+}
+
+/// Second login step for accounts with two-factor authentication.
+///
+/// `code` is either a TOTP code or an unused recovery code.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LoginMfa {
+    pub challenge_id: Uuid,
+    pub code: String,
+}
+
+/// Starts (or restarts) TOTP enrollment for the logged in user.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct EnrollTotp;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct EnrollTotpOk {
+    /// base32 secret for manual entry
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
+/// Finishes TOTP enrollment using a code from the authenticator app.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ConfirmTotp {
+    pub code: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ConfirmTotpOk {
+    /// Shown to the user once. Only hashes are stored on the server.
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DisableTotp {
+    pub code: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DisableTotpOk;
+
+/// Deletes the account of the logged in user after a grace period, during which the deletion
+/// can be cancelled with [`CancelAccountDeletion`].
+///
+/// Fails with `403 Forbidden` if the password is wrong.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DeleteAccount {
+    pub password: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DeleteAccountOk {
+    pub delete_after: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CancelAccountDeletion;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CancelAccountDeletionOk;
+
+route!("/account/create" => CreateUser);
+route!("/account/login" => Login);
+route!("/account/login/mfa" => LoginMfa);
+route!("/account/whoami" => Whoami);
+route!("/account/totp/enroll" => EnrollTotp);
+route!("/account/totp/confirm" => ConfirmTotp);
+route!("/account/totp/disable" => DisableTotp);
+route!("/account/delete" => DeleteAccount);
+route!("/account/delete/cancel" => CancelAccountDeletion);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_handles() {
+        assert!(validate_handle("some_user1").is_ok());
+        assert!(validate_handle("ab").is_err());
+        assert!(validate_handle(&"a".repeat(HANDLE_MAX_LEN + 1)).is_err());
+        assert!(validate_handle("has space").is_err());
+        assert!(validate_handle("ünicode").is_err());
+    }
+
+    #[test]
+    fn validates_passwords() {
+        assert!(validate_password("long enough").is_ok());
+        assert!(validate_password("short").is_err());
+    }
+}
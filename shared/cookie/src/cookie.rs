@@ -0,0 +1,276 @@
+use chrono::{DateTime, Utc};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+
+/// Characters which are not allowed in cookie values ([RFC 6265 section 4.1.1]) plus `%`, so
+/// encoded values can be decoded unambiguously.
+///
+/// [RFC 6265 section 4.1.1]: https://www.rfc-editor.org/rfc/rfc6265#section-4.1.1
+const VALUE_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'%')
+    .add(b',')
+    .add(b';')
+    .add(b'\\');
+
+/// Characters which are not allowed in cookie names (RFC 2616 separators).
+const NAME_ENCODE_SET: &AsciiSet = &VALUE_ENCODE_SET
+    .add(b'(')
+    .add(b')')
+    .add(b'<')
+    .add(b'>')
+    .add(b'@')
+    .add(b':')
+    .add(b'/')
+    .add(b'[')
+    .add(b']')
+    .add(b'?')
+    .add(b'=')
+    .add(b'{')
+    .add(b'}')
+    .add(b'\t');
+
+/// Format of `Expires` dates (IMF-fixdate, RFC 7231 section 7.1.1.1).
+pub(crate) const EXPIRES_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl SameSite {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Strict => "Strict",
+            Self::Lax => "Lax",
+            Self::None => "None",
+        }
+    }
+}
+
+/// A cookie along with its `Set-Cookie` attributes.
+///
+/// The `Display` implementation produces a `Set-Cookie` header value, which can also be assigned
+/// to `document.cookie` in the browser.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    max_age: Option<i64>,
+    expires: Option<DateTime<Utc>>,
+    domain: Option<String>,
+    path: Option<String>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    /// Cookie without any attributes.
+    pub fn new<N: Into<String>, V: Into<String>>(name: N, value: V) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            max_age: None,
+            expires: None,
+            domain: None,
+            path: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    pub fn build<N: Into<String>, V: Into<String>>(name: N, value: V) -> CookieBuilder {
+        CookieBuilder(Self::new(name, value))
+    }
+
+    /// Cookie which removes `name` from the client when set.
+    pub fn removal<N: Into<String>>(name: N) -> CookieBuilder {
+        Self::build(name, "")
+            .max_age(0)
+            .expires(DateTime::<Utc>::from(std::time::UNIX_EPOCH))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Decoded value.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Lifetime in seconds. Zero or negative values expire the cookie immediately.
+    pub fn max_age(&self) -> Option<i64> {
+        self.max_age
+    }
+
+    pub fn expires(&self) -> Option<DateTime<Utc>> {
+        self.expires
+    }
+
+    pub fn domain(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub fn secure(&self) -> bool {
+        self.secure
+    }
+
+    pub fn http_only(&self) -> bool {
+        self.http_only
+    }
+
+    pub fn same_site(&self) -> Option<SameSite> {
+        self.same_site
+    }
+
+    /// `name=value` pair as sent by clients in the `Cookie` header.
+    pub fn pair(&self) -> String {
+        let name = utf8_percent_encode(&self.name, NAME_ENCODE_SET);
+        let value = utf8_percent_encode(&self.value, VALUE_ENCODE_SET);
+        format!("{name}={value}")
+    }
+}
+
+impl std::fmt::Display for Cookie {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.pair())?;
+
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={max_age}")?;
+        }
+        if let Some(expires) = self.expires {
+            write!(f, "; Expires={}", expires.format(EXPIRES_FORMAT))?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={domain}")?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, "; Path={path}")?;
+        }
+        if self.secure {
+            f.write_str("; Secure")?;
+        }
+        if self.http_only {
+            f.write_str("; HttpOnly")?;
+        }
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={}", same_site.as_str())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Builder returned by [`Cookie::build`].
+#[derive(Clone, Debug)]
+pub struct CookieBuilder(Cookie);
+
+impl CookieBuilder {
+    pub fn max_age(mut self, seconds: i64) -> Self {
+        self.0.max_age = Some(seconds);
+        self
+    }
+
+    pub fn expires(mut self, expires: DateTime<Utc>) -> Self {
+        self.0.expires = Some(expires);
+        self
+    }
+
+    pub fn domain<S: Into<String>>(mut self, domain: S) -> Self {
+        self.0.domain = Some(domain.into());
+        self
+    }
+
+    pub fn path<S: Into<String>>(mut self, path: S) -> Self {
+        self.0.path = Some(path.into());
+        self
+    }
+
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.0.secure = secure;
+        self
+    }
+
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.0.http_only = http_only;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.0.same_site = Some(same_site);
+        self
+    }
+
+    pub fn finish(self) -> Cookie {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn formats_plain_cookie() {
+        assert_eq!(Cookie::new("id", "123").to_string(), "id=123");
+    }
+
+    #[test]
+    fn formats_all_attributes() {
+        let expires = Utc.with_ymd_and_hms(2015, 10, 21, 7, 28, 0).unwrap();
+        let cookie = Cookie::build("id", "a3fWa")
+            .max_age(2592000)
+            .expires(expires)
+            .domain("example.com")
+            .path("/docs")
+            .secure(true)
+            .http_only(true)
+            .same_site(SameSite::Strict)
+            .finish();
+
+        assert_eq!(
+            cookie.to_string(),
+            "id=a3fWa; Max-Age=2592000; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Domain=example.com; Path=/docs; Secure; HttpOnly; SameSite=Strict"
+        );
+    }
+
+    #[test]
+    fn encodes_characters_outside_of_cookie_octets() {
+        let cookie = Cookie::new("msg", "hello world; \"quoted\", 100%\\");
+        assert_eq!(
+            cookie.to_string(),
+            "msg=hello%20world%3B%20%22quoted%22%2C%20100%25%5C"
+        );
+    }
+
+    #[test]
+    fn keeps_base64_values_intact() {
+        // session signatures are base64 encoded
+        let cookie = Cookie::new("sig", "ab+/cd");
+        assert_eq!(cookie.to_string(), "sig=ab+/cd");
+    }
+
+    #[test]
+    fn encodes_separators_in_names() {
+        assert_eq!(Cookie::new("a=b", "c").pair(), "a%3Db=c");
+    }
+
+    #[test]
+    fn builds_removal_cookie() {
+        let cookie = Cookie::removal("id").path("/").finish();
+        assert_eq!(
+            cookie.to_string(),
+            "id=; Max-Age=0; Expires=Thu, 01 Jan 1970 00:00:00 GMT; Path=/"
+        );
+    }
+}
@@ -1,19 +1,19 @@
+pub mod cookie;
+pub mod parse;
+
+pub use cookie::{Cookie, CookieBuilder, SameSite};
+pub use parse::{get, parse_cookie_header, parse_set_cookie, Error};
+
 pub const SESSION_ID: &str = "session_id";
 pub const SESSION_SIGNATURE: &str = "session_signature";
+pub const CSRF_TOKEN: &str = "csrf_token";
 
+/// Gets the raw value of the cookie `key` from a `Cookie` header (or `document.cookie`).
+///
+/// Surrounding whitespace and double quotes are removed, but the value is not percent-decoded.
+/// Use [`get`] to get the decoded value.
 pub fn get_from_str<'a>(cookies: &'a str, key: &str) -> Option<&'a str> {
-    cookies
-        .split(';')
-        .find_map(|cookie| match cookie.split_once('=') {
-            Some((k, v)) => {
-                if k.trim() == key {
-                    Some(v)
-                } else {
-                    None
-                }
-            }
-            _ => None,
-        })
+    parse::pairs(cookies).find_map(|(k, v)| (k == key).then_some(v))
 }
 
 #[cfg(test)]
@@ -30,4 +30,12 @@ mod tests {
             get_from_str(cookie_str, "some_other_cookie").expect("failed to get some_other_cookie");
         assert_eq!(other, "test");
     }
+
+    #[test]
+    fn trims_and_unquotes_raw_values() {
+        let cookie_str = "a= 1 ;b=\"quoted\"";
+        assert_eq!(get_from_str(cookie_str, "a"), Some("1"));
+        assert_eq!(get_from_str(cookie_str, "b"), Some("quoted"));
+        assert_eq!(get_from_str(cookie_str, "c"), None);
+    }
 }
@@ -0,0 +1,355 @@
+//! Parsing of `Cookie` and `Set-Cookie` headers following the algorithms in
+//! [RFC 6265 section 5](https://www.rfc-editor.org/rfc/rfc6265#section-5).
+
+use chrono::{DateTime, TimeZone, Utc};
+use percent_encoding::percent_decode_str;
+
+use crate::cookie::{Cookie, CookieBuilder, SameSite};
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum Error {
+    #[error("missing cookie name")]
+    MissingName,
+
+    #[error("missing '=' in cookie pair")]
+    MissingEquals,
+}
+
+/// Splits a `Cookie` header into trimmed `(name, raw value)` pairs. Pairs without `=` or with an
+/// empty name are skipped.
+pub(crate) fn pairs(cookies: &str) -> impl Iterator<Item = (&str, &str)> {
+    cookies.split(';').filter_map(|pair| {
+        let (name, value) = pair.split_once('=')?;
+        let name = name.trim();
+        (!name.is_empty()).then(|| (name, unquote(value.trim())))
+    })
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn decode(value: &str) -> String {
+    percent_decode_str(value).decode_utf8_lossy().into_owned()
+}
+
+/// Parses a `Cookie` header (or `document.cookie`) into decoded `(name, value)` pairs.
+pub fn parse_cookie_header(cookies: &str) -> Vec<(String, String)> {
+    pairs(cookies)
+        .map(|(name, value)| (decode(name), decode(value)))
+        .collect()
+}
+
+/// Gets the decoded value of the cookie `key`.
+pub fn get(cookies: &str, key: &str) -> Option<String> {
+    parse_cookie_header(cookies)
+        .into_iter()
+        .find_map(|(name, value)| (name == key).then_some(value))
+}
+
+/// Parses a `Set-Cookie` header value.
+///
+/// Attributes which can't be understood are ignored, as required by the RFC.
+pub fn parse_set_cookie(set_cookie: &str) -> Result<Cookie, Error> {
+    let mut parts = set_cookie.split(';');
+
+    let pair = parts.next().unwrap_or_default();
+    let (name, value) = pair.split_once('=').ok_or(Error::MissingEquals)?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(Error::MissingName);
+    }
+
+    let cookie = Cookie::build(decode(name), decode(unquote(value.trim())));
+    let cookie = parts.fold(cookie, |cookie, attribute| {
+        let (key, value) = attribute
+            .split_once('=')
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .unwrap_or((attribute.trim(), ""));
+        apply_attribute(cookie, key, value)
+    });
+
+    Ok(cookie.finish())
+}
+
+fn apply_attribute(cookie: CookieBuilder, key: &str, value: &str) -> CookieBuilder {
+    match key.to_ascii_lowercase().as_str() {
+        "max-age" => match parse_max_age(value) {
+            Some(max_age) => cookie.max_age(max_age),
+            None => cookie,
+        },
+        "expires" => match parse_expires(value) {
+            Some(expires) => cookie.expires(expires),
+            None => cookie,
+        },
+        "domain" if !value.is_empty() => {
+            let domain = value.strip_prefix('.').unwrap_or(value);
+            cookie.domain(domain.to_ascii_lowercase())
+        }
+        // a path which doesn't start with `/` means "use the default path"
+        "path" if value.starts_with('/') => cookie.path(value),
+        "secure" => cookie.secure(true),
+        "httponly" => cookie.http_only(true),
+        "samesite" => match value.to_ascii_lowercase().as_str() {
+            "strict" => cookie.same_site(SameSite::Strict),
+            "lax" => cookie.same_site(SameSite::Lax),
+            "none" => cookie.same_site(SameSite::None),
+            _ => cookie,
+        },
+        _ => cookie,
+    }
+}
+
+/// `Max-Age` must be an optionally negative sequence of digits.
+fn parse_max_age(value: &str) -> Option<i64> {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    // out of range values are clamped
+    Some(value.parse().unwrap_or(if value.starts_with('-') {
+        i64::MIN
+    } else {
+        i64::MAX
+    }))
+}
+
+/// Parses a cookie date with the algorithm of
+/// [RFC 6265 section 5.1.1](https://www.rfc-editor.org/rfc/rfc6265#section-5.1.1).
+///
+/// The date is split into tokens which are matched against a time, a day of the month, a month
+/// and a year, in any order. This accepts RFC 850 and asctime dates and two-digit years, which
+/// servers still send.
+fn parse_expires(value: &str) -> Option<DateTime<Utc>> {
+    let mut time = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+
+    let tokens = value
+        .as_bytes()
+        .split(|b| is_date_delimiter(*b))
+        .filter(|token| !token.is_empty());
+    for token in tokens {
+        if time.is_none() {
+            if let Some(hms) = parse_time(token) {
+                time = Some(hms);
+                continue;
+            }
+        }
+        if day.is_none() {
+            if let Some((value, _)) = leading_number(token, 1, 2) {
+                day = Some(value);
+                continue;
+            }
+        }
+        if month.is_none() {
+            if let Some(value) = parse_month(token) {
+                month = Some(value);
+                continue;
+            }
+        }
+        if year.is_none() {
+            if let Some((value, _)) = leading_number(token, 2, 4) {
+                year = Some(value);
+            }
+        }
+    }
+
+    let (hour, minute, second) = time?;
+    let year = match year? {
+        year @ 70..=99 => year + 1900,
+        year @ 0..=69 => year + 2000,
+        year => year,
+    };
+    if year < 1601 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    // also rejects days which the month doesn't have
+    Utc.with_ymd_and_hms(year as i32, month?, day?, hour, minute, second)
+        .single()
+}
+
+fn is_date_delimiter(b: u8) -> bool {
+    matches!(b, 0x09 | 0x20..=0x2f | 0x3b..=0x40 | 0x5b..=0x60 | 0x7b..=0x7e)
+}
+
+/// The number made of the `min` to `max` digits at the start of `token`, and the rest of the
+/// token. More digits don't match.
+fn leading_number(token: &[u8], min: usize, max: usize) -> Option<(u32, &[u8])> {
+    let len = token.iter().take_while(|b| b.is_ascii_digit()).count();
+    if len < min || len > max {
+        return None;
+    }
+    let value = token[..len]
+        .iter()
+        .fold(0, |value, digit| value * 10 + u32::from(digit - b'0'));
+    Some((value, &token[len..]))
+}
+
+/// `hh:mm:ss`, where each field has one or two digits.
+fn parse_time(token: &[u8]) -> Option<(u32, u32, u32)> {
+    let (hour, rest) = leading_number(token, 1, 2)?;
+    let (minute, rest) = leading_number(rest.strip_prefix(b":")?, 1, 2)?;
+    let (second, _) = leading_number(rest.strip_prefix(b":")?, 1, 2)?;
+    Some((hour, minute, second))
+}
+
+/// Only the first three letters of a month are significant.
+fn parse_month(token: &[u8]) -> Option<u32> {
+    const MONTHS: [&[u8]; 12] = [
+        b"jan", b"feb", b"mar", b"apr", b"may", b"jun", b"jul", b"aug", b"sep", b"oct", b"nov",
+        b"dec",
+    ];
+    let prefix = token.get(..3)?.to_ascii_lowercase();
+    let index = MONTHS
+        .iter()
+        .position(|month| *month == prefix.as_slice())?;
+    Some(index as u32 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cookie_header() {
+        let cookies = parse_cookie_header("SID=31d4d96e407aad42; lang=en-US");
+        assert_eq!(
+            cookies,
+            vec![
+                ("SID".to_string(), "31d4d96e407aad42".to_string()),
+                ("lang".to_string(), "en-US".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_malformed_pairs() {
+        let cookies = parse_cookie_header("novalue; =nameless; a=1;;");
+        assert_eq!(cookies, vec![("a".to_string(), "1".to_string())]);
+    }
+
+    #[test]
+    fn decodes_values() {
+        assert_eq!(
+            get("msg=hello%20world%3B%20100%25", "msg").as_deref(),
+            Some("hello world; 100%")
+        );
+    }
+
+    #[test]
+    fn unquotes_values() {
+        assert_eq!(
+            get("a=\"quoted value\"", "a").as_deref(),
+            Some("quoted value")
+        );
+        // a lone quote is part of the value
+        assert_eq!(
+            get("a=\"unterminated", "a").as_deref(),
+            Some("\"unterminated")
+        );
+    }
+
+    #[test]
+    fn keeps_equals_signs_in_values() {
+        assert_eq!(get("a=b=c", "a").as_deref(), Some("b=c"));
+    }
+
+    #[test]
+    fn parses_set_cookie_attributes() {
+        let cookie = parse_set_cookie(
+            "id=a3fWa; Max-Age=2592000; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Domain=.Example.com; Path=/docs; Secure; HttpOnly; SameSite=Lax",
+        )
+        .unwrap();
+
+        assert_eq!(cookie.name(), "id");
+        assert_eq!(cookie.value(), "a3fWa");
+        assert_eq!(cookie.max_age(), Some(2592000));
+        assert_eq!(
+            cookie.expires(),
+            Some(Utc.with_ymd_and_hms(2015, 10, 21, 7, 28, 0).unwrap())
+        );
+        assert_eq!(cookie.domain(), Some("example.com"));
+        assert_eq!(cookie.path(), Some("/docs"));
+        assert!(cookie.secure());
+        assert!(cookie.http_only());
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+    }
+
+    #[test]
+    fn attribute_names_are_case_insensitive() {
+        let cookie =
+            parse_set_cookie("a=b; max-age=10; SECURE; httponly; samesite=strict").unwrap();
+        assert_eq!(cookie.max_age(), Some(10));
+        assert!(cookie.secure());
+        assert!(cookie.http_only());
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+    }
+
+    #[test]
+    fn ignores_invalid_attributes() {
+        let cookie = parse_set_cookie(
+            "a=b; Max-Age=1e3; Expires=tomorrow; Path=docs; Domain=; SameSite=sometimes; Unknown=1",
+        )
+        .unwrap();
+        assert_eq!(cookie, Cookie::new("a", "b"));
+    }
+
+    #[test]
+    fn parses_legacy_expiry_dates() {
+        let expires = Some(Utc.with_ymd_and_hms(2015, 10, 21, 7, 28, 0).unwrap());
+        assert_eq!(parse_expires("Wed, 21 Oct 2015 07:28:00 GMT"), expires);
+        // RFC 850
+        assert_eq!(parse_expires("Wednesday, 21-Oct-15 07:28:00 GMT"), expires);
+        // asctime
+        assert_eq!(parse_expires("Wed Oct 21 7:28:00 2015"), expires);
+        assert_eq!(parse_expires("21 october 2015 07:28:00"), expires);
+        assert_eq!(
+            parse_expires("Thu, 01-Jan-70 00:00:01 GMT"),
+            Some(Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 1).unwrap())
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_expiry_dates() {
+        assert_eq!(parse_expires("Wed, 21 Oct 2015"), None);
+        assert_eq!(parse_expires("Wed, 21 Oct 1600 07:28:00 GMT"), None);
+        assert_eq!(parse_expires("Wed, 21 Oct 2015 24:00:00 GMT"), None);
+        assert_eq!(parse_expires("Mon, 31 Feb 2015 07:28:00 GMT"), None);
+        assert_eq!(parse_expires("Wed, 210 Oct 2015 07:28:00 GMT"), None);
+    }
+
+    #[test]
+    fn last_attribute_wins() {
+        let cookie = parse_set_cookie("a=b; Max-Age=10; Max-Age=20; Path=/x; Path=/y").unwrap();
+        assert_eq!(cookie.max_age(), Some(20));
+        assert_eq!(cookie.path(), Some("/y"));
+    }
+
+    #[test]
+    fn parses_negative_max_age() {
+        let cookie = parse_set_cookie("a=b; Max-Age=-1").unwrap();
+        assert_eq!(cookie.max_age(), Some(-1));
+    }
+
+    #[test]
+    fn rejects_set_cookie_without_name() {
+        assert_eq!(parse_set_cookie("=b"), Err(Error::MissingName));
+        assert_eq!(parse_set_cookie("novalue"), Err(Error::MissingEquals));
+    }
+
+    #[test]
+    fn roundtrips_through_display() {
+        let cookie = Cookie::build("name", "value with spaces; and \"quotes\"")
+            .max_age(60)
+            .path("/")
+            .secure(true)
+            .same_site(SameSite::Strict)
+            .finish();
+        assert_eq!(parse_set_cookie(&cookie.to_string()).unwrap(), cookie);
+    }
+}
@@ -0,0 +1,171 @@
+//! Text rules shared by the API types, the database layer and the frontend: valid handles, and
+//! the hashtags (`#rust`) and mentions (`@handle`) inside post text.
+
+pub const HANDLE_MIN_LEN: usize = 3;
+pub const HANDLE_MAX_LEN: usize = 30;
+pub const TAG_MAX_LEN: usize = 50;
+
+/// Checks that a handle only has ASCII letters, digits and underscores, and is
+/// [`HANDLE_MIN_LEN`] to [`HANDLE_MAX_LEN`] characters long.
+pub fn validate_handle(handle: &str) -> Result<(), String> {
+    if !(HANDLE_MIN_LEN..=HANDLE_MAX_LEN).contains(&handle.len()) {
+        return Err(format!(
+            "handle must be {HANDLE_MIN_LEN} to {HANDLE_MAX_LEN} characters long"
+        ));
+    }
+    if !handle
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err("handle may only contain letters, numbers and underscores".to_string());
+    }
+    Ok(())
+}
+
+/// Piece of post text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    /// Hashtag without the `#`, as written.
+    Hashtag(&'a str),
+    /// Handle without the `@`.
+    Mention(&'a str),
+}
+
+/// Splits `text` into plain text, hashtags and mentions.
+///
+/// Hashtags are letters, digits and underscores after a `#`, with at least one letter. Mentions
+/// are valid handles after an `@`. Neither starts in the middle of a word, so `a#b` and
+/// `me@example.com` are plain text.
+pub fn segments(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = vec![];
+    let mut text_start = 0;
+    let mut prev = None;
+
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let starts_word = !prev.is_some_and(is_word_char);
+        prev = Some(c);
+        if !starts_word || (c != '#' && c != '@') {
+            continue;
+        }
+
+        let start = i + c.len_utf8();
+        let end = text[start..]
+            .find(|c: char| !is_word_char(c))
+            .map_or(text.len(), |len| start + len);
+        let word = &text[start..end];
+
+        let segment = if c == '#' && is_hashtag(word) {
+            Segment::Hashtag(word)
+        } else if c == '@' && validate_handle(word).is_ok() {
+            Segment::Mention(word)
+        } else {
+            continue;
+        };
+
+        if text_start < i {
+            segments.push(Segment::Text(&text[text_start..i]));
+        }
+        segments.push(segment);
+        text_start = end;
+        while chars.next_if(|&(i, _)| i < end).is_some() {}
+        prev = word.chars().last();
+    }
+
+    if text_start < text.len() {
+        segments.push(Segment::Text(&text[text_start..]));
+    }
+    segments
+}
+
+/// Distinct hashtags in `text`, normalized with [`normalize_tag`].
+pub fn hashtags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+    for segment in segments(text) {
+        if let Segment::Hashtag(tag) = segment {
+            let tag = tag.to_lowercase();
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    tags
+}
+
+/// Distinct handles mentioned in `text`.
+pub fn mentions(text: &str) -> Vec<&str> {
+    let mut handles = vec![];
+    for segment in segments(text) {
+        if let Segment::Mention(handle) = segment {
+            if !handles.contains(&handle) {
+                handles.push(handle);
+            }
+        }
+    }
+    handles
+}
+
+/// Hashtags are case-insensitive and stored in lowercase. Accepts tags with or without the `#`,
+/// and returns `None` if `tag` isn't a hashtag.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.strip_prefix('#').unwrap_or(tag);
+    (tag.chars().all(is_word_char) && is_hashtag(tag)).then(|| tag.to_lowercase())
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_hashtag(word: &str) -> bool {
+    word.chars().count() <= TAG_MAX_LEN && word.chars().any(char::is_alphabetic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_text_into_segments() {
+        assert_eq!(
+            segments("Hi @some_user, #Rust is #1 for #élan!"),
+            vec![
+                Segment::Text("Hi "),
+                Segment::Mention("some_user"),
+                Segment::Text(", "),
+                Segment::Hashtag("Rust"),
+                Segment::Text(" is #1 for "),
+                Segment::Hashtag("élan"),
+                Segment::Text("!"),
+            ]
+        );
+        assert_eq!(segments("#tag"), vec![Segment::Hashtag("tag")]);
+        assert!(segments("").is_empty());
+    }
+
+    #[test]
+    fn ignores_sigils_inside_words() {
+        assert_eq!(
+            segments("me@example.com a#b c_#d"),
+            vec![Segment::Text("me@example.com a#b c_#d")]
+        );
+        // too short for a handle
+        assert_eq!(segments("@ab"), vec![Segment::Text("@ab")]);
+    }
+
+    #[test]
+    fn collects_distinct_tags_and_mentions() {
+        let text = "#Rust #rust #go @alice @bob @alice";
+        assert_eq!(hashtags(text), vec!["rust", "go"]);
+        assert_eq!(mentions(text), vec!["alice", "bob"]);
+    }
+
+    #[test]
+    fn normalizes_tags() {
+        assert_eq!(normalize_tag("#Rust"), Some("rust".to_string()));
+        assert_eq!(normalize_tag("rust_lang"), Some("rust_lang".to_string()));
+        assert_eq!(normalize_tag("123"), None);
+        assert_eq!(normalize_tag("two words"), None);
+        assert_eq!(normalize_tag(&"a".repeat(TAG_MAX_LEN + 1)), None);
+    }
+}